@@ -0,0 +1,107 @@
+// Compares moving PTY output from a reader thread to the UI thread as freshly
+// allocated Vec<u8> copies (the old pipeline) and in pooled buffers that are
+// handed over without copying and reused once parsed (the current one).
+//
+// Run with: cargo bench --bench output_pipeline
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::io::Read;
+use std::thread;
+use tokio::sync::mpsc;
+
+#[path = "../src/terminal/output_pool.rs"]
+#[allow(dead_code)]
+mod output_pool;
+
+use output_pool::{BufferPool, OutputChunk};
+
+// Output produced per iteration
+const TOTAL: usize = 8 * 1024 * 1024;
+
+// A reader that produces TOTAL bytes of output, like a busy PTY
+struct Output {
+    remaining: usize,
+}
+
+impl Read for Output {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.remaining);
+        buf[..n].fill(b'x');
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+// Old pipeline: read into a stack buffer and copy each read into a new Vec
+fn copy_pipeline(chunk: usize) -> usize {
+    let (sender, mut receiver) = mpsc::channel::<Vec<u8>>(100);
+    let reader = thread::spawn(move || {
+        let mut output = Output { remaining: TOTAL };
+        let mut buffer = vec![0u8; chunk];
+        loop {
+            match output.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if sender.blocking_send(buffer[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut received = 0;
+    while let Some(data) = receiver.blocking_recv() {
+        received += black_box(&data[..]).len();
+    }
+    reader.join().unwrap();
+    received
+}
+
+// Current pipeline: read into pooled buffers and send them without copying
+fn pooled_pipeline(chunk: usize) -> usize {
+    let (sender, mut receiver) = mpsc::channel::<OutputChunk>(100);
+    let reader = thread::spawn(move || {
+        let mut output = Output { remaining: TOTAL };
+        let pool = BufferPool::new(chunk);
+        loop {
+            let mut buffer = pool.get();
+            match output.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if sender.blocking_send(pool.chunk(buffer, n)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut received = 0;
+    while let Some(data) = receiver.blocking_recv() {
+        received += black_box(&data[..]).len();
+    }
+    reader.join().unwrap();
+    received
+}
+
+fn output_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("output_pipeline");
+    group.throughput(Throughput::Bytes(TOTAL as u64));
+    group.sample_size(20);
+
+    for chunk in [1024, 16 * 1024] {
+        group.bench_with_input(BenchmarkId::new("vec_copy", chunk), &chunk, |b, &chunk| {
+            b.iter(|| assert_eq!(copy_pipeline(chunk), TOTAL));
+        });
+        group.bench_with_input(BenchmarkId::new("pooled", chunk), &chunk, |b, &chunk| {
+            b.iter(|| assert_eq!(pooled_pipeline(chunk), TOTAL));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, output_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,164 @@
+use iced::keyboard::{Key, Modifiers};
+
+/// Something the user can ask for, from a keyboard shortcut or a menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    // Shell
+    NewWindow,
+    NewOsWindow,
+    CloseWindow,
+    RenameWindow,
+    CommandLine,
+
+    // Edit
+    Copy,
+    Paste,
+
+    // View
+    SplitHorizontal,
+    SplitVertical,
+    ToggleZoom,
+    ToggleSidebar,
+    ToggleTransparency,
+    ToggleFullscreen,
+    ToggleBorderless,
+
+    // Window
+    FocusNext,
+    FocusPrevious,
+    MoveToNewOsWindow,
+}
+
+impl Action {
+    /// Stable identifier, used for menu items
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::NewWindow => "new-window",
+            Action::NewOsWindow => "new-os-window",
+            Action::CloseWindow => "close-window",
+            Action::RenameWindow => "rename-window",
+            Action::CommandLine => "command-line",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::SplitHorizontal => "split-horizontal",
+            Action::SplitVertical => "split-vertical",
+            Action::ToggleZoom => "toggle-zoom",
+            Action::ToggleSidebar => "toggle-sidebar",
+            Action::ToggleTransparency => "toggle-transparency",
+            Action::ToggleFullscreen => "toggle-fullscreen",
+            Action::ToggleBorderless => "toggle-borderless",
+            Action::FocusNext => "focus-next",
+            Action::FocusPrevious => "focus-previous",
+            Action::MoveToNewOsWindow => "move-to-new-os-window",
+        }
+    }
+
+    /// The action with an identifier
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.id() == id)
+    }
+
+    /// Text shown in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::NewWindow => "New Terminal",
+            Action::NewOsWindow => "New Window",
+            Action::CloseWindow => "Close Terminal",
+            Action::RenameWindow => "Rename Terminal…",
+            Action::CommandLine => "Run Command…",
+            Action::Copy => "Copy",
+            Action::Paste => "Paste",
+            Action::SplitHorizontal => "Split Horizontally",
+            Action::SplitVertical => "Split Vertically",
+            Action::ToggleZoom => "Zoom Terminal",
+            Action::ToggleSidebar => "Show Sidebar",
+            Action::ToggleTransparency => "Transparent Background",
+            Action::ToggleFullscreen => "Full Screen",
+            Action::ToggleBorderless => "Borderless Window",
+            Action::FocusNext => "Next Terminal",
+            Action::FocusPrevious => "Previous Terminal",
+            Action::MoveToNewOsWindow => "Move Terminal to New Window",
+        }
+    }
+
+    /// Key (with the command modifier) that triggers the action, in menu accelerator syntax
+    pub fn accelerator(self) -> Option<&'static str> {
+        match self {
+            Action::NewWindow => Some("CmdOrCtrl+T"),
+            Action::NewOsWindow => Some("CmdOrCtrl+N"),
+            Action::CloseWindow => Some("CmdOrCtrl+W"),
+            Action::CommandLine => Some("CmdOrCtrl+Shift+;"),
+            Action::Copy => Some("CmdOrCtrl+C"),
+            Action::Paste => Some("CmdOrCtrl+V"),
+            Action::SplitHorizontal => Some("CmdOrCtrl+D"),
+            Action::SplitVertical => Some("CmdOrCtrl+Shift+D"),
+            Action::ToggleZoom => Some("CmdOrCtrl+Enter"),
+            Action::FocusNext => Some("CmdOrCtrl+]"),
+            Action::FocusPrevious => Some("CmdOrCtrl+["),
+            _ => None,
+        }
+    }
+
+    /// The action bound to a key press, if any
+    pub fn from_shortcut(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        // F11 toggles fullscreen on its own, as in browsers and most terminals
+        if *key == Key::Named(iced::keyboard::key::Named::F11) && modifiers.is_empty() {
+            return Some(Action::ToggleFullscreen);
+        }
+        if !is_command(modifiers) {
+            return None;
+        }
+
+        let (Key::Character(c), shift) = (key, modifiers.shift()) else {
+            return match key {
+                Key::Named(iced::keyboard::key::Named::Enter) => Some(Action::ToggleZoom),
+                _ => None,
+            };
+        };
+
+        match (c.to_lowercase().as_str(), shift) {
+            ("t", _) => Some(Action::NewWindow),
+            ("n", _) => Some(Action::NewOsWindow),
+            ("w", _) => Some(Action::CloseWindow),
+            ("c", _) => Some(Action::Copy),
+            ("v", _) => Some(Action::Paste),
+            ("d", false) => Some(Action::SplitHorizontal),
+            ("d", true) => Some(Action::SplitVertical),
+            ("]", _) => Some(Action::FocusNext),
+            ("[", _) => Some(Action::FocusPrevious),
+            // Shift+; is : on most layouts, and some report it as such
+            (";" | ":", true) => Some(Action::CommandLine),
+            _ => None,
+        }
+    }
+
+    pub const ALL: [Action; 17] = [
+        Action::NewWindow,
+        Action::NewOsWindow,
+        Action::CloseWindow,
+        Action::RenameWindow,
+        Action::CommandLine,
+        Action::Copy,
+        Action::Paste,
+        Action::SplitHorizontal,
+        Action::SplitVertical,
+        Action::ToggleZoom,
+        Action::ToggleSidebar,
+        Action::ToggleTransparency,
+        Action::ToggleFullscreen,
+        Action::ToggleBorderless,
+        Action::FocusNext,
+        Action::FocusPrevious,
+        Action::MoveToNewOsWindow,
+    ];
+}
+
+/// Whether the modifiers are the platform's command modifier for application shortcuts.
+/// On macOS that is Cmd; elsewhere Ctrl+Shift, so plain Ctrl keys still reach the shell.
+fn is_command(modifiers: Modifiers) -> bool {
+    if cfg!(target_os = "macos") {
+        modifiers.logo()
+    } else {
+        modifiers.control() && modifiers.shift()
+    }
+}
@@ -0,0 +1,52 @@
+use iced::widget::{container, row, text, text_input};
+use iced::{Alignment, Color, Element, Length};
+
+use crate::styles::colors;
+
+/// What was done in the command line
+#[derive(Debug, Clone)]
+pub enum CommandLineMessage {
+    // The command being typed changed
+    Input(String),
+    // Enter: run the command
+    Submit,
+    // Escape: close without running anything
+    Cancel,
+}
+
+/// Id of the command line's text input, to give it focus when it opens
+pub fn input_id() -> text_input::Id {
+    text_input::Id::new("command-line")
+}
+
+/// Line along the bottom of an OS window where commands such as
+/// `move-to-window 2` are typed
+pub fn view<'a>(command: &str) -> Element<'a, CommandLineMessage> {
+    let line = row![
+        text(":").size(13),
+        text_input("move-to-window 2, break, join 2:1, …", command)
+            .id(input_id())
+            .on_input(CommandLineMessage::Input)
+            .on_submit(CommandLineMessage::Submit)
+            .size(13),
+    ]
+    .align_items(Alignment::Center)
+    .spacing(4)
+    .padding([2, 8]);
+
+    container(line)
+        .width(Length::Fill)
+        .style(iced::theme::Container::Custom(Box::new(|_theme: &iced::Theme| {
+            container::Appearance {
+                text_color: Some(colors::MATRIX_GREEN),
+                background: Some(iced::Background::Color(Color::BLACK)),
+                border: iced::Border {
+                    color: colors::MATRIX_GREEN,
+                    width: 1.0,
+                    radius: 0.0.into(),
+                },
+                shadow: iced::Shadow::default(),
+            }
+        })))
+        .into()
+}
@@ -0,0 +1,181 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::widget::{self, Tree, Widget};
+use iced::advanced::{mouse, overlay, renderer, Clipboard, Shell};
+use iced::event::{self, Event};
+use iced::{Element, Length, Point, Rectangle, Size, Theme, Vector};
+
+/// Content shown over another element at a point, e.g. a context menu over the
+/// terminal windows, which stay laid out and drawn underneath
+pub struct Floating<'a, Message> {
+    base: Element<'a, Message>,
+    content: Element<'a, Message>,
+    position: Point,
+}
+
+impl<'a, Message> Floating<'a, Message> {
+    /// Show `content` over `base`, with its top left corner at `position` (relative
+    /// to `base`), moved back in where it would stick out
+    pub fn new(base: impl Into<Element<'a, Message>>, content: impl Into<Element<'a, Message>>, position: Point) -> Self {
+        Self {
+            base: base.into(),
+            content: content.into(),
+            position,
+        }
+    }
+}
+
+impl<'a, Message> Widget<Message, Theme, iced::Renderer> for Floating<'a, Message> {
+    fn size(&self) -> Size<Length> {
+        self.base.as_widget().size()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.base), Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.base, &self.content]);
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &iced::Renderer, limits: &layout::Limits) -> layout::Node {
+        self.base.as_widget().layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.base.as_widget().draw(&tree.children[0], renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        self.base.as_widget().operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.base.as_widget_mut().on_event(&mut tree.children[0], event, layout, cursor, renderer, clipboard, shell, viewport)
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.base.as_widget().mouse_interaction(&tree.children[0], layout, cursor, viewport, renderer)
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, iced::Renderer>> {
+        let mut trees = tree.children.iter_mut();
+        let base = self.base.as_widget_mut().overlay(trees.next()?, layout, renderer, translation);
+
+        let bounds = layout.bounds() + translation;
+        let floating = overlay::Element::new(Box::new(FloatingOverlay {
+            content: &mut self.content,
+            tree: trees.next()?,
+            bounds,
+            position: bounds.position() + Vector::new(self.position.x, self.position.y),
+        }));
+
+        // Overlays of the base (e.g. tooltips) stay below the floating content
+        Some(overlay::Group::with_children(base.into_iter().chain(Some(floating)).collect()).overlay())
+    }
+}
+
+impl<'a, Message: 'a> From<Floating<'a, Message>> for Element<'a, Message> {
+    fn from(floating: Floating<'a, Message>) -> Self {
+        Element::new(floating)
+    }
+}
+
+/// The floating content, laid out at its position above everything else
+struct FloatingOverlay<'a, 'b, Message> {
+    content: &'b mut Element<'a, Message>,
+    tree: &'b mut Tree,
+    bounds: Rectangle,
+    position: Point,
+}
+
+impl<'a, 'b, Message> overlay::Overlay<Message, Theme, iced::Renderer> for FloatingOverlay<'a, 'b, Message> {
+    fn layout(&mut self, renderer: &iced::Renderer, _bounds: Size) -> layout::Node {
+        let limits = layout::Limits::new(Size::ZERO, self.bounds.size());
+        let node = self.content.as_widget().layout(self.tree, renderer, &limits);
+        let position = fit(self.position, node.size(), self.bounds);
+        node.move_to(position)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut iced::Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+    ) {
+        self.content.as_widget().draw(self.tree, renderer, theme, style, layout, cursor, &layout.bounds());
+    }
+
+    fn operate(&mut self, layout: Layout<'_>, renderer: &iced::Renderer, operation: &mut dyn widget::Operation<Message>) {
+        self.content.as_widget().operate(self.tree, layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(self.tree, event, layout, cursor, renderer, clipboard, shell, &layout.bounds())
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(self.tree, layout, cursor, viewport, renderer)
+    }
+}
+
+/// Where content of some size starting at `position` ends up inside `bounds`
+fn fit(position: Point, size: Size, bounds: Rectangle) -> Point {
+    Point::new(
+        position.x.min(bounds.x + bounds.width - size.width).max(bounds.x),
+        position.y.min(bounds.y + bounds.height - size.height).max(bounds.y),
+    )
+}
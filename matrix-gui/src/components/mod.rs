@@ -1,2 +1,7 @@
 pub mod sidebar;
-pub mod tooltip;
\ No newline at end of file
+pub mod tooltip;
+pub mod command_line;
+pub mod watermark;
+pub mod pane_menu;
+pub mod floating;
+pub mod title_bar;
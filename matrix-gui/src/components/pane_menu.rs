@@ -0,0 +1,126 @@
+use iced::widget::{button, column, container, horizontal_rule, text, text_input, Column};
+use iced::{Color, Element, Length, Point};
+use uuid::Uuid;
+
+use crate::actions::Action;
+use crate::styles::colors;
+use crate::MoveTarget;
+
+/// Height of a terminal window's title bar, where right-clicking opens its menu
+pub const TITLE_BAR_HEIGHT: f32 = 24.0;
+
+/// What was chosen from a terminal window's context menu
+#[derive(Debug, Clone)]
+pub enum PaneMenuMessage {
+    // An action on the terminal window, through the shared dispatcher
+    Action(Action),
+    // Rename: start, type and confirm
+    StartRename,
+    RenameInput(String),
+    Rename,
+    // Move the terminal window to another OS window
+    MoveTo(MoveTarget),
+    // Give the terminal window the look of a profile
+    Profile(String),
+}
+
+/// Context menu of a terminal window's title bar
+pub struct PaneMenu {
+    /// The terminal window the menu is for
+    pub window: Uuid,
+    /// Where the menu opens, in the OS window
+    pub position: Point,
+    // The new title being typed, once Rename is chosen
+    rename: Option<String>,
+}
+
+impl PaneMenu {
+    /// Open the menu for a terminal window
+    pub fn new(window: Uuid, position: Point) -> Self {
+        Self { window, position, rename: None }
+    }
+
+    /// Open the menu straight at renaming the terminal window
+    pub fn renaming(window: Uuid, position: Point, title: &str) -> Self {
+        Self { window, position, rename: Some(title.to_string()) }
+    }
+
+    /// Start renaming, with the current title to edit
+    pub fn start_rename(&mut self, title: &str) {
+        self.rename = Some(title.to_string());
+    }
+
+    /// Change the title being typed
+    pub fn set_rename(&mut self, title: String) {
+        self.rename = Some(title);
+    }
+
+    /// The title typed, if renaming
+    pub fn new_title(&self) -> Option<&str> {
+        self.rename.as_deref().map(str::trim).filter(|title| !title.is_empty())
+    }
+
+    /// Render the menu, to be shown at its position; `os_windows` is how many OS windows are open,
+    /// and `current` the number of the one showing the terminal window
+    pub fn view(&self, profiles: &[String], os_windows: usize, current: usize) -> Element<'_, PaneMenuMessage> {
+        let content: Element<PaneMenuMessage> = match &self.rename {
+            Some(title) => text_input("Title", title)
+                .on_input(PaneMenuMessage::RenameInput)
+                .on_submit(PaneMenuMessage::Rename)
+                .size(13)
+                .width(200)
+                .into(),
+            None => {
+                let mut items = Column::new()
+                    .push(item("Rename…", PaneMenuMessage::StartRename))
+                    .push(horizontal_rule(1));
+                for action in [Action::SplitHorizontal, Action::SplitVertical, Action::ToggleZoom] {
+                    items = items.push(item(action.label(), PaneMenuMessage::Action(action)));
+                }
+                items = items.push(horizontal_rule(1));
+
+                // Other OS windows, then a new one
+                for number in (1..=os_windows).filter(|n| *n != current) {
+                    items = items.push(item(&format!("Move to Window {}", number), PaneMenuMessage::MoveTo(MoveTarget::Number(number))));
+                }
+                items = items.push(item(Action::MoveToNewOsWindow.label(), PaneMenuMessage::MoveTo(MoveTarget::New)));
+
+                if !profiles.is_empty() {
+                    items = items.push(horizontal_rule(1));
+                    for name in profiles {
+                        items = items.push(item(&format!("Profile: {}", name), PaneMenuMessage::Profile(name.clone())));
+                    }
+                }
+
+                items = items.push(horizontal_rule(1))
+                    .push(item(Action::CloseWindow.label(), PaneMenuMessage::Action(Action::CloseWindow)));
+                items.width(220).into()
+            }
+        };
+
+        container(column![content].padding(4))
+            .style(iced::theme::Container::Custom(Box::new(|_theme: &iced::Theme| {
+                container::Appearance {
+                    text_color: Some(colors::MATRIX_GREEN),
+                    background: Some(iced::Background::Color(Color::BLACK)),
+                    border: iced::Border {
+                        color: colors::MATRIX_GREEN,
+                        width: 1.0,
+                        radius: 0.0.into(),
+                    },
+                    shadow: iced::Shadow::default(),
+                }
+            })))
+            .into()
+    }
+}
+
+/// A menu item
+fn item(label: &str, message: PaneMenuMessage) -> Element<'static, PaneMenuMessage> {
+    button(text(label.to_string()).size(13))
+        .on_press(message)
+        .width(Length::Fill)
+        .padding([3, 8])
+        .style(iced::theme::Button::Text)
+        .into()
+}
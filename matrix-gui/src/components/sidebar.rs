@@ -4,6 +4,9 @@ use iced::mouse::Cursor;
 
 use crate::styles::colors;
 
+/// Height of each icon in the sidebar
+const ICON_HEIGHT: f32 = 30.0;
+
 /// Sidebar icons
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SidebarIcon {
@@ -16,7 +19,6 @@ pub enum SidebarIcon {
     MainLayout,
     Zoom,
     CloseWindow,
-    Help,
 }
 
 /// Messages sent from the sidebar
@@ -30,7 +32,6 @@ struct SidebarIconDef {
     icon: SidebarIcon,
     symbol: &'static str,
     tooltip: &'static str,
-    shortcut: &'static str,
 }
 
 /// The sidebar component
@@ -54,61 +55,46 @@ impl Sidebar {
                 icon: SidebarIcon::NewWindow,
                 symbol: "N",
                 tooltip: "New Window",
-                shortcut: "Ctrl+N",
             },
             SidebarIconDef {
                 icon: SidebarIcon::SplitHorizontal,
                 symbol: "H",
                 tooltip: "Split Horizontal",
-                shortcut: "Ctrl+H",
             },
             SidebarIconDef {
                 icon: SidebarIcon::SplitVertical,
                 symbol: "V",
                 tooltip: "Split Vertical",
-                shortcut: "Ctrl+V",
             },
             SidebarIconDef {
                 icon: SidebarIcon::GridLayout,
                 symbol: "G",
                 tooltip: "Grid Layout",
-                shortcut: "Ctrl+G",
             },
             SidebarIconDef {
                 icon: SidebarIcon::HorizontalLayout,
                 symbol: "=",
                 tooltip: "Horizontal Layout",
-                shortcut: "Ctrl+Shift+H",
             },
             SidebarIconDef {
                 icon: SidebarIcon::VerticalLayout,
                 symbol: "‖",
                 tooltip: "Vertical Layout",
-                shortcut: "Ctrl+Shift+V",
             },
             SidebarIconDef {
                 icon: SidebarIcon::MainLayout,
                 symbol: "M",
                 tooltip: "Main Layout",
-                shortcut: "Ctrl+M",
             },
             SidebarIconDef {
                 icon: SidebarIcon::Zoom,
                 symbol: "Z",
                 tooltip: "Zoom Window",
-                shortcut: "Ctrl+Z",
             },
             SidebarIconDef {
                 icon: SidebarIcon::CloseWindow,
                 symbol: "X",
                 tooltip: "Close Window",
-                shortcut: "Ctrl+W",
-            },
-            SidebarIconDef {
-                icon: SidebarIcon::Help,
-                symbol: "?",
-                tooltip: "Help",
-                shortcut: "F1",
             },
         ];
         
@@ -129,6 +115,11 @@ impl Sidebar {
         }
     }
     
+    /// Whether the sidebar is shown
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    
     /// Toggle sidebar visibility
     pub fn toggle(&mut self) {
         self.visible = !self.visible;
@@ -142,8 +133,7 @@ impl Sidebar {
         }
         
         // Calculate which icon was hovered
-        let icon_height = 30.0;
-        let icon_index = (position.y / icon_height) as usize;
+        let icon_index = (position.y / ICON_HEIGHT) as usize;
         
         self.hovered_icon = if icon_index < self.icons.len() {
             Some(self.icons[icon_index].icon)
@@ -152,8 +142,8 @@ impl Sidebar {
         };
     }
     
-    /// Render the sidebar
-    pub fn view<'a>(&self) -> Element<'a, SidebarMessage> {
+    /// Render the sidebar with a background of the given opacity
+    pub fn view<'a>(&self, opacity: f32) -> Element<'a, SidebarMessage> {
         if !self.visible {
             // Return an empty element if not visible
             return Container::new(Text::new(""))
@@ -162,18 +152,36 @@ impl Sidebar {
                 .into();
         }
         
-        // For now, this is just a placeholder
-        // A real implementation would render each icon and handle interactions
-        Container::new(Text::new(""))
+        // A column of icons, each naming what it does when hovered
+        let icons = self.icons.iter().map(|def| {
+            let icon = iced::widget::button(
+                Text::new(def.symbol)
+                    .size(14)
+                    .width(iced::Length::Fill)
+                    .horizontal_alignment(iced::alignment::Horizontal::Center)
+            )
+            .on_press(SidebarMessage::IconClicked(def.icon))
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fixed(ICON_HEIGHT))
+            .style(iced::theme::Button::Text);
+            iced::widget::tooltip(icon, Text::new(def.tooltip).size(12), iced::widget::tooltip::Position::Right)
+                .style(iced::theme::Container::Box)
+                .into()
+        });
+        
+        Container::new(iced::widget::Column::with_children(icons))
             .width(iced::Length::Fixed(self.width))
             .height(iced::Length::Fill)
-            .style(iced::theme::Container::Custom(Box::new(|_theme| {
+            .style(iced::theme::Container::Custom(Box::new(move |_theme: &iced::Theme| {
                 iced::widget::container::Appearance {
-                    text_color: None,
-                    background: Some(iced::Background::Color(colors::BACKGROUND)),
-                    border_radius: 0.0,
-                    border_width: 1.0,
-                    border_color: colors::BORDER,
+                    text_color: Some(colors::MATRIX_GREEN),
+                    background: Some(iced::Background::Color(colors::background(opacity))),
+                    border: iced::Border {
+                        color: colors::BORDER,
+                        width: 1.0,
+                        radius: 0.0.into(),
+                    },
+                    shadow: iced::Shadow::default(),
                 }
             })))
             .into()
@@ -0,0 +1,59 @@
+use iced::widget::{button, container, horizontal_space, mouse_area, row, text};
+use iced::{Alignment, Color, Element, Length};
+
+use crate::styles::colors;
+
+/// Height of the title bar drawn on borderless OS windows
+pub const HEIGHT: f32 = 28.0;
+
+/// What was done on the title bar of a borderless OS window
+#[derive(Debug, Clone, Copy)]
+pub enum TitleBarMessage {
+    // Pressed on the bar, to move the window
+    Drag,
+    Minimize,
+    ToggleMaximize,
+    Close,
+}
+
+/// Title bar in place of the system's one, on OS windows drawn without decorations
+pub fn view<'a>(title: &str) -> Element<'a, TitleBarMessage> {
+    let bar = row![
+        text(title.to_string()).size(13),
+        horizontal_space(),
+        control("—", TitleBarMessage::Minimize),
+        control("□", TitleBarMessage::ToggleMaximize),
+        control("×", TitleBarMessage::Close),
+    ]
+    .align_items(Alignment::Center)
+    .padding([0, 0, 0, 10]);
+
+    let bar = container(bar)
+        .width(Length::Fill)
+        .height(HEIGHT)
+        .center_y()
+        .style(iced::theme::Container::Custom(Box::new(|_theme: &iced::Theme| {
+            container::Appearance {
+                text_color: Some(colors::MATRIX_GREEN),
+                background: Some(iced::Background::Color(Color::BLACK)),
+                border: iced::Border {
+                    color: colors::MATRIX_GREEN,
+                    width: 1.0,
+                    radius: 0.0.into(),
+                },
+                shadow: iced::Shadow::default(),
+            }
+        })));
+
+    // Pressing anywhere but the buttons drags the window, as on a native title bar
+    mouse_area(bar).on_press(TitleBarMessage::Drag).into()
+}
+
+/// A window control button
+fn control(label: &str, message: TitleBarMessage) -> Element<'static, TitleBarMessage> {
+    button(text(label.to_string()).size(13))
+        .on_press(message)
+        .padding([4, 12])
+        .style(iced::theme::Button::Text)
+        .into()
+}
@@ -1,4 +1,4 @@
-use iced::{Element, Rectangle, Color};
+use iced::{Element, Color};
 use iced::widget::{Container, Text};
 
 use crate::styles::colors;
@@ -21,7 +21,7 @@ impl Tooltip {
     }
     
     /// Render the tooltip
-    pub fn view<'a, Message>(&self) -> Element<'a, Message> {
+    pub fn view<'a, Message: 'a>(&'a self) -> Element<'a, Message> {
         // Create the tooltip content
         let title = Text::new(&self.title)
             .size(14)
@@ -38,9 +38,9 @@ impl Tooltip {
         // Arrange the content vertically
         let content = iced::widget::column![
             title,
-            iced::widget::vertical_space(5),
+            iced::widget::vertical_space().height(5),
             description,
-            iced::widget::vertical_space(5),
+            iced::widget::vertical_space().height(5),
             shortcut,
         ]
         .spacing(2)
@@ -48,13 +48,16 @@ impl Tooltip {
         
         // Create the tooltip container with a Matrix-style border
         Container::new(content)
-            .style(iced::theme::Container::Custom(Box::new(|_theme| {
+            .style(iced::theme::Container::Custom(Box::new(|_theme: &iced::Theme| {
                 iced::widget::container::Appearance {
                     text_color: None,
                     background: Some(iced::Background::Color(Color::BLACK)),
-                    border_radius: 0.0, // Square borders, not rounded
-                    border_width: 1.0,
-                    border_color: colors::MATRIX_GREEN,
+                    border: iced::Border {
+                        color: colors::MATRIX_GREEN,
+                        width: 1.0,
+                        radius: 0.0.into(), // Square borders, not rounded
+                    },
+                    shadow: iced::Shadow::default(),
                 }
             })))
             .into()
@@ -0,0 +1,44 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse;
+use iced::widget::canvas::{Frame, Geometry, Program, Text};
+use iced::{Color, Pixels, Rectangle, Renderer, Theme};
+
+/// Faint text drawn across a terminal window, behind its contents
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub text: String,
+    /// From 0.0 (invisible) to 1.0
+    pub opacity: f32,
+}
+
+impl<Message> Program<Message> for Watermark {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+
+        // As large as fits the width (monospace glyphs are about 0.6 as wide as tall)
+        let chars = self.text.chars().count().max(1) as f32;
+        let size = (bounds.width * 0.8 / (chars * 0.6)).min(bounds.height * 0.5);
+        let color = Color { a: self.opacity.clamp(0.0, 1.0), ..theme.palette().text };
+
+        frame.fill_text(Text {
+            content: self.text.clone(),
+            position: frame.center(),
+            color,
+            size: Pixels(size),
+            horizontal_alignment: Horizontal::Center,
+            vertical_alignment: Vertical::Center,
+            ..Text::default()
+        });
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -1,7 +1,16 @@
 use uuid::Uuid;
-use iced::Rectangle;
+use iced::{Point, Rectangle};
 use std::collections::HashMap;
 
+/// Ratios a dragged divider snaps to
+pub const SNAP_RATIOS: [f32; 3] = [1.0 / 3.0, 0.5, 2.0 / 3.0];
+
+/// How close a dragged divider has to come to a snap ratio to snap to it
+pub const SNAP_TOLERANCE: f32 = 0.02;
+
+// Smallest share of a split either side can be dragged down to
+const MIN_RATIO: f32 = 0.05;
+
 /// Direction to split a window
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SplitDirection {
@@ -9,6 +18,15 @@ pub enum SplitDirection {
     Vertical,   // Split vertically (one above the other)
 }
 
+/// Arrangements the windows of a layout can be put in at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutPreset {
+    Grid,
+    Horizontal, // All side by side
+    Vertical,   // All one above the other
+    Main,       // The first window large on the left, the others stacked on the right
+}
+
 /// A node in the layout tree
 #[derive(Debug, Clone)]
 pub enum LayoutNode {
@@ -21,6 +39,49 @@ pub enum LayoutNode {
     },
 }
 
+/// The divider between the two sides of a split, as last laid out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divider {
+    // The side taken at each split on the way from the root, true for the second
+    path: Vec<bool>,
+    direction: SplitDirection,
+    // The area the split divides
+    area: Rectangle,
+    // Where the divider is: an x for side by side splits, a y for stacked ones
+    position: f32,
+}
+
+impl Divider {
+    /// The ratio that puts the divider at a point
+    pub fn ratio_at(&self, point: Point) -> f32 {
+        let ratio = match self.direction {
+            SplitDirection::Horizontal => (point.x - self.area.x) / self.area.width,
+            SplitDirection::Vertical => (point.y - self.area.y) / self.area.height,
+        };
+        ratio.clamp(MIN_RATIO, 1.0 - MIN_RATIO)
+    }
+    
+    /// Whether a point is within `tolerance` of the divider
+    fn is_near(&self, point: Point, tolerance: f32) -> bool {
+        let a = self.area;
+        match self.direction {
+            SplitDirection::Horizontal => {
+                (point.x - self.position).abs() <= tolerance && point.y >= a.y && point.y <= a.y + a.height
+            },
+            SplitDirection::Vertical => {
+                (point.y - self.position).abs() <= tolerance && point.x >= a.x && point.x <= a.x + a.width
+            },
+        }
+    }
+}
+
+/// The snap ratio within `SNAP_TOLERANCE` of a ratio, or the ratio itself
+pub fn snap(ratio: f32) -> f32 {
+    SNAP_RATIOS.into_iter()
+        .find(|snap| (ratio - snap).abs() <= SNAP_TOLERANCE)
+        .unwrap_or(ratio)
+}
+
 /// Layout manager that organizes windows in a tree structure
 pub struct LayoutManager {
     // The root of the layout tree
@@ -34,6 +95,10 @@ pub struct LayoutManager {
     
     // Calculated rectangles for each window
     window_rects: HashMap<Uuid, Rectangle>,
+    
+    // The area last laid out, and the dividers of its splits
+    area: Rectangle,
+    dividers: Vec<Divider>,
 }
 
 impl LayoutManager {
@@ -44,6 +109,8 @@ impl LayoutManager {
             pre_zoom_layout: None,
             zoomed_window: None,
             window_rects: HashMap::new(),
+            area: Rectangle::default(),
+            dividers: Vec::new(),
         }
     }
     
@@ -218,15 +285,18 @@ impl LayoutManager {
     pub fn calculate_layout(&mut self, area: Rectangle) {
         // Clear the current layout
         self.window_rects.clear();
+        self.dividers.clear();
+        self.area = area;
         
         // Calculate the layout if we have a root
-        if let Some(root) = &self.root {
-            self.calculate_node_layout(root, area);
+        if let Some(root) = self.root.take() {
+            self.calculate_node_layout(&root, area, &mut Vec::new());
+            self.root = Some(root);
         }
     }
     
-    /// Helper to calculate layout for a node
-    fn calculate_node_layout(&mut self, node: &LayoutNode, area: Rectangle) {
+    /// Helper to calculate layout for a node; `path` leads to it from the root
+    fn calculate_node_layout(&mut self, node: &LayoutNode, area: Rectangle, path: &mut Vec<bool>) {
         match node {
             LayoutNode::Window(id) => {
                 // Store the rectangle for this window
@@ -279,13 +349,29 @@ impl LayoutManager {
                     },
                 };
                 
+                // Remember where the divider is, for dragging it
+                let position = match direction {
+                    SplitDirection::Horizontal => second_area.x,
+                    SplitDirection::Vertical => second_area.y,
+                };
+                self.dividers.push(Divider { path: path.clone(), direction: *direction, area, position });
+                
                 // Calculate layout for the children
-                self.calculate_node_layout(first, first_area);
-                self.calculate_node_layout(second, second_area);
+                path.push(false);
+                self.calculate_node_layout(first, first_area, path);
+                path.pop();
+                path.push(true);
+                self.calculate_node_layout(second, second_area, path);
+                path.pop();
             }
         }
     }
     
+    /// The layout tree, to draw the windows in
+    pub fn root(&self) -> Option<&LayoutNode> {
+        self.root.as_ref()
+    }
+    
     /// Get the rectangle for a window
     pub fn get_window_rect(&self, window_id: &Uuid) -> Option<Rectangle> {
         self.window_rects.get(window_id).copied()
@@ -296,8 +382,84 @@ impl LayoutManager {
         &self.window_rects
     }
     
+    /// The divider within `tolerance` of a point, innermost first
+    pub fn divider_at(&self, point: Point, tolerance: f32) -> Option<Divider> {
+        self.dividers.iter().rev().find(|divider| divider.is_near(point, tolerance)).cloned()
+    }
+    
+    /// Move a divider to a ratio of its split and lay the windows out again
+    pub fn set_ratio(&mut self, divider: &Divider, new_ratio: f32) {
+        let mut node = self.root.as_mut();
+        for &second_side in &divider.path {
+            node = match node {
+                Some(LayoutNode::Split { first, second, .. }) => {
+                    Some(if second_side { &mut **second } else { &mut **first })
+                },
+                _ => None,
+            };
+        }
+        if let Some(LayoutNode::Split { ratio, .. }) = node {
+            *ratio = new_ratio.clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        }
+        self.calculate_layout(self.area);
+    }
+    
     /// Check if a window is zoomed
     pub fn is_zoomed(&self, window_id: &Uuid) -> bool {
         self.zoomed_window == Some(*window_id)
     }
+    
+    /// Replace the layout with the windows arranged in a preset, in the order given
+    pub fn arrange(&mut self, preset: LayoutPreset, windows: &[Uuid]) {
+        if windows.is_empty() {
+            return;
+        }
+        
+        // A preset takes the place of any zoom
+        self.pre_zoom_layout = None;
+        self.zoomed_window = None;
+        
+        let leaves = |ids: &[Uuid]| ids.iter().map(|id| LayoutNode::Window(*id)).collect::<Vec<_>>();
+        self.root = match preset {
+            LayoutPreset::Horizontal => even_split(leaves(windows), SplitDirection::Horizontal),
+            LayoutPreset::Vertical => even_split(leaves(windows), SplitDirection::Vertical),
+            LayoutPreset::Grid => {
+                // As many rows as columns, or one fewer
+                let rows = (windows.len() as f32).sqrt().ceil() as usize;
+                let columns = windows.len().div_ceil(rows);
+                let rows = windows.chunks(columns)
+                    .filter_map(|row| even_split(leaves(row), SplitDirection::Horizontal))
+                    .collect();
+                even_split(rows, SplitDirection::Vertical)
+            },
+            LayoutPreset::Main => {
+                let main = LayoutNode::Window(windows[0]);
+                match even_split(leaves(&windows[1..]), SplitDirection::Vertical) {
+                    Some(stack) => Some(LayoutNode::Split {
+                        direction: SplitDirection::Horizontal,
+                        ratio: 0.7,
+                        first: Box::new(main),
+                        second: Box::new(stack),
+                    }),
+                    None => Some(main),
+                }
+            },
+        };
+        self.calculate_layout(self.area);
+    }
+}
+
+/// Nodes split in one direction, each given the same share
+fn even_split(nodes: Vec<LayoutNode>, direction: SplitDirection) -> Option<LayoutNode> {
+    let count = nodes.len();
+    nodes.into_iter().enumerate().rev().reduce(|(_, second), (index, first)| {
+        // The first of the nodes left takes its share of them
+        let node = LayoutNode::Split {
+            direction,
+            ratio: 1.0 / (count - index) as f32,
+            first: Box::new(first),
+            second: Box::new(second),
+        };
+        (index, node)
+    }).map(|(_, node)| node)
 }
\ No newline at end of file
@@ -3,34 +3,170 @@ pub mod terminal;
 pub mod layout;
 pub mod styles;
 pub mod utils;
+pub mod settings;
+pub mod actions;
+pub mod window_state;
+#[cfg(target_os = "macos")]
+pub mod menu;
+#[cfg(feature = "tray")]
+pub mod tray;
 
 use iced::{
-    executor, keyboard, mouse, Application, Color, Command, Element, Event, Length, 
-    Renderer, Subscription, Theme
+    executor, keyboard, mouse, multi_window::Application, window, Color, Command, Element, Event, Length,
+    Point, Rectangle, Size, Subscription, Theme
 };
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::components::sidebar::{Sidebar, SidebarMessage};
+use crate::actions::Action;
+use crate::components::command_line::{self, CommandLineMessage};
+use crate::components::floating::Floating;
+use crate::components::pane_menu::{PaneMenu, PaneMenuMessage, TITLE_BAR_HEIGHT};
+use crate::components::sidebar::{Sidebar, SidebarIcon, SidebarMessage};
+use crate::components::title_bar::{self, TitleBarMessage};
 use crate::terminal::window::{TerminalWindow, TerminalMessage};
-use crate::layout::manager::{LayoutManager, LayoutNode, SplitDirection};
-use crate::styles::theme::matrix_theme;
+use crate::layout::manager::{self, Divider, LayoutManager, LayoutNode, LayoutPreset, SplitDirection};
+use crate::settings::GuiSettings;
+use crate::styles::colors;
+use crate::styles::theme::{matrix_theme, theme_by_name, AppBackgroundStyle};
+use crate::utils::speech::Announcer;
+use crate::window_state::{self as geometry, WindowState};
 
-/// Main application state
-pub struct MatrixApp {
-    // Window management
-    windows: HashMap<Uuid, TerminalWindow>,
+/// How far from a divider, in pixels, pressing the mouse starts dragging it
+const DIVIDER_GRAB: f32 = 4.0;
+
+/// A split's divider being dragged with the mouse
+struct SplitDrag {
+    // The OS window it's in
+    window: window::Id,
+    divider: Divider,
+    // The ratio it's at, shown next to the cursor
+    ratio: f32,
+}
+
+/// A native OS window with its own layout of terminal windows
+pub struct OsWindow {
+    // The iced window this is shown in
+    id: window::Id,
+    
+    // Terminal windows shown in this OS window and how they are laid out
+    panes: Vec<Uuid>,
     layout_manager: LayoutManager,
     
     // UI components
     sidebar: Sidebar,
     
-    // Application state
+    // Focus and zoom within this OS window
     focused_window: Option<Uuid>,
     is_zoomed: bool,
     
+    // Scale factor of the monitor the OS window is on, measured as its physical
+    // size over its logical size (iced has no event for it)
+    scale_factor: f32,
+    size: Size,
+    measuring_scale: bool,
+    
+    // Shown fullscreen, without the borderless title bar
+    fullscreen: bool,
+}
+
+impl OsWindow {
+    /// Create an empty OS window
+    fn new(id: window::Id) -> Self {
+        Self {
+            id,
+            panes: Vec::new(),
+            layout_manager: LayoutManager::new(),
+            sidebar: Sidebar::new(),
+            focused_window: None,
+            is_zoomed: false,
+            scale_factor: 1.0,
+            size: Size::new(800.0, 600.0),
+            measuring_scale: false,
+            fullscreen: false,
+        }
+    }
+    
+    /// Add a terminal window to the layout and focus it
+    fn add_pane(&mut self, window_id: Uuid) {
+        self.panes.push(window_id);
+        self.layout_manager.add_window(window_id);
+        self.focused_window = Some(window_id);
+    }
+    
+    /// Add a terminal window as a split next to another one and focus it
+    fn add_pane_beside(&mut self, window_id: Uuid, beside: &Uuid) {
+        self.panes.push(window_id);
+        self.layout_manager.split_window(beside, SplitDirection::Horizontal, window_id, 0.5);
+        self.focused_window = Some(window_id);
+    }
+    
+    /// Remove a terminal window from the layout
+    fn remove_pane(&mut self, window_id: &Uuid) {
+        self.panes.retain(|id| id != window_id);
+        self.layout_manager.remove_window(window_id);
+        
+        if self.focused_window == Some(*window_id) {
+            self.focused_window = self.panes.last().cloned();
+        }
+    }
+}
+
+/// Main application state
+pub struct MatrixApp {
+    // Every terminal window, whichever OS window shows it
+    windows: HashMap<Uuid, TerminalWindow>,
+    
+    // OS windows, in the order they were opened
+    os_windows: Vec<OsWindow>,
+    
+    // The OS window that last had focus
+    focused_os_window: window::Id,
+    
+    // Command being typed in the focused OS window's command line, while it's open
+    command_line: Option<String>,
+    
     // Theming
     theme: Theme,
+    
+    // GUI settings (background opacity)
+    settings: GuiSettings,
+    
+    // Speech output for screen reader users
+    announcer: Announcer,
+    
+    // Native menu bar
+    #[cfg(target_os = "macos")]
+    menu: Option<menu::MenuBar>,
+    
+    // Layout of the last OS window, kept with its terminal windows running in background mode
+    background: Option<OsWindow>,
+    
+    // Tray icon and global hotkey that bring background terminal windows back
+    #[cfg(feature = "tray")]
+    tray: Option<tray::Tray>,
+    
+    // Terminal window under the mouse and since when, to focus it after a delay
+    hover_focus: Option<(Uuid, Instant)>,
+    
+    // A mouse button is held down, so hovering doesn't move the focus
+    mouse_dragging: bool,
+    
+    // Where the mouse is in the focused OS window
+    cursor_position: Point,
+    
+    // A split's divider being dragged
+    split_drag: Option<SplitDrag>,
+    
+    // Modifier keys held; Alt turns snapping off while dragging a divider
+    modifiers: keyboard::Modifiers,
+    
+    // Context menu opened on a terminal window's title bar
+    pane_menu: Option<PaneMenu>,
+    
+    // Geometry of the OS window last moved or resized, saved on exit
+    window_state: WindowState,
 }
 
 /// Messages that can be sent to the application
@@ -47,56 +183,486 @@ pub enum Message {
     CloseWindow(Uuid),
     FocusWindow(Uuid),
     
+    // OS window management
+    NewOsWindow,
+    OsWindowClosed(window::Id),
+    MoveToWindow(Uuid, MoveTarget),
+    
+    // An OS window moved to a monitor with another scale factor
+    ScaleFactorMeasured(window::Id, Size<u32>),
+    ScaleFactorChanged(window::Id, f32),
+    
+    // An OS window moved or was resized, with its mode and then whether it's maximized, fetched after
+    WindowMode(window::Id, geometry::Change, window::Mode),
+    WindowChanged(geometry::Change, bool),
+    
+    // Raise Matrix, reopening the OS window left in the background
+    QuickAttach,
+    
+    // Close every terminal window and exit
+    Quit,
+    
     // Layout messages
     SplitWindow(Uuid, SplitDirection),
     ZoomToggle(Uuid),
     
     // UI component messages
     Sidebar(SidebarMessage),
+    PaneMenu(PaneMenuMessage),
+    TitleBar(window::Id, TitleBarMessage),
+    CommandLine(CommandLineMessage),
+    
+    // A typed command, e.g. ":move-to-window 2"
+    RunCommand(String),
+    
+    // An action from a keyboard shortcut or the menu bar
+    Action(Action),
+    
+    // Clipboard contents read for pasting
+    Paste(Option<String>),
+    
+    // Check the system's appearance, and the result: dark or not (None if unknown)
+    CheckAppearance,
+    AppearanceChecked(Option<bool>),
     
     // System messages
     Tick,
 }
 
+/// Where `:move-to-window` moves a terminal window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveTarget {
+    // An open OS window, numbered from 1 in the order they were opened
+    Number(usize),
+    // A new OS window
+    New,
+}
+
+impl MoveTarget {
+    /// Parse the argument of `:move-to-window`
+    pub fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "new" => Some(MoveTarget::New),
+            number => number.parse().ok().filter(|n| *n > 0).map(MoveTarget::Number),
+        }
+    }
+}
+
+/// A terminal window named by `:join`, as `<os window>:<index>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaneRef {
+    // An open OS window, numbered from 1 in the order they were opened
+    pub os_window: usize,
+    // A terminal window in it, numbered from 1 in the order they were added
+    pub pane: usize,
+}
+
+impl PaneRef {
+    /// Parse the argument of `:join`
+    pub fn parse(arg: &str) -> Option<Self> {
+        let (os_window, pane) = arg.split_once(':')?;
+        let number = |s: &str| s.parse().ok().filter(|n: &usize| *n > 0);
+        Some(PaneRef {
+            os_window: number(os_window)?,
+            pane: number(pane)?,
+        })
+    }
+}
+
+impl MatrixApp {
+    /// The OS window showing a terminal window
+    fn os_window_of(&mut self, window_id: &Uuid) -> Option<&mut OsWindow> {
+        self.os_windows.iter_mut().find(|w| w.panes.contains(window_id))
+    }
+    
+    /// An OS window by its iced id
+    fn os_window(&self, id: window::Id) -> Option<&OsWindow> {
+        self.os_windows.iter().find(|w| w.id == id)
+    }
+    
+    /// The OS window that has focus
+    fn focused_os_window(&mut self) -> Option<&mut OsWindow> {
+        let id = self.focused_os_window;
+        self.os_windows.iter_mut().find(|w| w.id == id)
+    }
+    
+    /// The focused terminal window of the focused OS window
+    fn focused_window(&self) -> Option<Uuid> {
+        self.os_window(self.focused_os_window).and_then(|w| w.focused_window)
+    }
+    
+    /// Open a new OS window, returning its id and the command that opens it
+    fn open_os_window(&mut self) -> (window::Id, Command<Message>) {
+        let (id, spawn) = window::spawn(window_settings(self.settings.borderless));
+        self.os_windows.push(OsWindow::new(id));
+        self.focused_os_window = id;
+        
+        (id, spawn)
+    }
+    
+    /// Give a new terminal window the look of the default profile
+    fn apply_default_profile(&self, terminal_window: &mut TerminalWindow) {
+        let profile = self.settings.default_profile.as_deref().and_then(|name| self.settings.profile(name));
+        if let Some(profile) = profile {
+            terminal_window.set_profile(profile);
+        }
+    }
+    
+    /// Create a terminal window with a running shell in an OS window
+    fn create_pane(&mut self, os_window: window::Id, title: &str) -> Command<Message> {
+        let window_id = Uuid::new_v4();
+        let mut terminal_window = TerminalWindow::new(window_id, title);
+        self.apply_default_profile(&mut terminal_window);
+        if let Some(target) = self.os_window(os_window) {
+            let _ = terminal_window.update(TerminalMessage::ScaleFactor(target.scale_factor));
+        }
+        
+        // Start the terminal process
+        let command = terminal_window.spawn_shell();
+        
+        // Add window to our collection
+        self.windows.insert(window_id, terminal_window);
+        
+        if let Some(target) = self.os_windows.iter_mut().find(|w| w.id == os_window) {
+            target.add_pane(window_id);
+        }
+        
+        command.map(move |msg| Message::Terminal(window_id, msg))
+    }
+    
+    /// Save the OS window's geometry for the next start
+    fn save_window_state(&self) {
+        if let Err(e) = self.window_state.save() {
+            log::warn!("Error saving the window state: {}", e);
+        }
+    }
+    
+    /// Save the GUI settings after a change
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            log::warn!("Error saving GUI settings: {}", e);
+        }
+    }
+    
+    /// Move a terminal window into another OS window, keeping its process running
+    fn move_to_window(&mut self, window_id: Uuid, target: MoveTarget) -> Command<Message> {
+        let Some(source) = self.os_window_of(&window_id).map(|w| w.id) else {
+            return Command::none();
+        };
+        
+        let (target_id, command) = match target {
+            MoveTarget::New => self.open_os_window(),
+            MoveTarget::Number(n) => match self.os_windows.get(n - 1) {
+                Some(w) if w.id != source => (w.id, Command::none()),
+                _ => return Command::none(),
+            },
+        };
+        
+        self.transfer_pane(window_id, source, target_id, None, command)
+    }
+    
+    /// Arrange the terminal windows of the focused OS window in a preset layout
+    fn arrange(&mut self, preset: LayoutPreset) {
+        let Some(os_window) = self.focused_os_window() else {
+            return;
+        };
+        
+        // The main layout gives the focused terminal window the large side
+        let mut panes = os_window.panes.clone();
+        if let Some(index) = os_window.focused_window.and_then(|id| panes.iter().position(|pane| *pane == id)) {
+            let focused = panes.remove(index);
+            panes.insert(0, focused);
+        }
+        os_window.is_zoomed = false;
+        os_window.layout_manager.arrange(preset, &panes);
+    }
+    
+    /// Move a terminal window out into a new OS window of its own
+    fn break_pane(&mut self, window_id: Uuid) -> Command<Message> {
+        // Breaking out the only terminal window would just swap OS windows
+        let alone = self.os_window_of(&window_id).map_or(true, |w| w.panes.len() < 2);
+        if alone {
+            log::warn!("Only terminal window in its OS window; nothing to break out");
+            return Command::none();
+        }
+        self.move_to_window(window_id, MoveTarget::New)
+    }
+    
+    /// Pull a terminal window from another OS window into the focused one, split beside its focused terminal window
+    fn join_pane(&mut self, pane: PaneRef) -> Command<Message> {
+        let Some((source, window_id)) = self.os_windows.get(pane.os_window - 1)
+            .and_then(|w| Some((w.id, *w.panes.get(pane.pane - 1)?))) else {
+            log::warn!("No terminal window {}:{} to join", pane.os_window, pane.pane);
+            return Command::none();
+        };
+        let target_id = self.focused_os_window;
+        if source == target_id {
+            log::warn!("Terminal window {}:{} is already in this OS window", pane.os_window, pane.pane);
+            return Command::none();
+        }
+        
+        let beside = self.focused_window();
+        self.transfer_pane(window_id, source, target_id, beside, Command::none())
+    }
+    
+    /// Take a terminal window from one OS window into another, beside a terminal window there or wherever the layout puts it
+    fn transfer_pane(&mut self, window_id: Uuid, source: window::Id, target_id: window::Id, beside: Option<Uuid>, command: Command<Message>) -> Command<Message> {
+        if let Some(source_window) = self.os_windows.iter_mut().find(|w| w.id == source) {
+            source_window.remove_pane(&window_id);
+        }
+        if let Some(target_window) = self.os_windows.iter_mut().find(|w| w.id == target_id) {
+            match beside {
+                Some(beside) => target_window.add_pane_beside(window_id, &beside),
+                None => target_window.add_pane(window_id),
+            }
+        }
+        self.focused_os_window = target_id;
+        
+        // The terminal window takes on the DPI of the monitor it's now shown on
+        let scale = self.os_window(target_id).map_or(1.0, |w| w.scale_factor);
+        let command = Command::batch([command, self.update(Message::Terminal(window_id, TerminalMessage::ScaleFactor(scale)))]);
+        
+        // An OS window left without terminal windows closes
+        let source_empty = self.os_window(source).map_or(false, |w| w.panes.is_empty());
+        if source_empty {
+            self.os_windows.retain(|w| w.id != source);
+            Command::batch([command, window::close(source)])
+        } else {
+            command
+        }
+    }
+    
+    /// Perform an action on the focused OS window and terminal window
+    fn dispatch(&mut self, action: Action) -> Command<Message> {
+        let focused = self.focused_window();
+        let message = match (action, focused) {
+            (Action::NewWindow, _) => Message::CreateWindow,
+            (Action::NewOsWindow, _) => Message::NewOsWindow,
+            (Action::CloseWindow, Some(id)) => Message::CloseWindow(id),
+            (Action::RenameWindow, Some(id)) => {
+                // Rename in the title bar's menu, opened at the top left of the terminal window
+                let position = self.os_window(self.focused_os_window)
+                    .and_then(|w| w.layout_manager.get_window_rects().get(&id))
+                    .map_or(Point::ORIGIN, |rect| Point::new(rect.x, rect.y + TITLE_BAR_HEIGHT));
+                let title = self.windows.get(&id).map_or("", |w| w.title());
+                self.pane_menu = Some(PaneMenu::renaming(id, position, title));
+                return Command::none();
+            },
+            (Action::CommandLine, _) => {
+                self.command_line = Some(String::new());
+                return iced::widget::text_input::focus(command_line::input_id());
+            },
+            (Action::Copy, Some(id)) => {
+                return match self.windows.get(&id).and_then(|w| w.selected_text()) {
+                    Some(text) => iced::clipboard::write(text),
+                    None => Command::none(),
+                };
+            },
+            (Action::Paste, Some(_)) => return iced::clipboard::read(Message::Paste),
+            (Action::SplitHorizontal, Some(id)) => Message::SplitWindow(id, SplitDirection::Horizontal),
+            (Action::SplitVertical, Some(id)) => Message::SplitWindow(id, SplitDirection::Vertical),
+            (Action::ToggleZoom, Some(id)) => Message::ZoomToggle(id),
+            (Action::ToggleSidebar, _) => {
+                if let Some(os_window) = self.focused_os_window() {
+                    os_window.sidebar.toggle();
+                }
+                return Command::none();
+            },
+            (Action::ToggleTransparency, _) => Message::RunCommand(String::from("transparency")),
+            (Action::ToggleFullscreen, _) => Message::RunCommand(String::from("fullscreen")),
+            (Action::ToggleBorderless, _) => Message::RunCommand(String::from("borderless")),
+            (Action::FocusNext | Action::FocusPrevious, Some(id)) => {
+                let Some(os_window) = self.focused_os_window() else {
+                    return Command::none();
+                };
+                let count = os_window.panes.len();
+                let Some(index) = os_window.panes.iter().position(|pane| *pane == id) else {
+                    return Command::none();
+                };
+                let next = if action == Action::FocusNext {
+                    (index + 1) % count
+                } else {
+                    (index + count - 1) % count
+                };
+                Message::FocusWindow(os_window.panes[next])
+            },
+            (Action::MoveToNewOsWindow, Some(id)) => Message::MoveToWindow(id, MoveTarget::New),
+            // Nothing to act on
+            (_, None) => return Command::none(),
+        };
+        
+        self.update(message)
+    }
+    
+    /// Act on what was chosen from a terminal window's context menu
+    fn pane_menu_chosen(&mut self, message: PaneMenuMessage) -> Command<Message> {
+        let Some(menu) = &mut self.pane_menu else {
+            return Command::none();
+        };
+        let id = menu.window;
+        
+        match message {
+            PaneMenuMessage::StartRename => {
+                let title = self.windows.get(&id).map_or("", |w| w.title());
+                menu.start_rename(title);
+                return Command::none();
+            },
+            PaneMenuMessage::RenameInput(title) => {
+                menu.set_rename(title);
+                return Command::none();
+            },
+            _ => {}
+        }
+        
+        let menu = self.pane_menu.take();
+        match message {
+            PaneMenuMessage::Action(action) => {
+                // Actions apply to the focused terminal window, so focus this one first
+                if let Some(os_window) = self.os_window_of(&id) {
+                    os_window.focused_window = Some(id);
+                    self.focused_os_window = os_window.id;
+                }
+                self.dispatch(action)
+            },
+            PaneMenuMessage::Rename => {
+                let title = menu.as_ref().and_then(|m| m.new_title()).map(str::to_string);
+                if let (Some(window), Some(title)) = (self.windows.get_mut(&id), title) {
+                    window.set_title(&title);
+                }
+                Command::none()
+            },
+            PaneMenuMessage::MoveTo(target) => self.move_to_window(id, target),
+            PaneMenuMessage::Profile(name) => {
+                if let (Some(window), Some(profile)) = (self.windows.get_mut(&id), self.settings.profiles.get(&name)) {
+                    window.set_profile(profile);
+                }
+                Command::none()
+            },
+            _ => Command::none(),
+        }
+    }
+    
+    /// Enable, disable and check menu items to match the current layout
+    #[cfg(target_os = "macos")]
+    fn update_menu(&self) {
+        let Some(menu) = &self.menu else {
+            return;
+        };
+        let os_window = self.os_window(self.focused_os_window);
+        let focused = self.focused_window();
+        
+        menu.update(&menu::MenuState {
+            has_window: focused.is_some(),
+            has_selection: focused
+                .and_then(|id| self.windows.get(&id))
+                .is_some_and(|w| w.selected_text().is_some()),
+            has_splits: os_window.is_some_and(|w| w.panes.len() > 1),
+            zoomed: os_window.is_some_and(|w| w.is_zoomed),
+            sidebar: os_window.is_some_and(|w| w.sidebar.is_visible()),
+            transparency: self.settings.transparency,
+            borderless: self.settings.borderless,
+        });
+    }
+}
+
+/// Settings of every OS window
+fn window_settings(borderless: bool) -> window::Settings {
+    window::Settings {
+        // Borderless windows draw their own title bar
+        decorations: !borderless,
+        size: iced::Size::new(800.0, 600.0),
+        min_size: Some(iced::Size::new(400.0, 300.0)),
+        // Closing a window closes only its terminal windows
+        exit_on_close_request: false,
+        // Needed for background opacity, which can be toggled at any time
+        transparent: true,
+        ..window::Settings::default()
+    }
+}
+
 impl Application for MatrixApp {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = ();
-
-    fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        // Create a default layout manager
-        let layout_manager = LayoutManager::new();
+    // The window state saved by the last run, if any
+    type Flags = Option<WindowState>;
+    
+    fn new(flags: Self::Flags) -> (Self, Command<Message>) {
+        // The main window is opened by iced itself
+        let main_window = OsWindow::new(window::Id::MAIN);
         
-        // Create a sidebar
-        let sidebar = Sidebar::new();
+        let settings = GuiSettings::load().unwrap_or_else(|e| {
+            log::warn!("Error loading GUI settings: {}", e);
+            GuiSettings::default()
+        });
+        let theme = theme_by_name(&settings.theme).unwrap_or_else(|| {
+            log::warn!("Unknown theme {:?}", settings.theme);
+            matrix_theme()
+        });
         
         // Create the initial application state
-        let app = MatrixApp {
+        #[allow(unused_mut)]
+        let mut app = MatrixApp {
             windows: HashMap::new(),
-            layout_manager,
-            sidebar,
-            focused_window: None,
-            is_zoomed: false,
-            theme: matrix_theme(),
+            os_windows: vec![main_window],
+            focused_os_window: window::Id::MAIN,
+            command_line: None,
+            theme,
+            announcer: Announcer::new(&settings.accessibility),
+            settings,
+            #[cfg(target_os = "macos")]
+            menu: menu::MenuBar::install()
+                .map_err(|e| log::warn!("Error creating the menu bar: {}", e))
+                .ok(),
+            background: None,
+            #[cfg(feature = "tray")]
+            tray: None,
+            hover_focus: None,
+            mouse_dragging: false,
+            cursor_position: Point::ORIGIN,
+            split_drag: None,
+            modifiers: keyboard::Modifiers::default(),
+            pane_menu: None,
+            window_state: flags.unwrap_or_default(),
         };
         
+        // The tray icon is shown from the start so background mode can be used at any time
+        #[cfg(feature = "tray")]
+        {
+            app.tray = tray::Tray::install(app.settings.quick_attach_hotkey.as_deref())
+                .map_err(|e| log::warn!("Error creating the tray icon: {}", e))
+                .ok();
+        }
+        
         // Command to create an initial window
-        let command = Command::perform(
+        let mut command = Command::perform(
             async { },
             |_| Message::CreateWindow
         );
         
+        // Start in the system's appearance rather than after the first check
+        if app.settings.follow_system_appearance {
+            command = Command::batch([command, Command::perform(async {}, |_| Message::CheckAppearance)]);
+        }
+        
+        // Maximize the main window or make it fullscreen again, as it was when Matrix last exited
+        let restore = app.window_state.restore(window::Id::MAIN);
+        command = Command::batch(std::iter::once(command).chain(restore));
+        
+        // The main window is opened with decorations, which borderless mode takes off
+        if app.settings.borderless {
+            command = Command::batch([command, window::toggle_decorations(window::Id::MAIN)]);
+        }
+        
         (app, command)
     }
     
-    fn title(&self) -> String {
+    fn title(&self, window: window::Id) -> String {
         let title = "Matrix Terminal";
         
-        if let Some(id) = self.focused_window {
-            if let Some(window) = self.windows.get(&id) {
-                return format!("{} - {}", title, window.title());
-            }
+        let focused = self.os_window(window).and_then(|w| w.focused_window);
+        if let Some(terminal) = focused.and_then(|id| self.windows.get(&id)) {
+            return format!("{} - {}", title, terminal.title());
         }
         
         title.to_string()
@@ -106,37 +672,410 @@ impl Application for MatrixApp {
         match message {
             Message::Event(event) => {
                 match event {
+                    Event::Keyboard(keyboard::Event::KeyPressed { key: keyboard::Key::Named(keyboard::key::Named::Escape), .. })
+                        if self.pane_menu.is_some() =>
+                    {
+                        self.pane_menu = None;
+                        Command::none()
+                    },
                     Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
-                        // Handle keyboard shortcuts
-                        // TODO: Implement keyboard shortcuts
+                        // Application shortcuts (Cmd on macOS, Ctrl+Shift elsewhere)
+                        if let Some(action) = Action::from_shortcut(&key, modifiers) {
+                            return self.dispatch(action);
+                        }
+                        
+                        // Other keys go to the focused terminal window, encoded as its application asked
+                        let Some((id, terminal)) = self.focused_window().and_then(|id| Some((id, self.windows.get(&id)?))) else {
+                            return Command::none();
+                        };
+                        match utils::keyboard::key_to_terminal_input(&key, modifiers, terminal.keyboard_mode()) {
+                            Some(input) => self.update(Message::Terminal(id, TerminalMessage::Input(input))),
+                            None => Command::none(),
+                        }
+                    },
+                    Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                        self.modifiers = modifiers;
                         Command::none()
                     },
                     Event::Mouse(mouse::Event::CursorMoved { position, .. }) => {
-                        // Handle mouse movement
-                        self.sidebar.handle_hover(position);
+                        self.cursor_position = position;
+                        
+                        // Move the divider being dragged, snapping to the preset ratios unless Alt is held
+                        if let Some(drag) = &mut self.split_drag {
+                            let ratio = drag.divider.ratio_at(position);
+                            drag.ratio = if self.modifiers.alt() { ratio } else { manager::snap(ratio) };
+                            let window = drag.window;
+                            if let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == window) {
+                                os_window.layout_manager.set_ratio(&drag.divider, drag.ratio);
+                            }
+                            return Command::none();
+                        }
+                        
+                        // Handle mouse movement (in the window under the cursor, which has focus)
+                        if let Some(os_window) = self.focused_os_window() {
+                            os_window.sidebar.handle_hover(position);
+                        }
+                        
+                        // Start the delay before focusing the terminal window under the mouse
+                        if self.settings.focus_follows_mouse && !self.mouse_dragging {
+                            let hovered = self.os_window(self.focused_os_window).and_then(|w| {
+                                w.layout_manager.get_window_rects().iter()
+                                    .find(|(_, rect)| rect.contains(position))
+                                    .map(|(id, _)| *id)
+                                    .filter(|id| w.focused_window != Some(*id))
+                            });
+                            match hovered {
+                                Some(id) if self.hover_focus.is_some_and(|(pending, _)| pending == id) => {}
+                                Some(id) => self.hover_focus = Some((id, Instant::now())),
+                                None => self.hover_focus = None,
+                            }
+                        }
                         Command::none()
                     },
+                    Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                        // Right-clicking a terminal window's title bar opens its menu
+                        let position = self.cursor_position;
+                        self.pane_menu = self.os_window(self.focused_os_window).and_then(|w| {
+                            w.layout_manager.get_window_rects().iter()
+                                .find(|(_, rect)| rect.contains(position) && position.y < rect.y + TITLE_BAR_HEIGHT)
+                                .map(|(id, _)| PaneMenu::new(*id, position))
+                        });
+                        Command::none()
+                    },
+                    Event::Mouse(mouse::Event::ButtonPressed(button)) => {
+                        // Clicking anywhere but the menu closes it
+                        self.pane_menu = None;
+                        
+                        // Pressing on a divider between terminal windows starts dragging it
+                        if button == mouse::Button::Left {
+                            let position = self.cursor_position;
+                            let window = self.focused_os_window;
+                            self.split_drag = self.os_window(window)
+                                .and_then(|w| w.layout_manager.divider_at(position, DIVIDER_GRAB))
+                                .map(|divider| SplitDrag { window, ratio: divider.ratio_at(position), divider });
+                        }
+                        
+                        // Selecting or dragging across windows keeps the focus where it is
+                        self.mouse_dragging = true;
+                        self.hover_focus = None;
+                        Command::none()
+                    },
+                    Event::Mouse(mouse::Event::ButtonReleased(_)) => {
+                        self.mouse_dragging = false;
+                        self.split_drag = None;
+                        Command::none()
+                    },
+                    Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                        // Scroll the focused terminal window's history, up for positive lines
+                        let lines = match delta {
+                            mouse::ScrollDelta::Lines { y, .. } => (y * self.settings.scroll_lines.max(1) as f32).round() as i32,
+                            mouse::ScrollDelta::Pixels { y, .. } => (y / 16.0).round() as i32,
+                        };
+                        let lines = if self.settings.natural_scrolling { -lines } else { lines };
+                        match self.focused_window() {
+                            Some(id) if lines != 0 => self.update(Message::Terminal(id, TerminalMessage::Scroll(lines))),
+                            _ => Command::none(),
+                        }
+                    },
+                    Event::Window(id, window::Event::Opened { size, .. }) => {
+                        if let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == id) {
+                            os_window.size = size;
+                        }
+                        self.measure_scale_factor(id)
+                    },
+                    Event::Window(id, window::Event::Resized { width, height }) => {
+                        // Terminal windows are laid out right of the sidebar, and below the title bar of borderless windows
+                        let borderless = self.settings.borderless;
+                        if let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == id) {
+                            os_window.size = Size::new(width as f32, height as f32);
+                            let sidebar = os_window.sidebar.width();
+                            let top = if borderless && !os_window.fullscreen { title_bar::HEIGHT } else { 0.0 };
+                            os_window.layout_manager.calculate_layout(Rectangle {
+                                x: sidebar,
+                                y: top,
+                                width: (width as f32 - sidebar).max(0.0),
+                                height: (height as f32 - top).max(0.0),
+                            });
+                        }
+                        
+                        // Remember the size unless it's maximized or fullscreen, which are fetched to tell
+                        let change = geometry::Change::Resized(Size::new(width as f32, height as f32));
+                        // Moving to a monitor with another scale factor resizes the window too
+                        Command::batch([
+                            window::fetch_mode(id, move |mode| Message::WindowMode(id, change, mode)),
+                            self.measure_scale_factor(id),
+                        ])
+                    },
+                    Event::Window(id, window::Event::Moved { x, y }) => {
+                        let change = geometry::Change::Moved(Point::new(x as f32, y as f32));
+                        Command::batch([
+                            window::fetch_mode(id, move |mode| Message::WindowMode(id, change, mode)),
+                            self.measure_scale_factor(id),
+                        ])
+                    },
+                    Event::Window(id, window::Event::Focused) => {
+                        self.focused_os_window = id;
+                        Command::none()
+                    },
+                    Event::Window(id, window::Event::CloseRequested) => {
+                        Command::perform(async { }, move |_| Message::OsWindowClosed(id))
+                    },
                     _ => Command::none(),
                 }
             },
             
             Message::CreateWindow => {
-                let window_id = Uuid::new_v4();
-                let mut terminal_window = TerminalWindow::new(window_id, "New Terminal");
+                let os_window = self.focused_os_window;
+                self.create_pane(os_window, "New Terminal")
+            },
+            
+            Message::NewOsWindow => {
+                let (id, open) = self.open_os_window();
+                Command::batch([open, self.create_pane(id, "New Terminal")])
+            },
+            
+            Message::OsWindowClosed(id) => {
+                // Close the terminal windows shown in the OS window
+                let Some(index) = self.os_windows.iter().position(|w| w.id == id) else {
+                    return Command::none();
+                };
+                let closed = self.os_windows.remove(index);
                 
-                // Start the terminal process
-                let command = terminal_window.spawn_shell();
+                // In background mode the last OS window hides, its terminal windows keep running
+                if self.os_windows.is_empty() && self.settings.background_mode {
+                    self.save_window_state();
+                    self.background = Some(closed);
+                    return window::change_mode(id, window::Mode::Hidden);
+                }
                 
-                // Add window to our collection
-                self.windows.insert(window_id, terminal_window);
+                let mut commands: Vec<Command<Message>> = closed.panes.iter()
+                    .filter_map(|pane| self.windows.remove(pane).map(|t| (pane, t)))
+                    .map(|(pane, mut terminal)| {
+                        let pane = *pane;
+                        terminal.close().map(move |msg| Message::Terminal(pane, msg))
+                    })
+                    .collect();
+                commands.push(window::close(id));
                 
-                // Focus the new window
-                self.focused_window = Some(window_id);
+                // The application ends with its last OS window
+                match self.os_windows.first() {
+                    Some(next) => self.focused_os_window = next.id,
+                    None => self.save_window_state(),
+                }
                 
-                // Update the layout
-                self.layout_manager.add_window(window_id);
+                Command::batch(commands)
+            },
+            
+            Message::MoveToWindow(id, target) => self.move_to_window(id, target),
+            
+            Message::WindowMode(id, change, mode) => {
+                let fullscreen = mode == window::Mode::Fullscreen;
+                if let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == id) {
+                    os_window.fullscreen = fullscreen;
+                }
+                self.window_state.fullscreen = fullscreen;
+                window::fetch_maximized(id, move |maximized| Message::WindowChanged(change, maximized))
+            },
+            
+            Message::WindowChanged(change, maximized) => {
+                self.window_state.update(change, maximized);
+                Command::none()
+            },
+            
+            Message::ScaleFactorMeasured(id, physical) => {
+                let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == id) else {
+                    return Command::none();
+                };
+                os_window.measuring_scale = false;
+                match scale_factor(physical, os_window.size) {
+                    Some(scale) if scale != os_window.scale_factor => self.update(Message::ScaleFactorChanged(id, scale)),
+                    _ => Command::none(),
+                }
+            },
+            
+            Message::ScaleFactorChanged(id, scale) => {
+                // Cell metrics and PTY sizes follow the new DPI; iced re-rasterizes the text itself
+                let Some(os_window) = self.os_windows.iter_mut().find(|w| w.id == id) else {
+                    return Command::none();
+                };
+                os_window.scale_factor = scale;
+                let commands: Vec<_> = os_window.panes.clone().into_iter()
+                    .map(|pane| self.update(Message::Terminal(pane, TerminalMessage::ScaleFactor(scale))))
+                    .collect();
+                Command::batch(commands)
+            },
+            
+            Message::QuickAttach => {
+                match self.background.take() {
+                    // Show the hidden OS window again, with its layout
+                    Some(os_window) => {
+                        let id = os_window.id;
+                        self.os_windows.push(os_window);
+                        self.focused_os_window = id;
+                        Command::batch([window::change_mode(id, window::Mode::Windowed), window::gain_focus(id)])
+                    },
+                    None => window::gain_focus(self.focused_os_window),
+                }
+            },
+            
+            Message::Quit => {
+                let mut commands: Vec<Command<Message>> = self.windows.drain()
+                    .map(|(id, mut terminal)| terminal.close().map(move |msg| Message::Terminal(id, msg)))
+                    .collect();
+                self.save_window_state();
                 
-                command.map(move |msg| Message::Terminal(window_id, msg))
+                // The application ends once its last OS window, hidden or not, is closed
+                let hidden = self.background.take().map(|w| w.id);
+                commands.extend(self.os_windows.drain(..).map(|w| w.id).chain(hidden).map(window::close));
+                Command::batch(commands)
+            },
+            
+            Message::Action(action) => self.dispatch(action),
+            
+            Message::Paste(text) => {
+                // Send the clipboard to the focused terminal window
+                match (self.focused_window(), text) {
+                    (Some(id), Some(text)) => self.update(Message::Terminal(id, TerminalMessage::Input(text.into_bytes()))),
+                    _ => Command::none(),
+                }
+            },
+            
+            Message::CheckAppearance => {
+                // Checked off the UI thread, as it runs a command
+                Command::perform(async { utils::appearance::dark_mode() }, Message::AppearanceChecked)
+            },
+            
+            Message::AppearanceChecked(dark) => {
+                // The next frame is drawn with the new theme; nothing is cleared first
+                let name = match dark {
+                    Some(true) => &self.settings.dark_theme,
+                    Some(false) => &self.settings.light_theme,
+                    None => return Command::none(),
+                };
+                match theme_by_name(name) {
+                    Some(theme) => self.theme = theme,
+                    None => log::warn!("Unknown theme {:?}", name),
+                }
+                Command::none()
+            },
+            
+            Message::RunCommand(command) => {
+                let parts: Vec<&str> = command.trim_start_matches(':').split_whitespace().collect();
+                match parts.as_slice() {
+                    ["move-to-window", arg] => {
+                        match (self.focused_window(), MoveTarget::parse(arg)) {
+                            (Some(id), Some(target)) => self.move_to_window(id, target),
+                            _ => Command::none(),
+                        }
+                    },
+                    ["break"] => {
+                        match self.focused_window() {
+                            Some(id) => self.break_pane(id),
+                            None => Command::none(),
+                        }
+                    },
+                    ["join", arg] => {
+                        match PaneRef::parse(arg) {
+                            Some(pane) => self.join_pane(pane),
+                            None => {
+                                log::warn!("Expected :join <os window>:<index>: {}", arg);
+                                Command::none()
+                            }
+                        }
+                    },
+                    ["layout", name] => {
+                        let preset = match *name {
+                            "grid" => Some(LayoutPreset::Grid),
+                            "horizontal" | "h" => Some(LayoutPreset::Horizontal),
+                            "vertical" | "v" => Some(LayoutPreset::Vertical),
+                            "main" | "m" => Some(LayoutPreset::Main),
+                            _ => None,
+                        };
+                        match preset {
+                            Some(preset) => self.arrange(preset),
+                            None => log::warn!("Unknown layout: {}", name),
+                        }
+                        Command::none()
+                    },
+                    ["profile", name] => {
+                        // Give the focused terminal window the look of a profile
+                        let window = self.focused_window().and_then(|id| self.windows.get_mut(&id));
+                        match (window, self.settings.profiles.get(*name)) {
+                            (Some(window), Some(profile)) => window.set_profile(profile),
+                            (_, None) => log::warn!("Unknown profile: {}", name),
+                            _ => {}
+                        }
+                        Command::none()
+                    },
+                    ["background"] => {
+                        // Toggle keeping terminal windows running when the last OS window closes
+                        self.settings.background_mode = !self.settings.background_mode;
+                        self.save_settings();
+                        Command::none()
+                    },
+                    ["fullscreen"] => {
+                        // Toggle native fullscreen for the focused OS window
+                        let Some(os_window) = self.focused_os_window() else {
+                            return Command::none();
+                        };
+                        os_window.fullscreen = !os_window.fullscreen;
+                        let mode = if os_window.fullscreen { window::Mode::Fullscreen } else { window::Mode::Windowed };
+                        window::change_mode(os_window.id, mode)
+                    },
+                    ["borderless"] => {
+                        // Toggle drawing OS windows without the system's decorations
+                        self.settings.borderless = !self.settings.borderless;
+                        self.save_settings();
+                        Command::batch(self.os_windows.iter().map(|w| window::toggle_decorations(w.id)))
+                    },
+                    ["transparency"] => {
+                        // Toggle the background opacity
+                        self.settings.transparency = !self.settings.transparency;
+                        self.save_settings();
+                        Command::none()
+                    },
+                    ["theme", name] => {
+                        // Switch theme, e.g. to high-contrast, and keep it for the next start
+                        match theme_by_name(name) {
+                            Some(theme) => {
+                                self.announcer.announce_ui(&format!("Theme {}", theme));
+                                self.theme = theme;
+                                self.settings.theme = name.to_string();
+                                self.save_settings();
+                            }
+                            None => log::warn!("Unknown theme: {}", name),
+                        }
+                        Command::none()
+                    },
+                    ["speak"] | ["speak", _] => {
+                        // Read out the last lines of the focused terminal window
+                        let count = match parts.get(1) {
+                            Some(count) => count.parse().unwrap_or(0),
+                            None => self.settings.accessibility.max_lines,
+                        };
+                        let text = self.focused_window()
+                            .and_then(|id| self.windows.get(&id))
+                            .map(|window| window.recent_text(count))
+                            .unwrap_or_default();
+                        self.announcer.speak(&text);
+                        Command::none()
+                    },
+                    ["opacity", value] => {
+                        match value.parse::<f32>() {
+                            Ok(opacity) if (0.0..=1.0).contains(&opacity) => {
+                                self.settings.background_opacity = opacity;
+                                self.settings.transparency = opacity < 1.0;
+                                self.save_settings();
+                            }
+                            _ => log::warn!("Opacity must be between 0.0 and 1.0: {}", value),
+                        }
+                        Command::none()
+                    },
+                    _ => {
+                        log::warn!("Unknown command: {}", command);
+                        Command::none()
+                    }
+                }
             },
             
             Message::Terminal(id, terminal_message) => {
@@ -150,24 +1089,21 @@ impl Application for MatrixApp {
             
             Message::CloseWindow(id) => {
                 if let Some(mut terminal) = self.windows.remove(&id) {
-                    // Restructure the layout
-                    self.layout_manager.remove_window(&id);
-                    
-                    // Update focus if needed
-                    if self.focused_window == Some(id) {
-                        self.focused_window = self.windows.keys().next().cloned();
+                    // Restructure the layout of the OS window showing it
+                    if let Some(os_window) = self.os_window_of(&id) {
+                        os_window.remove_pane(&id);
                     }
                     
                     // Close the terminal
-                    terminal.close()
+                    terminal.close().map(move |msg| Message::Terminal(id, msg))
                 } else {
                     Command::none()
                 }
             },
             
             Message::FocusWindow(id) => {
-                if self.windows.contains_key(&id) {
-                    self.focused_window = Some(id);
+                if let Some(os_window) = self.os_window_of(&id) {
+                    os_window.focused_window = Some(id);
                 }
                 Command::none()
             },
@@ -177,6 +1113,7 @@ impl Application for MatrixApp {
                     // Create a new window
                     let new_id = Uuid::new_v4();
                     let mut terminal_window = TerminalWindow::new(new_id, "Split Terminal");
+                    self.apply_default_profile(&mut terminal_window);
                     
                     // Start the terminal process
                     let command = terminal_window.spawn_shell();
@@ -184,11 +1121,12 @@ impl Application for MatrixApp {
                     // Add window to our collection
                     self.windows.insert(new_id, terminal_window);
                     
-                    // Update the layout
-                    self.layout_manager.split_window(&id, direction, new_id, 0.5);
-                    
-                    // Focus the new window
-                    self.focused_window = Some(new_id);
+                    // Update the layout of the OS window and focus the new window
+                    if let Some(os_window) = self.os_window_of(&id) {
+                        os_window.panes.push(new_id);
+                        os_window.layout_manager.split_window(&id, direction, new_id, 0.5);
+                        os_window.focused_window = Some(new_id);
+                    }
                     
                     command.map(move |msg| Message::Terminal(new_id, msg))
                 } else {
@@ -197,32 +1135,81 @@ impl Application for MatrixApp {
             },
             
             Message::ZoomToggle(id) => {
-                if self.windows.contains_key(&id) {
-                    if self.is_zoomed {
+                if let Some(os_window) = self.os_window_of(&id) {
+                    if os_window.is_zoomed {
                         // Unzoom
-                        self.is_zoomed = false;
-                        self.layout_manager.unzoom();
+                        os_window.is_zoomed = false;
+                        os_window.layout_manager.unzoom();
                     } else {
                         // Zoom
-                        self.is_zoomed = true;
-                        self.layout_manager.zoom_window(&id);
-                        self.focused_window = Some(id);
+                        os_window.is_zoomed = true;
+                        os_window.layout_manager.zoom_window(&id);
+                        os_window.focused_window = Some(id);
                     }
                 }
                 Command::none()
             },
             
+            Message::PaneMenu(menu_message) => self.pane_menu_chosen(menu_message),
+            
+            Message::TitleBar(id, title_bar_message) => {
+                match title_bar_message {
+                    TitleBarMessage::Drag => window::drag(id),
+                    TitleBarMessage::Minimize => window::minimize(id, true),
+                    TitleBarMessage::ToggleMaximize => window::toggle_maximize(id),
+                    TitleBarMessage::Close => self.update(Message::OsWindowClosed(id)),
+                }
+            },
+            
             Message::Sidebar(sidebar_message) => {
                 match sidebar_message {
                     SidebarMessage::IconClicked(icon) => {
-                        // Handle sidebar icon clicks
-                        // TODO: Implement sidebar icon handling
+                        // Icons do what their shortcuts or layout commands do
+                        let preset = match icon {
+                            SidebarIcon::NewWindow => return self.dispatch(Action::NewWindow),
+                            SidebarIcon::SplitHorizontal => return self.dispatch(Action::SplitHorizontal),
+                            SidebarIcon::SplitVertical => return self.dispatch(Action::SplitVertical),
+                            SidebarIcon::Zoom => return self.dispatch(Action::ToggleZoom),
+                            SidebarIcon::CloseWindow => return self.dispatch(Action::CloseWindow),
+                            SidebarIcon::GridLayout => LayoutPreset::Grid,
+                            SidebarIcon::HorizontalLayout => LayoutPreset::Horizontal,
+                            SidebarIcon::VerticalLayout => LayoutPreset::Vertical,
+                            SidebarIcon::MainLayout => LayoutPreset::Main,
+                        };
+                        self.arrange(preset);
                         Command::none()
                     }
                 }
             },
             
+            Message::CommandLine(CommandLineMessage::Input(command)) => {
+                self.command_line = Some(command);
+                Command::none()
+            },
+            
+            Message::CommandLine(CommandLineMessage::Cancel) => {
+                self.command_line = None;
+                Command::none()
+            },
+            
+            Message::CommandLine(CommandLineMessage::Submit) => {
+                // Closed first, so the command acts on the layout without it
+                match self.command_line.take().filter(|command| !command.trim().is_empty()) {
+                    Some(command) => self.update(Message::RunCommand(command)),
+                    None => Command::none(),
+                }
+            },
+            
             Message::Tick => {
+                // Focus the terminal window the mouse has rested on long enough
+                let delay = Duration::from_millis(self.settings.focus_delay_ms);
+                if let Some((id, _)) = self.hover_focus.filter(|(_, since)| since.elapsed() >= delay) {
+                    self.hover_focus = None;
+                    if let Some(os_window) = self.os_window_of(&id) {
+                        os_window.focused_window = Some(id);
+                    }
+                }
+                
                 // Update terminal windows
                 let mut commands = Vec::new();
                 
@@ -232,47 +1219,270 @@ impl Application for MatrixApp {
                     }
                 }
                 
+                // Speak focus changes and new output of the focused terminal window
+                if let Some(window) = self.focused_window().and_then(|id| self.windows.get(&id)) {
+                    self.announcer.focus_changed(window);
+                    self.announcer.announce_new_output(window);
+                }
+                
+                // Actions chosen from the menu bar, then refresh what it shows
+                #[cfg(target_os = "macos")]
+                {
+                    let actions = self.menu.as_ref().map(|menu| menu.poll()).unwrap_or_default();
+                    for action in actions {
+                        commands.push(self.dispatch(action));
+                    }
+                    self.update_menu();
+                }
+                
+                // Requests from the tray icon and global hotkey
+                #[cfg(feature = "tray")]
+                {
+                    let requests = self.tray.as_ref().map(|tray| tray.poll()).unwrap_or_default();
+                    for request in requests {
+                        commands.push(self.update(match request {
+                            tray::TrayRequest::Show => Message::QuickAttach,
+                            tray::TrayRequest::Quit => Message::Quit,
+                        }));
+                    }
+                }
+                
                 Command::batch(commands)
             }
         }
     }
     
     fn subscription(&self) -> Subscription<Message> {
-        Subscription::batch([
-            // Listen for system events (keyboard, mouse, etc.)
-            iced::subscription::events().map(Message::Event),
+        let mut subscriptions = vec![
+            // Listen for system events (keyboard, mouse, windows opening and closing)
+            iced::event::listen().map(Message::Event),
             
             // Add a tick subscription for terminal updates
             iced::time::every(std::time::Duration::from_millis(16))
                 .map(|_| Message::Tick),
-        ])
+        ];
+        
+        // Escape closes the command line; its text input keeps the key from the listener above
+        if self.command_line.is_some() {
+            subscriptions.push(iced::event::listen_with(|event, _status| match event {
+                Event::Keyboard(keyboard::Event::KeyPressed { key: keyboard::Key::Named(keyboard::key::Named::Escape), .. }) => {
+                    Some(Message::CommandLine(CommandLineMessage::Cancel))
+                },
+                _ => None,
+            }));
+        }
+        
+        // Follow the system's light or dark mode
+        if self.settings.follow_system_appearance {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_secs(3))
+                    .map(|_| Message::CheckAppearance),
+            );
+        }
+        
+        Subscription::batch(subscriptions)
     }
     
-    fn view(&self) -> Element<Message> {
-        // TODO: Implement the view function
-        // This will render the sidebar and terminal windows according to layout
-        iced::widget::container(
-            iced::widget::text("Matrix Terminal")
-                .size(24)
-                .style(iced::theme::Text::Color(Color::from_rgb(0.0, 1.0, 0.25)))
-        )
+    fn view(&self, window: window::Id) -> Element<Message> {
+        let opacity = self.settings.opacity();
+        
+        // A terminal window's context menu is shown in the OS window it was opened in
+        let menu = self.pane_menu.as_ref()
+            .filter(|menu| self.os_window(window).is_some_and(|w| w.panes.contains(&menu.window)));
+        // The ratio of a divider being dragged is shown next to the cursor while it's dragged
+        let drag = self.split_drag.as_ref().filter(|drag| drag.window == window);
+        
+        // The sidebar, then the terminal windows as the layout splits them
+        let os_window = self.os_window(window);
+        let panes: Element<Message> = match os_window.and_then(|w| w.layout_manager.root()) {
+            Some(root) => self.layout_view(root, opacity),
+            None => iced::widget::container(
+                iced::widget::text("Matrix Terminal")
+                    .size(24)
+                    .style(iced::theme::Text::Color(Color::from_rgb(0.0, 1.0, 0.25)))
+            )
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+        };
+        let content: Element<Message> = match os_window {
+            Some(os_window) => iced::widget::row![
+                os_window.sidebar.view(opacity).map(Message::Sidebar),
+                panes,
+            ].into(),
+            None => panes,
+        };
+        
+        // The context menu or the ratio float over the terminal windows, which stay visible below
+        let content: Element<Message> = match (menu, drag) {
+            (Some(menu), _) => {
+                let profiles: Vec<String> = self.settings.profiles.keys().cloned().collect();
+                let current = self.os_windows.iter().position(|w| w.id == window).map_or(0, |i| i + 1);
+                let view = menu.view(&profiles, self.os_windows.len(), current).map(Message::PaneMenu);
+                Floating::new(content, view, menu.position).into()
+            },
+            (None, Some(drag)) => {
+                // Just below and right of the cursor
+                let position = self.cursor_position + iced::Vector::new(12.0, 16.0);
+                Floating::new(content, split_ratio_label(drag.ratio), position).into()
+            },
+            (None, None) => content,
+        };
+        
+        // The command line is shown along the bottom of the focused OS window
+        let content: Element<Message> = match &self.command_line {
+            Some(command) if window == self.focused_os_window => iced::widget::column![
+                iced::widget::container(content).height(Length::Fill),
+                command_line::view(command).map(Message::CommandLine),
+            ].into(),
+            _ => content,
+        };
+        
+        // Borderless windows have a title bar of their own, except in fullscreen
+        let fullscreen = self.os_window(window).is_some_and(|w| w.fullscreen);
+        let content: Element<Message> = if self.settings.borderless && !fullscreen {
+            iced::widget::column![
+                title_bar::view(&self.title(window)).map(move |message| Message::TitleBar(window, message)),
+                content,
+            ].into()
+        } else {
+            content
+        };
+        
+        iced::widget::container(content)
         .width(Length::Fill)
         .height(Length::Fill)
-        .center_x()
-        .center_y()
-        .style(iced::theme::Container::Custom(Box::new(|_theme| {
+        .style(iced::theme::Container::Custom(Box::new(move |_theme: &Theme| {
             iced::widget::container::Appearance {
                 text_color: None,
-                background: Some(iced::Background::Color(Color::BLACK)),
-                border_radius: 0.0,
-                border_width: 0.0,
-                border_color: Color::TRANSPARENT,
+                background: Some(iced::Background::Color(colors::background(opacity))),
+                border: iced::Border::default(),
+                shadow: iced::Shadow::default(),
             }
         })))
         .into()
     }
     
-    fn theme(&self) -> Theme {
+    fn theme(&self, _window: window::Id) -> Theme {
         self.theme.clone()
     }
-}
\ No newline at end of file
+    
+    fn style(&self) -> iced::theme::Application {
+        // Windows are cleared to the (possibly transparent) background color
+        iced::theme::Application::Custom(Box::new(AppBackgroundStyle {
+            opacity: self.settings.opacity(),
+        }))
+    }
+}
+
+impl MatrixApp {
+    /// Find out the scale factor of an OS window from the physical size of a capture
+    /// of it, unless that's already under way
+    fn measure_scale_factor(&mut self, id: window::Id) -> Command<Message> {
+        match self.os_windows.iter_mut().find(|w| w.id == id) {
+            Some(os_window) if !os_window.measuring_scale => {
+                os_window.measuring_scale = true;
+                window::screenshot(id, move |screenshot| Message::ScaleFactorMeasured(id, screenshot.size))
+            },
+            _ => Command::none(),
+        }
+    }
+    
+    /// The terminal windows of a layout node, side by side or stacked in the ratios of its splits
+    fn layout_view(&self, node: &LayoutNode, opacity: f32) -> Element<Message> {
+        match node {
+            LayoutNode::Window(id) => match self.windows.get(id) {
+                Some(terminal) => {
+                    let id = *id;
+                    terminal.view(opacity).map(move |msg| Message::Terminal(id, msg))
+                },
+                None => iced::widget::Space::new(Length::Fill, Length::Fill).into(),
+            },
+            LayoutNode::Split { direction, ratio, first, second } => {
+                // Portions in thousandths of the split
+                let first_portion = (ratio * 1000.0).round().clamp(1.0, 999.0) as u16;
+                let portions = [first_portion, 1000 - first_portion];
+                let [first, second] = [first, second].map(|node| self.layout_view(node, opacity));
+                match direction {
+                    SplitDirection::Horizontal => iced::widget::row![
+                        iced::widget::container(first).width(Length::FillPortion(portions[0])),
+                        iced::widget::container(second).width(Length::FillPortion(portions[1])),
+                    ].into(),
+                    SplitDirection::Vertical => iced::widget::column![
+                        iced::widget::container(first).height(Length::FillPortion(portions[0])),
+                        iced::widget::container(second).height(Length::FillPortion(portions[1])),
+                    ].into(),
+                }
+            },
+        }
+    }
+}
+
+/// The scale factor of a window with a physical and a logical size, to the hundredth
+fn scale_factor(physical: Size<u32>, logical: Size) -> Option<f32> {
+    (physical.width > 0 && logical.width >= 1.0).then(|| (physical.width as f32 / logical.width * 100.0).round() / 100.0)
+}
+
+/// The percentage a dragged divider is at
+fn split_ratio_label<'a>(ratio: f32) -> Element<'a, Message> {
+    iced::widget::container(
+        iced::widget::text(format!("{:.0}%", ratio * 100.0)).size(13)
+    )
+    .padding([2, 6])
+    .style(iced::theme::Container::Custom(Box::new(|_theme: &Theme| {
+        iced::widget::container::Appearance {
+            text_color: Some(colors::MATRIX_GREEN),
+            background: Some(iced::Background::Color(Color::BLACK)),
+            border: iced::Border {
+                color: colors::MATRIX_GREEN,
+                width: 1.0,
+                radius: 0.0.into(),
+            },
+            shadow: iced::Shadow::default(),
+        }
+    })))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measured_scale_factor_recomputes_cell_metrics() {
+        let (mut app, _) = MatrixApp::new(None);
+        let pane = Uuid::new_v4();
+        app.windows.insert(pane, TerminalWindow::new(pane, "Terminal"));
+        app.os_windows[0].add_pane(pane);
+        let _ = app.update(Message::Event(Event::Window(window::Id::MAIN, window::Event::Resized { width: 800, height: 600 })));
+        assert!(app.os_windows[0].measuring_scale);
+        let width = app.windows[&pane].metrics().width;
+
+        // The window moved to a monitor at twice the density: cells snap to its pixels
+        let _ = app.update(Message::ScaleFactorMeasured(window::Id::MAIN, Size::new(1600, 1200)));
+        assert!(!app.os_windows[0].measuring_scale);
+        assert_eq!(app.os_windows[0].scale_factor, 2.0);
+        let metrics = app.windows[&pane].metrics();
+        assert_eq!(metrics.scale_factor, 2.0);
+        assert_ne!(metrics.width, width);
+        assert_eq!(metrics.width * 2.0, (metrics.width * 2.0).round());
+
+        // A capture of a minimized window says nothing about its scale
+        let _ = app.update(Message::ScaleFactorMeasured(window::Id::MAIN, Size::new(0, 0)));
+        assert_eq!(app.windows[&pane].metrics().scale_factor, 2.0);
+    }
+
+    #[test]
+    fn completed_output_lines_can_be_read_out() {
+        let mut window = TerminalWindow::new(Uuid::new_v4(), "Terminal");
+        let _ = window.update(TerminalMessage::Output(b"one\r\ntwo\r\nthree\r\npartial".to_vec()));
+        assert_eq!(window.completed_lines(), 3);
+
+        // Only completed lines, and no more than there are
+        assert_eq!(window.recent_text(2).trim(), "two\nthree");
+        assert_eq!(window.recent_text(10).trim(), "one\ntwo\nthree");
+        assert_eq!(window.recent_text(0), "");
+    }
+}
@@ -0,0 +1,162 @@
+//! Native menu bar on macOS, with the standard Shell, Edit, View, Window and Help menus.
+//! Menu items dispatch the same actions as keyboard shortcuts.
+
+use muda::accelerator::Accelerator;
+use muda::{AboutMetadata, CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use std::collections::HashMap;
+
+use crate::actions::Action;
+
+/// What the menu shows as enabled or checked
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MenuState {
+    /// A terminal window has focus
+    pub has_window: bool,
+    /// The focused terminal window has a selection
+    pub has_selection: bool,
+    /// More than one terminal window is shown in the focused OS window
+    pub has_splits: bool,
+    /// The focused OS window is zoomed
+    pub zoomed: bool,
+    /// The sidebar is shown
+    pub sidebar: bool,
+    /// The background is transparent
+    pub transparency: bool,
+    /// OS windows are drawn without the system's title bar and borders
+    pub borderless: bool,
+}
+
+/// An item of the menu bar
+enum Item {
+    Plain(MenuItem),
+    Check(CheckMenuItem),
+}
+
+/// The application's menu bar
+pub struct MenuBar {
+    // Kept alive for as long as the menu is shown
+    _menu: Menu,
+    items: HashMap<Action, Item>,
+}
+
+impl MenuBar {
+    /// Build the menu bar and install it as the application menu
+    pub fn install() -> anyhow::Result<Self> {
+        let menu = Menu::new();
+        let mut items = HashMap::new();
+
+        let app_menu = Submenu::new("Matrix", true);
+        app_menu.append_items(&[
+            &PredefinedMenuItem::about(None, Some(AboutMetadata {
+                name: Some("Matrix Terminal".to_string()),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                ..Default::default()
+            })),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::services(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::hide(None),
+            &PredefinedMenuItem::hide_others(None),
+            &PredefinedMenuItem::show_all(None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::quit(None),
+        ])?;
+
+        let shell_menu = Submenu::new("Shell", true);
+        add_items(&shell_menu, &mut items, &[Action::NewWindow, Action::NewOsWindow])?;
+        shell_menu.append(&PredefinedMenuItem::separator())?;
+        add_items(&shell_menu, &mut items, &[Action::RenameWindow, Action::CloseWindow])?;
+        shell_menu.append(&PredefinedMenuItem::separator())?;
+        add_items(&shell_menu, &mut items, &[Action::CommandLine])?;
+
+        let edit_menu = Submenu::new("Edit", true);
+        add_items(&edit_menu, &mut items, &[Action::Copy, Action::Paste])?;
+        edit_menu.append(&PredefinedMenuItem::select_all(None))?;
+
+        let view_menu = Submenu::new("View", true);
+        add_items(&view_menu, &mut items, &[Action::SplitHorizontal, Action::SplitVertical])?;
+        view_menu.append(&PredefinedMenuItem::separator())?;
+        add_items(&view_menu, &mut items, &[Action::ToggleZoom, Action::ToggleSidebar, Action::ToggleTransparency])?;
+        view_menu.append(&PredefinedMenuItem::separator())?;
+        add_items(&view_menu, &mut items, &[Action::ToggleBorderless])?;
+        view_menu.append(&PredefinedMenuItem::fullscreen(None))?;
+
+        let window_menu = Submenu::new("Window", true);
+        window_menu.append(&PredefinedMenuItem::minimize(None))?;
+        window_menu.append(&PredefinedMenuItem::separator())?;
+        add_items(&window_menu, &mut items, &[Action::FocusNext, Action::FocusPrevious, Action::MoveToNewOsWindow])?;
+
+        let help_menu = Submenu::new("Help", true);
+        help_menu.append(&PredefinedMenuItem::about(Some("About Matrix Terminal"), None))?;
+
+        menu.append_items(&[&app_menu, &shell_menu, &edit_menu, &view_menu, &window_menu, &help_menu])?;
+        menu.init_for_nsapp();
+        window_menu.set_as_windows_menu_for_nsapp();
+        help_menu.set_as_help_menu_for_nsapp();
+
+        Ok(Self { _menu: menu, items })
+    }
+
+    /// Actions chosen from the menu since the last poll
+    pub fn poll(&self) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            if let Some(action) = Action::from_id(event.id().as_ref()) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    /// Enable, disable and check items to reflect the current layout
+    pub fn update(&self, state: &MenuState) {
+        for (action, item) in &self.items {
+            let enabled = match action {
+                Action::NewWindow | Action::NewOsWindow | Action::Paste | Action::CommandLine
+                | Action::ToggleSidebar | Action::ToggleTransparency
+                | Action::ToggleFullscreen | Action::ToggleBorderless => true,
+                Action::Copy => state.has_selection,
+                Action::ToggleZoom | Action::FocusNext | Action::FocusPrevious => state.has_splits,
+                Action::CloseWindow | Action::RenameWindow | Action::SplitHorizontal | Action::SplitVertical
+                | Action::MoveToNewOsWindow => state.has_window,
+            };
+
+            match item {
+                Item::Plain(item) => item.set_enabled(enabled),
+                Item::Check(item) => {
+                    item.set_enabled(enabled);
+                    item.set_checked(match action {
+                        Action::ToggleZoom => state.zoomed,
+                        Action::ToggleSidebar => state.sidebar,
+                        Action::ToggleBorderless => state.borderless,
+                        _ => state.transparency,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Append menu items for actions, toggles as check items
+fn add_items(submenu: &Submenu, items: &mut HashMap<Action, Item>, actions: &[Action]) -> anyhow::Result<()> {
+    for &action in actions {
+        let accelerator = action.accelerator()
+            .map(|text| text.parse::<Accelerator>())
+            .transpose()?;
+
+        let item = match action {
+            Action::ToggleZoom | Action::ToggleSidebar | Action::ToggleTransparency | Action::ToggleBorderless => {
+                let item = CheckMenuItem::with_id(action.id(), action.label(), true, false, accelerator);
+                submenu.append(&item)?;
+                Item::Check(item)
+            }
+            _ => {
+                let item = MenuItem::with_id(action.id(), action.label(), true, accelerator);
+                submenu.append(&item)?;
+                Item::Plain(item)
+            }
+        };
+        items.insert(action, item);
+    }
+    Ok(())
+}
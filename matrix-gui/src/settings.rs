@@ -0,0 +1,147 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Settings of the GUI, stored as JSON next to the terminal's settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiSettings {
+    /// Opacity of window backgrounds, from 0.0 (invisible) to 1.0 (opaque)
+    pub background_opacity: f32,
+    /// Whether the background opacity is applied at startup
+    pub transparency: bool,
+    /// Keep terminal windows running in the background when the last OS window closes
+    pub background_mode: bool,
+    /// Global shortcut that raises Matrix and attaches to its terminal windows
+    pub quick_attach_hotkey: Option<String>,
+    /// Theme at startup: "matrix", "high-contrast" or one of iced's themes
+    pub theme: String,
+    /// Switch between the light and dark theme as the system's appearance changes
+    pub follow_system_appearance: bool,
+    /// Themes used in light and dark mode: "matrix" or one of iced's themes, e.g. "Solarized Light"
+    pub light_theme: String,
+    pub dark_theme: String,
+    /// Named looks for terminal windows, chosen with `:profile <name>`
+    pub profiles: BTreeMap<String, GuiProfile>,
+    /// Profile of new terminal windows
+    pub default_profile: Option<String>,
+    /// Focus the terminal window under the mouse once it has rested there focus_delay_ms
+    pub focus_follows_mouse: bool,
+    pub focus_delay_ms: u64,
+    /// Lines scrolled per wheel tick
+    pub scroll_lines: usize,
+    /// Scroll the content the way the wheel moves, as on a touchpad
+    pub natural_scrolling: bool,
+    /// Draw OS windows without the system's decorations, under a Matrix-styled title bar
+    pub borderless: bool,
+    /// Speech output for screen reader users
+    pub accessibility: GuiAccessibility,
+}
+
+/// What is spoken, and by which command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiAccessibility {
+    /// Command used to speak text, e.g. "spd-say" (None disables speech)
+    pub speech_command: Option<String>,
+    /// Speak new output lines of the focused terminal window
+    pub announce_output: bool,
+    /// Speak focus and theme changes
+    pub announce_ui: bool,
+    /// Maximum number of output lines spoken per update
+    pub max_lines: usize,
+}
+
+impl Default for GuiAccessibility {
+    fn default() -> Self {
+        Self {
+            speech_command: None,
+            announce_output: true,
+            announce_ui: true,
+            max_lines: 5,
+        }
+    }
+}
+
+/// Look of the terminal windows using a profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiProfile {
+    /// Text drawn faintly behind the terminal, e.g. "PRODUCTION"
+    pub watermark: Option<String>,
+    /// Opacity of the watermark, from 0.0 (invisible) to 1.0
+    pub watermark_opacity: f32,
+}
+
+impl Default for GuiProfile {
+    fn default() -> Self {
+        Self {
+            watermark: None,
+            watermark_opacity: 0.08,
+        }
+    }
+}
+
+impl Default for GuiSettings {
+    fn default() -> Self {
+        Self {
+            background_opacity: 0.9,
+            transparency: false,
+            background_mode: false,
+            quick_attach_hotkey: Some(String::from("CmdOrCtrl+Shift+M")),
+            theme: String::from("matrix"),
+            follow_system_appearance: false,
+            light_theme: String::from("Light"),
+            dark_theme: String::from("matrix"),
+            profiles: BTreeMap::new(),
+            default_profile: None,
+            focus_follows_mouse: false,
+            focus_delay_ms: 150,
+            scroll_lines: 3,
+            natural_scrolling: false,
+            borderless: false,
+            accessibility: GuiAccessibility::default(),
+        }
+    }
+}
+
+impl GuiSettings {
+    /// Location of the settings file
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("matrix").join("gui.json"))
+    }
+
+    /// Load the settings, falling back to the defaults if there are none
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Ok(serde_json::from_slice(&fs::read(path)?)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Save the settings
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow::anyhow!("no configuration directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// A profile by name
+    pub fn profile(&self, name: &str) -> Option<&GuiProfile> {
+        self.profiles.get(name)
+    }
+
+    /// The background opacity in effect
+    pub fn opacity(&self) -> f32 {
+        if self.transparency {
+            self.background_opacity.clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+}
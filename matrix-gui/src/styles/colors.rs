@@ -23,6 +23,11 @@ pub const DARKER_GREEN: Color = Color::from_rgb(
 pub const BACKGROUND: Color = Color::BLACK;
 pub const BACKGROUND_LIGHT: Color = Color::from_rgb(0.1, 0.1, 0.1);
 
+/// The background color at an opacity (for transparent windows)
+pub fn background(opacity: f32) -> Color {
+    Color { a: opacity, ..BACKGROUND }
+}
+
 // Border colors
 pub const BORDER: Color = DARK_GREEN;
 pub const BORDER_FOCUSED: Color = MATRIX_GREEN;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use iced::{Theme, Application};
+use iced::{Application, Color, Theme};
 use crate::styles::colors;
 
 /// Creates a Matrix-themed iced theme
@@ -19,27 +19,81 @@ pub fn matrix_theme() -> Theme {
     theme
 }
 
+/// Name of the high-contrast theme
+const HIGH_CONTRAST: &str = "High Contrast";
+
+/// White on black with yellow highlights, for low vision
+pub fn high_contrast_theme() -> Theme {
+    Theme::custom(HIGH_CONTRAST.to_string(), iced::theme::Palette {
+        background: Color::BLACK,
+        text: Color::WHITE,
+        primary: Color::from_rgb(1.0, 1.0, 0.0),
+        success: Color::from_rgb(0.0, 1.0, 0.0),
+        danger: Color::from_rgb(1.0, 0.3, 0.3),
+    })
+}
+
+/// Whether a theme is the high-contrast one, whose colors replace Matrix's greens
+fn is_high_contrast(theme: &Theme) -> bool {
+    theme.to_string() == HIGH_CONTRAST
+}
+
+/// A theme by name: "matrix" (or "default") for the Matrix theme, "high-contrast",
+/// otherwise one of iced's built-in themes, matched case-insensitively (e.g. "light",
+/// "Solarized Dark")
+pub fn theme_by_name(name: &str) -> Option<Theme> {
+    if name.eq_ignore_ascii_case("matrix") || name.eq_ignore_ascii_case("default") {
+        return Some(matrix_theme());
+    }
+    if name.eq_ignore_ascii_case("high-contrast") || name.eq_ignore_ascii_case(HIGH_CONTRAST) {
+        return Some(high_contrast_theme());
+    }
+    Theme::ALL.iter().find(|theme| theme.to_string().eq_ignore_ascii_case(name)).cloned()
+}
+
 /// Custom container style for terminal windows with non-rounded borders
 pub struct TerminalContainerStyle {
     pub focused: bool,
+    // Background opacity (1.0 unless the window is transparent)
+    pub opacity: f32,
 }
 
 impl iced::widget::container::StyleSheet for TerminalContainerStyle {
     type Style = iced::Theme;
 
-    fn appearance(&self, _theme: &Self::Style) -> iced::widget::container::Appearance {
-        let border_color = if self.focused {
-            colors::BORDER_FOCUSED
-        } else {
-            colors::BORDER
+    fn appearance(&self, theme: &Self::Style) -> iced::widget::container::Appearance {
+        let border_color = match (is_high_contrast(theme), self.focused) {
+            (true, true) => theme.palette().primary,
+            (true, false) => theme.palette().text,
+            (false, true) => colors::BORDER_FOCUSED,
+            (false, false) => colors::BORDER,
         };
 
         iced::widget::container::Appearance {
-            text_color: None,
-            background: Some(iced::Background::Color(colors::BACKGROUND)),
-            border_radius: 0.0, // Square borders, not rounded
-            border_width: 1.0,
-            border_color,
+            text_color: is_high_contrast(theme).then(|| theme.palette().text),
+            background: Some(iced::Background::Color(colors::background(self.opacity))),
+            border: iced::Border {
+                color: border_color,
+                width: 1.0,
+                radius: 0.0.into(), // Square borders, not rounded
+            },
+            shadow: iced::Shadow::default(),
+        }
+    }
+}
+
+/// Application style: the color windows are cleared to before anything is drawn
+pub struct AppBackgroundStyle {
+    pub opacity: f32,
+}
+
+impl iced::application::StyleSheet for AppBackgroundStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _theme: &Self::Style) -> iced::application::Appearance {
+        iced::application::Appearance {
+            background_color: colors::background(self.opacity),
+            text_color: colors::MATRIX_GREEN,
         }
     }
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
-use iced::{Color, Rectangle, Size};
+use iced::{Color, Point, Rectangle, Size};
+use iced::widget::canvas::{Frame, LineDash, Path, Stroke};
 use alacritty_terminal::{
-    term::Term,
-    event::EventListener,
+    term::{cell::Flags, Term},
+    event::VoidListener,
     grid::Dimensions,
+    index::{Column, Line},
+    vte::ansi,
 };
 
 use crate::styles::colors;
@@ -14,12 +17,45 @@ pub struct TerminalCell {
     pub background: Color,
     pub bold: bool,
     pub italic: bool,
-    pub underline: bool,
+    pub underline: UnderlineStyle,
+    // Color of the underline, if the application set one apart from the text's (SGR 58)
+    pub underline_color: Option<Color>,
+}
+
+/// How a cell is underlined (SGR 4:1 to 4:5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    None,
+    Single,
+    Double,
+    // Curly, as editors mark diagnostics
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// The underline a cell's flags ask for
+    pub fn from_flags(flags: Flags) -> Self {
+        if flags.contains(Flags::UNDERCURL) {
+            UnderlineStyle::Curly
+        } else if flags.contains(Flags::DOUBLE_UNDERLINE) {
+            UnderlineStyle::Double
+        } else if flags.contains(Flags::DOTTED_UNDERLINE) {
+            UnderlineStyle::Dotted
+        } else if flags.contains(Flags::DASHED_UNDERLINE) {
+            UnderlineStyle::Dashed
+        } else if flags.contains(Flags::UNDERLINE) {
+            UnderlineStyle::Single
+        } else {
+            UnderlineStyle::None
+        }
+    }
 }
 
 /// Terminal renderer for iced
 pub struct TerminalRenderer {
-    term: Term<EventListener>,
+    term: Term<VoidListener>,
     cell_width: f32,
     cell_height: f32,
     font_size: f32,
@@ -27,7 +63,7 @@ pub struct TerminalRenderer {
 
 impl TerminalRenderer {
     /// Create a new terminal renderer
-    pub fn new(term: Term<EventListener>, font_size: f32) -> Self {
+    pub fn new(term: Term<VoidListener>, font_size: f32) -> Self {
         // Calculate cell dimensions based on font size
         // This is a simplified approach - would need to adjust based on actual font metrics
         let cell_width = font_size * 0.6;
@@ -41,41 +77,85 @@ impl TerminalRenderer {
         }
     }
     
-    /// Get the dimensions of the terminal in cells
-    pub fn dimensions(&self) -> Dimensions {
-        self.term.dimensions()
+    /// Get the dimensions of the terminal in cells, as columns and rows
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.term.columns(), self.term.screen_lines())
     }
     
     /// Calculate the size needed to render the terminal
     pub fn calculate_size(&self) -> Size {
-        let dim = self.dimensions();
-        let width = self.cell_width * dim.cols as f32;
-        let height = self.cell_height * dim.rows as f32;
+        let (cols, rows) = self.dimensions();
+        let width = self.cell_width * cols as f32;
+        let height = self.cell_height * rows as f32;
         
         Size::new(width, height)
     }
     
     /// Get a cell at a specific position
     pub fn cell_at(&self, row: usize, col: usize) -> Option<TerminalCell> {
-        let dim = self.dimensions();
+        let (cols, rows) = self.dimensions();
         
         // Check if the position is valid
-        if row >= dim.rows as usize || col >= dim.cols as usize {
+        if row >= rows || col >= cols {
             return None;
         }
         
-        // This is a placeholder - would need to extract data from the actual term
-        // A real implementation would pull this information from the terminal
+        // Colors aren't resolved against a palette yet; the character and its
+        // attributes come from the terminal
+        let cell = &self.term.grid()[Line(row as i32)][Column(col)];
+        let underline_color = match cell.underline_color() {
+            Some(ansi::Color::Spec(rgb)) => Some(Color::from_rgb8(rgb.r, rgb.g, rgb.b)),
+            _ => None,
+        };
         Some(TerminalCell {
-            character: ' ',
+            character: cell.c,
             foreground: colors::MATRIX_GREEN,
             background: Color::BLACK,
-            bold: false,
-            italic: false,
-            underline: false,
+            bold: cell.flags.contains(Flags::BOLD),
+            italic: cell.flags.contains(Flags::ITALIC),
+            underline: UnderlineStyle::from_flags(cell.flags),
+            underline_color,
         })
     }
     
+    /// Draw a cell's underline along the bottom of its bounds
+    pub fn draw_underline(&self, frame: &mut Frame, bounds: Rectangle, cell: &TerminalCell) {
+        let color = cell.underline_color.unwrap_or(cell.foreground);
+        let thickness = (self.font_size / 14.0).max(1.0);
+        let y = bounds.y + bounds.height - thickness * 2.0;
+        let (left, right) = (bounds.x, bounds.x + bounds.width);
+        let line = |y: f32| Path::line(Point::new(left, y), Point::new(right, y));
+        let stroke = Stroke::default().with_color(color).with_width(thickness);
+        
+        match cell.underline {
+            UnderlineStyle::None => {}
+            UnderlineStyle::Single => frame.stroke(&line(y), stroke),
+            UnderlineStyle::Double => {
+                frame.stroke(&line(y - thickness * 2.0), stroke.clone());
+                frame.stroke(&line(y), stroke);
+            }
+            UnderlineStyle::Curly => {
+                // One wave per cell, so neighbouring cells join up
+                let amplitude = thickness * 1.5;
+                let middle = (left + right) / 2.0;
+                let wave = Path::new(|path| {
+                    path.move_to(Point::new(left, y));
+                    path.quadratic_curve_to(Point::new((left + middle) / 2.0, y - amplitude * 2.0), Point::new(middle, y));
+                    path.quadratic_curve_to(Point::new((middle + right) / 2.0, y + amplitude * 2.0), Point::new(right, y));
+                });
+                frame.stroke(&wave, stroke);
+            }
+            UnderlineStyle::Dotted => frame.stroke(&line(y), Stroke {
+                line_dash: LineDash { segments: &[thickness, thickness], offset: 0 },
+                ..stroke
+            }),
+            UnderlineStyle::Dashed => frame.stroke(&line(y), Stroke {
+                line_dash: LineDash { segments: &[thickness * 3.0, thickness * 2.0], offset: 0 },
+                ..stroke
+            }),
+        }
+    }
+    
     /// Render the terminal to a canvas
     pub fn render(&self, bounds: Rectangle) -> Vec<iced::widget::canvas::Geometry> {
         // This would use iced::widget::canvas to render the terminal
@@ -1,2 +1,5 @@
 pub mod window;
-pub mod emulator;
\ No newline at end of file
+pub mod emulator;
+// The TUI's key encoder, shared so both frontends send applications the same bytes
+#[path = "../../../src/terminal/keyboard.rs"]
+pub mod keyboard;
\ No newline at end of file
@@ -1,19 +1,27 @@
-use iced::{Command, Element, Rectangle, Size};
+use iced::{Command, Element, Size};
 use uuid::Uuid;
 use std::io::Write;
 use anyhow::Result;
-use portable_pty::{native_pty_system, PtySize, PtySystem, CommandBuilder, Child};
+use portable_pty::{native_pty_system, PtySize, CommandBuilder, Child};
 use std::sync::{Arc, Mutex};
 use alacritty_terminal::{
-    term::{Term, TermMode},
-    event::{EventListener, Event as TermEvent},
-    grid::Dimensions,
-    index::{Line, Column, Point},
+    term::{test::TermSize, Config, Term},
+    event::VoidListener,
+    grid::{Dimensions, Scroll},
+    index::{Column, Line, Point},
     vte::ansi,
 };
 
+use crate::components::watermark::Watermark;
+use crate::terminal::keyboard::KeyboardMode;
+use crate::settings::GuiProfile;
 use crate::styles::colors;
 use crate::styles::theme::TerminalContainerStyle;
+use crate::utils::font::FontMetrics;
+use crate::utils::keyboard;
+
+/// Font size the terminal is drawn at
+const FONT_SIZE: f32 = 14.0;
 
 /// Messages that can be sent to terminal windows
 #[derive(Debug, Clone)]
@@ -24,6 +32,12 @@ pub enum TerminalMessage {
     ProcessExit(i32),
     Focused,
     Unfocused,
+    // Scroll the history by lines, up for positive
+    Scroll(i32),
+    // Back to the newest output
+    JumpToBottom,
+    // Shown on a monitor with another scale factor
+    ScaleFactor(f32),
 }
 
 /// A terminal window component
@@ -37,29 +51,36 @@ pub struct TerminalWindow {
     is_focused: bool,
     
     // Terminal emulation
-    term: Term<EventListener>,
+    term: Term<VoidListener>,
+    parser: ansi::Processor,
+    
+    // xterm modifyOtherKeys level the application asked for; kitty's flags are kept by the terminal
+    modify_other_keys: u8,
     
     // PTY handling
     pty_master: Option<Box<dyn portable_pty::MasterPty + Send>>,
     pty_writer: Option<Arc<Mutex<Box<dyn Write + Send>>>>,
     child_process: Option<Box<dyn Child + Send + Sync>>,
+    
+    // Faint text drawn behind the terminal, from its profile
+    watermark: Option<Watermark>,
+    
+    // Size of a character cell, for the terminal's size in cells and pixels
+    metrics: FontMetrics,
+    
+    // Lines of output that arrived while the view was scrolled back
+    unseen_lines: usize,
+    
+    // Lines of output completed so far, for telling which are new
+    completed_lines: u64,
 }
 
 impl TerminalWindow {
     /// Create a new terminal window
     pub fn new(id: Uuid, title: &str) -> Self {
-        // Create an event listener for the terminal
-        let event_listener = EventListener::new();
-        
-        // Create a terminal with default config
-        let term = Term::new(
-            alacritty_terminal::term::Config::default(),
-            &alacritty_terminal::grid::Dimensions::new(80, 24),
-            event_listener
-        );
-        
-        // Set up modes (e.g., enable application cursor keys)
-        term.set_mode(TermMode::APP_CURSOR);
+        // Create a terminal with default config, keeping track of the kitty keyboard protocol
+        let config = Config { kitty_keyboard: true, ..Config::default() };
+        let term = Term::new(config, &TermSize::new(80, 24), VoidListener);
         
         Self {
             id,
@@ -67,12 +88,38 @@ impl TerminalWindow {
             size: Size::new(800.0, 600.0),
             is_focused: false,
             term,
+            parser: ansi::Processor::new(),
+            modify_other_keys: 0,
             pty_master: None,
             pty_writer: None,
             child_process: None,
+            watermark: None,
+            metrics: FontMetrics::new(FONT_SIZE, 1.0),
+            unseen_lines: 0,
+            completed_lines: 0,
+        }
+    }
+    
+    /// The size of the PTY for a terminal of `columns` by `rows`, in cells and physical pixels
+    fn pty_size(&self, columns: u16, rows: u16) -> PtySize {
+        let (width, height) = self.metrics.calculate_terminal_size(columns, rows);
+        let scale = self.metrics.scale_factor;
+        PtySize {
+            rows,
+            cols: columns,
+            pixel_width: (width * scale).round() as u16,
+            pixel_height: (height * scale).round() as u16,
         }
     }
     
+    /// Take on the look of a profile
+    pub fn set_profile(&mut self, profile: &GuiProfile) {
+        self.watermark = profile.watermark.as_ref().map(|text| Watermark {
+            text: text.clone(),
+            opacity: profile.watermark_opacity,
+        });
+    }
+    
     /// Spawn a shell in the terminal
     pub fn spawn_shell(&mut self) -> Command<TerminalMessage> {
         // Get native PTY system
@@ -82,12 +129,7 @@ impl TerminalWindow {
         let columns = 80u16;
         let rows = 24u16;
         
-        match pty_system.openpty(PtySize {
-            rows,
-            cols: columns,
-            pixel_width: 0,
-            pixel_height: 0,
-        }) {
+        match pty_system.openpty(self.pty_size(columns, rows)) {
             Ok(pair) => {
                 self.pty_master = Some(pair.master);
                 
@@ -176,6 +218,11 @@ impl TerminalWindow {
     /// Handle terminal update
     pub fn update(&mut self, message: TerminalMessage) -> Command<TerminalMessage> {
         match message {
+            // P goes back to the newest output while scrolled back
+            TerminalMessage::Input(data) if self.term.grid().display_offset() > 0 && (data == b"p" || data == b"P") => {
+                self.update(TerminalMessage::JumpToBottom)
+            },
+            
             TerminalMessage::Input(data) => {
                 if let Err(e) = self.send_input(&data) {
                     log::error!("Failed to send input: {}", e);
@@ -184,8 +231,19 @@ impl TerminalWindow {
             },
             
             TerminalMessage::Output(data) => {
+                // Scroll lock: the terminal keeps a view scrolled back where it is, so
+                // count the lines that arrive below it
+                let lines = data.iter().filter(|&&b| b == b'\n').count();
+                self.completed_lines += lines as u64;
+                if self.term.grid().display_offset() > 0 {
+                    self.unseen_lines += lines;
+                }
+                
                 // Process the received data in the terminal
-                self.term.take_child().unwrap().advance_bytes(&data);
+                self.parser.advance(&mut self.term, &data);
+                if let Some(level) = keyboard::modify_other_keys_request(&data) {
+                    self.modify_other_keys = level;
+                }
                 
                 // Continue reading from the PTY
                 if let Some(pty_master) = &mut self.pty_master {
@@ -202,27 +260,31 @@ impl TerminalWindow {
             TerminalMessage::Resize(size) => {
                 self.size = size;
                 
-                // Calculate terminal dimensions based on size
-                // This is simplified and would need to be based on actual font metrics
-                let cols = (size.width / 8.0) as u16;
-                let rows = (size.height / 16.0) as u16;
+                // Calculate terminal dimensions from the size of a character cell
+                let (cols, rows) = self.metrics.pixel_to_cell(size.width, size.height);
                 
                 // Resize the terminal
-                self.term.resize(Dimensions::new(cols, rows));
+                self.term.resize(TermSize::new(cols as usize, rows as usize));
                 
                 // Resize the PTY
+                let pty_size = self.pty_size(cols, rows);
                 if let Some(pty_master) = &mut self.pty_master {
-                    let _ = pty_master.resize(PtySize {
-                        rows,
-                        cols,
-                        pixel_width: 0,
-                        pixel_height: 0,
-                    });
+                    let _ = pty_master.resize(pty_size);
                 }
                 
                 Command::none()
             },
             
+            TerminalMessage::ScaleFactor(scale) => {
+                if scale == self.metrics.scale_factor {
+                    return Command::none();
+                }
+                
+                // Cells snap to the new pixel grid, which can change how many fit
+                self.metrics = FontMetrics::new(FONT_SIZE, scale);
+                self.update(TerminalMessage::Resize(self.size))
+            },
+            
             TerminalMessage::ProcessExit(_status) => {
                 // Handle process exit
                 // For now, we'll just log it
@@ -230,6 +292,20 @@ impl TerminalWindow {
                 Command::none()
             },
             
+            TerminalMessage::Scroll(lines) => {
+                self.term.scroll_display(Scroll::Delta(lines));
+                if self.term.grid().display_offset() == 0 {
+                    self.unseen_lines = 0;
+                }
+                Command::none()
+            },
+            
+            TerminalMessage::JumpToBottom => {
+                self.term.scroll_display(Scroll::Bottom);
+                self.unseen_lines = 0;
+                Command::none()
+            },
+            
             TerminalMessage::Focused => {
                 self.is_focused = true;
                 Command::none()
@@ -263,29 +339,90 @@ impl TerminalWindow {
         Command::none()
     }
     
+    /// Text of the current selection, if any
+    pub fn selected_text(&self) -> Option<String> {
+        self.term.selection_to_string().filter(|text| !text.is_empty())
+    }
+    
+    /// How key presses are to be encoded for the application running in the terminal
+    pub fn keyboard_mode(&self) -> KeyboardMode {
+        keyboard::keyboard_mode(*self.term.mode(), self.modify_other_keys)
+    }
+    
     /// Get the terminal title
     pub fn title(&self) -> &str {
         &self.title
     }
     
+    /// Rename the terminal
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+    
     /// Get the terminal ID
     pub fn id(&self) -> Uuid {
         self.id
     }
     
-    /// Render the terminal
-    pub fn view(&self) -> Element<TerminalMessage> {
+    /// Lines of output completed so far
+    pub fn completed_lines(&self) -> u64 {
+        self.completed_lines
+    }
+    
+    /// The text of the last `count` completed lines, those above the cursor
+    pub fn recent_text(&self, count: usize) -> String {
+        let cursor = self.term.grid().cursor.point.line;
+        let start = Line(cursor.0 - count as i32).max(self.term.topmost_line());
+        if count == 0 || start >= cursor {
+            return String::new();
+        }
+        let end = Point::new(Line(cursor.0 - 1), self.term.last_column());
+        self.term.bounds_to_string(Point::new(start, Column(0)), end)
+    }
+    
+    /// Size of a character cell at the terminal's scale factor
+    pub fn metrics(&self) -> &FontMetrics {
+        &self.metrics
+    }
+    
+    /// Render the terminal with a background of the given opacity
+    pub fn view(&self, opacity: f32) -> Element<TerminalMessage> {
         // This is just a placeholder for now
         // A real implementation would render the terminal content
-        iced::widget::container(
-            iced::widget::text(&self.title)
-                .size(14)
-                .style(iced::theme::Text::Color(colors::MATRIX_GREEN))
-        )
+        let title = iced::widget::text(&self.title)
+            .size(14)
+            .style(iced::theme::Text::Color(colors::MATRIX_GREEN));
+        
+        // The watermark fills the terminal area; cells are to be drawn over it
+        let content: Element<TerminalMessage> = match &self.watermark {
+            Some(watermark) => iced::widget::column![
+                title,
+                iced::widget::canvas(watermark.clone())
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fill),
+            ].into(),
+            None => title.into(),
+        };
+        
+        // While scrolled back, a pill says how much output arrived below and goes back to it
+        let content: Element<TerminalMessage> = if self.unseen_lines > 0 {
+            let pill = iced::widget::button(
+                iced::widget::text(format!("↓ {} new line{} (P)", self.unseen_lines, if self.unseen_lines == 1 { "" } else { "s" })).size(12)
+            )
+            .on_press(TerminalMessage::JumpToBottom);
+            iced::widget::column![
+                content,
+                iced::widget::container(pill).width(iced::Length::Fill).align_x(iced::alignment::Horizontal::Right),
+            ].into()
+        } else {
+            content
+        };
+        
+        iced::widget::container(content)
         .width(iced::Length::Fill)
         .height(iced::Length::Fill)
         .style(iced::theme::Container::Custom(Box::new(
-            TerminalContainerStyle { focused: self.is_focused }
+            TerminalContainerStyle { focused: self.is_focused, opacity }
         )))
         .into()
     }
@@ -0,0 +1,95 @@
+//! Tray icon and global hotkey for background mode: Matrix keeps its terminal windows
+//! running with no OS window open, and the tray icon or hotkey brings them back.
+
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem};
+use tray_icon::{ClickType, Icon, TrayIcon, TrayIconBuilder, TrayIconEvent};
+
+/// Something asked for from the tray icon or global hotkey
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrayRequest {
+    /// Raise Matrix, reopening a window for the background terminal windows
+    Show,
+    /// Close every terminal window and exit
+    Quit,
+}
+
+/// The tray icon with its menu, and the global hotkey if one is registered
+pub struct Tray {
+    // Kept alive for as long as the icon is shown
+    _icon: TrayIcon,
+    show_item: MenuItem,
+    quit_item: MenuItem,
+    hotkeys: Option<(GlobalHotKeyManager, HotKey)>,
+}
+
+impl Tray {
+    /// Show the tray icon and register the hotkey (e.g. "CmdOrCtrl+Shift+M")
+    pub fn install(hotkey: Option<&str>) -> anyhow::Result<Self> {
+        let show_item = MenuItem::new("Show Matrix", true, None);
+        let quit_item = MenuItem::new("Quit Matrix", true, None);
+        let menu = Menu::new();
+        menu.append_items(&[&show_item, &quit_item])?;
+
+        let icon = TrayIconBuilder::new()
+            .with_tooltip("Matrix Terminal")
+            .with_icon(matrix_icon()?)
+            .with_menu(Box::new(menu))
+            .build()?;
+
+        let hotkeys = match hotkey {
+            Some(text) => {
+                let manager = GlobalHotKeyManager::new()?;
+                let hotkey: HotKey = text.parse()?;
+                manager.register(hotkey)?;
+                Some((manager, hotkey))
+            }
+            None => None,
+        };
+
+        Ok(Self { _icon: icon, show_item, quit_item, hotkeys })
+    }
+
+    /// Requests since the last poll
+    pub fn poll(&self) -> Vec<TrayRequest> {
+        let mut requests = Vec::new();
+
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            if event.id() == self.show_item.id() {
+                requests.push(TrayRequest::Show);
+            } else if event.id() == self.quit_item.id() {
+                requests.push(TrayRequest::Quit);
+            }
+        }
+
+        // A left click on the icon raises Matrix, the menu opens on a right click
+        while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+            if event.click_type == ClickType::Left {
+                requests.push(TrayRequest::Show);
+            }
+        }
+
+        while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+            let ours = self.hotkeys.as_ref().is_some_and(|(_, hotkey)| hotkey.id() == event.id);
+            if ours && event.state == HotKeyState::Pressed {
+                requests.push(TrayRequest::Show);
+            }
+        }
+
+        requests
+    }
+}
+
+/// A small green square with a dark border
+fn matrix_icon() -> anyhow::Result<Icon> {
+    const SIZE: u32 = 32;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let edge = x < 2 || y < 2 || x >= SIZE - 2 || y >= SIZE - 2;
+            rgba.extend_from_slice(if edge { &[0, 40, 10, 255] } else { &[0, 255, 65, 255] });
+        }
+    }
+    Ok(Icon::from_rgba(rgba, SIZE, SIZE)?)
+}
@@ -0,0 +1,19 @@
+/// The system's light or dark appearance, as reported by macOS (the global
+/// AppleInterfaceStyle default) or the freedesktop color-scheme setting (gsettings)
+use std::process::Command;
+
+/// Whether the system is in dark mode, or None where it can't be found out
+pub fn dark_mode() -> Option<bool> {
+    if cfg!(target_os = "macos") {
+        // Only set at all in dark mode
+        let output = Command::new("defaults").args(["read", "-g", "AppleInterfaceStyle"]).output().ok()?;
+        return Some(String::from_utf8_lossy(&output.stdout).trim().eq_ignore_ascii_case("dark"));
+    }
+
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).contains("dark"))
+}
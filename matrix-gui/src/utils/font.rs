@@ -4,26 +4,33 @@ pub struct FontMetrics {
     pub height: f32,
     pub descender: f32,
     pub line_height: f32,
+    /// Physical pixels per logical pixel on the monitor the terminal is shown on
+    pub scale_factor: f32,
 }
 
 impl FontMetrics {
-    /// Create default font metrics for a given font size
-    pub fn new(font_size: f32) -> Self {
+    /// Create default font metrics for a given font size on a monitor with some scale factor
+    pub fn new(font_size: f32, scale_factor: f32) -> Self {
+        // Cells are a whole number of physical pixels, so the grid stays crisp and
+        // hit-testing lines up with what's drawn at any DPI
+        let snap = |size: f32| (size * scale_factor).round().max(1.0) / scale_factor;
+        
         // These are approximate values that work well for monospace fonts
-        let width = font_size * 0.6;         // Character width (approximate for monospace)
-        let height = font_size;              // Base character height
-        let descender = font_size * 0.2;     // Space below the baseline
-        let line_height = font_size * 1.2;   // Total height including line spacing
+        let width = snap(font_size * 0.6);         // Character width (approximate for monospace)
+        let height = font_size;                    // Base character height
+        let descender = font_size * 0.2;           // Space below the baseline
+        let line_height = snap(font_size * 1.2);   // Total height including line spacing
         
         Self {
             width,
             height,
             descender,
             line_height,
+            scale_factor,
         }
     }
     
-    /// Calculate the size needed for a terminal with the given dimensions
+    /// Calculate the size needed for a terminal with the given dimensions, in logical pixels
     pub fn calculate_terminal_size(&self, columns: u16, rows: u16) -> (f32, f32) {
         let width = self.width * columns as f32;
         let height = self.line_height * rows as f32;
@@ -1,71 +1,107 @@
-use iced::keyboard::{self, KeyCode, Modifiers};
+use alacritty_terminal::term::TermMode;
+use iced::keyboard::{key::Named, Modifiers};
 
-/// Convert iced key events to terminal input bytes
-pub fn key_to_terminal_input(key: KeyCode, modifiers: Modifiers) -> Option<Vec<u8>> {
-    match key {
-        // Basic ASCII characters
-        KeyCode::Char(c) => {
-            let mut bytes = Vec::new();
-            
-            // Handle control characters
-            if modifiers.control() {
-                let control_char = match c {
-                    'a'..='z' => (c as u8 - b'a' + 1),
-                    'A'..='Z' => (c as u8 - b'A' + 1),
-                    // Add more control character mappings as needed
-                    _ => return None,
-                };
-                bytes.push(control_char);
-            } else {
-                // Regular character
-                bytes.extend_from_slice(c.to_string().as_bytes());
+use crate::terminal::keyboard::{
+    encode_key, Key, KeyModifiers, KeyboardMode, KITTY_DISAMBIGUATE, KITTY_REPORT_ALL_KEYS,
+};
+
+/// The key the terminal's encoder knows an iced key as, if it's one it encodes
+pub fn terminal_key(key: &iced::keyboard::Key) -> Option<Key> {
+    let key = match key {
+        iced::keyboard::Key::Character(text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Char(c),
+                _ => return None,
             }
-            
-            Some(bytes)
         },
-        
-        // Special keys
-        KeyCode::Enter => Some(vec![b'\r']),
-        KeyCode::Tab => Some(vec![b'\t']),
-        KeyCode::Backspace => Some(vec![0x7F]), // Delete character
-        KeyCode::Escape => Some(vec![0x1B]),    // ESC
-        
-        // Function keys (F1-F12)
-        KeyCode::F(num) => {
-            // Convert function keys to their typical escape sequences
-            // This is a simplified implementation
-            let seq = match num {
-                1 => b"\x1BOP".to_vec(),
-                2 => b"\x1BOQ".to_vec(),
-                3 => b"\x1BOR".to_vec(),
-                4 => b"\x1BOS".to_vec(),
-                5 => b"\x1B[15~".to_vec(),
-                6 => b"\x1B[17~".to_vec(),
-                7 => b"\x1B[18~".to_vec(),
-                8 => b"\x1B[19~".to_vec(),
-                9 => b"\x1B[20~".to_vec(),
-                10 => b"\x1B[21~".to_vec(),
-                11 => b"\x1B[23~".to_vec(),
-                12 => b"\x1B[24~".to_vec(),
-                _ => return None,
-            };
-            
-            Some(seq)
+        iced::keyboard::Key::Named(named) => match named {
+            Named::Enter => Key::Enter,
+            Named::Tab => Key::Tab,
+            Named::Backspace => Key::Backspace,
+            Named::Escape => Key::Esc,
+            Named::Space => Key::Char(' '),
+            Named::ArrowUp => Key::Up,
+            Named::ArrowDown => Key::Down,
+            Named::ArrowLeft => Key::Left,
+            Named::ArrowRight => Key::Right,
+            Named::Home => Key::Home,
+            Named::End => Key::End,
+            Named::PageUp => Key::PageUp,
+            Named::PageDown => Key::PageDown,
+            Named::Insert => Key::Insert,
+            Named::Delete => Key::Delete,
+            Named::F1 => Key::F(1),
+            Named::F2 => Key::F(2),
+            Named::F3 => Key::F(3),
+            Named::F4 => Key::F(4),
+            Named::F5 => Key::F(5),
+            Named::F6 => Key::F(6),
+            Named::F7 => Key::F(7),
+            Named::F8 => Key::F(8),
+            Named::F9 => Key::F(9),
+            Named::F10 => Key::F(10),
+            Named::F11 => Key::F(11),
+            Named::F12 => Key::F(12),
+            _ => return None,
         },
-        
-        // Arrow keys and navigation
-        KeyCode::Up => Some(b"\x1B[A".to_vec()),
-        KeyCode::Down => Some(b"\x1B[B".to_vec()),
-        KeyCode::Right => Some(b"\x1B[C".to_vec()),
-        KeyCode::Left => Some(b"\x1B[D".to_vec()),
-        KeyCode::Home => Some(b"\x1B[H".to_vec()),
-        KeyCode::End => Some(b"\x1B[F".to_vec()),
-        KeyCode::PageUp => Some(b"\x1B[5~".to_vec()),
-        KeyCode::PageDown => Some(b"\x1B[6~".to_vec()),
-        KeyCode::Delete => Some(b"\x1B[3~".to_vec()),
-        KeyCode::Insert => Some(b"\x1B[2~".to_vec()),
-        
-        // Unhandled keys
-        _ => None,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The keyboard protocol the application asked for: kitty flags from the emulator's
+/// modes, and the modifyOtherKeys level seen in its output
+pub fn keyboard_mode(mode: TermMode, modify_other_keys: u8) -> KeyboardMode {
+    let mut kitty_flags = 0;
+    if mode.contains(TermMode::DISAMBIGUATE_ESC_CODES) {
+        kitty_flags |= KITTY_DISAMBIGUATE;
+    }
+    if mode.contains(TermMode::REPORT_ALL_KEYS_AS_ESC) {
+        kitty_flags |= KITTY_REPORT_ALL_KEYS;
+    }
+    KeyboardMode { kitty_flags, modify_other_keys }
+}
+
+/// Convert an iced key press to terminal input bytes, with the encoder the TUI uses
+pub fn key_to_terminal_input(key: &iced::keyboard::Key, modifiers: Modifiers, mode: KeyboardMode) -> Option<Vec<u8>> {
+    // Shift+Tab has its own key in the encoder
+    let key = match terminal_key(key)? {
+        Key::Tab if modifiers.shift() => Key::BackTab,
+        key => key,
+    };
+    let modifiers = KeyModifiers {
+        shift: modifiers.shift(),
+        alt: modifiers.alt(),
+        ctrl: modifiers.control(),
+    };
+    Some(encode_key(key, modifiers, mode))
+}
+
+/// The modifyOtherKeys level set by the last request in some output (CSI > 4 ; v m,
+/// or CSI > 4 n to turn it off), if there's one. The emulator doesn't keep this mode.
+pub fn modify_other_keys_request(output: &[u8]) -> Option<u8> {
+    const INTRODUCER: &[u8] = b"\x1b[>4";
+
+    let mut level = None;
+    let mut rest = output;
+    while let Some(start) = rest.windows(INTRODUCER.len()).position(|w| w == INTRODUCER) {
+        rest = &rest[start + INTRODUCER.len()..];
+        let Some(end) = rest.iter().position(|b| !(b.is_ascii_digit() || *b == b';')) else {
+            break;
+        };
+
+        // Parameters other than 4 (e.g. 45) are other resources
+        let params = std::str::from_utf8(&rest[..end]).unwrap_or("");
+        if params.is_empty() || params.starts_with(';') {
+            let value = params.split(';').nth(1).and_then(|v| v.parse::<u8>().ok()).unwrap_or(0);
+            match rest[end] {
+                b'm' => level = Some(value.min(2)),
+                b'n' => level = Some(0),
+                _ => {},
+            }
+        }
+        rest = &rest[end..];
     }
-}
\ No newline at end of file
+    level
+}
@@ -1,2 +1,3 @@
 pub mod keyboard;
-pub mod font;
\ No newline at end of file
+pub mod font;pub mod appearance;
+pub mod speech;
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use uuid::Uuid;
+
+use crate::settings::GuiAccessibility;
+use crate::terminal::window::TerminalWindow;
+
+/// Sends output and UI state to an external speech command (e.g. speech-dispatcher's
+/// spd-say), as the TUI does
+pub struct Announcer {
+    // Program and arguments of the speech command
+    command: Vec<String>,
+    // Speak new output lines of the focused terminal window
+    announce_output: bool,
+    // Speak focus and theme changes
+    announce_ui: bool,
+    // Maximum number of lines spoken per update
+    max_lines: usize,
+    // Number of completed lines already spoken for each terminal window
+    seen_lines: HashMap<Uuid, u64>,
+    // Last terminal window announced as focused
+    last_focused: Option<Uuid>,
+    // Speech processes that may still be running
+    children: Vec<Child>,
+}
+
+impl Announcer {
+    /// Create an announcer from the accessibility settings
+    pub fn new(settings: &GuiAccessibility) -> Self {
+        let command = settings.speech_command
+            .as_deref()
+            .map(|cmd| cmd.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+
+        Self {
+            command,
+            announce_output: settings.announce_output,
+            announce_ui: settings.announce_ui,
+            max_lines: settings.max_lines,
+            seen_lines: HashMap::new(),
+            last_focused: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Is a speech command configured
+    pub fn is_enabled(&self) -> bool {
+        !self.command.is_empty()
+    }
+
+    /// Speak a piece of text
+    pub fn speak(&mut self, text: &str) {
+        // Reap speech processes that have finished
+        self.children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let text = text.trim();
+        if !self.is_enabled() || text.is_empty() {
+            return;
+        }
+
+        let spawned = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match spawned {
+            Ok(child) => self.children.push(child),
+            Err(e) => log::warn!("Error running speech command: {}", e),
+        }
+    }
+
+    /// Speak a UI state change (focus, theme) if enabled
+    pub fn announce_ui(&mut self, text: &str) {
+        if self.announce_ui {
+            self.speak(text);
+        }
+    }
+
+    /// Announce a change of the focused terminal window
+    pub fn focus_changed(&mut self, window: &TerminalWindow) {
+        let id = window.id();
+        if self.last_focused == Some(id) {
+            return;
+        }
+
+        self.last_focused = Some(id);
+
+        // Don't read out everything that happened while the window was in the background
+        self.seen_lines.insert(id, window.completed_lines());
+
+        self.announce_ui(&format!("Focused {}", window.title()));
+    }
+
+    /// Speak the lines completed in a terminal window since the last update
+    pub fn announce_new_output(&mut self, window: &TerminalWindow) {
+        let completed = window.completed_lines();
+        let seen = self.seen_lines.insert(window.id(), completed).unwrap_or(completed);
+
+        if !self.announce_output || completed <= seen {
+            return;
+        }
+
+        let new_lines = (completed - seen) as usize;
+        let text = window.recent_text(new_lines.min(self.max_lines));
+        self.speak(&text);
+    }
+}
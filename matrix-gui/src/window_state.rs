@@ -0,0 +1,106 @@
+use anyhow::Result;
+use iced::{window, Point, Size};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Where and how large the OS window was, restored at the next start
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    /// Top-left corner on the desktop, once the window has been moved
+    pub position: Option<(f32, f32)>,
+    /// Size when neither maximized nor fullscreen
+    pub width: f32,
+    pub height: f32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+}
+
+/// A change to an OS window's geometry
+#[derive(Debug, Clone, Copy)]
+pub enum Change {
+    Moved(Point),
+    Resized(Size),
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            position: None,
+            width: 1024.0,
+            height: 768.0,
+            maximized: false,
+            fullscreen: false,
+        }
+    }
+}
+
+impl WindowState {
+    /// Location of the saved window state, in the data directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("matrix").join("gui-window.json"))
+    }
+
+    /// Load the saved window state, if there is one
+    pub fn load() -> Result<Option<Self>> {
+        match Self::path() {
+            Some(path) if path.exists() => Ok(Some(serde_json::from_slice(&fs::read(path)?)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Save the window state
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow::anyhow!("no data directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Forget the saved window state, e.g. after the window ended up off-screen
+    pub fn reset() -> Result<()> {
+        match Self::path() {
+            Some(path) if path.exists() => Ok(fs::remove_file(path)?),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record a move or resize; while maximized or fullscreen the window isn't where
+    /// it goes back to, so only the mode is kept
+    pub fn update(&mut self, change: Change, maximized: bool) {
+        self.maximized = maximized;
+        if maximized || self.fullscreen {
+            return;
+        }
+        match change {
+            Change::Moved(position) => self.position = Some((position.x, position.y)),
+            Change::Resized(size) => {
+                self.width = size.width;
+                self.height = size.height;
+            },
+        }
+    }
+
+    /// Open a window where this state says
+    pub fn apply(&self, settings: &mut window::Settings) {
+        settings.size = Size::new(self.width, self.height);
+        if let Some((x, y)) = self.position {
+            settings.position = window::Position::Specific(Point::new(x, y));
+        }
+    }
+
+    /// Commands that put an opened window back in the saved mode
+    pub fn restore<Message: 'static>(&self, id: window::Id) -> Vec<iced::Command<Message>> {
+        let mut commands = Vec::new();
+        if self.maximized {
+            commands.push(window::maximize(id, true));
+        }
+        if self.fullscreen {
+            commands.push(window::change_mode(id, window::Mode::Fullscreen));
+        }
+        commands
+    }
+}
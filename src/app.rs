@@ -1,18 +1,190 @@
 use crate::terminal::terminal::Terminal;
 use crate::terminal::events::{EventHandler, AppEvent};
-use crate::config::settings::Settings;
-use crate::ui::{style, widgets, window_manager::{WindowManager, SplitDirection, Direction}, sidebar::{Sidebar, SidebarIcon}};
+use crate::terminal::keyboard::{self, Key};
+use crate::terminal::clipboard::{self, Clipboard, ClipboardRequest};
+use crate::terminal::tmux::{TmuxSession, TmuxEvent, PaneInfo};
+use crate::terminal::triggers::{Triggers, TriggerEvent};
+use crate::terminal::export;
+use crate::terminal::sandbox::{self, quote};
+use crate::terminal::links::FileLink;
+use crate::terminal::buffer::MarkKind;
+use crate::terminal::supervisor::Supervisor;
+use crate::terminal::pipe::OutputPipe;
+use crate::terminal::style::{StyleMapping, StyleRun};
+use crate::terminal::copy_mode::{CopyAction, CopyMode};
+use crate::terminal::snippets::SnippetFill;
+use crate::terminal::redact::Redactor;
+use crate::terminal::smart_select::{SmartMatch, SmartSelection};
+use crate::terminal::process::ReaderStatus;
+use crate::config::settings::{Settings, Osc52Policy, EditorTarget, PasteNewlines, RestoreMode};
+use crate::config::macros::{Macros, Recording};
+use crate::config::check;
+use crate::history::{History, Source};
+use crate::frecency::{self, Frecency};
+use crate::session::Session;
+use crate::workspace::{self, WorkspaceTemplate};
+use crate::metrics::{Metrics, MetricsServer};
+use crate::remote::{self, Channels, EventWatcher, RemoteServer};
+use crate::ui::picker::{Picker, PickerItem};
+use crate::ui::notifications::Notifications;
+use crate::ui::preferences::Preferences;
+use crate::ui::bell::Bell;
+use crate::ui::status_bar::StatusBar;
+use crate::ui::status_scripts::StatusScripts;
+use crate::ui::lock_screen::LockScreen;
+use crate::ui::config_errors::ConfigErrors;
+use crate::ui::hints::{HintAction, Hints};
+use crate::ui::screenshot;
+use crate::ui::low_color::{self, ColorDepth};
+use crate::ui::panels::{self, PanelKind, Panels};
+use crate::ui::keymap::{self, Chord, Keymaps, Lookup, Mode};
+use crate::ui::theme_files::{self, ThemeWatcher};
+use crate::ui::theme_editor::{EditorAction, ThemeEditor};
+use crate::ui::cheatsheet::{self, Cheatsheet};
+use crate::ui::tutorial::{Progress, Tutorial};
+use crate::ui::diff_view::DiffView;
+use crate::ui::pager::Pager;
+use crate::utils::appearance::{Appearance, AppearanceWatcher};
+use crate::utils::paths;
+use crate::ui::{style::Theme, widgets, window_manager::{WindowManager, SplitDirection, Direction}, sidebar::{Sidebar, SidebarIcon}, accessibility::Announcer};
 use anyhow::Result;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, BorderType};
 use crossterm::event::{KeyCode, KeyModifiers, KeyEvent, MouseEvent, MouseEventKind, MouseButton};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use uuid::Uuid;
 
+// Name of the session saved automatically
+const AUTOSAVE_SESSION: &str = "last";
+
+// How often window titles are matched to their foreground programs, and the
+// current window's directory noted
+const TITLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long :theme preview shows a theme before going back
+const THEME_PREVIEW_TIME: Duration = Duration::from_secs(10);
+
+// Recently used directories offered next to the bookmarks
+const MAX_RECENT_DIRS: usize = 20;
+
+// Most search results listed in the picker
+const MAX_SEARCH_RESULTS: usize = 1000;
+
+// Characters of each copy previewed when picking one to paste
+const PASTE_PREVIEW_CHARS: usize = 60;
+
+// How far an arrow moves a border in resize mode, and with Shift (fractions of the split)
+const RESIZE_STEP: f32 = 0.02;
+const RESIZE_STEP_LARGE: f32 = 0.1;
+
+// Longest time between the clicks of a double-click
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+// Macro recorded into when no name is given
+const DEFAULT_MACRO: &str = "q";
+
 // Application state
 pub enum AppState {
     Normal,
     Command,
+    // Waiting for the user to allow or deny an OSC 52 clipboard write
+    ClipboardPrompt,
+    // Showing where a split will go before creating it
+    SplitPreview,
+    // Choosing an entry from a picker
+    Picker,
+    // Editing settings
+    Preferences,
+    // Editing the colors of the theme
+    ThemeEditor,
+    // Looking through the keys in effect
+    Cheatsheet,
+    // Scrubbing through the focused window's recorded screens
+    Timeline,
+    // Comparing two windows' lines
+    Diff,
+    // Reading a file or a window's history in the built-in pager
+    Pager,
+    // Moving a cursor over a window's scrollback to select and copy text
+    Copy,
+    // Moving the focused window's borders with the keys bound in resize mode
+    Resize,
+    // Typing a pattern to search the focused window for
+    Search,
+    // Asking for the placeholders of a snippet being inserted
+    Snippet,
+    // Every window hidden until the password is typed
+    Locked,
+    // Choosing a labelled match in the focused window to copy, paste or open
+    Hints,
+    // Asking whether to bring back the last session on start
+    RestorePrompt,
+    // Listing what's wrong with the settings file
+    ConfigErrors,
+}
+
+// What choosing a picker or sidebar panel entry does
+#[derive(Clone)]
+enum PickerTarget {
+    // Show a line of a window
+    Line { window: Uuid, line: usize },
+    // Open a file location from a window's output
+    File { window: Uuid, link: FileLink },
+    // Focus a window
+    Window(Uuid),
+    // Make an earlier copy the clipboard contents again
+    ClipboardEntry(usize),
+    // Paste an earlier copy into the focused window
+    Paste(usize),
+    // Play a macro
+    Macro(String),
+    // Insert a snippet
+    Snippet(String),
+    // Type a command from the history, without running it
+    Command(String),
+    // Change to a directory, or open a window there
+    Directory { path: String, new_window: bool },
+    // Run a command again in the focused window
+    Run(String),
+    // Open a window connected to an SSH host
+    Ssh(String),
+}
+
+// Windows that receive typed input together
+#[derive(Debug, Clone, PartialEq)]
+enum BroadcastTarget {
+    // Every window
+    All,
+    // Windows in a group
+    Group(String),
+}
+
+// A split waiting for confirmation
+struct SplitPreview {
+    // Window being split
+    target: Uuid,
+    direction: SplitDirection,
+    // Does the new window go left of / above the target
+    new_first: bool,
+}
+
+impl SplitPreview {
+    // Area the new window will take up
+    fn rect(&self, target: Rect) -> Rect {
+        match (self.direction, self.new_first) {
+            (SplitDirection::Horizontal, first) => {
+                let width = target.width / 2;
+                let x = if first { target.x } else { target.x + target.width - width };
+                Rect::new(x, target.y, width, target.height)
+            }
+            (SplitDirection::Vertical, first) => {
+                let height = target.height / 2;
+                let y = if first { target.y } else { target.y + target.height - height };
+                Rect::new(target.x, y, target.width, height)
+            }
+        }
+    }
 }
 
 pub struct App {
@@ -34,20 +206,196 @@ pub struct App {
     command_history: Vec<String>,
     // Sidebar for icon-based controls
     sidebar: Sidebar,
+    // Tool panels docked in the sidebar, and the last search's results for the search panel
+    panels: Panels<PickerTarget>,
+    search_results: Vec<(PickerItem, PickerTarget)>,
+    // Active color theme and its name
+    theme: Theme,
+    theme_name: String,
+    // Notices theme files being written, to show the active one again
+    theme_watcher: ThemeWatcher,
+    // When a theme being previewed was shown; it's dropped after THEME_PREVIEW_TIME unless kept
+    theme_preview: Option<Instant>,
+    // Reports the system's light or dark appearance, when themes follow it
+    appearance: Option<AppearanceWatcher>,
+    // Colors the terminal can show; frames are brought down to them
+    color_depth: ColorDepth,
+    // Screen reader / speech output
+    announcer: Announcer,
+    // Clipboard shared by all windows
+    clipboard: Clipboard,
+    // Clipboard write awaiting confirmation (window, decoded text)
+    pending_clipboard: Option<(Uuid, String)>,
+    // Attached tmux control-mode sessions
+    tmux_sessions: Vec<TmuxSession>,
+    // Split being previewed
+    split_preview: Option<SplitPreview>,
+    // When the session was last saved automatically
+    last_autosave: Instant,
+    // When window titles were last matched to their foreground programs
+    last_title_poll: Instant,
+    // Open picker and what each of its entries does
+    picker: Option<Picker>,
+    picker_targets: Vec<PickerTarget>,
+    // Settings overlay, while open
+    preferences: Option<Preferences>,
+    theme_editor: Option<ThemeEditor>,
+    // Keys in effect, while shown
+    cheatsheet: Option<Cheatsheet>,
+    // Guided tour, while it's being taken
+    tutorial: Option<Tutorial>,
+    // Two windows' lines compared, while shown
+    diff_view: Option<DiffView>,
+    // File or history being read in the built-in pager
+    pager: Option<Pager>,
+    // Output trigger rules
+    triggers: Triggers,
+    // Transient messages in the corner of the screen
+    notifications: Notifications,
+    // Windows typed input is broadcast to
+    broadcast: Option<BroadcastTarget>,
+    // Saved keystroke macros, the one being recorded and the last one recorded or played
+    macros: Macros,
+    recording: Option<Recording>,
+    last_macro: Option<String>,
+    // Snippet whose placeholders are being asked for
+    snippet: Option<SnippetFill>,
+    // Commands run at shell prompts, for searching
+    history: History,
+    // How often and recently directories and commands were used, for quick-open
+    frecency: Frecency,
+    // Masks secrets in text leaving Matrix
+    redactor: Redactor,
+    // Patterns selected whole by double-click and hint mode
+    smart_selection: SmartSelection,
+    // Labels over the matches in the focused window, in hint mode
+    hints: Option<Hints>,
+    // Status bar segments from external commands, run in the background
+    status_scripts: StatusScripts,
+    // Last session, while asking whether to restore it
+    restore_offer: Option<Session>,
+    // Problems found in the settings file, until dismissed
+    config_errors: Option<ConfigErrors>,
+    // Started with the default settings; settings, macros and the automatic session aren't saved
+    safe_mode: bool,
+    // Directories the current window has been in, most recent first
+    recent_dirs: VecDeque<String>,
+    // Lock screen while locked, and the state to go back to when unlocked
+    lock_screen: Option<(LockScreen, AppState)>,
+    // When a key was last pressed or the mouse last used, for locking when idle
+    last_user_input: Instant,
+    // File to save a screenshot of the next frame to
+    pending_screenshot: Option<String>,
+    // Window under the mouse and since when, to focus it after a delay (focus follows mouse)
+    hover_focus: Option<(Uuid, Instant)>,
+    // Key sequences bound to commands in each mode, and the keys of one typed so far
+    keymaps: Keymaps,
+    pending_keys: Vec<Chord>,
+    pending_since: Instant,
+    // A mouse button is held down, so hovering doesn't move the focus
+    mouse_dragging: bool,
+    // Where and when the left button was last pressed, to tell double-clicks
+    last_click: Option<(u16, u16, Instant)>,
+    // Audible bell
+    bell: Bell,
+    // Remote control: the local socket, and the WebSocket server when enabled
+    remote: RemoteServer,
+    // Remote control clients waiting for a signal
+    channels: Channels,
+    // Changes to the windows, for clients subscribed to events
+    remote_events: EventWatcher,
+    // Frame timing and the endpoint serving it
+    metrics: Metrics,
+    metrics_server: Option<MetricsServer>,
+    // Something changed that the next frame has to show
+    redraw: bool,
+    // When the last frame was drawn, for the frame rate limit
+    last_draw: Instant,
+}
+
+// How Matrix was started, from the command line
+#[derive(Debug, Default)]
+pub struct StartOptions {
+    // Ignore the settings, macros and saved sessions and start with the defaults,
+    // leaving the files as they are
+    pub safe_mode: bool,
+    // Start with a fresh window and take the guided tour
+    pub tutorial: bool,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let settings = Settings::load()?;
-        let terminal = Terminal::new()?;
+    pub fn new(options: &StartOptions) -> Result<Self> {
+        // Mistakes in the settings file are shown once started, with the defaults used instead
+        let (settings, config_problems) = if options.safe_mode {
+            (Settings::new(), Vec::new())
+        } else {
+            check::load(&Settings::path())
+        };
+        let terminal = Terminal::new(settings.keyboard.enhance_host_keyboard)?;
         let tick_rate = Duration::from_millis(settings.general.tick_rate_ms);
-        let events = EventHandler::new(tick_rate);
+        let events = EventHandler::new(tick_rate, frame_interval(&settings));
         
         // Create the window manager with an initial size
-        let window_manager = WindowManager::new(Rect::new(0, 0, 80, 24));
+        let mut window_manager = WindowManager::new(Rect::new(0, 0, 80, 24));
+        window_manager.set_wrap_navigation(settings.ui.wrap_navigation);
         
         // Create the sidebar
         let sidebar = Sidebar::new();
+        let mut panels = Panels::new(settings.ui.panel_width);
+        for name in &settings.ui.panels {
+            match PanelKind::from_name(name) {
+                Some(kind) => panels.open(kind),
+                None => eprintln!("Unknown panel {}", name),
+            }
+        }
+        panels.set_items(PanelKind::SshHosts, ssh_host_items());
+        
+        // Resolve the configured theme
+        let theme = Theme::load(&settings.ui.theme).unwrap_or_else(|e| {
+            eprintln!("Error loading theme: {:#}", e);
+            Theme::default()
+        });
+        let theme_name = settings.ui.theme.clone();
+        let appearance = settings.ui.follow_system_appearance.then(AppearanceWatcher::start);
+        let color_depth = ColorDepth::from_name(&settings.ui.color_depth).unwrap_or_else(low_color::detect);
+        
+        // Set up speech output for screen reader users
+        let announcer = Announcer::new(&settings.accessibility);
+        
+        // Compile the output triggers
+        let triggers = Triggers::new(&settings.triggers);
+        
+        let mut clipboard = Clipboard::new();
+        clipboard.set_history_size(settings.clipboard.history_size);
+        
+        let macros = if options.safe_mode {
+            Macros::default()
+        } else {
+            Macros::load().unwrap_or_else(|e| {
+                eprintln!("Error loading macros: {}", e);
+                Macros::default()
+            })
+        };
+        
+        let mut history = History::load().unwrap_or_else(|e| {
+            eprintln!("Error loading command history: {}", e);
+            History::default()
+        });
+        if settings.history.import_shell {
+            history.import_shell(settings.history.max_entries);
+        }
+        // Bindings that don't parse were reported with the other settings problems
+        let (keymaps, _) = Keymaps::new(&settings.keyboard.bindings, &settings.keyboard.modes);
+        
+        let frecency = Frecency::load().unwrap_or_else(|e| {
+            eprintln!("Error loading quick-open ranking: {}", e);
+            Frecency::default()
+        });
+        
+        // Compile the secret patterns
+        let redactor = Redactor::new(&settings.redaction.patterns);
+        let smart_selection = SmartSelection::new(&settings.selection_rules);
+        let status_scripts = StatusScripts::new(&settings.status_scripts);
         
         // Create the app
         let mut app = Self {
@@ -60,10 +408,112 @@ impl App {
             command_buffer: String::new(),
             command_history: Vec::new(),
             sidebar,
+            panels,
+            search_results: Vec::new(),
+            theme,
+            theme_name,
+            theme_watcher: ThemeWatcher::new(),
+            theme_preview: None,
+            appearance,
+            color_depth,
+            announcer,
+            clipboard,
+            pending_clipboard: None,
+            tmux_sessions: Vec::new(),
+            split_preview: None,
+            last_autosave: Instant::now(),
+            last_title_poll: Instant::now(),
+            picker: None,
+            picker_targets: Vec::new(),
+            preferences: None,
+            theme_editor: None,
+            cheatsheet: None,
+            tutorial: None,
+            diff_view: None,
+            pager: None,
+            triggers,
+            notifications: Notifications::new(Duration::from_secs(5)),
+            broadcast: None,
+            macros,
+            recording: None,
+            last_macro: None,
+            snippet: None,
+            history,
+            frecency,
+            redactor,
+            smart_selection,
+            hints: None,
+            status_scripts,
+            restore_offer: None,
+            config_errors: None,
+            safe_mode: options.safe_mode,
+            recent_dirs: VecDeque::new(),
+            lock_screen: None,
+            pending_screenshot: None,
+            hover_focus: None,
+            keymaps,
+            pending_keys: Vec::new(),
+            pending_since: Instant::now(),
+            mouse_dragging: false,
+            last_click: None,
+            last_user_input: Instant::now(),
+            bell: Bell::default(),
+            remote: RemoteServer::default(),
+            channels: Channels::default(),
+            remote_events: EventWatcher::default(),
+            metrics: Metrics::default(),
+            metrics_server: None,
+            redraw: true,
+            last_draw: Instant::now(),
         };
         
-        // Create an initial window
-        app.create_window("Matrix Terminal")?;
+        // Serve metrics for monitoring long-running sessions
+        if app.settings.metrics.endpoint {
+            match MetricsServer::start(app.settings.metrics.port) {
+                Ok(server) => app.metrics_server = Some(server),
+                Err(e) => eprintln!("Error starting metrics endpoint: {}", e),
+            }
+        }
+        
+        // Let scripts (matrix ctl) and companion tools drive Matrix
+        if let Err(e) = app.remote.listen_local() {
+            eprintln!("Error starting the control socket: {}", e);
+        }
+        if app.settings.remote.enabled {
+            app.start_remote();
+        }
+        
+        // Set up the workspace bound to the directory Matrix starts in, or else an initial window
+        let cwd = std::env::current_dir().unwrap_or_default();
+        if app.safe_mode {
+            app.create_window("Matrix Terminal")?;
+            app.announcer.announce_ui("Safe mode: default settings, nothing is saved");
+            if options.tutorial {
+                app.start_tutorial();
+            }
+            return Ok(app);
+        }
+        match workspace::template_for(&app.settings.workspaces, &cwd) {
+            _ if options.tutorial => {
+                app.create_window("Matrix Terminal")?;
+                app.start_tutorial();
+            }
+            Some((name, base)) => {
+                if let Err(e) = app.apply_template(&name, &base) {
+                    eprintln!("Error applying workspace template {}: {}", name, e);
+                    app.create_window("Matrix Terminal")?;
+                }
+            }
+            None => {
+                app.create_window("Matrix Terminal")?;
+                app.offer_last_session();
+            }
+        }
+        
+        if !config_problems.is_empty() {
+            app.config_errors = Some(ConfigErrors::new(config_problems));
+            app.state = AppState::ConfigErrors;
+        }
         
         Ok(app)
     }
@@ -81,7 +531,7 @@ impl App {
             
             // Split window horizontally (Ctrl+H)
             (KeyCode::Char('h'), KeyModifiers::CONTROL) => {
-                if let Err(e) = self.split_window(SplitDirection::Horizontal) {
+                if let Err(e) = self.begin_split(SplitDirection::Horizontal) {
                     eprintln!("Error splitting window: {}", e);
                 }
                 true
@@ -89,7 +539,7 @@ impl App {
             
             // Split window vertically (Ctrl+V)
             (KeyCode::Char('v'), KeyModifiers::CONTROL) => {
-                if let Err(e) = self.split_window(SplitDirection::Vertical) {
+                if let Err(e) = self.begin_split(SplitDirection::Vertical) {
                     eprintln!("Error splitting window: {}", e);
                 }
                 true
@@ -143,6 +593,43 @@ impl App {
                 true
             },
             
+            // Resize the current window (Ctrl+Alt+Arrows)
+            (KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right, m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                let direction = match key_code {
+                    KeyCode::Up => Direction::Up,
+                    KeyCode::Down => Direction::Down,
+                    KeyCode::Left => Direction::Left,
+                    _ => Direction::Right,
+                };
+                if let Err(e) = self.resize_focused_window(direction, 0.05, false) {
+                    eprintln!("Error resizing window: {}", e);
+                }
+                true
+            },
+            
+            // Back to the newest output from a view scrolled back (P)
+            (KeyCode::Char('p' | 'P'), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                if self.window_manager.focused_window().is_some_and(|w| w.buffer.is_scrolled_back()) =>
+            {
+                if let Some(window) = self.window_manager.focused_window_mut() {
+                    window.buffer.scroll_to_bottom();
+                }
+                true
+            },
+            
+            // Scroll back through history (Shift+PageUp/PageDown)
+            (KeyCode::PageUp, KeyModifiers::SHIFT) | (KeyCode::PageDown, KeyModifiers::SHIFT) => {
+                if let Some(window) = self.window_manager.focused_window_mut() {
+                    let page = window.size().height.saturating_sub(2).max(1) as usize;
+                    if key_code == KeyCode::PageUp {
+                        window.buffer.scroll_up(page);
+                    } else {
+                        window.buffer.scroll_down(page);
+                    }
+                }
+                true
+            },
+            
             // Zoom toggle (Ctrl+Z)
             (KeyCode::Char('z'), KeyModifiers::CONTROL) => {
                 if let Some(id) = self.window_manager.focused_window().map(|w| w.id()) {
@@ -213,6 +700,84 @@ impl App {
                 true
             },
             
+            // Copy mode (Alt+C)
+            (KeyCode::Char('c'), KeyModifiers::ALT) => {
+                self.enter_copy_mode();
+                true
+            },
+            
+            // Lock the screen (Alt+L)
+            (KeyCode::Char('l'), KeyModifiers::ALT) => {
+                self.lock();
+                true
+            },
+            
+            // Change to a bookmarked or recent directory (Alt+B)
+            (KeyCode::Char('b'), KeyModifiers::ALT) => {
+                self.pick_directory(false);
+                true
+            },
+            
+            // Quick-open a directory, command or window (Ctrl+Shift+O)
+            (KeyCode::Char('O'), m) if m == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                self.quick_open();
+                true
+            },
+            
+            // Resize the focused window with the arrows (Alt+=)
+            (KeyCode::Char('='), KeyModifiers::ALT) => {
+                self.enter_mode(Mode::Resize);
+                true
+            },
+            
+            // Search the command history (Alt+H)
+            (KeyCode::Char('h'), KeyModifiers::ALT) => {
+                self.pick_history();
+                true
+            },
+            
+            // Insert a snippet (Alt+S)
+            (KeyCode::Char('s'), KeyModifiers::ALT) => {
+                self.pick_snippet();
+                true
+            },
+            
+            // Start or stop recording a macro (Alt+R)
+            (KeyCode::Char('r'), KeyModifiers::ALT) => {
+                self.toggle_recording(DEFAULT_MACRO);
+                true
+            },
+            
+            // Play the last macro again (Alt+P)
+            (KeyCode::Char('p'), KeyModifiers::ALT) => {
+                match self.last_macro.clone() {
+                    Some(name) => self.play_macro(&name, 1),
+                    None => self.announcer.announce_ui("No macro to play"),
+                }
+                true
+            },
+            
+            // Paste from the copy history (Alt+V)
+            (KeyCode::Char('v'), KeyModifiers::ALT) => {
+                self.pick_clipboard_entry("Paste", PickerTarget::Paste);
+                true
+            },
+            
+            // Label the URLs, paths, hashes... in the current window to copy one (Alt+E)
+            (KeyCode::Char('e'), KeyModifiers::ALT) => {
+                self.enter_hint_mode(HintAction::Copy);
+                true
+            },
+            
+            // Resume output paused by flow control (Ctrl+Q); otherwise the key goes to the window
+            (KeyCode::Char('q'), KeyModifiers::CONTROL) if self.window_manager.focused_window().is_some_and(|w| w.output_paused) => {
+                if let Some(window) = self.window_manager.focused_window_mut() {
+                    window.resume_output();
+                }
+                self.announcer.announce_ui("Output resumed");
+                true
+            },
+            
             // Toggle sidebar (Ctrl+B)
             (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
                 self.sidebar.toggle();
@@ -230,26 +795,31 @@ impl App {
         let window_id = self.window_manager.create_window(title)?;
         
         // Start a shell in the window
-        if let Some(window) = self.window_manager.windows_mut().get_mut(&window_id) {
-            // Use the default shell from settings
-            let shell = &self.settings.general.default_shell;
-            window.spawn_process(shell, None)?;
+        self.start_shell(window_id)
+    }
+    
+    // Start splitting the current window, showing a preview first if enabled
+    fn begin_split(&mut self, direction: SplitDirection) -> Result<()> {
+        if !self.settings.ui.split_preview {
+            return self.split_window(direction, false);
+        }
+        
+        if let Some(target) = self.window_manager.focused_window().map(|w| w.id()) {
+            self.split_preview = Some(SplitPreview { target, direction, new_first: false });
+            self.state = AppState::SplitPreview;
+            self.announcer.announce_ui("Split preview");
         }
         
         Ok(())
     }
     
-    // Split the current window
-    fn split_window(&mut self, direction: SplitDirection) -> Result<()> {
+    // Split the current window; the new window goes left/above when `new_first` is set
+    fn split_window(&mut self, direction: SplitDirection, new_first: bool) -> Result<()> {
         if let Some(window_id) = self.window_manager.focused_window().map(|w| w.id()) {
-            let new_id = self.window_manager.split_window(window_id, direction, 0.5)?;
+            let new_id = self.window_manager.split_window_placed(window_id, direction, 0.5, new_first)?;
             
             // Start a shell in the new window
-            if let Some(window) = self.window_manager.windows_mut().get_mut(&new_id) {
-                // Use the default shell from settings
-                let shell = &self.settings.general.default_shell;
-                window.spawn_process(shell, None)?;
-            }
+            self.start_shell(new_id)?;
             
             // Focus the new window
             self.window_manager.focus_window(new_id)?;
@@ -258,6 +828,113 @@ impl App {
         Ok(())
     }
     
+    // Start the default shell in a window
+    fn start_shell(&mut self, window_id: Uuid) -> Result<()> {
+        self.start_command(window_id, None, None, None)
+    }
+    
+    // Start a shell command in a window, or the default shell if there is none,
+    // with the limits of a profile if one is named, in a directory if one is given
+    fn start_command(&mut self, window_id: Uuid, command: Option<&str>, profile: Option<&str>, dir: Option<&str>) -> Result<()> {
+        let profile = match profile {
+            Some(name) => Some(self.settings.profiles.get(name).ok_or_else(|| anyhow::anyhow!("no profile named {}", name))?),
+            None => None,
+        };
+        
+        let dirty_border = self.dirty_border();
+        if let Some(window) = self.window_manager.windows_mut().get_mut(&window_id) {
+            // Keyboard protocols applications may opt into
+            let keyboard = &self.settings.keyboard;
+            window.buffer.set_keyboard_support(keyboard.kitty_protocol, keyboard.modify_other_keys);
+            window.buffer.set_tab_width(self.settings.general.tab_width);
+            
+            // Lines colored by triggers
+            window.line_highlights = self.triggers.highlights();
+            
+            // Border tinted while the window's repository has changes
+            window.dirty_border = dirty_border;
+            window.style_mapping = StyleMapping::new(&self.settings.ui);
+            window.highlight_files = self.settings.ui.highlight_files;
+            
+            // Colors reported to applications that ask
+            let (foreground, background) = self.theme.colors();
+            window.buffer.set_default_colors(foreground, background);
+            
+            // Secrets masked on screen
+            window.redactor = self.settings.redaction.mask_scrollback.then(|| self.redactor.clone());
+            
+            // Pausing output that floods the window
+            window.flow_control = self.settings.general.flow_control;
+            
+            // History size and in-memory compression
+            let scrollback = &self.settings.scrollback;
+            let working_set = scrollback.compress.then_some(scrollback.working_set_lines);
+            window.buffer.set_scrollback(scrollback.max_lines, working_set);
+            window.timeline.configure(Duration::from_secs(scrollback.timeline_interval_secs), scrollback.timeline_snapshots);
+            
+            // What programs are told about the terminal
+            let term = profile.and_then(|profile| profile.term.clone()).unwrap_or_else(|| self.settings.general.term.clone());
+            window.env = vec![(String::from("TERM"), term)];
+            if self.settings.general.colorterm {
+                window.env.push((String::from("COLORTERM"), String::from("truecolor")));
+            }
+            
+            // A profile's commands, typed at the shell's first prompt
+            if let (None, Some(profile)) = (command, profile) {
+                window.queue_init_commands(&profile.init_commands, Duration::from_millis(self.settings.general.init_delay_ms));
+            }
+            
+            window.command = command.map(str::to_string);
+            match (command, profile) {
+                (Some(command), None) => window.spawn_with_args("sh", &["-c", command], dir)?,
+                (None, None) => {
+                    // Use the default shell from settings
+                    let shell = &self.settings.general.default_shell;
+                    if self.settings.general.login_shell {
+                        window.spawn_login_shell(shell, dir)?;
+                    } else {
+                        window.spawn_process(shell, dir)?;
+                    }
+                }
+                (command, Some(profile)) => {
+                    let (program, args) = match command {
+                        Some(command) => sandbox::wrap(profile, "sh", &["-c", command]),
+                        None => {
+                            // Through the profile's wrapper argv[0] can't be set, so a login shell gets -l
+                            let login = profile.login_shell.unwrap_or(self.settings.general.login_shell);
+                            let args: Vec<&str> = login.then_some("-l").into_iter()
+                                .chain(profile.shell_args.iter().map(String::as_str))
+                                .collect();
+                            sandbox::wrap(profile, &self.settings.general.default_shell, &args)
+                        }
+                    };
+                    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                    window.spawn_with_args(&program, &args, dir)?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Grow the focused window towards a direction
+    fn resize_focused_window(&mut self, direction: Direction, amount: f32, snap: bool) -> Result<()> {
+        if let Some(window_id) = self.window_manager.focused_window().map(|w| w.id()) {
+            self.window_manager.resize_window(window_id, direction, amount, snap)?;
+        }
+        
+        Ok(())
+    }
+    
+    // Move the focused window to the far side of the window next to it
+    fn move_focused_window(&mut self, direction: Direction) -> Result<()> {
+        let Some(window_id) = self.window_manager.focused_window().map(|w| w.id()) else { return Ok(()) };
+        let Some(target_id) = self.window_manager.window_in_direction(window_id, direction) else {
+            anyhow::bail!("No window in that direction");
+        };
+        self.window_manager.move_window(window_id, target_id, direction)
+    }
+    
     // Focus the next window
     fn focus_next_window(&mut self) -> Result<()> {
         let window_ids: Vec<Uuid> = self.window_manager.windows().keys().cloned().collect();
@@ -300,69 +977,334 @@ impl App {
                 window.update()?;
             }
             
-            // Draw UI
-            self.terminal.draw(|f| {
-                // Get terminal size
-                let size = f.size();
-                
-                // Create a layout with sidebar and main area
-                let sidebar_width = self.sidebar.width();
-                
-                // If sidebar is active, reserve space for it
-                let main_area = if self.sidebar.is_active() {
-                    Rect::new(
-                        sidebar_width, // X position after sidebar
-                        size.y,
-                        size.width.saturating_sub(sidebar_width), // Width minus sidebar
-                        size.height
-                    )
-                } else {
-                    size
-                };
-                
-                // Resize the window manager to fit the main area
-                let _ = self.window_manager.resize(main_area);
-                
-                // Render the windows
-                for window in self.window_manager.windows().values() {
-                    let paragraph = window.render();
-                    f.render_widget(paragraph, window.size());
-                }
-                
-                // Render the sidebar if active
-                if self.sidebar.is_active() {
-                    self.sidebar.render(f, size);
-                }
-                
-                // Render the command line if in command mode
-                if let AppState::Command = self.state {
-                    // Create a command line at the bottom
-                    let command_height = 1;
-                    let command_rect = Rect::new(
-                        0,
-                        size.height.saturating_sub(command_height),
-                        size.width,
-                        command_height,
-                    );
-                    
-                    let command_text = format!(": {}", self.command_buffer);
-                    let command_paragraph = widgets::create_paragraph(&command_text, Style::default().fg(Color::Yellow));
-                    f.render_widget(command_paragraph, command_rect);
+            // Report output readers the watchdog restarted or gave up on
+            self.report_reader_events();
+            
+            // Handle clipboard requests made by applications
+            self.handle_clipboard_requests();
+            
+            // Keep tmux panes in sync with their windows
+            self.update_tmux_sessions();
+            
+            // Act on output that matches a trigger
+            self.run_triggers();
+            
+            // Flash and sound the bell for windows that rang it
+            self.ring_bells();
+            
+            // Remember the commands run at shell prompts
+            self.record_commands();
+            
+            // Answer remote control clients and tell them what changed
+            self.handle_remote_requests();
+            self.publish_remote_events();
+            
+            // Show which windows receive broadcast input
+            let receivers = self.broadcast_receivers();
+            for (id, window) in self.window_manager.windows_mut() {
+                window.broadcasting = receivers.contains(id);
+            }
+            
+            // Dim windows nobody has used for a while
+            let idle_after = self.settings.ui.idle_dim_secs;
+            for window in self.window_manager.windows_mut().values_mut() {
+                window.idle = idle_after > 0 && window.idle_for().as_secs() >= idle_after;
+            }
+            
+            // Output is drawn once per frame however many chunks arrived, and only
+            // output in windows that are shown needs a frame; windows hidden by a
+            // zoom or pushed off screen aren't drawn at all
+            let visible = self.visible_windows();
+            for (id, window) in self.window_manager.windows_mut() {
+                if window.take_damage() && visible.contains(id) {
+                    self.redraw = true;
                 }
-            })?;
+            }
+            
+            if self.redraw && self.last_draw.elapsed() >= frame_interval(&self.settings) {
+                self.redraw = false;
+                self.last_draw = Instant::now();
+                self.draw()?;
+            }
             
             // Handle events
-            match self.events.next()? {
+            let event = self.events.next()?;
+            
+            // Anything but the end of a quiet frame may change what's shown
+            if !matches!(event, AppEvent::Frame) {
+                self.redraw = true;
+            }
+            
+            if matches!(event, AppEvent::Key(_) | AppEvent::Mouse(_)) {
+                self.last_user_input = Instant::now();
+            }
+            
+            match event {
                 AppEvent::Key(key) => self.handle_key_event(key),
                 AppEvent::Mouse(event) => self.handle_mouse_event(event),
                 AppEvent::Tick => self.update_on_tick(),
-                AppEvent::Quit => self.running = false,
+                AppEvent::Resize | AppEvent::Frame => {}
+                AppEvent::Quit => match &mut self.lock_screen {
+                    // Quitting is no way past the lock screen; Ctrl+C only clears the password
+                    Some((lock_screen, _)) => {
+                        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+                        lock_screen.handle_key(key, &self.settings.lock.auth_command);
+                    }
+                    None => self.running = false,
+                },
+            }
+            
+            self.focus_hovered_window();
+            self.expire_pending_keys();
+        }
+        
+        // Keep the session for the next start, unless the last one is still on offer
+        if self.settings.session.autosave && self.restore_offer.is_none() && !self.safe_mode {
+            self.save_session(AUTOSAVE_SESSION);
+        }
+        
+        Ok(())
+    }
+    
+    // List the windows and bookmarks in their panels, as they are now
+    fn refresh_panels(&mut self) {
+        if self.panels.is_open(PanelKind::Windows) {
+            let focused = self.window_manager.focused_window().map(|w| w.id());
+            let ids = self.window_manager.layout().map(|l| l.window_ids()).unwrap_or_default();
+            let windows = ids.iter().filter_map(|id| self.window_manager.windows().get(id))
+                .map(|w| {
+                    let marker = if Some(w.id()) == focused { "●" } else { " " };
+                    let detail = if w.buffer.is_scrolled_back() { format!("+{}", w.buffer.unseen_lines()) } else { idle_text(w.last_output.elapsed()) };
+                    (PickerItem::new(format!("{}{}", marker, w.title), detail), PickerTarget::Window(w.id()))
+                })
+                .collect();
+            self.panels.set_items(PanelKind::Windows, windows);
+        }
+        if self.panels.is_open(PanelKind::Bookmarks) {
+            let bookmarks = self.settings.bookmarks.iter()
+                .map(|dir| (PickerItem::new(dir.as_str(), ""), PickerTarget::Directory { path: dir.clone(), new_window: false }))
+                .collect();
+            self.panels.set_items(PanelKind::Bookmarks, bookmarks);
+        }
+    }
+    
+    // Open or close a sidebar panel, showing the sidebar if it's hidden
+    fn toggle_panel(&mut self, kind: PanelKind) {
+        if !self.sidebar.is_active() {
+            self.sidebar.toggle();
+            self.panels.open(kind);
+        } else if !self.panels.toggle(kind) {
+            return;
+        }
+        match kind {
+            PanelKind::Search => self.panels.set_items(kind, self.search_results.clone()),
+            PanelKind::SshHosts => self.panels.set_items(kind, ssh_host_items()),
+            PanelKind::Windows | PanelKind::Bookmarks => {}
+        }
+    }
+    
+    // Windows in the layout that are at least partly on screen
+    fn visible_windows(&self) -> Vec<Uuid> {
+        let screen = crossterm::terminal::size()
+            .map(|(width, height)| Rect::new(0, 0, width, height))
+            .unwrap_or_default();
+        visible_windows(&self.window_manager, screen)
+    }
+    
+    // Draw a frame
+    fn draw(&mut self) -> Result<()> {
+        // Status bar and panel contents, gathered before drawing
+        let status_bar = self.settings.ui.status_bar.then(|| self.status_bar());
+        self.refresh_panels();
+        let key_hints = self.mode().filter(|_| !self.pending_keys.is_empty())
+            .map(|mode| (keymap::sequence_name(&self.pending_keys), self.keymaps.get(mode).continuations(&self.pending_keys)));
+        
+        let frame_start = Instant::now();
+        let frame = self.terminal.draw(|f| {
+            // Get terminal size
+            let size = f.size();
+            
+            // Create a layout with sidebar and main area; open panels widen the sidebar
+            let sidebar_width = self.sidebar.width() + self.panels.width();
+            
+            // Reserve the bottom row for the status bar
+            let status_height = if status_bar.is_some() { 1 } else { 0 };
+            
+            // If sidebar is active, reserve space for it
+            let main_area = if self.sidebar.is_active() {
+                Rect::new(
+                    sidebar_width, // X position after sidebar
+                    size.y,
+                    size.width.saturating_sub(sidebar_width), // Width minus sidebar
+                    size.height.saturating_sub(status_height)
+                )
+            } else {
+                Rect::new(size.x, size.y, size.width, size.height.saturating_sub(status_height))
+            };
+            
+            // Resize the window manager to fit the main area
+            let _ = self.window_manager.resize(main_area);
+            
+            // Render the windows; those hidden by a zoom keep their old place and aren't drawn
+            let windows = self.window_manager.windows();
+            for window in visible_windows(&self.window_manager, size).iter().filter_map(|id| windows.get(id)) {
+                let paragraph = window.render(&self.theme);
+                f.render_widget(paragraph, window.size());
+                if self.settings.ui.scrollbar {
+                    window.render_scrollbar(f, &self.theme);
+                }
+                window.render_scroll_pill(f, &self.theme);
+            }
+            
+            // Render the sidebar if active, its panels first so tooltips go over them
+            if self.sidebar.is_active() {
+                self.panels.render(f, panel_dock(&self.sidebar, &self.panels, main_area), &self.theme);
+                self.sidebar.render(f, size);
+            }
+            
+            // Render the status bar (the command line is drawn over it)
+            if let Some(status_bar) = &status_bar {
+                status_bar.render(f, size, &self.theme);
+            }
+            
+            // List the keys that can follow a sequence begun
+            if let Some((pending, hints)) = &key_hints {
+                keymap::render_hints(f, main_area, pending, hints, &self.theme);
+            }
+            
+            // Render the command line if in command mode
+            if let AppState::Command | AppState::Search = self.state {
+                // Create a command line at the bottom
+                let command_height = 1;
+                let command_rect = Rect::new(
+                    0,
+                    size.height.saturating_sub(command_height),
+                    size.width,
+                    command_height,
+                );
+                
+                let prompt = if let AppState::Search = self.state { "/" } else { ":" };
+                let command_text = format!("{} {}", prompt, self.command_buffer);
+                let command_paragraph = widgets::create_paragraph(&command_text, Style::default().fg(self.theme.command));
+                f.render_widget(command_paragraph, command_rect);
+            }
+            
+            // Ask for a snippet's placeholders on the command line
+            if let (AppState::Snippet, Some(snippet)) = (&self.state, &self.snippet) {
+                if let Some(placeholder) = snippet.current() {
+                    let prompt_rect = Rect::new(0, size.height.saturating_sub(1), size.width, 1);
+                    let default = placeholder.default.as_ref().map(|d| format!(" [{}]", d)).unwrap_or_default();
+                    let prompt_text = format!("{} › {}{}: {}", snippet.name, placeholder.name, default, snippet.input);
+                    let prompt_paragraph = widgets::create_paragraph(&prompt_text, Style::default().fg(self.theme.command));
+                    f.render_widget(prompt_paragraph, prompt_rect);
+                }
+            }
+            
+            // Label the matches in hint mode, with the action on the command line
+            if let Some(hints) = &self.hints {
+                hints.render(f, size, &self.theme);
+                let prompt_rect = Rect::new(0, size.height.saturating_sub(1), size.width, 1);
+                let prompt_text = format!("Hints ({}): type a label, Tab changes the action, Esc cancels", hints.action.name());
+                let prompt_paragraph = widgets::create_paragraph(&prompt_text, Style::default().fg(self.theme.command));
+                f.render_widget(prompt_paragraph, prompt_rect);
+            }
+            
+            // Render the picker over the windows
+            if let Some(picker) = &self.picker {
+                picker.render(f, size, &self.theme);
+            }
+            
+            // Render the settings overlay
+            if let Some(preferences) = &self.preferences {
+                preferences.render(f, size, &self.settings, &self.theme);
+            }
+            if let Some(editor) = &self.theme_editor {
+                editor.render(f, size, &self.theme);
+            }
+            if let Some(cheatsheet) = &self.cheatsheet {
+                cheatsheet.render(f, size, &self.theme);
+            }
+            if let Some(diff_view) = &self.diff_view {
+                diff_view.render(f, size, &self.theme);
+            }
+            if let Some(pager) = &self.pager {
+                pager.render(f, size, &self.theme, StyleMapping::new(&self.settings.ui));
+            }
+            if let Some(tutorial) = &self.tutorial {
+                tutorial.render(f, size, &self.theme);
+            }
+            
+            // Render notifications in the top right corner
+            self.notifications.render(f, size, &self.theme);
+            
+            // Outline where a previewed split will put the new window
+            if let Some(preview) = &self.split_preview {
+                if let Some(target) = self.window_manager.windows().get(&preview.target) {
+                    let block = Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Double)
+                        .border_style(Style::default().fg(self.theme.focused_border).add_modifier(Modifier::BOLD))
+                        .title("New window: arrows move, Enter splits, Esc cancels")
+                        .style(Style::default().add_modifier(Modifier::DIM));
+                    f.render_widget(block, preview.rect(target.size()));
+                }
+            }
+            
+            // Ask before letting an application set the clipboard
+            if let (AppState::ClipboardPrompt, Some((window_id, text))) = (&self.state, &self.pending_clipboard) {
+                let title = self.window_manager.windows().get(window_id)
+                    .map(|w| w.title.as_str())
+                    .unwrap_or("A window");
+                let prompt_rect = Rect::new(0, size.height.saturating_sub(1), size.width, 1);
+                let prompt_text = format!("{} wants to set the clipboard ({} bytes). Allow? [y/n]", title, text.len());
+                let prompt_paragraph = widgets::create_paragraph(&prompt_text, Style::default().fg(self.theme.command));
+                f.render_widget(prompt_paragraph, prompt_rect);
+            }
+            
+            // Offer to bring back the last session
+            if let (AppState::RestorePrompt, Some(session)) = (&self.state, &self.restore_offer) {
+                let prompt_rect = Rect::new(0, size.height.saturating_sub(1), size.width, 1);
+                let prompt_text = format!(
+                    "Restore the last session ({} windows)? [y]es, [r]estore and rerun commands, [n]o",
+                    session.windows.len()
+                );
+                let prompt_paragraph = widgets::create_paragraph(&prompt_text, Style::default().fg(self.theme.command));
+                f.render_widget(prompt_paragraph, prompt_rect);
+            }
+            
+            // What's wrong with the settings file
+            if let Some(config_errors) = &self.config_errors {
+                config_errors.render(f, size, &self.theme);
+            }
+            
+            // The lock screen hides everything else
+            if let Some((lock_screen, _)) = &self.lock_screen {
+                lock_screen.render(f, size, &self.theme);
+            }
+            
+            // Bring the frame down to the colors and characters the terminal has
+            low_color::degrade(f.buffer_mut(), self.color_depth, self.settings.ui.ascii_borders);
+        })?;
+        let screenshot = self.pending_screenshot.take().map(|path| (path, frame.clone()));
+        let budget = Duration::from_millis(self.settings.general.tick_rate_ms);
+        self.metrics.record_frame(frame_start.elapsed(), budget);
+        
+        if let Some((path, buffer)) = screenshot {
+            match screenshot::to_png(&buffer, &self.theme).and_then(|png| Ok(std::fs::write(&path, png)?)) {
+                Ok(()) => self.announcer.announce_ui(&format!("Screenshot saved to {}", path)),
+                Err(e) => eprintln!("Error saving screenshot: {}", e),
             }
         }
+        
         Ok(())
     }
     
     fn handle_key_event(&mut self, key: KeyEvent) {
+        // Key sequences bound in the current mode come first
+        if self.handle_key_sequence(&key) {
+            return;
+        }
+        
         match self.state {
             AppState::Normal => {
                 // Check for keyboard shortcuts
@@ -371,39 +1313,32 @@ impl App {
                     return;
                 } else if key.code == KeyCode::Char(':') {
                     // Enter command mode
-                    self.state = AppState::Command;
-                    self.command_buffer.clear();
+                    self.enter_mode(Mode::Command);
                 } else {
-                    // Pass the key to the focused window
-                    if let Some(window) = self.window_manager.focused_window_mut() {
-                        // Convert the key to bytes
-                        let mut bytes = Vec::new();
-                        
-                        match key.code {
-                            KeyCode::Char(c) => {
-                                bytes.push(c as u8);
-                            }
-                            KeyCode::Enter => {
-                                bytes.push(b'\n');
-                            }
-                            KeyCode::Tab => {
-                                bytes.push(b'\t');
-                            }
-                            KeyCode::Backspace => {
-                                bytes.push(8); // ASCII backspace
-                            }
-                            KeyCode::Esc => {
-                                bytes.push(27); // ASCII escape
+                    // Pass the key to the focused window, and to the windows it broadcasts to
+                    let focused = self.window_manager.focused_window().map(|w| w.id());
+                    for id in self.input_targets() {
+                        // Read-only windows keep their keystrokes out of the process
+                        if let Some(window) = self.window_manager.windows_mut().get_mut(&id).filter(|w| !w.read_only) {
+                            // Encode the key for the protocol the application asked for
+                            let bytes = match terminal_key(&key) {
+                                Some((code, mods)) => {
+                                    keyboard::encode_key(code, mods, window.buffer.keyboard_mode())
+                                }
+                                None => Vec::new(),
+                            };
+                            
+                            // A macro being recorded gets the keys as the focused window saw them
+                            if let Some(recording) = self.recording.as_mut().filter(|_| Some(id) == focused) {
+                                recording.input.push_str(&String::from_utf8_lossy(&bytes));
                             }
-                            // Add other key conversions as needed
-                            _ => {}
-                        }
-                        
-                        // Send the input to the process
-                        if !bytes.is_empty() {
-                            if let Err(e) = window.send_input(&bytes) {
-                                // Handle error
-                                eprintln!("Error sending input: {}", e);
+                            
+                            // Send the input to the process
+                            if !bytes.is_empty() {
+                                if let Err(e) = window.send_input(&bytes) {
+                                    // Handle error
+                                    eprintln!("Error sending input: {}", e);
+                                }
                             }
                         }
                     }
@@ -420,11 +1355,11 @@ impl App {
                         self.command_buffer.pop();
                     }
                     KeyCode::Enter => {
+                        // Return to normal mode, unless the command opens another (a picker or prompt)
+                        self.state = AppState::Normal;
+                        
                         // Execute the command
                         self.execute_command();
-                        
-                        // Return to normal mode
-                        self.state = AppState::Normal;
                     }
                     KeyCode::Esc => {
                         // Cancel command mode
@@ -433,32 +1368,1647 @@ impl App {
                     _ => {}
                 }
             }
+            AppState::Search => {
+                match key.code {
+                    KeyCode::Char(c) => self.command_buffer.push(c),
+                    KeyCode::Backspace => {
+                        self.command_buffer.pop();
+                    }
+                    KeyCode::Enter => {
+                        self.state = AppState::Normal;
+                        let pattern = self.command_buffer.clone();
+                        if !pattern.is_empty() {
+                            if let Err(e) = self.search_windows(&pattern, false) {
+                                self.notifications.push(&format!("Can't search: {}", e));
+                            }
+                        }
+                    }
+                    KeyCode::Esc => self.state = AppState::Normal,
+                    _ => {}
+                }
+            }
+            AppState::Resize => {
+                // Arrows move the focused window's border a little, with Shift a lot, stopping
+                // at quarters, thirds and the half (keyboard.modes.resize can bind other keys)
+                let direction = match key.code {
+                    KeyCode::Left => Some(Direction::Left),
+                    KeyCode::Right => Some(Direction::Right),
+                    KeyCode::Up => Some(Direction::Up),
+                    KeyCode::Down => Some(Direction::Down),
+                    KeyCode::Esc | KeyCode::Enter => {
+                        self.enter_mode(Mode::Normal);
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(direction) = direction {
+                    let step = if key.modifiers.contains(KeyModifiers::SHIFT) { RESIZE_STEP_LARGE } else { RESIZE_STEP };
+                    if let Err(e) = self.resize_focused_window(direction, step, true) {
+                        self.announcer.announce_ui(&e.to_string());
+                    }
+                }
+            }
+            AppState::SplitPreview => {
+                if let Some(preview) = &mut self.split_preview {
+                    match key.code {
+                        // Arrows choose the side the new window goes on
+                        KeyCode::Left | KeyCode::Right => {
+                            preview.direction = SplitDirection::Horizontal;
+                            preview.new_first = key.code == KeyCode::Left;
+                        }
+                        KeyCode::Up | KeyCode::Down => {
+                            preview.direction = SplitDirection::Vertical;
+                            preview.new_first = key.code == KeyCode::Up;
+                        }
+                        KeyCode::Enter => {
+                            let (direction, new_first) = (preview.direction, preview.new_first);
+                            self.split_preview = None;
+                            self.state = AppState::Normal;
+                            if let Err(e) = self.split_window(direction, new_first) {
+                                eprintln!("Error splitting window: {}", e);
+                            }
+                        }
+                        KeyCode::Esc => {
+                            self.split_preview = None;
+                            self.state = AppState::Normal;
+                        }
+                        _ => {}
+                    }
+                } else {
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::Picker => {
+                if let Some(picker) = &mut self.picker {
+                    match key.code {
+                        KeyCode::Up => picker.select_prev(),
+                        KeyCode::Down | KeyCode::Tab => picker.select_next(),
+                        KeyCode::Backspace => picker.pop_char(),
+                        KeyCode::Char(c) => picker.push_char(c),
+                        KeyCode::Enter => {
+                            let selected = picker.selected_index();
+                            self.close_picker(selected);
+                        }
+                        KeyCode::Esc => self.close_picker(None),
+                        _ => {}
+                    }
+                } else {
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::Copy => {
+                let action = match self.window_manager.focused_window_mut() {
+                    Some(window) => match &mut window.copy_mode {
+                        Some(copy) => copy.handle_key(key, &mut window.buffer),
+                        None => CopyAction::Exit,
+                    },
+                    None => CopyAction::Exit,
+                };
+                match action {
+                    CopyAction::None => {}
+                    CopyAction::Copy(text) => {
+                        let copied = text.chars().count();
+                        if let Err(e) = self.clipboard.set(text) {
+                            eprintln!("Error setting clipboard: {}", e);
+                        }
+                        self.exit_copy_mode();
+                        self.announcer.announce_ui(&format!("Copied {} characters", copied));
+                    }
+                    CopyAction::Append(text) => {
+                        if let Err(e) = self.clipboard.append(&text) {
+                            eprintln!("Error setting clipboard: {}", e);
+                        }
+                        self.exit_copy_mode();
+                        self.announcer.announce_ui("Appended to the clipboard");
+                    }
+                    CopyAction::Exit => self.exit_copy_mode(),
+                }
+            }
+            AppState::Preferences => {
+                if let Some(preferences) = &mut self.preferences {
+                    match key.code {
+                        KeyCode::Up if !preferences.is_editing() => preferences.select_prev(),
+                        KeyCode::Down | KeyCode::Tab if !preferences.is_editing() => preferences.select_next(),
+                        KeyCode::Backspace => preferences.pop_char(),
+                        KeyCode::Char(c) => preferences.push_char(c),
+                        KeyCode::Enter => {
+                            if let Some(changed) = preferences.activate(&self.settings) {
+                                self.apply_settings(changed);
+                            }
+                        }
+                        KeyCode::Esc if preferences.is_editing() => preferences.cancel_edit(),
+                        KeyCode::Esc => {
+                            self.preferences = None;
+                            self.state = AppState::Normal;
+                        }
+                        _ => {}
+                    }
+                } else {
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::ThemeEditor => {
+                let Some(editor) = &mut self.theme_editor else {
+                    self.state = AppState::Normal;
+                    return;
+                };
+                match editor.handle_key(key.code, &mut self.theme) {
+                    EditorAction::Changed => self.theme_colors_changed(),
+                    EditorAction::Save(name) => match theme_files::save(&name, &self.theme) {
+                        Ok(path) => {
+                            editor.set_message(format!("Saved {}", path.display()));
+                            self.settings.ui.theme = name.clone();
+                            self.theme_name = name;
+                        }
+                        Err(e) => editor.set_message(format!("Error saving theme: {:#}", e)),
+                    },
+                    EditorAction::Close => {
+                        // Changes not saved are dropped
+                        self.theme_editor = None;
+                        self.state = AppState::Normal;
+                        let name = self.theme_name.clone();
+                        if let Err(e) = self.apply_theme(&name) {
+                            eprintln!("Error loading theme: {:#}", e);
+                        }
+                    }
+                    EditorAction::None => {}
+                }
+            }
+            AppState::Cheatsheet => {
+                if !self.cheatsheet.as_mut().is_some_and(|cheatsheet| cheatsheet.handle_key(key.code)) {
+                    self.cheatsheet = None;
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::Pager => {
+                if !self.pager.as_mut().is_some_and(|pager| pager.handle_key(key.code)) {
+                    self.pager = None;
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::Diff => {
+                if !self.diff_view.as_mut().is_some_and(|diff_view| diff_view.handle_key(key.code)) {
+                    self.diff_view = None;
+                    self.state = AppState::Normal;
+                }
+            }
+            AppState::Timeline => {
+                let Some(window) = self.window_manager.focused_window_mut() else {
+                    self.state = AppState::Normal;
+                    return;
+                };
+                match key.code {
+                    KeyCode::Left | KeyCode::Char('h') => window.scrub_timeline(-1),
+                    KeyCode::Right | KeyCode::Char('l') => window.scrub_timeline(1),
+                    KeyCode::PageUp => window.scrub_timeline(-10),
+                    KeyCode::PageDown => window.scrub_timeline(10),
+                    KeyCode::Home | KeyCode::Char('g') => window.scrub_timeline(isize::MIN / 2),
+                    KeyCode::End | KeyCode::Char('G') => window.scrub_timeline(isize::MAX / 2),
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                        window.close_timeline();
+                        self.state = AppState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            AppState::Snippet => {
+                let Some(snippet) = &mut self.snippet else {
+                    self.state = AppState::Normal;
+                    return;
+                };
+                match key.code {
+                    KeyCode::Char(c) => snippet.input.push(c),
+                    KeyCode::Backspace => {
+                        snippet.input.pop();
+                    }
+                    KeyCode::Enter => {
+                        snippet.answer();
+                        self.finish_snippet();
+                    }
+                    KeyCode::Esc => {
+                        self.snippet = None;
+                        self.state = AppState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+            AppState::Locked => {
+                let Some((lock_screen, _)) = &mut self.lock_screen else {
+                    self.state = AppState::Normal;
+                    return;
+                };
+                lock_screen.handle_key(key, &self.settings.lock.auth_command);
+            }
+            AppState::Hints => {
+                let Some(hints) = &mut self.hints else {
+                    self.state = AppState::Normal;
+                    return;
+                };
+                match key.code {
+                    KeyCode::Char(c) => {
+                        if let Some(found) = hints.push_char(c) {
+                            let action = hints.action;
+                            self.exit_hint_mode();
+                            self.act_on_hint(found, action);
+                        }
+                    }
+                    KeyCode::Backspace => hints.pop_char(),
+                    KeyCode::Tab => hints.action = hints.action.next(),
+                    KeyCode::Esc => self.exit_hint_mode(),
+                    _ => {}
+                }
+            }
+            AppState::ConfigErrors => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter) {
+                    self.config_errors = None;
+                    // The restore prompt waits behind the problems
+                    self.state = if self.restore_offer.is_some() { AppState::RestorePrompt } else { AppState::Normal };
+                }
+            }
+            AppState::RestorePrompt => {
+                let rerun = match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => false,
+                    KeyCode::Char('r') | KeyCode::Char('R') => true,
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        self.restore_offer = None;
+                        self.state = AppState::Normal;
+                        return;
+                    }
+                    _ => return,
+                };
+                self.state = AppState::Normal;
+                if let Some(session) = self.restore_offer.take() {
+                    if let Err(e) = self.restore_windows(session, rerun) {
+                        eprintln!("Error restoring session: {}", e);
+                    }
+                }
+            }
+            AppState::ClipboardPrompt => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        if let Some((_, text)) = self.pending_clipboard.take() {
+                            if let Err(e) = self.clipboard.set(text) {
+                                eprintln!("Error setting clipboard: {}", e);
+                            }
+                        }
+                        self.state = AppState::Normal;
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        self.pending_clipboard = None;
+                        self.state = AppState::Normal;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    
+    // Notify about windows whose output reader stopped while their process was running
+    fn report_reader_events(&mut self) {
+        for window in self.window_manager.windows_mut().values_mut() {
+            for event in window.take_reader_events() {
+                let text = match event {
+                    ReaderStatus::Restarted => format!("{}: output stopped, reader restarted", window.title),
+                    _ => format!("{}: output reader keeps failing, window no longer updates", window.title),
+                };
+                self.notifications.push(&text);
+                self.redraw = true;
+            }
+        }
+    }
+    
+    // Visual and audible bell for the windows that rang it since the last update
+    fn ring_bells(&mut self) {
+        let mut audible = false;
+        for window in self.window_manager.windows_mut().values_mut() {
+            if window.buffer.take_bells() == 0 {
+                continue;
+            }
+            if self.settings.bell.visual {
+                window.flash();
+            }
+            // A bell in another window asks for attention
+            if !window.is_focused() {
+                window.marked = true;
+            }
+            audible |= !window.bell_muted;
+        }
+        
+        if audible {
+            self.bell.ring(&self.settings.bell);
+        }
+    }
+    
+    // Add the commands typed at shell prompts since the last update to the history
+    fn record_commands(&mut self) {
+        let mut recorded = false;
+        for (id, window) in self.window_manager.windows_mut().iter_mut() {
+            for command in window.buffer.take_typed_commands() {
+                window.last_command = Some(command.clone());
+                if self.settings.history.record {
+                    let command = self.redactor.redact(&command).into_owned();
+                    self.frecency.visit(frecency::Kind::Command, &command);
+                    self.history.push(command, *id, &window.title, self.settings.history.max_entries);
+                    recorded = true;
+                }
+            }
+        }
+        
+        if recorded {
+            if let Err(e) = self.history.save() {
+                eprintln!("Error saving command history: {}", e);
+            }
+            if let Err(e) = self.frecency.save() {
+                eprintln!("Error saving quick-open ranking: {}", e);
+            }
+        }
+    }
+    
+    // List the bookmarked and recently used directories, to change to the one picked or
+    // open a window there
+    fn pick_directory(&mut self, new_window: bool) {
+        let bookmarks = self.settings.bookmarks.iter().map(|dir| (dir, "bookmark"));
+        let recent = self.recent_dirs.iter()
+            .filter(|dir| !self.settings.bookmarks.contains(dir))
+            .map(|dir| (dir, "recent"));
+        let dirs: Vec<(&String, &str)> = bookmarks.chain(recent).collect();
+        if dirs.is_empty() {
+            self.announcer.announce_ui("No bookmarks (:bookmark add)");
+            return;
+        }
+        
+        let items = dirs.iter().map(|(dir, kind)| PickerItem::new(dir.as_str(), *kind)).collect();
+        let targets = dirs.iter()
+            .map(|(dir, _)| PickerTarget::Directory { path: dir.to_string(), new_window })
+            .collect();
+        let title = if new_window { "Open a window in" } else { "Change directory" };
+        self.open_picker(Picker::new(title, items), targets);
+    }
+    
+    // Type cd at the current window's prompt, or split it with a shell in the directory
+    fn go_to_directory(&mut self, path: &str, new_window: bool) -> Result<()> {
+        let Some(window_id) = self.window_manager.focused_window().map(|w| w.id()) else { return Ok(()) };
+        if new_window {
+            let new_id = self.window_manager.split_window_placed(window_id, SplitDirection::Vertical, 0.5, false)?;
+            self.start_command(new_id, None, None, Some(path))?;
+            self.window_manager.focus_window(new_id)?;
+        } else if let Some(window) = self.window_manager.focused_window_mut() {
+            window.send_input(format!("cd {}\r", quote(path)).as_bytes())?;
+        }
+        Ok(())
+    }
+    
+    // Save the bookmarks along with the rest of the settings
+    fn save_bookmarks(&mut self) {
+        self.save_settings();
+    }
+    
+    // Write the settings to disk; in safe mode the user's file is left alone
+    fn save_settings(&self) {
+        if self.safe_mode {
+            return;
+        }
+        if let Err(e) = self.settings.save(&Settings::path()) {
+            eprintln!("Error saving settings: {}", e);
+        }
+    }
+    
+    // Write the macros to disk, except in safe mode, which starts without the user's
+    fn save_macros(&mut self) {
+        if self.safe_mode {
+            return;
+        }
+        if let Err(e) = self.macros.save() {
+            self.notifications.push(&format!("Can't save macros: {}", e));
+        }
+    }
+    
+    // Search the command history, typing the command picked at the focused window's prompt
+    fn pick_history(&mut self) {
+        let focused = self.window_manager.focused_window().map(|w| w.id());
+        let found = self.history.search_order(focused);
+        if found.is_empty() {
+            self.announcer.announce_ui("No command history (needs shell integration or history.import_shell)");
+            return;
+        }
+        
+        let items = found.iter()
+            .map(|(command, source)| {
+                let detail = match source {
+                    Source::ThisWindow => "this window",
+                    Source::Window(title) => title,
+                    Source::Shell => "shell history",
+                };
+                PickerItem::new(*command, detail)
+            })
+            .collect();
+        let targets = found.iter().map(|(command, _)| PickerTarget::Command(command.to_string())).collect();
+        self.open_picker(Picker::new("Command history", items), targets);
+    }
+    
+    // Directories, commands and windows in one list, most frequently and recently used
+    // first; picking one changes to the directory, runs the command again or focuses
+    // the window
+    fn quick_open(&mut self) {
+        let mut entries: Vec<(f64, PickerItem, PickerTarget)> = Vec::new();
+        
+        // Directories visited, and bookmarked or recent ones not ranked yet
+        let mut dirs = self.frecency.scores(frecency::Kind::Directory);
+        for dir in self.recent_dirs.iter().chain(&self.settings.bookmarks) {
+            if !dirs.iter().any(|(d, _)| d == dir) {
+                dirs.push((dir, 0.0));
+            }
+        }
+        for (dir, score) in dirs {
+            entries.push((score, PickerItem::new(dir, "cd"), PickerTarget::Directory { path: dir.to_string(), new_window: false }));
+        }
+        
+        // Commands run, and those in the history not ranked yet
+        let mut commands = self.frecency.scores(frecency::Kind::Command);
+        for (command, _) in self.history.search_order(None) {
+            if !commands.iter().any(|(c, _)| *c == command) {
+                commands.push((command, 0.0));
+            }
+        }
+        for (command, score) in commands {
+            entries.push((score, PickerItem::new(command, "rerun"), PickerTarget::Run(command.to_string())));
+        }
+        
+        // Windows, as used once when last typed in
+        for window in self.window_manager.windows().values() {
+            let score = frecency::score(1, window.last_input.elapsed().as_secs());
+            entries.push((score, PickerItem::new(window.title.clone(), "focus"), PickerTarget::Window(window.id())));
+        }
+        
+        // Highest first; ties keep directories, then commands, then windows
+        entries.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let (items, targets) = entries.into_iter().map(|(_, item, target)| (item, target)).unzip();
+        self.open_picker(Picker::new("Quick open", items), targets);
+    }
+    
+    // Serve remote control over a WebSocket too
+    #[cfg(feature = "remote")]
+    fn start_remote(&mut self) {
+        let token = match &self.settings.remote.token {
+            Some(token) => Ok(token.clone()),
+            None => remote::load_or_create_token(),
+        };
+        match token.and_then(|token| self.remote.listen_websocket(self.settings.remote.port, token)) {
+            Ok(port) => self.notifications.push(&format!("Remote control on 127.0.0.1:{}", port)),
+            Err(e) => eprintln!("Error starting remote control: {}", e),
+        }
+    }
+    
+    #[cfg(not(feature = "remote"))]
+    fn start_remote(&mut self) {
+        eprintln!("Remote control over a WebSocket needs Matrix built with the \"remote\" feature");
+    }
+    
+    // Let remote control clients waiting on a channel carry on
+    fn signal_channel(&mut self, channel: &str) {
+        let woken = self.channels.signal(channel);
+        self.announcer.announce_ui(&format!("Signaled {} ({} waiting)", channel, woken));
+    }
+    
+    // Send subscribed remote control clients the changes to the windows
+    fn publish_remote_events(&mut self) {
+        self.remote.publish(self.remote_events.events(&self.window_manager));
+    }
+    
+    // Answer the requests of remote control clients
+    fn handle_remote_requests(&mut self) {
+        let requests = self.remote.take_requests();
+        for request in requests {
+            // Answered when the channel is signaled
+            if request.method == "wait_for" {
+                match request.params.get("channel").and_then(|c| c.as_str()).map(str::to_string) {
+                    Some(channel) => self.channels.wait(&channel, request),
+                    None => {
+                        request.reply(Err(anyhow::anyhow!("missing channel")));
+                    }
+                }
+                continue;
+            }
+            
+            // Requests that only read don't change what's shown
+            if !matches!(request.method.as_str(), "windows" | "layout" | "screen" | "capture" | "signal") {
+                self.redraw = true;
+            }
+            let result = self.remote_call(&request.method, &request.params);
+            request.reply(result);
+        }
+    }
+    
+    // Carry out a remote control request
+    fn remote_call(&mut self, method: &str, params: &serde_json::Value) -> Result<serde_json::Value> {
+        // The window a request is about, by ID or number in the layout, the focused one by default
+        let window_id = match params.get("window") {
+            Some(serde_json::Value::String(id)) => Some(Uuid::parse_str(id)?),
+            Some(serde_json::Value::Number(number)) => {
+                let window = number.as_u64().and_then(|n| self.window_by_number(n as usize));
+                Some(window.ok_or_else(|| anyhow::anyhow!("no window {}", number))?)
+            }
+            _ => self.window_manager.focused_window().map(|w| w.id()),
+        };
+        
+        match method {
+            "windows" => Ok(self.window_manager.windows().values().map(remote::window_json).collect()),
+            "layout" => Ok(self.window_manager.layout().map(remote::layout_json).unwrap_or_default()),
+            "send_input" => {
+                let text = params.get("text").and_then(|t| t.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("missing text"))?;
+                let window = window_id.and_then(|id| self.window_manager.windows_mut().get_mut(&id))
+                    .ok_or_else(|| anyhow::anyhow!("no such window"))?;
+                window.send_input(text.as_bytes())?;
+                Ok(serde_json::Value::Null)
+            }
+            "focus" => {
+                let id = window_id.ok_or_else(|| anyhow::anyhow!("no such window"))?;
+                self.window_manager.focus_window(id)?;
+                Ok(serde_json::Value::Null)
+            }
+            "screen" => {
+                let window = window_id.and_then(|id| self.window_manager.windows().get(&id))
+                    .ok_or_else(|| anyhow::anyhow!("no such window"))?;
+                Ok(remote::screen_json(window))
+            }
+            "signal" => {
+                let channel = params.get("channel").and_then(|c| c.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("missing channel"))?;
+                Ok(serde_json::Value::from(self.channels.signal(channel)))
+            }
+            "capture" => {
+                let id = window_id.ok_or_else(|| anyhow::anyhow!("no such window"))?;
+                let count = params.get("lines").and_then(|n| n.as_u64()).map(|n| n as usize);
+                let lines = self.capture(id, count).ok_or_else(|| anyhow::anyhow!("no such window"))?;
+                let lines = match params.get("format").and_then(|f| f.as_str()) {
+                    Some("json") => export::to_json(&lines),
+                    _ => lines.into_iter().map(|(text, _)| serde_json::Value::from(text.trim_end())).collect(),
+                };
+                Ok(serde_json::json!({ "window": id.to_string(), "lines": lines }))
+            }
+            _ => Err(anyhow::anyhow!("unknown method {}", method)),
+        }
+    }
+    
+    // Contents of the status bar
+    fn status_bar(&self) -> StatusBar {
+        let mut status = StatusBar::default();
+        let focused = self.window_manager.focused_window();
+        
+        if let Some(window) = focused {
+            status.push_left(window.title.clone());
+            if let Some(group) = &window.group {
+                status.push_left(format!("group {}", group));
+            }
+        }
+        if let Some(git) = focused.filter(|_| self.settings.git.status).and_then(|w| w.git.status.as_ref()) {
+            status.push_left(git.label());
+        }
+        status.push_left(format!("{} windows", self.window_manager.windows().len()));
+        for (text, right) in self.status_scripts.outputs() {
+            if right {
+                status.push_right(text);
+            } else {
+                status.push_left(text);
+            }
+        }
+        
+        match &self.broadcast {
+            Some(BroadcastTarget::All) => status.push_right("⇉ all"),
+            Some(BroadcastTarget::Group(name)) => status.push_right(format!("⇉ {}", name)),
+            None => {}
+        }
+        if self.settings.bell.muted {
+            status.push_right("🔕 muted");
+        } else if focused.is_some_and(|w| w.bell_muted) {
+            status.push_right("🔕 window");
+        }
+        if let Some(badge) = focused.and_then(|w| w.buffer.last_command()).and_then(|c| c.badge()) {
+            status.push_right(badge);
+        }
+        if focused.is_some_and(|w| w.read_only) {
+            status.push_right("🔒 read-only");
+        }
+        if let Some(copy) = focused.and_then(|w| w.copy_mode.as_ref()) {
+            status.push_right(if copy.block { "COPY block" } else { "COPY" });
+        } else if let Some(mode) = self.mode().filter(|mode| *mode != Mode::Normal) {
+            status.push_right(mode.name().to_uppercase());
+        }
+        if focused.is_some_and(|w| w.output_paused) {
+            status.push_right("⏸ output paused (Ctrl+Q)");
+        }
+        if let Some(recording) = &self.recording {
+            status.push_right(format!("● REC {}", recording.name));
+        }
+        if self.safe_mode {
+            status.push_right("SAFE MODE");
+        }
+        if !self.pending_keys.is_empty() {
+            status.push_right(format!("⌨ {} …", keymap::sequence_name(&self.pending_keys)));
+        }
+        
+        status
+    }
+    
+    // Windows typed input goes to: the focused window, and the windows it broadcasts to
+    fn input_targets(&self) -> Vec<Uuid> {
+        let receivers = self.broadcast_receivers();
+        match self.window_manager.focused_window().map(|w| w.id()) {
+            Some(id) if receivers.contains(&id) => receivers,
+            Some(id) => vec![id],
+            None => Vec::new(),
+        }
+    }
+    
+    // Windows receiving broadcast input
+    fn broadcast_receivers(&self) -> Vec<Uuid> {
+        let windows = self.window_manager.windows();
+        match &self.broadcast {
+            Some(BroadcastTarget::All) => windows.keys().cloned().collect(),
+            Some(BroadcastTarget::Group(name)) => windows.values()
+                .filter(|w| w.group.as_deref() == Some(name.as_str()))
+                .map(|w| w.id())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+    
+    // Attach to a tmux session in control mode, locally or on a host over ssh
+    fn attach_tmux(&mut self, host: Option<&str>) -> Result<()> {
+        let area = self.window_manager.focused_window()
+            .map(|w| w.size())
+            .unwrap_or_else(|| Rect::new(0, 0, 80, 24));
+        let session = TmuxSession::attach(host, area.width, area.height)?;
+        self.tmux_sessions.push(session);
+        Ok(())
+    }
+    
+    // Process tmux notifications, opening and closing windows as panes come and go
+    fn update_tmux_sessions(&mut self) {
+        let mut index = 0;
+        while index < self.tmux_sessions.len() {
+            let events = match self.tmux_sessions[index].poll() {
+                Ok(events) => events,
+                Err(e) => vec![TmuxEvent::Exited(Some(e.to_string()))],
+            };
+            
+            let mut exited = false;
+            for event in events {
+                match event {
+                    TmuxEvent::Panes(panes) => {
+                        if let Err(e) = self.sync_tmux_panes(index, &panes) {
+                            eprintln!("Error mapping tmux panes: {}", e);
+                        }
+                    }
+                    TmuxEvent::Exited(reason) => {
+                        if let Some(reason) = reason {
+                            eprintln!("tmux session ended: {}", reason);
+                        }
+                        exited = true;
+                    }
+                }
+            }
+            
+            if exited {
+                // Close the windows that showed the session's panes
+                let session = self.tmux_sessions.remove(index);
+                for window_id in session.window_ids() {
+                    if self.window_manager.windows().contains_key(&window_id) {
+                        let _ = self.window_manager.close_window(window_id);
+                    }
+                }
+            } else {
+                index += 1;
+            }
+        }
+    }
+    
+    // Map the panes tmux reported onto windows
+    fn sync_tmux_panes(&mut self, index: usize, panes: &[PaneInfo]) -> Result<()> {
+        let session = &mut self.tmux_sessions[index];
+        
+        // Close windows of panes that are gone
+        for pane_id in session.pane_ids() {
+            if !panes.iter().any(|p| p.pane_id == pane_id) {
+                if let Some(window_id) = session.disconnect_pane(&pane_id) {
+                    if self.window_manager.windows().contains_key(&window_id) {
+                        self.window_manager.close_window(window_id)?;
+                    }
+                }
+            }
+        }
+        
+        // Open windows for new panes, next to the session's other panes
+        let known = session.pane_ids();
+        for pane in panes.iter().filter(|p| !known.contains(&p.pane_id)) {
+            let anchor = self.tmux_sessions[index].window_ids().into_iter().last()
+                .or_else(|| self.window_manager.focused_window().map(|w| w.id()));
+            
+            let window_id = match anchor {
+                Some(anchor) => self.window_manager.split_window(anchor, SplitDirection::Vertical, 0.5)?,
+                None => self.window_manager.create_window("tmux")?,
+            };
+            
+            let controller = self.tmux_sessions[index].connect_pane(&pane.pane_id, window_id)?;
+            if let Some(window) = self.window_manager.windows_mut().get_mut(&window_id) {
+                window.title = format!("tmux {} {}", pane.window_id, pane.title);
+                window.attach_process(Box::new(controller))?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Write the focused window's scrollback to a standalone HTML file
+    fn export_html(&mut self, path: &str) -> Result<()> {
+        let window = self.window_manager.focused_window()
+            .ok_or_else(|| anyhow::anyhow!("no window to export"))?;
+        // Secrets are masked character for character, so the colors still line up
+        let lines: Vec<_> = window.buffer.styled_lines().into_iter()
+            .map(|(text, styles)| (self.redactor.redact(&text).into_owned(), styles))
+            .collect();
+        let html = export::to_html(&window.title, &lines, &self.theme, window.style_mapping);
+        std::fs::write(path, html)?;
+        
+        self.announcer.announce_ui(&format!("Exported {} to {}", window.title, path));
+        Ok(())
+    }
+    
+    // The last lines of a window (by default as many as it shows) with their styles,
+    // secrets masked; blank lines at the end are left out
+    fn capture(&self, window_id: Uuid, count: Option<usize>) -> Option<Vec<(String, Vec<StyleRun>)>> {
+        let window = self.window_manager.windows().get(&window_id)?;
+        let mut lines = window.buffer.styled_lines();
+        let end = lines.iter().rposition(|(line, _)| !line.trim().is_empty()).map_or(0, |i| i + 1);
+        lines.truncate(end);
+        let count = count.unwrap_or_else(|| window.buffer.visible_lines().len());
+        let lines = lines.split_off(lines.len().saturating_sub(count));
+        Some(lines.into_iter().map(|(text, styles)| (self.redactor.redact(&text).into_owned(), styles)).collect())
+    }
+    
+    // Save the last lines of the current window to a file: plain text, or JSON with styles
+    // for a .json file
+    fn capture_to_file(&mut self, path: &str, count: Option<usize>) -> Result<()> {
+        let window_id = self.window_manager.focused_window().map(|w| w.id())
+            .ok_or_else(|| anyhow::anyhow!("no window to capture"))?;
+        let lines = self.capture(window_id, count).unwrap_or_default();
+        let contents = if path.ends_with(".json") {
+            serde_json::to_string_pretty(&export::to_json(&lines))?
+        } else {
+            lines.iter().map(|(text, _)| format!("{}\n", text.trim_end())).collect()
+        };
+        std::fs::write(path, contents)?;
+        
+        self.announcer.announce_ui(&format!("Captured {} lines to {}", lines.len(), path));
+        Ok(())
+    }
+    
+    // A window by its number in the layout, counting from 1
+    fn window_by_number(&self, number: usize) -> Option<Uuid> {
+        let ids = self.window_manager.layout().map(|l| l.window_ids()).unwrap_or_default();
+        number.checked_sub(1).and_then(|i| ids.get(i).copied())
+    }
+    
+    // A window by its number in the layout, or else its title (ignoring case)
+    fn window_named(&self, name: &str) -> Option<Uuid> {
+        if let Ok(number) = name.parse::<usize>() {
+            return self.window_by_number(number);
+        }
+        self.window_manager.windows().values()
+            .find(|window| window.title.eq_ignore_ascii_case(name))
+            .map(|window| window.id())
+    }
+    
+    // Window and history line at a point on a scrollbar
+    fn scrollbar_line_at(&self, column: u16, row: u16) -> Option<(Uuid, usize)> {
+        if !self.settings.ui.scrollbar {
+            return None;
+        }
+        self.window_manager.windows().values()
+            .find_map(|w| w.scrollbar_line_at(column, row).map(|line| (w.id(), line)))
+    }
+    
+    // Save changed settings and apply them to the running application
+    fn apply_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.save_settings();
+        
+        let name = self.settings.ui.theme.clone();
+        if let Err(e) = self.apply_theme(&name) {
+            eprintln!("Error loading theme: {:#}", e);
+        }
+        // A new watcher reports the current appearance, so the theme follows it right away
+        self.appearance = self.settings.ui.follow_system_appearance.then(AppearanceWatcher::start);
+        self.color_depth = ColorDepth::from_name(&self.settings.ui.color_depth).unwrap_or_else(low_color::detect);
+        self.panels.set_width(self.settings.ui.panel_width);
+        self.keymaps = Keymaps::new(&self.settings.keyboard.bindings, &self.settings.keyboard.modes).0;
+        if let Some(cheatsheet) = &mut self.cheatsheet {
+            cheatsheet.update(&self.keymaps);
+        }
+        self.events.set_tick_rate(Duration::from_millis(self.settings.general.tick_rate_ms));
+        self.events.set_frame_interval(frame_interval(&self.settings));
+        self.window_manager.set_wrap_navigation(self.settings.ui.wrap_navigation);
+        self.announcer = Announcer::new(&self.settings.accessibility);
+        
+        self.clipboard.set_history_size(self.settings.clipboard.history_size);
+        self.redactor = Redactor::new(&self.settings.redaction.patterns);
+        self.smart_selection = SmartSelection::new(&self.settings.selection_rules);
+        self.status_scripts = StatusScripts::new(&self.settings.status_scripts);
+        let mask_scrollback = self.settings.redaction.mask_scrollback;
+        let dirty_border = self.dirty_border();
+        
+        // Windows pick up keyboard, scrollback, tab, flow control, border and attribute changes right away
+        let keyboard = &self.settings.keyboard;
+        let scrollback = &self.settings.scrollback;
+        let working_set = scrollback.compress.then_some(scrollback.working_set_lines);
+        for window in self.window_manager.windows_mut().values_mut() {
+            window.buffer.set_keyboard_support(keyboard.kitty_protocol, keyboard.modify_other_keys);
+            window.buffer.set_scrollback(scrollback.max_lines, working_set);
+            window.timeline.configure(Duration::from_secs(scrollback.timeline_interval_secs), scrollback.timeline_snapshots);
+            window.buffer.set_tab_width(self.settings.general.tab_width);
+            window.flow_control = self.settings.general.flow_control;
+            window.redactor = mask_scrollback.then(|| self.redactor.clone());
+            window.dirty_border = dirty_border;
+            window.style_mapping = StyleMapping::new(&self.settings.ui);
+            window.highlight_files = self.settings.ui.highlight_files;
+        }
+    }
+    
+    // Border color for windows whose repository has uncommitted changes, if enabled
+    fn dirty_border(&self) -> Option<Color> {
+        let name = self.settings.git.dirty_border.as_deref()?;
+        match name.parse::<Color>() {
+            Ok(color) => Some(color),
+            Err(_) => {
+                eprintln!("Unknown border color: {}", name);
+                None
+            }
+        }
+    }
+    
+    // Switch to a theme by name, built-in or from a theme file
+    fn apply_theme(&mut self, name: &str) -> Result<()> {
+        let theme = Theme::load(name)?;
+        self.set_theme(theme);
+        self.theme_name = name.to_string();
+        self.theme_preview = None;
+        Ok(())
+    }
+    
+    // Switch the color theme, telling applications in every window about the new colors
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.theme_colors_changed();
+    }
+    
+    // Tell applications in every window about the theme's colors after they changed
+    fn theme_colors_changed(&mut self) {
+        let (foreground, background) = self.theme.colors();
+        for window in self.window_manager.windows_mut().values_mut() {
+            window.buffer.set_default_colors(foreground, background);
+        }
+    }
+    
+    // Pick one of the file locations shown in the current window
+    fn pick_link(&mut self) {
+        let Some(window) = self.window_manager.focused_window() else { return };
+        let links = window.visible_links();
+        if links.is_empty() {
+            self.announcer.announce_ui("No file locations in this window");
+            return;
+        }
+        
+        let window_id = window.id();
+        let items = links.iter()
+            .map(|link| {
+                let column = link.column.map(|c| format!(":{}", c)).unwrap_or_default();
+                PickerItem::new(format!("{}:{}{}", link.file, link.line, column), "")
+            })
+            .collect();
+        let targets = links.into_iter()
+            .map(|link| PickerTarget::File { window: window_id, link })
+            .collect();
+        self.open_picker(Picker::new("Open", items), targets);
+    }
+    
+    // List the windows, longest idle first, and focus the one picked
+    fn pick_window(&mut self) {
+        let mut windows: Vec<_> = self.window_manager.windows().values().collect();
+        windows.sort_by_key(|w| std::cmp::Reverse(w.idle_for()));
+        
+        let items = windows.iter()
+            .map(|w| PickerItem::new(
+                w.title.clone(),
+                format!(
+                    "idle {} (input {}, output {})",
+                    idle_text(w.idle_for()),
+                    idle_text(w.last_input.elapsed()),
+                    idle_text(w.last_output.elapsed()),
+                ),
+            ))
+            .collect();
+        let targets = windows.iter().map(|w| PickerTarget::Window(w.id())).collect();
+        self.open_picker(Picker::new("Windows", items), targets);
+    }
+    
+    // Open a file location in the configured editor, next to the window it was shown in
+    fn open_link(&mut self, window_id: Uuid, link: &FileLink) -> Result<()> {
+        // Relative paths are the window's, so the editor starts in its directory
+        let dir = self.window_manager.windows().get(&window_id).and_then(|w| w.current_dir());
+        if !link.exists(dir.as_deref()) {
+            self.announcer.announce_ui(&format!("File not found: {}", link.file));
+            return Ok(());
+        }
+        
+        let command = link.editor_command(&self.settings.editor.command);
+        match self.settings.editor.open_in {
+            EditorTarget::Split => {
+                let new_id = self.window_manager.split_window_placed(window_id, SplitDirection::Vertical, 0.5, false)?;
+                self.start_command(new_id, Some(&command), None, dir.as_deref())?;
+                self.window_manager.focus_window(new_id)?;
+            }
+            EditorTarget::External => {
+                let mut editor = std::process::Command::new("sh");
+                if let Some(dir) = &dir {
+                    editor.current_dir(dir);
+                }
+                editor
+                    .arg("-c")
+                    .arg(&command)
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn()?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Check new output against the trigger rules
+    fn run_triggers(&mut self) {
+        let mut events = Vec::new();
+        for (id, window) in self.window_manager.windows() {
+            events.extend(self.triggers.scan(*id, &window.title, &window.buffer));
+        }
+        
+        let windows = self.window_manager.windows();
+        self.triggers.retain_windows(|id| windows.contains_key(id));
+        
+        for event in events {
+            match event {
+                TriggerEvent::Notify { text } => {
+                    self.announcer.announce_ui(&text);
+                    self.notifications.push(&text);
+                    self.redraw = true;
+                }
+                TriggerEvent::Hit { window, lines_back } => {
+                    if let Some(w) = self.window_manager.windows_mut().get_mut(&window) {
+                        w.buffer.mark_recent_line(lines_back, MarkKind::Trigger);
+                    }
+                }
+                TriggerEvent::Mark { window } => {
+                    // The focused window is already getting attention
+                    let focused = self.window_manager.focused_window().map(|w| w.id());
+                    if let Some(w) = self.window_manager.windows_mut().get_mut(&window) {
+                        w.marked = focused != Some(window);
+                    }
+                }
+            }
+        }
+    }
+    
+    // List recent copies, previewing each, for picking one to restore or paste
+    fn pick_clipboard_entry(&mut self, title: &str, target: fn(usize) -> PickerTarget) {
+        let items: Vec<PickerItem> = self.clipboard.history()
+            .map(|text| {
+                // The start of the text on one line, line breaks shown as ⏎
+                let mut preview: String = text.trim_end().chars().take(PASTE_PREVIEW_CHARS).collect();
+                preview = preview.replace('\n', " ⏎ ");
+                if text.trim_end().chars().count() > PASTE_PREVIEW_CHARS {
+                    preview.push('…');
+                }
+                let lines = text.lines().count();
+                let detail = if lines > 1 { format!("{} lines", lines) } else { format!("{} characters", text.chars().count()) };
+                PickerItem::new(preview, detail)
+            })
+            .collect();
+        if items.is_empty() {
+            self.announcer.announce_ui("Nothing copied yet");
+            return;
+        }
+        
+        let targets = (0..items.len()).map(target).collect();
+        self.open_picker(Picker::new(title, items), targets);
+    }
+    
+    // Paste text into the focused window the way the clipboard settings say
+    fn paste(&mut self, text: &str) {
+        let newlines = self.settings.clipboard.paste_newlines;
+        let Some(window) = self.window_manager.focused_window_mut() else { return };
+        let data = clipboard::paste_data(text, newlines, window.buffer.bracketed_paste());
+        match window.send_input(data.as_bytes()) {
+            Ok(()) => self.announcer.announce_ui(&format!("Pasted {} characters", text.chars().count())),
+            Err(e) => self.notifications.push(&format!("Can't paste: {}", e)),
+        }
+    }
+    
+    // Hide every window behind the lock screen until the password is typed
+    fn lock(&mut self) {
+        if self.lock_screen.is_some() {
+            return;
+        }
+        let resume = std::mem::replace(&mut self.state, AppState::Locked);
+        self.lock_screen = Some((LockScreen::default(), resume));
+        self.announcer.announce_ui("Screen locked");
+    }
+    
+    // Insert a snippet into the focused window, first asking for its placeholders
+    fn insert_snippet(&mut self, name: &str) {
+        let Some(text) = self.settings.snippets.get(name) else {
+            self.notifications.push(&format!("No snippet named {}", name));
+            return;
+        };
+        self.snippet = Some(SnippetFill::new(name, text));
+        self.finish_snippet();
+    }
+    
+    // Paste a snippet once every placeholder is filled in, or keep asking
+    fn finish_snippet(&mut self) {
+        match &self.snippet {
+            Some(snippet) if snippet.current().is_some() => self.state = AppState::Snippet,
+            Some(snippet) => {
+                let text = snippet.text();
+                self.snippet = None;
+                self.state = AppState::Normal;
+                self.paste(&text);
+            }
+            None => self.state = AppState::Normal,
+        }
+    }
+    
+    // List the snippets and insert the one picked
+    fn pick_snippet(&mut self) {
+        let items: Vec<PickerItem> = self.settings.snippets.iter()
+            .map(|(name, text)| {
+                let preview: String = text.replace('\n', " ⏎ ").chars().take(PASTE_PREVIEW_CHARS).collect();
+                PickerItem::new(name.as_str(), preview)
+            })
+            .collect();
+        if items.is_empty() {
+            self.announcer.announce_ui("No snippets (settings: snippets)");
+            return;
+        }
+        
+        let targets = self.settings.snippets.keys().map(|name| PickerTarget::Snippet(name.clone())).collect();
+        self.open_picker(Picker::new("Snippets", items), targets);
+    }
+    
+    // Start recording typed input as a macro, or stop and save the one being recorded
+    fn toggle_recording(&mut self, name: &str) {
+        match self.recording.take() {
+            Some(recording) if recording.input.is_empty() => self.announcer.announce_ui("Nothing recorded"),
+            Some(recording) => {
+                self.macros.insert(recording.name.clone(), recording.input);
+                self.save_macros();
+                self.announcer.announce_ui(&format!("Recorded macro {}", recording.name));
+                self.last_macro = Some(recording.name);
+            }
+            None => {
+                self.recording = Some(Recording { name: name.to_string(), input: String::new() });
+                self.announcer.announce_ui(&format!("Recording macro {}", name));
+            }
+        }
+    }
+    
+    // Type a macro into the windows typed input goes to, `count` times over
+    fn play_macro(&mut self, name: &str, count: usize) {
+        let Some(input) = self.macros.get(name).map(|input| input.repeat(count)) else {
+            self.notifications.push(&format!("No macro named {}", name));
+            return;
+        };
+        
+        for id in self.input_targets() {
+            if let Some(window) = self.window_manager.windows_mut().get_mut(&id).filter(|w| !w.read_only) {
+                if let Err(e) = window.send_input(input.as_bytes()) {
+                    eprintln!("Error sending input: {}", e);
+                }
+            }
+        }
+        self.last_macro = Some(name.to_string());
+    }
+    
+    // List the saved macros and play the one picked
+    fn pick_macro(&mut self) {
+        let items: Vec<PickerItem> = self.macros.names()
+            .map(|name| {
+                // Control characters spelled out, so escape sequences are readable
+                let input = self.macros.get(name).unwrap_or_default();
+                let preview: String = input.escape_debug().take(PASTE_PREVIEW_CHARS).collect();
+                PickerItem::new(name, preview)
+            })
+            .collect();
+        if items.is_empty() {
+            self.announcer.announce_ui("No macros recorded");
+            return;
+        }
+        
+        let targets = self.macros.names().map(|name| PickerTarget::Macro(name.to_string())).collect();
+        self.open_picker(Picker::new("Macros", items), targets);
+    }
+    
+    // Select text in the focused window's scrollback with the keyboard
+    fn enter_copy_mode(&mut self) {
+        if let Some(window) = self.window_manager.focused_window_mut() {
+            window.copy_mode = Some(CopyMode::new(&window.buffer));
+            self.state = AppState::Copy;
+            self.announcer.announce_ui("Copy mode");
+        }
+    }
+    
+    // Leave copy mode, back at the bottom of the scrollback
+    fn exit_copy_mode(&mut self) {
+        for window in self.window_manager.windows_mut().values_mut() {
+            if window.copy_mode.take().is_some() {
+                window.buffer.scroll_to_bottom();
+            }
+        }
+        self.state = AppState::Normal;
+    }
+    
+    // Open a picker; `targets` says what each item does
+    fn open_picker(&mut self, picker: Picker, targets: Vec<PickerTarget>) {
+        self.picker = Some(picker);
+        self.picker_targets = targets;
+        self.state = AppState::Picker;
+    }
+    
+    // Close the picker, acting on the chosen item if any
+    fn close_picker(&mut self, selected: Option<usize>) {
+        self.picker = None;
+        self.state = AppState::Normal;
+        let targets = std::mem::take(&mut self.picker_targets);
+        if let Some(target) = selected.and_then(|i| targets.into_iter().nth(i)) {
+            self.choose_target(target);
+        }
+    }
+    
+    // Do what a picker or panel entry is for
+    fn choose_target(&mut self, target: PickerTarget) {
+        match target {
+            PickerTarget::Line { window, line } => {
+                if let Some(w) = self.window_manager.windows_mut().get_mut(&window) {
+                    w.buffer.scroll_to_line(line);
+                }
+                if let Err(e) = self.window_manager.focus_window(window) {
+                    eprintln!("Error focusing window: {}", e);
+                }
+            }
+            PickerTarget::File { window, link } => {
+                if let Err(e) = self.open_link(window, &link) {
+                    eprintln!("Error opening {}: {}", link.file, e);
+                }
+            }
+            PickerTarget::ClipboardEntry(index) => {
+                if let Err(e) = self.clipboard.restore(index) {
+                    eprintln!("Error setting clipboard: {}", e);
+                }
+            }
+            PickerTarget::Macro(name) => self.play_macro(&name, 1),
+            PickerTarget::Snippet(name) => self.insert_snippet(&name),
+            PickerTarget::Directory { path, new_window } => {
+                if let Err(e) = self.go_to_directory(&path, new_window) {
+                    self.notifications.push(&format!("Can't open {}: {}", path, e));
+                }
+            }
+            PickerTarget::Command(command) => {
+                // Lines joined so nothing runs before Enter is pressed
+                if let Some(window) = self.window_manager.focused_window_mut() {
+                    let data = clipboard::paste_data(&command, PasteNewlines::Join, window.buffer.bracketed_paste());
+                    if let Err(e) = window.send_input(data.as_bytes()) {
+                        self.notifications.push(&format!("Can't type the command: {}", e));
+                    }
+                }
+            }
+            PickerTarget::Paste(index) => {
+                let text = self.clipboard.history().nth(index).map(str::to_string);
+                if let Some(text) = text {
+                    self.paste(&text);
+                }
+            }
+            PickerTarget::Window(window) => {
+                if let Err(e) = self.window_manager.focus_window(window) {
+                    eprintln!("Error focusing window: {}", e);
+                }
+            }
+            PickerTarget::Run(command) => {
+                if let Some(window) = self.window_manager.focused_window_mut() {
+                    let data = clipboard::paste_data(&command, PasteNewlines::Join, false);
+                    if let Err(e) = window.send_input(format!("{}\r", data).as_bytes()) {
+                        self.notifications.push(&format!("Can't run the command: {}", e));
+                    }
+                }
+            }
+            PickerTarget::Ssh(host) => {
+                let result = self.window_manager.create_window(&host)
+                    .and_then(|id| self.start_command(id, Some(&format!("ssh {}", quote(&host))), None, None));
+                if let Err(e) = result {
+                    self.notifications.push(&format!("Can't connect to {}: {}", host, e));
+                }
+            }
+        }
+    }
+    
+    // Search windows for a regular expression, highlight the matches and list them in a picker
+    fn search_windows(&mut self, pattern: &str, all_windows: bool) -> Result<()> {
+        let regex = regex::Regex::new(pattern)?;
+        let focused = self.window_manager.focused_window().map(|w| w.id());
+        
+        let mut items = Vec::new();
+        let mut targets = Vec::new();
+        
+        // Search windows in a stable order
+        let mut windows: Vec<_> = self.window_manager.windows_mut().iter_mut().collect();
+        windows.sort_by(|a, b| a.1.title.cmp(&b.1.title));
+        
+        for (id, window) in windows {
+            if !all_windows && Some(*id) != focused {
+                continue;
+            }
+            
+            window.highlight = Some(regex.clone());
+            
+            // Mark every match on the scrollbar
+            let results = window.buffer.search_regex(&regex);
+            window.buffer.clear_marks(MarkKind::Search);
+            for found in &results {
+                window.buffer.add_mark(found.line, MarkKind::Search);
+            }
+            
+            for found in results {
+                if items.len() >= MAX_SEARCH_RESULTS {
+                    break;
+                }
+                items.push(PickerItem::new(
+                    format!("{}:{}:{}", window.title, found.line + 1, found.range.start + 1),
+                    found.text.trim().to_string(),
+                ));
+                targets.push(PickerTarget::Line { window: *id, line: found.line });
+            }
+        }
+        
+        // The results stay listed in the search panel
+        self.search_results = items.iter().cloned().zip(targets.iter().cloned()).collect();
+        self.panels.set_items(PanelKind::Search, self.search_results.clone());
+        
+        self.open_picker(Picker::new(&format!("Search /{}/", pattern), items), targets);
+        Ok(())
+    }
+    
+    // Apply the clipboard requests (OSC 52) applications made, according to the clipboard settings
+    fn handle_clipboard_requests(&mut self) {
+        let policy = self.settings.clipboard.osc52;
+        let max_bytes = self.settings.clipboard.max_bytes;
+        let allow_read = policy == Osc52Policy::Allow && self.settings.clipboard.osc52_read;
+        
+        for (id, window) in self.window_manager.windows_mut().iter_mut() {
+            for request in window.buffer.take_clipboard_requests() {
+                match request {
+                    ClipboardRequest::Set(payload) => {
+                        if policy == Osc52Policy::Off {
+                            continue;
+                        }
+                        
+                        let text = match clipboard::decode_payload(&payload, max_bytes) {
+                            Ok(text) => text,
+                            Err(e) => {
+                                eprintln!("Ignoring clipboard request from {}: {}", window.title, e);
+                                continue;
+                            }
+                        };
+                        
+                        if policy == Osc52Policy::Allow {
+                            if let Err(e) = self.clipboard.set(text) {
+                                eprintln!("Error setting clipboard: {}", e);
+                            }
+                        } else if self.pending_clipboard.is_none() {
+                            // One prompt at a time; requests arriving while it is open are dropped
+                            self.pending_clipboard = Some((*id, text));
+                            // Behind the lock screen the prompt waits for it to be unlocked
+                            match &mut self.lock_screen {
+                                Some((_, resume)) => *resume = AppState::ClipboardPrompt,
+                                None => self.state = AppState::ClipboardPrompt,
+                            }
+                            self.redraw = true;
+                        }
+                    }
+                    ClipboardRequest::Query => {
+                        if allow_read {
+                            let reply = clipboard::encode_reply(self.clipboard.get().unwrap_or(""));
+                            window.buffer.respond(&reply);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Handle mouse events
+    fn handle_mouse_event(&mut self, event: MouseEvent) {
+        // Nothing behind the lock screen can be clicked
+        if self.lock_screen.is_some() {
+            return;
+        }
+        
+        // Mouse moves, clicks and the wheel
+        match event.kind {
+            MouseEventKind::Moved => {
+                // If the mouse is in the sidebar area, determine which icon is being hovered
+                if self.sidebar.is_active() && event.column < self.sidebar.width() {
+                    let hovered_icon = self.sidebar.icon_at_position(event.row);
+                    self.sidebar.set_hover(hovered_icon);
+                } else {
+                    // Clear hover state when mouse leaves sidebar
+                    self.sidebar.set_hover(None);
+                }
+                
+                // Start the delay before focusing the window under the mouse
+                if self.settings.ui.focus_follows_mouse && !self.mouse_dragging {
+                    let hovered = self.window_at(event.column, event.row)
+                        .filter(|id| self.window_manager.focused_window().map(|w| w.id()) != Some(*id));
+                    match hovered {
+                        Some(id) if self.hover_focus.is_some_and(|(pending, _)| pending == id) => {}
+                        Some(id) => self.hover_focus = Some((id, Instant::now())),
+                        None => self.hover_focus = None,
+                    }
+                }
+            },
+            MouseEventKind::Drag(_) if self.panels.drag_to(self.panel_dock(), event.column, event.row) => {}
+            MouseEventKind::Drag(_) => {
+                // Selecting or dragging across windows keeps the focus where it is
+                self.mouse_dragging = true;
+                self.hover_focus = None;
+            },
+            MouseEventKind::Up(_) => {
+                self.mouse_dragging = false;
+                self.panels.end_drag();
+            },
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown if self.in_panels(event.column, event.row) => {
+                let up = (event.kind == MouseEventKind::ScrollUp) != self.settings.mouse.natural_scrolling;
+                self.panels.scroll(self.panel_dock(), event.column, event.row, up, self.settings.mouse.scroll_lines.max(1));
+            },
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                // Scroll the window under the mouse, or the focused one
+                let target = self.window_at(event.column, event.row)
+                    .or_else(|| self.window_manager.focused_window().map(|w| w.id()));
+                let up = (event.kind == MouseEventKind::ScrollUp) != self.settings.mouse.natural_scrolling;
+                let lines = self.settings.mouse.scroll_lines.max(1);
+                let alternate_scroll = self.settings.mouse.alternate_scroll;
+                
+                let Some(window) = target.and_then(|id| self.window_manager.windows_mut().get_mut(&id)) else { return };
+                if alternate_scroll && window.buffer.alternate_screen() {
+                    // Full-screen programs have no scrollback here; they scroll themselves
+                    if window.read_only {
+                        return;
+                    }
+                    let key = if up { Key::Up } else { Key::Down };
+                    let bytes = keyboard::encode_key(key, keyboard::KeyModifiers::default(), window.buffer.keyboard_mode());
+                    if let Err(e) = window.send_input(&bytes.repeat(lines)) {
+                        eprintln!("Error sending input: {}", e);
+                    }
+                } else if up {
+                    window.buffer.scroll_up(lines);
+                } else {
+                    window.buffer.scroll_down(lines);
+                }
+            },
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Handle clicks on the sidebar
+                if self.sidebar.is_active() && event.column < self.sidebar.width() {
+                    if let Some(icon) = self.sidebar.icon_at_position(event.row) {
+                        self.handle_sidebar_click(icon);
+                    }
+                } else if self.in_panels(event.column, event.row) {
+                    // Drag a panel's border, or act on the item clicked
+                    let dock = self.panel_dock();
+                    if !self.panels.start_drag(dock, event.column, event.row) {
+                        if let Some(target) = self.panels.item_at(dock, event.column, event.row).cloned() {
+                            self.choose_target(target);
+                        }
+                    }
+                } else if let Some(window) = self.window_manager.windows_mut().values_mut().find(|w| w.scroll_pill_at(event.column, event.row)) {
+                    // Back to the newest output
+                    window.buffer.scroll_to_bottom();
+                } else if let Some((window, line)) = self.scrollbar_line_at(event.column, event.row) {
+                    // Jump to the clicked position, centered in the view
+                    if let Some(w) = self.window_manager.windows_mut().get_mut(&window) {
+                        let (_, visible) = w.buffer.viewport();
+                        w.buffer.scroll_to_line(line.saturating_sub(visible / 2));
+                    }
+                } else if self.last_click.take().is_some_and(|(column, row, at)| {
+                    column == event.column && row == event.row && at.elapsed() <= DOUBLE_CLICK_TIME
+                }) {
+                    self.select_at(event.column, event.row);
+                } else {
+                    self.last_click = Some((event.column, event.row, Instant::now()));
+                    
+                    // Open a file location clicked in a window
+                    let clicked = self.window_manager.windows().values()
+                        .find_map(|w| w.link_at(event.column, event.row).map(|link| (w.id(), link)));
+                    if let Some((window, link)) = clicked {
+                        if let Err(e) = self.open_link(window, &link) {
+                            eprintln!("Error opening {}: {}", link.file, e);
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    
+    // Where the sidebar's panels are
+    fn panel_dock(&self) -> Rect {
+        panel_dock(&self.sidebar, &self.panels, self.window_manager.area())
+    }
+    
+    // Whether a position is in the sidebar's panels
+    fn in_panels(&self, column: u16, row: u16) -> bool {
+        let dock = self.panel_dock();
+        self.sidebar.is_active() && column >= dock.x && column < dock.right() && row >= dock.y && row < dock.bottom()
+    }
+    
+    // Copy what's double-clicked: a smart selection match (URL, path, hash...) or
+    // else the word
+    fn select_at(&mut self, column: u16, row: u16) {
+        let Some(window) = self.window_at(column, row).and_then(|id| self.window_manager.windows().get(&id)) else { return };
+        let Some((line, col)) = window.line_at(column, row) else { return };
+        
+        let (kind, text) = match self.smart_selection.at(&line, col) {
+            Some(found) => (found.kind, found.text),
+            None => {
+                let chars: Vec<char> = line.chars().collect();
+                if chars.get(col).is_none_or(|c| c.is_whitespace()) {
+                    return;
+                }
+                let start = chars[..col].iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+                let end = chars[col..].iter().position(|c| c.is_whitespace()).map_or(chars.len(), |i| col + i);
+                (String::from("word"), chars[start..end].iter().collect())
+            }
+        };
+        
+        if let Err(e) = self.clipboard.set(text.clone()) {
+            eprintln!("Error setting clipboard: {}", e);
+        }
+        self.announcer.announce_ui(&format!("Copied {}: {}", kind, text));
+    }
+    
+    // Label the matches shown in the focused window for choosing one with the keyboard
+    fn enter_hint_mode(&mut self, action: HintAction) {
+        let Some(window) = self.window_manager.focused_window() else { return };
+        let hints = Hints::new(&window.content(), window.content_area(), |line| self.smart_selection.find(line), action);
+        if hints.is_empty() {
+            self.announcer.announce_ui("Nothing to select");
+            return;
+        }
+        self.hints = Some(hints);
+        self.state = AppState::Hints;
+        self.redraw = true;
+    }
+    
+    fn exit_hint_mode(&mut self) {
+        self.hints = None;
+        self.state = AppState::Normal;
+        self.redraw = true;
+    }
+    
+    // Copy, paste or open the match chosen in hint mode
+    fn act_on_hint(&mut self, found: SmartMatch, action: HintAction) {
+        match action {
+            HintAction::Copy => {
+                if let Err(e) = self.clipboard.set(found.text.clone()) {
+                    eprintln!("Error setting clipboard: {}", e);
+                }
+                self.announcer.announce_ui(&format!("Copied {}: {}", found.kind, found.text));
+            }
+            HintAction::Paste => self.paste(&found.text),
+            HintAction::Open => {
+                let Some(command) = &found.open else {
+                    self.announcer.announce_ui(&format!("No way to open a {}", found.kind));
+                    return;
+                };
+                let spawned = std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn();
+                match spawned {
+                    Ok(_) => self.announcer.announce_ui(&format!("Opening {}", found.text)),
+                    Err(e) => eprintln!("Error opening {}: {}", found.text, e),
+                }
+            }
+        }
+    }
+    
+    // The window shown at a point on the screen
+    fn window_at(&self, column: u16, row: u16) -> Option<Uuid> {
+        let point = Rect::new(column, row, 1, 1);
+        self.visible_windows().into_iter()
+            .find(|id| self.window_manager.windows().get(id).is_some_and(|w| w.size().intersects(point)))
+    }
+    
+    // The mode keys are typed in, if it's one with key bindings (overlays and prompts
+    // have none)
+    fn mode(&self) -> Option<Mode> {
+        match self.state {
+            AppState::Normal => Some(Mode::Normal),
+            AppState::Copy => Some(Mode::Copy),
+            AppState::Resize => Some(Mode::Resize),
+            AppState::Search => Some(Mode::Search),
+            AppState::Command => Some(Mode::Command),
+            _ => None,
+        }
+    }
+    
+    // Switch to a mode, leaving the one keys are typed in now
+    fn enter_mode(&mut self, mode: Mode) {
+        if self.mode() == Some(Mode::Copy) && mode != Mode::Copy {
+            self.exit_copy_mode();
+        }
+        self.pending_keys.clear();
+        match mode {
+            Mode::Normal => self.state = AppState::Normal,
+            Mode::Copy => self.enter_copy_mode(),
+            Mode::Resize => {
+                self.state = AppState::Resize;
+                self.announcer.announce_ui("Resize mode");
+            }
+            Mode::Search | Mode::Command => {
+                self.command_buffer.clear();
+                self.state = if mode == Mode::Search { AppState::Search } else { AppState::Command };
+                self.announcer.announce_ui(if mode == Mode::Search { "Search" } else { "Command mode" });
+            }
         }
     }
     
-    // Handle mouse events
-    fn handle_mouse_event(&mut self, event: MouseEvent) {
-        // Only process mouse moves and clicks
-        match event.kind {
-            MouseEventKind::Moved => {
-                // If the mouse is in the sidebar area, determine which icon is being hovered
-                if self.sidebar.is_active() && event.column < self.sidebar.width() {
-                    let hovered_icon = self.sidebar.icon_at_position(event.row);
-                    self.sidebar.set_hover(hovered_icon);
-                } else {
-                    // Clear hover state when mouse leaves sidebar
-                    self.sidebar.set_hover(None);
-                }
-            },
-            MouseEventKind::Down(MouseButton::Left) => {
-                // Handle clicks on the sidebar
-                if self.sidebar.is_active() && event.column < self.sidebar.width() {
-                    if let Some(icon) = self.sidebar.icon_at_position(event.row) {
-                        self.handle_sidebar_click(icon);
-                    }
+    // Follow a key sequence bound to a command in the current mode; false if the key
+    // isn't part of one
+    fn handle_key_sequence(&mut self, key: &KeyEvent) -> bool {
+        let Some(mode) = self.mode() else { return false };
+        self.pending_keys.push(Chord::from_event(key));
+        self.pending_since = Instant::now();
+        match self.keymaps.get(mode).lookup(&self.pending_keys) {
+            Lookup::Command(command) => {
+                let command = command.to_string();
+                self.pending_keys.clear();
+                self.run_command(&command);
+                true
+            }
+            Lookup::Pending(_) => true,
+            Lookup::None => {
+                // A key no sequence continues with ends it; a key starting none goes on as usual
+                let started = self.pending_keys.len() > 1;
+                if started {
+                    self.announcer.announce_ui(&format!("{} is not bound", keymap::sequence_name(&self.pending_keys)));
                 }
-            },
-            _ => {}
+                self.pending_keys.clear();
+                started
+            }
+        }
+    }
+    
+    // Give up on a key sequence left unfinished for keyboard.chord_timeout_ms, running
+    // the command bound to the keys typed if there is one
+    fn expire_pending_keys(&mut self) {
+        let timeout = Duration::from_millis(self.settings.keyboard.chord_timeout_ms);
+        if self.pending_keys.is_empty() || self.pending_since.elapsed() < timeout {
+            return;
+        }
+        let keys = std::mem::take(&mut self.pending_keys);
+        let Some(mode) = self.mode() else { return };
+        if let Lookup::Pending(Some(command)) = self.keymaps.get(mode).lookup(&keys) {
+            let command = command.to_string();
+            self.run_command(&command);
+        }
+        self.redraw = true;
+    }
+    
+    // Focus the window the mouse has rested on long enough (focus follows mouse)
+    fn focus_hovered_window(&mut self) {
+        let Some((id, since)) = self.hover_focus else { return };
+        if since.elapsed() < Duration::from_millis(self.settings.ui.focus_delay_ms) {
+            return;
+        }
+        self.hover_focus = None;
+        
+        // Leave the focus alone while a mode or overlay is using the keyboard
+        if matches!(self.state, AppState::Normal) {
+            if let Err(e) = self.window_manager.focus_window(id) {
+                eprintln!("Error focusing window: {}", e);
+            }
+            self.redraw = true;
         }
     }
     
@@ -471,12 +3021,12 @@ impl App {
                 }
             },
             SidebarIcon::SplitHorizontal => {
-                if let Err(e) = self.split_window(SplitDirection::Horizontal) {
+                if let Err(e) = self.begin_split(SplitDirection::Horizontal) {
                     eprintln!("Error splitting window: {}", e);
                 }
             },
             SidebarIcon::SplitVertical => {
-                if let Err(e) = self.split_window(SplitDirection::Vertical) {
+                if let Err(e) = self.begin_split(SplitDirection::Vertical) {
                     eprintln!("Error splitting window: {}", e);
                 }
             },
@@ -522,130 +3072,701 @@ impl App {
                         eprintln!("Error zooming window: {}", e);
                     }
                 }
-            },
-            SidebarIcon::CloseWindow => {
-                if let Err(e) = self.close_current_window() {
-                    eprintln!("Error closing window: {}", e);
+            },
+            SidebarIcon::CloseWindow => {
+                if let Err(e) = self.close_current_window() {
+                    eprintln!("Error closing window: {}", e);
+                }
+            },
+            SidebarIcon::Help => {
+                self.display_help();
+            },
+        }
+    }
+    
+    fn execute_command(&mut self) {
+        // Add the command to history
+        if !self.command_buffer.is_empty() {
+            self.command_history.push(self.command_buffer.clone());
+        }
+        
+        // Clone the command buffer
+        let command = self.command_buffer.clone();
+        self.run_command(&command);
+        
+        // The tutorial may be waiting for this command
+        if let Some(name) = command.split_whitespace().next() {
+            let progress = self.tutorial_progress();
+            if self.tutorial.as_mut().is_some_and(|tutorial| tutorial.command_run(name, &progress)) {
+                self.announce_tutorial();
+            }
+        }
+    }
+    
+    // Run a command as typed after ':'
+    fn run_command(&mut self, command: &str) {
+        let command = command.to_string();
+        
+        // Parse and execute the command
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        
+        if let Some(cmd) = parts.first() {
+            match *cmd {
+                "q" | "quit" => {
+                    // Quit the application
+                    self.running = false;
+                }
+                "new" => {
+                    // Create a new window, with the limits of a profile if one is given,
+                    // running the command after -- instead of a shell if there is one
+                    let (options, program) = match command.split_once(" -- ") {
+                        Some((options, program)) => (options, Some(program.trim()).filter(|p| !p.is_empty())),
+                        None => (command.as_str(), None),
+                    };
+                    let mut rest: Vec<&str> = options.split_whitespace().skip(1).collect();
+                    let supervise = rest.first() == Some(&"--supervise");
+                    if supervise {
+                        rest.remove(0);
+                    }
+                    let (profile, rest) = match rest.as_slice() {
+                        ["--profile", name, rest @ ..] => (Some(*name), rest),
+                        rest => (None, rest),
+                    };
+                    let title = rest.first().copied().or(program).unwrap_or("New Terminal");
+                    
+                    if let Some(name) = profile.filter(|name| !self.settings.profiles.contains_key(*name)) {
+                        self.notifications.push(&format!("No profile named {}", name));
+                    } else if supervise && program.is_none() {
+                        self.notifications.push("Only a command can be supervised: :new --supervise [title] -- <command>");
+                    } else {
+                        let named = !rest.is_empty();
+                        let result = self.window_manager.create_window(title).and_then(|id| {
+                            // A window named by the user keeps its name
+                            if let Some(window) = self.window_manager.windows_mut().get_mut(&id) {
+                                window.auto_title = !named;
+                                // Restarted when it fails
+                                window.supervisor = supervise.then(Supervisor::default);
+                            }
+                            self.start_command(id, program, profile, None)
+                        });
+                        if let Err(e) = result {
+                            // Handle error
+                            eprintln!("Error creating window: {}", e);
+                        }
+                    }
+                }
+                "split" => {
+                    // Split the current window
+                    let direction = if parts.len() > 1 && parts[1] == "h" {
+                        SplitDirection::Horizontal
+                    } else {
+                        SplitDirection::Vertical
+                    };
+                    
+                    if let Err(e) = self.begin_split(direction) {
+                        // Handle error
+                        eprintln!("Error splitting window: {}", e);
+                    }
+                }
+                "layout" => {
+                    // Apply a layout
+                    if parts.len() > 1 && (parts[1] == "undo" || parts[1] == "redo") {
+                        // Step through the layout history
+                        let steps = parts.get(2).and_then(|n| n.parse().ok()).unwrap_or(1);
+                        let result = if parts[1] == "undo" {
+                            self.window_manager.undo_layout(steps)
+                        } else {
+                            self.window_manager.redo_layout(steps)
+                        };
+                        
+                        match result {
+                            Ok(number) => self.announcer.announce_ui(&format!("Layout {}", number)),
+                            Err(e) => eprintln!("Error restoring layout: {}", e),
+                        }
+                    } else if parts.len() > 1 {
+                        let layout_type = parts[1];
+                        let window_ids: Vec<Uuid> = self.window_manager.windows().keys().cloned().collect();
+                        
+                        if window_ids.is_empty() {
+                            eprintln!("No windows to arrange");
+                            return;
+                        }
+                        
+                        let result = match layout_type {
+                            "grid" => {
+                                self.window_manager.apply_grid_layout(&window_ids)
+                            },
+                            "horizontal" | "h" => {
+                                self.window_manager.apply_horizontal_layout(&window_ids)
+                            },
+                            "vertical" | "v" => {
+                                self.window_manager.apply_vertical_layout(&window_ids)
+                            },
+                            "main" | "m" => {
+                                if let Some(main_id) = self.window_manager.focused_window().map(|w| w.id()) {
+                                    let stack_ids: Vec<Uuid> = window_ids.into_iter()
+                                        .filter(|&id| id != main_id)
+                                        .collect();
+                                    self.window_manager.apply_main_and_stack_layout(main_id, &stack_ids)
+                                } else {
+                                    Ok(()) // No focused window
+                                }
+                            },
+                            _ => {
+                                eprintln!("Unknown layout: {}", layout_type);
+                                Ok(())
+                            }
+                        };
+                        
+                        if let Err(e) = result {
+                            eprintln!("Error applying layout: {}", e);
+                        }
+                    } else {
+                        eprintln!("Usage: layout [grid|horizontal|vertical|main|undo|redo]");
+                    }
+                }
+                "resize" => {
+                    // Move the border of the current window
+                    let direction = match parts.get(1) {
+                        Some(&"up") => Some(Direction::Up),
+                        Some(&"down") => Some(Direction::Down),
+                        Some(&"left") => Some(Direction::Left),
+                        Some(&"right") => Some(Direction::Right),
+                        _ => None,
+                    };
+                    let percent: f32 = parts.get(2).and_then(|p| p.parse().ok()).unwrap_or(5.0);
+                    
+                    if let Some(direction) = direction {
+                        if let Err(e) = self.resize_focused_window(direction, percent / 100.0, false) {
+                            eprintln!("Error resizing window: {}", e);
+                        }
+                    } else if parts.len() == 1 {
+                        self.enter_mode(Mode::Resize);
+                    } else {
+                        eprintln!("Usage: resize [up|down|left|right] [percent]");
+                    }
+                }
+                "move" => {
+                    // Move the current window past its neighbour in a direction
+                    let direction = match parts.get(1) {
+                        Some(&"up") => Some(Direction::Up),
+                        Some(&"down") => Some(Direction::Down),
+                        Some(&"left") => Some(Direction::Left),
+                        Some(&"right") => Some(Direction::Right),
+                        _ => None,
+                    };
+                    
+                    if let Some(direction) = direction {
+                        if let Err(e) = self.move_focused_window(direction) {
+                            eprintln!("Error moving window: {}", e);
+                        }
+                    } else {
+                        eprintln!("Usage: move [up|down|left|right]");
+                    }
+                }
+                "zoom" => {
+                    // Zoom the current window
+                    if let Some(id) = self.window_manager.focused_window().map(|w| w.id()) {
+                        if let Err(e) = self.window_manager.zoom_window(Some(id)) {
+                            eprintln!("Error zooming window: {}", e);
+                        }
+                    } else {
+                        eprintln!("No window to zoom");
+                    }
+                }
+                "close" => {
+                    // Close the current window
+                    if let Err(e) = self.close_current_window() {
+                        // Handle error
+                        eprintln!("Error closing window: {}", e);
+                    }
+                }
+                "help" => {
+                    // Display help information
+                    self.display_help();
+                }
+                "panel" => {
+                    // Toggle a sidebar panel, or set the panels' width
+                    let kind = parts.get(1).and_then(|name| PanelKind::from_name(name));
+                    match (parts.get(1).copied(), parts.get(2).and_then(|n| n.parse::<u16>().ok()), kind) {
+                        (Some("width"), Some(width), _) => self.panels.set_width(width),
+                        (_, _, Some(kind)) => self.toggle_panel(kind),
+                        (None, _, _) => {
+                            let open: Vec<&str> = self.panels.open_kinds().iter().map(|kind| kind.name()).collect();
+                            self.announcer.announce_ui(&format!("Panels open: {}", if open.is_empty() { String::from("none") } else { open.join(", ") }));
+                        }
+                        _ => {
+                            let names: Vec<&str> = PanelKind::ALL.iter().map(|kind| kind.name()).collect();
+                            eprintln!("Usage: panel <{}> | panel width <columns>", names.join("|"));
+                        }
+                    }
+                }
+                "sidebar" => {
+                    // Toggle sidebar
+                    self.sidebar.toggle();
+                }
+                "theme" => {
+                    // Switch the color theme, or try one out for a while
+                    let mut available: Vec<String> = Theme::NAMES.iter().map(|name| name.to_string()).collect();
+                    available.extend(theme_files::names());
+                    match (parts.get(1).copied(), parts.get(2)) {
+                        (Some("preview"), Some(name)) => match self.apply_theme(name) {
+                            Ok(()) => {
+                                self.theme_preview = Some(Instant::now());
+                                self.announcer.announce_ui(&format!(
+                                    "Previewing theme {} for {} seconds (:theme keep to keep it)", name, THEME_PREVIEW_TIME.as_secs()
+                                ));
+                            }
+                            Err(e) => eprintln!("{:#} (available: {})", e, available.join(", ")),
+                        },
+                        (Some("edit"), None) => {
+                            self.theme_editor = Some(ThemeEditor::default());
+                            self.state = AppState::ThemeEditor;
+                        }
+                        (Some("keep"), _) if self.theme_preview.is_some() => {
+                            self.theme_preview = None;
+                            self.settings.ui.theme = self.theme_name.clone();
+                            self.announcer.announce_ui(&format!("Theme {}", self.theme_name));
+                        }
+                        (Some(name), None) => match self.apply_theme(name) {
+                            Ok(()) => {
+                                self.settings.ui.theme = name.to_string();
+                                self.announcer.announce_ui(&format!("Theme {}", name));
+                            }
+                            Err(e) => eprintln!("{:#} (available: {})", e, available.join(", ")),
+                        },
+                        _ => eprintln!("Usage: theme [{}] | theme preview <name> | theme keep | theme edit", available.join("|")),
+                    }
+                }
+                "tmux" => {
+                    // Attach to tmux in control mode, or detach from all sessions
+                    match parts.get(1) {
+                        Some(&"detach") => {
+                            for session in &mut self.tmux_sessions {
+                                if let Err(e) = session.detach() {
+                                    eprintln!("Error detaching from tmux: {}", e);
+                                }
+                            }
+                        }
+                        host => {
+                            if let Err(e) = self.attach_tmux(host.copied()) {
+                                eprintln!("Error attaching to tmux: {}", e);
+                            }
+                        }
+                    }
+                }
+                "session" => {
+                    // Save or restore a named session (the automatic one is called "last")
+                    let name = parts.get(2).copied().unwrap_or(AUTOSAVE_SESSION);
+                    match parts.get(1) {
+                        Some(&"save") => self.save_session(name),
+                        Some(&"restore") => {
+                            let restored = Session::load(name).and_then(|session| self.restore_windows(session, false));
+                            if let Err(e) = restored {
+                                eprintln!("Error restoring session: {}", e);
+                            }
+                        }
+                        _ => eprintln!("Usage: session [save|restore] [name]"),
+                    }
+                }
+                "workspace" => {
+                    // Save the current windows as a workspace template, or set one up here
+                    match (parts.get(1), parts.get(2), parts.get(3)) {
+                        (Some(&"template"), Some(&"save"), Some(name)) => {
+                            if let Err(e) = self.save_template(name) {
+                                eprintln!("Error saving workspace template: {}", e);
+                            }
+                        }
+                        (Some(&"template"), Some(&"apply"), Some(name)) => {
+                            let base = self.workspace_dir();
+                            if let Err(e) = self.apply_template(name, &base) {
+                                eprintln!("Error applying workspace template: {}", e);
+                            }
+                        }
+                        _ => eprintln!("Usage: workspace template [save|apply] <name>"),
+                    }
+                }
+                "search" | "search-all" => {
+                    // Everything after the command is the pattern, spaces included
+                    let pattern = command.trim_start()[cmd.len()..].trim();
+                    if pattern.is_empty() {
+                        eprintln!("Usage: {} <regex>", cmd);
+                    } else if let Err(e) = self.search_windows(pattern, *cmd == "search-all") {
+                        eprintln!("Error searching: {}", e);
+                    }
+                }
+                "filter" => {
+                    // Show only the lines of the current window matching a pattern
+                    let pattern = command.trim_start()[cmd.len()..].trim();
+                    if pattern.is_empty() {
+                        eprintln!("Usage: filter <regex> | filter clear");
+                    } else if let Some(window) = self.window_manager.focused_window_mut() {
+                        if pattern == "clear" {
+                            window.set_filter(None);
+                        } else {
+                            match regex::Regex::new(pattern) {
+                                Ok(regex) => {
+                                    window.set_filter(Some(regex));
+                                    let count = window.filter_matches().unwrap_or(0);
+                                    self.announcer.announce_ui(&format!("{} matching lines", count));
+                                }
+                                Err(e) => eprintln!("Invalid filter pattern: {}", e),
+                            }
+                        }
+                    }
+                }
+                "raw" => {
+                    // Show files shown with cat as they came out, or colored again
+                    if let Some(window) = self.window_manager.focused_window_mut() {
+                        window.raw_output = !window.raw_output;
+                        let message = if window.raw_output { "Showing raw text" } else { "Coloring files shown with cat" };
+                        self.announcer.announce_ui(message);
+                    }
+                }
+                "view" => {
+                    // Read a file, or the current window's history, in the built-in pager
+                    let path = command.trim_start()[cmd.len()..].trim();
+                    let pager = if path.is_empty() {
+                        self.window_manager.focused_window().map(|w| (w.id(), w.title.clone()))
+                            .and_then(|(id, title)| Some((title, self.capture(id, Some(usize::MAX))?)))
+                            .map(|(title, lines)| {
+                                let mut pager = Pager::new(&title, lines);
+                                pager.go_to_end();
+                                pager
+                            })
+                    } else {
+                        // Relative to the window's directory, as typed in its shell
+                        let dir = self.window_manager.focused_window().and_then(|w| w.current_dir()).unwrap_or_default();
+                        match Pager::open(&std::path::Path::new(&dir).join(paths::expand_home(path))) {
+                            Ok(pager) => Some(pager),
+                            Err(e) => {
+                                eprintln!("Error opening {}: {}", path, e);
+                                None
+                            }
+                        }
+                    };
+                    if let Some(pager) = pager {
+                        self.announcer.announce_ui(&format!("Viewing {} lines", pager.line_count()));
+                        self.pager = Some(pager);
+                        self.state = AppState::Pager;
+                    }
+                }
+                "diff" => {
+                    // Compare the current window's lines with another window's
+                    let name = command.trim_start()[cmd.len()..].trim();
+                    let other = self.window_named(name).and_then(|id| self.window_manager.windows().get(&id));
+                    match (self.window_manager.focused_window(), other) {
+                        _ if name.is_empty() => eprintln!("Usage: diff <window number or title>"),
+                        (Some(current), Some(other)) => {
+                            let diff_view = DiffView::new(&current.title, &current.content(), &other.title, &other.content());
+                            self.announcer.announce_ui(&format!("{} lines differ", diff_view.changed()));
+                            self.diff_view = Some(diff_view);
+                            self.state = AppState::Diff;
+                        }
+                        (_, None) => eprintln!("No window {}", name),
+                        (None, _) => {}
+                    }
+                }
+                "timeline" => {
+                    // Scrub through what the current window showed
+                    if let Some(window) = self.window_manager.focused_window_mut() {
+                        if window.open_timeline() {
+                            let count = window.timeline.len();
+                            self.state = AppState::Timeline;
+                            self.announcer.announce_ui(&format!("Timeline, {} screens recorded", count));
+                        } else {
+                            eprintln!("The timeline is off (settings: scrollback.timeline_interval_secs)");
+                        }
+                    }
+                }
+                "tutorial" => {
+                    // Take the guided tour, skip a step of it or leave it
+                    let progress = self.tutorial_progress();
+                    match (parts.get(1).copied(), &mut self.tutorial) {
+                        (None, _) => self.start_tutorial(),
+                        (Some("skip"), Some(tutorial)) => {
+                            tutorial.skip(&progress);
+                            self.announce_tutorial();
+                        }
+                        (Some("quit"), _) => self.tutorial = None,
+                        _ => eprintln!("Usage: tutorial | tutorial skip | tutorial quit"),
+                    }
+                }
+                "cheatsheet" => {
+                    // Show the keys in effect, or write them out as Markdown
+                    match (parts.get(1), parts.get(2)) {
+                        (None, _) => {
+                            self.cheatsheet = Some(Cheatsheet::new(&self.keymaps));
+                            self.state = AppState::Cheatsheet;
+                        }
+                        (Some(&"export"), Some(path)) => {
+                            let markdown = cheatsheet::to_markdown(&cheatsheet::entries(&self.keymaps));
+                            match std::fs::write(path, markdown) {
+                                Ok(()) => self.announcer.announce_ui(&format!("Cheatsheet saved to {}", path)),
+                                Err(e) => eprintln!("Error saving cheatsheet: {}", e),
+                            }
+                        }
+                        _ => eprintln!("Usage: cheatsheet | cheatsheet export <file>"),
+                    }
+                }
+                "export" => {
+                    // Save the current window's scrollback, with colors, to a file
+                    match (parts.get(1), parts.get(2)) {
+                        (Some(&"html"), Some(path)) => {
+                            if let Err(e) = self.export_html(path) {
+                                eprintln!("Error exporting window: {}", e);
+                            }
+                        }
+                        _ => eprintln!("Usage: export html <file>"),
+                    }
+                }
+                "pipe" => {
+                    // Copy the current window's output from now on to a command, or stop
+                    let program = command.trim_start()[cmd.len()..].trim();
+                    let Some(window) = self.window_manager.focused_window_mut() else { return };
+                    if program.is_empty() || program == "stop" {
+                        if let Some(pipe) = window.pipe.take() {
+                            self.announcer.announce_ui(&format!("Stopped piping output to {}", pipe.command));
+                        }
+                    } else {
+                        match OutputPipe::start(program, window.current_dir().as_deref()) {
+                            Ok(pipe) => {
+                                window.pipe = Some(pipe);
+                                self.announcer.announce_ui(&format!("Piping output to {}", program));
+                            }
+                            Err(e) => eprintln!("Error piping output: {}", e),
+                        }
+                    }
+                }
+                "signal-channel" => {
+                    // Wake the scripts waiting on a channel (matrix ctl wait-for)
+                    match parts.get(1) {
+                        Some(channel) => self.signal_channel(channel),
+                        None => eprintln!("Usage: signal-channel <channel>"),
+                    }
+                }
+                "capture" => {
+                    // Save the last lines of the current window to a file
+                    match (parts.get(1), parts.get(2).map(|n| n.parse::<usize>())) {
+                        (Some(path), None | Some(Ok(_))) => {
+                            let count = parts.get(2).and_then(|n| n.parse().ok());
+                            if let Err(e) = self.capture_to_file(path, count) {
+                                eprintln!("Error capturing window: {}", e);
+                            }
+                        }
+                        _ => eprintln!("Usage: capture <file> [lines]"),
+                    }
+                }
+                "screenshot" => {
+                    // Save the next frame as a PNG (default: matrix-<time>.png here)
+                    let path = parts.get(1).map(|path| path.to_string()).unwrap_or_else(|| {
+                        let secs = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map_or(0, |d| d.as_secs());
+                        format!("matrix-{}.png", secs)
+                    });
+                    self.pending_screenshot = Some(path);
+                }
+                "hints" => {
+                    // Label the matches in the current window to copy, paste or open one
+                    match parts.get(1).map_or(Some(HintAction::Copy), |name| HintAction::parse(name)) {
+                        Some(action) => self.enter_hint_mode(action),
+                        None => eprintln!("Usage: hints [copy|paste|open]"),
+                    }
+                }
+                "preferences" | "prefs" => {
+                    // Edit settings in an overlay
+                    self.preferences = Some(Preferences::default());
+                    self.state = AppState::Preferences;
+                }
+                "links" => {
+                    // Pick a file location shown in the current window to open it
+                    self.pick_link();
+                }
+                "mute" => {
+                    // Silence the bell for the current window, or every window
+                    if parts.get(1) == Some(&"all") {
+                        self.settings.bell.muted = !self.settings.bell.muted;
+                        let state = if self.settings.bell.muted { "Bell muted" } else { "Bell unmuted" };
+                        self.announcer.announce_ui(state);
+                    } else if let Some(window) = self.window_manager.focused_window_mut() {
+                        window.bell_muted = !window.bell_muted;
+                        let state = if window.bell_muted { "Window bell muted" } else { "Window bell unmuted" };
+                        self.announcer.announce_ui(state);
+                    }
+                }
+                "title" => {
+                    // Name the current window, or with no name let it follow its foreground program
+                    if let Some(window) = self.window_manager.focused_window_mut() {
+                        if parts.len() > 1 {
+                            window.title = parts[1..].join(" ");
+                            window.auto_title = false;
+                        } else {
+                            window.auto_title = true;
+                            window.refresh_title();
+                        }
+                    }
                 }
-            },
-            SidebarIcon::Help => {
-                self.display_help();
-            },
-        }
-    }
-    
-    fn execute_command(&mut self) {
-        // Add the command to history
-        if !self.command_buffer.is_empty() {
-            self.command_history.push(self.command_buffer.clone());
-        }
-        
-        // Clone the command buffer
-        let command = self.command_buffer.clone();
-        
-        // Parse and execute the command
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        
-        if let Some(cmd) = parts.first() {
-            match *cmd {
-                "q" | "quit" => {
-                    // Quit the application
-                    self.running = false;
+                "paste" => {
+                    // Choose one of the recent copies to paste into the current window
+                    self.pick_clipboard_entry("Paste", PickerTarget::Paste);
                 }
-                "new" => {
-                    // Create a new window
-                    let title = if parts.len() > 1 {
-                        parts[1]
-                    } else {
-                        "New Terminal"
-                    };
-                    
-                    if let Err(e) = self.create_window(title) {
-                        // Handle error
-                        eprintln!("Error creating window: {}", e);
+                "paste-buffer" => {
+                    // Choose one of the recent copies as the clipboard contents
+                    self.pick_clipboard_entry("Paste buffers", PickerTarget::ClipboardEntry);
+                }
+                "copy-mode" => {
+                    // Select and copy text from the current window's scrollback
+                    self.enter_copy_mode();
+                }
+                "windows" => {
+                    // Pick a window from a list showing how long each has been idle
+                    self.pick_window();
+                }
+                "lock" => {
+                    // Stop or resume passing keystrokes to the current window's process
+                    if let Some(window) = self.window_manager.focused_window_mut() {
+                        window.read_only = !window.read_only;
+                        let state = if window.read_only { "Window locked read-only" } else { "Window unlocked" };
+                        self.announcer.announce_ui(state);
                     }
                 }
-                "split" => {
-                    // Split the current window
-                    let direction = if parts.len() > 1 && parts[1] == "h" {
-                        SplitDirection::Horizontal
-                    } else {
-                        SplitDirection::Vertical
-                    };
-                    
-                    if let Err(e) = self.split_window(direction) {
-                        // Handle error
-                        eprintln!("Error splitting window: {}", e);
+                "group" => {
+                    // Put the current window in a group, or take it out of its group
+                    let name = parts.get(1).map(|n| n.to_string());
+                    if let Some(window) = self.window_manager.focused_window_mut() {
+                        window.group = name.clone();
+                    }
+                    match name {
+                        Some(name) => self.announcer.announce_ui(&format!("Window in group {}", name)),
+                        None => self.announcer.announce_ui("Window not in a group"),
                     }
                 }
-                "layout" => {
-                    // Apply a layout
-                    if parts.len() > 1 {
-                        let layout_type = parts[1];
-                        let window_ids: Vec<Uuid> = self.window_manager.windows().keys().cloned().collect();
-                        
-                        if window_ids.is_empty() {
-                            eprintln!("No windows to arrange");
+                "broadcast" => {
+                    // Send typed input to every window, to a group, or only the focused window
+                    let target = match (parts.get(1), parts.get(2)) {
+                        (None, _) if self.broadcast.is_some() => None,
+                        (None, _) | (Some(&"all"), _) => Some(BroadcastTarget::All),
+                        (Some(&"group"), Some(name)) => Some(BroadcastTarget::Group(name.to_string())),
+                        (Some(&"group"), None) => {
+                            // The current window's group
+                            match self.window_manager.focused_window().and_then(|w| w.group.clone()) {
+                                Some(name) => Some(BroadcastTarget::Group(name)),
+                                None => {
+                                    eprintln!("The current window is not in a group");
+                                    return;
+                                }
+                            }
+                        }
+                        (Some(&"off"), _) => None,
+                        _ => {
+                            eprintln!("Usage: broadcast [all | group [name] | off]");
                             return;
                         }
-                        
-                        let result = match layout_type {
-                            "grid" => {
-                                self.window_manager.apply_grid_layout(&window_ids)
-                            },
-                            "horizontal" | "h" => {
-                                self.window_manager.apply_horizontal_layout(&window_ids)
-                            },
-                            "vertical" | "v" => {
-                                self.window_manager.apply_vertical_layout(&window_ids)
-                            },
-                            "main" | "m" => {
-                                if let Some(main_id) = self.window_manager.focused_window().map(|w| w.id()) {
-                                    let stack_ids: Vec<Uuid> = window_ids.into_iter()
-                                        .filter(|&id| id != main_id)
-                                        .collect();
-                                    self.window_manager.apply_main_and_stack_layout(main_id, &stack_ids)
-                                } else {
-                                    Ok(()) // No focused window
-                                }
-                            },
-                            _ => {
-                                eprintln!("Unknown layout: {}", layout_type);
-                                Ok(())
+                    };
+                    
+                    match &target {
+                        Some(BroadcastTarget::All) => self.announcer.announce_ui("Broadcasting to all windows"),
+                        Some(BroadcastTarget::Group(name)) => self.announcer.announce_ui(&format!("Broadcasting to group {}", name)),
+                        None => self.announcer.announce_ui("Broadcast off"),
+                    }
+                    self.broadcast = target;
+                }
+                "bookmark" => {
+                    // Bookmark a directory (the current window's, if none is given), or remove one
+                    let current = self.window_manager.focused_window().and_then(|w| w.current_dir());
+                    let path = parts.get(2).map(|p| p.to_string()).or(current);
+                    match (parts.get(1), path) {
+                        (Some(&"add"), Some(path)) => {
+                            if !self.settings.bookmarks.contains(&path) {
+                                self.settings.bookmarks.push(path.clone());
+                                self.save_bookmarks();
                             }
-                        };
-                        
-                        if let Err(e) = result {
-                            eprintln!("Error applying layout: {}", e);
+                            self.announcer.announce_ui(&format!("Bookmarked {}", path));
                         }
-                    } else {
-                        eprintln!("Usage: layout [grid|horizontal|vertical|main]");
+                        (Some(&"remove"), Some(path)) => {
+                            self.settings.bookmarks.retain(|dir| *dir != path);
+                            self.save_bookmarks();
+                            self.announcer.announce_ui(&format!("Removed bookmark {}", path));
+                        }
+                        (Some(&"add" | &"remove"), None) => eprintln!("The current window's directory is not known"),
+                        _ => eprintln!("Usage: bookmark add|remove [path]"),
                     }
                 }
-                "zoom" => {
-                    // Zoom the current window
-                    if let Some(id) = self.window_manager.focused_window().map(|w| w.id()) {
-                        if let Err(e) = self.window_manager.zoom_window(Some(id)) {
-                            eprintln!("Error zooming window: {}", e);
+                "bookmarks" => {
+                    // Pick a directory to change to, or to open a window in
+                    self.pick_directory(parts.get(1) == Some(&"new"));
+                }
+                "lock-screen" => {
+                    // Hide everything until the password is typed
+                    self.lock();
+                }
+                "mode" => {
+                    // Switch to an input mode
+                    match parts.get(1).and_then(|name| Mode::from_name(name)) {
+                        Some(mode) => self.enter_mode(mode),
+                        None => {
+                            let names: Vec<&str> = Mode::ALL.iter().map(|mode| mode.name()).collect();
+                            eprintln!("Usage: mode <{}>", names.join("|"));
                         }
-                    } else {
-                        eprintln!("No window to zoom");
                     }
                 }
-                "close" => {
-                    // Close the current window
-                    if let Err(e) = self.close_current_window() {
-                        // Handle error
-                        eprintln!("Error closing window: {}", e);
+                "quick-open" => {
+                    // Directories, commands and windows ranked by frecency
+                    self.quick_open();
+                }
+                "history" => {
+                    // Search commands run at prompts in every window
+                    self.pick_history();
+                }
+                "snippets" | "snippet" => match parts.get(1) {
+                    // Insert a snippet by name, or pick one
+                    Some(name) => self.insert_snippet(name),
+                    None => self.pick_snippet(),
+                },
+                "record" => {
+                    // Start or stop recording typed input as a macro
+                    self.toggle_recording(parts.get(1).copied().unwrap_or(DEFAULT_MACRO));
+                }
+                "play" => {
+                    // Type a macro into the current window (and windows it broadcasts to)
+                    let name = parts.get(1).map(|n| n.to_string()).or_else(|| self.last_macro.clone());
+                    let count = parts.get(2).and_then(|n| n.parse().ok()).unwrap_or(1);
+                    match name {
+                        Some(name) => self.play_macro(&name, count),
+                        None => eprintln!("Usage: play <name> [count]"),
                     }
                 }
-                "help" => {
-                    // Display help information
-                    self.display_help();
+                "macros" => match (parts.get(1), parts.get(2)) {
+                    (None, _) => self.pick_macro(),
+                    (Some(&"delete"), Some(name)) => {
+                        if self.macros.remove(name) {
+                            self.save_macros();
+                            self.announcer.announce_ui(&format!("Deleted macro {}", name));
+                        }
+                    }
+                    _ => eprintln!("Usage: macros [delete <name>]"),
+                },
+                "nohl" => {
+                    // Clear search highlights
+                    for window in self.window_manager.windows_mut().values_mut() {
+                        window.highlight = None;
+                        window.buffer.clear_marks(MarkKind::Search);
+                    }
                 }
-                "sidebar" => {
-                    // Toggle sidebar
-                    self.sidebar.toggle();
+                "stats" => {
+                    // Show scrollback memory use for every window
+                    self.display_stats();
+                }
+                "speak" => {
+                    // Read out the most recent lines of the focused window
+                    let count = parts.get(1).and_then(|n| n.parse().ok()).unwrap_or(5);
+                    if !self.announcer.is_enabled() {
+                        eprintln!("No speech command configured");
+                    } else if let Some(window) = self.window_manager.focused_window() {
+                        let text = window.buffer.recent_lines(count).join("\n");
+                        self.announcer.speak(&text);
+                    }
                 }
                 // Add more commands as needed
                 _ => {
@@ -656,11 +3777,369 @@ impl App {
         }
     }
     
+    // Save the current session under a name
+    fn save_session(&mut self, name: &str) {
+        let session = Session::capture(&self.window_manager, self.settings.session.max_scrollback_bytes, &self.redactor);
+        if let Err(e) = session.save(name) {
+            eprintln!("Error saving session: {}", e);
+        }
+    }
+    
+    // The workspace Matrix was started in: the directory bound to a template, or else
+    // the directory itself
+    fn workspace_dir(&self) -> std::path::PathBuf {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        workspace::template_for(&self.settings.workspaces, &cwd).map_or(cwd, |(_, base)| base)
+    }
+    
+    // Save the current windows as a workspace template, directories relative to the workspace
+    fn save_template(&mut self, name: &str) -> Result<()> {
+        WorkspaceTemplate::capture(&self.window_manager, &self.workspace_dir()).save(name)?;
+        self.announcer.announce_ui(&format!("Saved workspace template {}", name));
+        Ok(())
+    }
+    
+    // Replace the current windows with those of a workspace template, shells starting
+    // in its directories (relative to `base`) and running its commands
+    fn apply_template(&mut self, name: &str, base: &std::path::Path) -> Result<()> {
+        let template = WorkspaceTemplate::load(name)?;
+        
+        let old_ids: Vec<Uuid> = self.window_manager.windows().keys().cloned().collect();
+        for id in old_ids {
+            self.window_manager.close_window(id)?;
+        }
+        
+        let mut ids = Vec::new();
+        for saved in &template.windows {
+            let id = self.window_manager.create_window(&saved.title)?;
+            let dir = saved.dir.as_deref().map(|dir| workspace::resolve(dir, base).to_string_lossy().into_owned());
+            self.start_command(id, None, None, Some(dir.as_deref().unwrap_or(&base.to_string_lossy())))?;
+            if let Some(window) = self.window_manager.windows_mut().get_mut(&id) {
+                window.group = saved.group.clone();
+                window.auto_title = false;
+                // Typed into the shell at its first prompt, after the init commands
+                let delay = Duration::from_millis(self.settings.general.init_delay_ms);
+                window.queue_init_commands(&saved.init_commands, delay);
+                if let Some(command) = &saved.command {
+                    window.queue_init_commands(std::slice::from_ref(command), delay);
+                    window.command = Some(command.clone());
+                }
+            }
+            ids.push(id);
+        }
+        
+        if ids.is_empty() {
+            return self.create_window("Matrix Terminal");
+        }
+        
+        match template.layout.as_ref().and_then(|l| l.to_layout(&ids, Rect::default())) {
+            Some(layout) => self.window_manager.set_layout(layout)?,
+            None => self.window_manager.apply_grid_layout(&ids)?,
+        }
+        if let Some(&id) = template.focused.and_then(|i| ids.get(i)) {
+            self.window_manager.focus_window(id)?;
+        }
+        
+        Ok(())
+    }
+    
+    // Start the guided tour from its first step
+    fn start_tutorial(&mut self) {
+        self.tutorial = Some(Tutorial::new(self.tutorial_progress()));
+        self.announce_tutorial();
+    }
+    
+    // The state of the app the tutorial's steps look at
+    fn tutorial_progress(&self) -> Progress {
+        Progress {
+            windows: self.window_manager.windows().len(),
+            focused: self.window_manager.focused_window().map(|w| w.id()),
+            copy_mode: matches!(self.state, AppState::Copy),
+            clipboard: self.clipboard.get().map(str::to_string),
+        }
+    }
+    
+    // Say what the tutorial's current step asks for
+    fn announce_tutorial(&mut self) {
+        if let Some(tutorial) = &self.tutorial {
+            self.announcer.announce_ui(&tutorial.instructions());
+        }
+    }
+    
+    // Bring back the session saved when Matrix last ran, asking first unless the
+    // settings say to always (or never) restore it
+    fn offer_last_session(&mut self) {
+        let restore = self.settings.session.restore;
+        if restore == RestoreMode::Never {
+            return;
+        }
+        let Ok(session) = Session::load(AUTOSAVE_SESSION) else { return };
+        if session.windows.is_empty() {
+            return;
+        }
+        
+        if restore == RestoreMode::Always {
+            if let Err(e) = self.restore_windows(session, self.settings.session.rerun_commands) {
+                eprintln!("Error restoring session: {}", e);
+            }
+        } else {
+            self.restore_offer = Some(session);
+            self.state = AppState::RestorePrompt;
+        }
+    }
+    
+    // Replace the current windows with a saved session, restoring layout, directories
+    // and scrollback, and if asked, running each window's last command again
+    fn restore_windows(&mut self, session: Session, rerun: bool) -> Result<()> {
+        // Close the current windows
+        let old_ids: Vec<Uuid> = self.window_manager.windows().keys().cloned().collect();
+        for id in old_ids {
+            self.window_manager.close_window(id)?;
+        }
+        
+        // Recreate the windows with their output and a fresh shell
+        let mut ids = Vec::new();
+        for saved in &session.windows {
+            let id = self.window_manager.create_window(&saved.title)?;
+            if let Some(window) = self.window_manager.windows_mut().get_mut(&id) {
+                window.group = saved.group.clone();
+                window.auto_title = !saved.manual_title;
+                match saved.scrollback() {
+                    Ok(text) if !text.is_empty() => {
+                        window.buffer.write(text.as_bytes())?;
+                        window.buffer.write(b"\n")?;
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Error restoring scrollback of {}: {}", saved.title, e),
+                }
+            }
+            
+            // In its old directory, if that's still there
+            let dir = saved.dir.as_deref().filter(|dir| std::path::Path::new(dir).is_dir());
+            self.start_command(id, None, None, dir)?;
+            if let (true, Some(command), Some(window)) = (rerun, &saved.command, self.window_manager.windows_mut().get_mut(&id)) {
+                window.send_input(format!("{}\r", command).as_bytes())?;
+            }
+            ids.push(id);
+        }
+        
+        if ids.is_empty() {
+            return self.create_window("Matrix Terminal");
+        }
+        
+        // Rebuild the layout around the new windows
+        match session.layout.as_ref().and_then(|l| l.to_layout(&ids, Rect::default())) {
+            Some(layout) => self.window_manager.set_layout(layout)?,
+            None => self.window_manager.apply_grid_layout(&ids)?,
+        }
+        if let Some(&id) = session.focused.and_then(|i| ids.get(i)) {
+            self.window_manager.focus_window(id)?;
+        }
+        
+        Ok(())
+    }
+    
     fn update_on_tick(&mut self) {
-        // Update state on tick
-        // Nothing to do yet
+        // Hide old notifications and clear the mark of the window being looked at
+        self.notifications.expire();
+        
+        // Lock the screen once the user has been away long enough, and unlock it once
+        // the password checks out
+        let idle_minutes = self.settings.lock.idle_minutes;
+        if idle_minutes > 0 && self.last_user_input.elapsed() >= Duration::from_secs(idle_minutes * 60) {
+            self.lock();
+        }
+        if self.lock_screen.as_mut().is_some_and(|(lock_screen, _)| lock_screen.poll()) {
+            if let Some((_, resume)) = self.lock_screen.take() {
+                self.state = resume;
+            }
+            self.last_user_input = Instant::now();
+            self.announcer.announce_ui("Unlocked");
+        }
+        if let Some(window) = self.window_manager.focused_window_mut() {
+            window.marked = false;
+        }
+        
+        // Switch theme along with the system's light or dark mode
+        if let Some(appearance) = self.appearance.as_ref().and_then(|watcher| watcher.poll()) {
+            let name = match appearance {
+                Appearance::Light => self.settings.ui.light_theme.clone(),
+                Appearance::Dark => self.settings.ui.dark_theme.clone(),
+            };
+            if let Err(e) = self.apply_theme(&name) {
+                eprintln!("Error loading theme: {:#}", e);
+            }
+        }
+        
+        // Show the theme again when its file is saved, for whoever is writing it
+        if self.theme_watcher.poll().contains(&self.theme_name) {
+            let name = self.theme_name.clone();
+            let preview = self.theme_preview;
+            match self.apply_theme(&name) {
+                Ok(()) => {
+                    self.theme_preview = preview;
+                    self.announcer.announce_ui(&format!("Theme {} reloaded", name));
+                }
+                Err(e) => eprintln!("Error reloading theme: {:#}", e),
+            }
+            self.redraw = true;
+        }
+        
+        // Move the tutorial on once its step is done, and close it a while after the last
+        if self.tutorial.is_some() {
+            let progress = self.tutorial_progress();
+            if self.tutorial.as_mut().is_some_and(|tutorial| tutorial.observe(&progress)) {
+                self.announce_tutorial();
+                self.redraw = true;
+            }
+            if self.tutorial.as_ref().is_some_and(Tutorial::is_over) {
+                self.tutorial = None;
+                self.redraw = true;
+            }
+        }
+        
+        // Go back to the chosen theme once a preview is over
+        if self.theme_preview.is_some_and(|since| since.elapsed() >= THEME_PREVIEW_TIME) {
+            let name = self.settings.ui.theme.clone();
+            match self.apply_theme(&name) {
+                Ok(()) => self.announcer.announce_ui(&format!("Preview over, back to theme {}", name)),
+                Err(e) => eprintln!("Error loading theme: {:#}", e),
+            }
+            self.redraw = true;
+        }
+        
+        // Rerun the status bar scripts that are due, in the focused window's directory
+        if self.settings.ui.status_bar {
+            let dir = self.window_manager.focused_window().and_then(|w| w.current_dir());
+            if self.status_scripts.refresh(dir.as_deref()) {
+                self.redraw = true;
+            }
+        }
+        
+        // Ask git about the windows' directories again after each prompt or cd
+        if self.settings.git.status || self.settings.git.dirty_border.is_some() {
+            for window in self.window_manager.windows_mut().values_mut() {
+                let prompted = window.buffer.take_prompts() > 0;
+                let dir = window.current_dir();
+                if window.git.refresh(dir, prompted) {
+                    self.redraw = true;
+                }
+            }
+        }
+        
+        // Record the windows' screens for the timeline
+        for window in self.window_manager.windows_mut().values_mut() {
+            window.record_timeline();
+        }
+        
+        // Save the session periodically so it survives a crash
+        let interval = Duration::from_secs(self.settings.session.autosave_interval_secs);
+        let autosave = self.settings.session.autosave && self.restore_offer.is_none() && !self.safe_mode;
+        if autosave && self.last_autosave.elapsed() >= interval {
+            self.last_autosave = Instant::now();
+            self.save_session(AUTOSAVE_SESSION);
+        }
+        
+        // Follow the programs started and left in each window, and the directories visited
+        if self.last_title_poll.elapsed() >= TITLE_POLL_INTERVAL {
+            self.last_title_poll = Instant::now();
+            if self.settings.ui.auto_title {
+                for window in self.window_manager.windows_mut().values_mut() {
+                    window.refresh_title();
+                }
+            }
+            
+            if let Some(dir) = self.window_manager.focused_window().and_then(|w| w.current_dir()) {
+                if self.recent_dirs.front() != Some(&dir) {
+                    self.frecency.visit(frecency::Kind::Directory, &dir);
+                    if let Err(e) = self.frecency.save() {
+                        eprintln!("Error saving quick-open ranking: {}", e);
+                    }
+                    self.recent_dirs.retain(|d| *d != dir);
+                    self.recent_dirs.push_front(dir);
+                    self.recent_dirs.truncate(MAX_RECENT_DIRS);
+                }
+            }
+        }
+        
+        // Refresh the metrics served on the endpoint
+        if let Some(server) = &self.metrics_server {
+            server.update(self.metrics.to_prometheus(self.window_manager.windows().values()));
+        }
+        
+        // Keep screen reader output in sync with the focused window
+        if self.announcer.is_enabled() {
+            if let Some(window) = self.window_manager.focused_window() {
+                self.announcer.focus_changed(window.id(), &window.title, &window.buffer);
+                self.announcer.announce_new_output(window.id(), &window.buffer);
+            }
+        }
     }
 
+    // Show scrollback statistics for all windows
+    fn display_stats(&mut self) {
+        let mut text = String::from("Scrollback statistics\n\n");
+        let mut windows: Vec<_> = self.window_manager.windows().values().collect();
+        windows.sort_by(|a, b| a.title.cmp(&b.title));
+        
+        for window in windows {
+            let stats = window.buffer.scrollback_stats();
+            let ratio = if stats.compressed_bytes > 0 {
+                stats.raw_bytes as f64 / stats.compressed_bytes as f64
+            } else {
+                1.0
+            };
+            text.push_str(&format!(
+                "{}\n  lines: {} ({} uncompressed)\n  compressed: {} chunks, {} KiB -> {} KiB ({:.1}x)\n\n",
+                window.title,
+                stats.total_lines,
+                stats.uncompressed_lines,
+                stats.compressed_chunks,
+                stats.raw_bytes / 1024,
+                stats.compressed_bytes / 1024,
+                ratio,
+            ));
+        }
+        
+        // Output read and drawing performance
+        let metrics = &self.metrics;
+        text.push_str(&format!(
+            "Performance
+  windows: {}
+  bytes read: {} KiB
+  frames: {} ({} over the {} ms budget)
+  frame time: {:.1} ms last, {:.1} ms average, {:.1} ms max
+",
+            self.window_manager.windows().len(),
+            self.window_manager.windows().values().map(|w| w.bytes_read).sum::<u64>() / 1024,
+            metrics.frames,
+            metrics.dropped_frames,
+            self.settings.general.tick_rate_ms,
+            metrics.last_frame.as_secs_f64() * 1000.0,
+            metrics.average_frame().as_secs_f64() * 1000.0,
+            metrics.max_frame.as_secs_f64() * 1000.0,
+        ));
+        if let Some(server) = &self.metrics_server {
+            text.push_str(&format!("  endpoint: http://127.0.0.1:{}/metrics
+", server.port()));
+        }
+        
+        self.show_text_window("Stats", &text);
+    }
+    
+    // Open a window showing a piece of text, like the help window
+    fn show_text_window(&mut self, title: &str, text: &str) {
+        if let Ok(window_id) = self.window_manager.create_window(title) {
+            if let Some(window) = self.window_manager.windows_mut().get_mut(&window_id) {
+                if let Err(e) = window.buffer.write(text.as_bytes()) {
+                    eprintln!("Error displaying {}: {}", title, e);
+                }
+                
+                self.window_manager.focus_window(window_id).ok();
+            }
+        }
+    }
+    
     fn display_help(&mut self) {
         // Create a help window with information about commands and features
         let help_text = "
@@ -673,12 +4152,16 @@ WINDOW MANAGEMENT:
   Ctrl+H        Split the current window horizontally
   Ctrl+V        Split the current window vertically
   Ctrl+W        Close the current window
+  (splits show a preview: arrows pick the side, Enter splits, Esc cancels)
 
 NAVIGATION:
   Ctrl+Tab            Move to the next window
   Ctrl+Shift+Tab      Move to the previous window
   Ctrl+Up/Down/Left/Right  Navigate between windows in the specified direction
   Ctrl+Z              Toggle zoom on the current window
+  Ctrl+Alt+Arrows     Grow the current window in that direction
+  Shift+PgUp/PgDn     Scroll the current window's history
+  P                   Back to the newest output, when scrolled back
 
 LAYOUTS:
   Ctrl+G              Apply grid layout to all windows
@@ -688,20 +4171,137 @@ LAYOUTS:
 
 INTERFACE:
   Ctrl+B              Toggle sidebar
+  :theme [name]       Switch theme (default, high-contrast, light, or a file in themes/,
+                      shown again whenever the file is saved)
+  :theme preview <name>  Show a theme for 10 seconds, then go back unless :theme keep
+  :theme edit         Change the theme's colors and save them as a theme file
+                      (settings: ui.follow_system_appearance switches between
+                      ui.light_theme and ui.dark_theme with the system's light or dark mode)
+  :speak [n]          Read the last n lines of the current window aloud
+  y / n               Allow or deny a program setting the clipboard (OSC 52)
 
 COMMAND MODE:
   :                   Enter command mode
   :new [title]        Create a new window with optional title
+  :new --profile <name> [title] Create a window with a profile's limits and sandbox (settings: profiles)
+  :new [title] -- <command> Create a window running a command instead of a shell
+  :new --supervise [title] -- <command> Same, restarting the command when it fails (waiting longer each time)
   :split [h]          Split window (vertically by default, horizontally with 'h')
   :close              Close the current window
   :layout [type]      Apply layout (grid, horizontal, vertical, main)
+                      Ratios you adjust are remembered per layout
+  :layout undo [n]    Undo the last n layout changes (split, close, zoom, preset)
+  :layout redo [n]    Redo the last n undone layout changes
+  :resize [dir] [n]   Move the current window's border by n percent
+  Alt+= / :resize     Resize mode: arrows move the current window's border 2%, Shift+arrows
+                      10%, stopping at 25/33/50/66/75%; Esc or Enter leaves
+  :move [dir]         Move the current window to the far side of its neighbour (up, down, left, right)
   :sidebar            Toggle sidebar
+  :panel <name>       Toggle a sidebar panel: windows, search (the last :search's results),
+                      bookmarks or ssh (hosts in ~/.ssh/config); click an entry to go to it,
+                      drag a panel's border to resize it (settings: ui.panels, ui.panel_width)
+  :panel width <n>    Set the panels' width in columns
+  :panel              Say which panels are open
+  :session save [n]   Save layout and scrollback as session n (default: last)
+  :session restore [n] Restore a saved session (last is saved automatically, and offered on
+                      start; settings: session.restore)
+  :workspace template save <n>   Save the windows, their directories and commands as template n
+  :workspace template apply <n>  Replace the windows with template n's (settings: workspaces
+                      binds directories to templates applied when Matrix starts there)
+  :tmux [host]        Attach to tmux (-CC) locally or over ssh, panes open as windows
+  :tmux detach        Detach from tmux, leaving its session running
   :help               Show this help information
+  :cheatsheet         Show every key in effect, built in or bound in the settings, by mode;
+                      type to search it
+  :cheatsheet export <file> Save the keys in effect as Markdown
+  :tutorial           Take a guided tour of splits, navigation, copy mode and commands,
+                      each step moving on once done (also: matrix tutorial)
+  :tutorial skip|quit Skip the current step, or leave the tour
+  :preferences        Edit settings (saved to settings.json and applied right away)
+  :stats              Show scrollback size and compression for each window, bytes read
+                      and frame times (settings: metrics.endpoint serves them for Prometheus)
+  :search <regex>     Search the current window, pick a match to jump to it
+  :search-all <regex> Search every window and highlight all matches
+  :nohl               Clear search highlights
+  :filter <regex>     Show only the current window's lines matching a pattern
+  :filter clear       Show all lines again
+  :raw                Toggle showing files shown with cat in the current window as raw text
+                      rather than colored by syntax (settings: ui.highlight_files)
+  :view [file]        Read a file, or the current window's history, in the built-in pager:
+                      line numbers, / to search, n/N between matches, files colored by syntax
+  :diff <window>      Compare the current window's lines with another's (by number in the
+                      layout or title), side by side or unified (Tab switches)
+  :timeline           Scrub through the current window's screen as recorded every few seconds:
+                      Left/Right step, Home/End jump to the oldest/newest, Esc leaves
+                      (settings: scrollback.timeline_interval_secs, timeline_snapshots)
+  :capture <file> [lines] Save the current window's last lines (default: a screenful), as JSON with styles for a .json file
+  :signal-channel <channel> Let scripts waiting on a channel (matrix ctl wait-for) carry on
+  :pipe <command>     Copy the current window's output from now on to a command's input
+  :pipe stop          Stop copying it
+  :export html <file> Save the current window's scrollback, with colors, as HTML
+                      (secrets are masked; settings: redaction.patterns)
+  :screenshot [file]  Save the screen as a PNG (default: matrix-<time>.png)
+  :links              Pick a file:line shown in the current window to open in the editor
+                      (or click an underlined file:line)
+  :mute               Mute or unmute the bell for the current window
+  :mute all           Mute or unmute the bell for every window
+  :lock               Make the current window read-only, or writable again
+  Alt+L / :lock-screen Hide every window until your password is typed
+                      (settings: lock.idle_minutes locks automatically)
+  :windows            List windows by idle time and focus the one picked
+                      (settings: ui.focus_follows_mouse focuses the window under the mouse)
+  :title [name]       Name the current window (no name: title it after its program)
+  :group [name]       Put the current window in a group (no name: leave its group)
+  :broadcast [all]    Send typed input to every window (no argument: toggle)
+  :broadcast group [name] Send typed input to a group's windows (default: current window's)
+  :broadcast off      Send typed input only to the current window
+  Alt+R / :record [name] Record typed input as a macro (default q); again to stop and save it
+  Alt+P / :play [name] [n] Type a macro into the current window n times (default: the last one)
+  :macros             Pick a saved macro to play (saved to macros.json)
+  :macros delete <name> Forget a macro
+  :bookmark add|remove [path] Bookmark a directory (default: the current window's)
+  Alt+B / :bookmarks  Pick a bookmarked or recent directory and cd to it in the current window
+  :bookmarks new      Pick a directory and open a new window there
+  Alt+H / :history    Search commands run at prompts (this window's first) and type the one picked
+                      (settings: history; needs shell integration, or history.import_shell)
+  <sequence>          Run the command a key sequence is bound to (settings: keyboard.bindings,
+                      e.g. Ctrl+A w v to split); after the first keys, the keys that can
+                      follow are listed until one is typed or keyboard.chord_timeout_ms passes
+  :mode <name>        Switch input mode: normal, copy, resize, search (type a pattern, Enter
+                      lists the matches) or command; each mode has its own bindings
+                      (settings: keyboard.modes, e.g. resize: Left to resize left 5); Esc leaves
+  Ctrl+Shift+O / :quick-open Recent directories (cd), commands (run again) and windows (focus),
+                      most frequently and recently used first
+  Alt+S / :snippets [name] Insert a snippet (settings: snippets); {{name}} or {{name:default}}
+                      placeholders are asked for first
   :quit               Exit the application
   q                   Exit the application (when in command mode)
 
+COPY MODE:
+  Alt+C / :copy-mode  Move a cursor over the current window's scrollback
+  Arrows, hjkl        Move the cursor (PageUp/PageDown, 0/$ line start/end, g/G top/bottom)
+  v or Space          Start or clear a selection
+  Ctrl+V              Toggle a rectangular (block) selection
+  y or Enter          Copy the selection and leave copy mode
+  a                   Add the selection to the end of the clipboard and leave copy mode
+  :paste-buffer       Choose one of the recent copies as the clipboard contents
+  Alt+V / :paste      Choose one of the recent copies to paste into the current window
+  Esc or q            Leave copy mode
+
+HINT MODE:
+  Alt+E / :hints      Label the URLs, paths, hashes... in the current window and copy the
+                      one whose label is typed (:hints paste or :hints open to start with
+                      that action; a rule's open command in selection_rules opens it)
+  Tab                 Switch between copy, paste and open
+  Esc                 Leave hint mode
+
 TERMINAL:
   Ctrl+C              Send SIGINT to the current process
+  Ctrl+Q              Resume output paused because it came too fast (settings: general.flow_control)
+  Double-click        Copy the URL, path, IP, hash or UUID clicked, or else the word
+                      (settings: selection_rules add patterns, e.g. ticket IDs)
+  Mouse wheel         Scroll the window under the mouse (settings: mouse); in full-screen
+                      programs it sends arrow keys
   Other keys          Passed to the terminal process
 
 FEATURES:
@@ -709,6 +4309,24 @@ FEATURES:
   • Multiple navigation methods (sequential, directional, etc.)
   • Customizable layout presets (grid, horizontal, vertical, main+stack)
   • Window zooming for focused work
+  • Scrollbar on each window with search, prompt and trigger marks; click it to jump
+  • Shell integration (OSC 133): finished commands get a ✓/✗ badge with their duration
+  • Visual bell and optional sound (settings: bell); a bell in another window marks it (●)
+  • Status bar with the current window, broadcast, mute and read-only indicators, and
+    segments showing what scripts print (settings: status_scripts)
+  • Git branch, changes and ahead/behind counts of the current window's repository in the
+    status bar, updated at each prompt (settings: git; dirty_border tints changed repos)
+  • Remote control with matrix ctl over a local socket, and over a localhost WebSocket with a
+    token (settings: remote; needs the remote feature)
+  • Broadcast input to all windows or a group; receiving windows get a red border (⇉)
+  • Keystroke macros, recorded once and replayed into one window or broadcast
+  • Snippets with placeholders for long commands you type often
+  • Command history searched across windows and sessions, and optionally the shell's own
+  • Directory bookmarks, and the directories you work in remembered for quick cd
+  • Lock screen, by hand or when idle, unlocked with your password
+  • Secrets (keys, tokens, passwords) masked in exports, sessions and history, and
+    optionally on screen once they scroll off the prompt (settings: redaction)
+  • Output triggers (settings: triggers) highlight lines, notify, mark windows (●) or run hooks
   • Intelligent window closing that preserves layout structure
   • Matrix-style sidebar with hover tooltips for easy access to functionality
   • Command execution in terminals
@@ -733,10 +4351,82 @@ For more information, visit the project repository.
     }
 }
 
-pub fn run() -> Result<()> {
+// Windows in the layout that are at least partly inside the screen
+fn visible_windows(window_manager: &WindowManager, screen: Rect) -> Vec<Uuid> {
+    let windows = window_manager.windows();
+    window_manager.layout()
+        .map(|layout| layout.window_ids())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|id| windows.get(id).is_some_and(|w| !w.size().intersection(screen).is_empty()))
+        .collect()
+}
+
+// Where the sidebar's panels go: between its icons and the windows
+fn panel_dock(sidebar: &Sidebar, panels: &Panels<PickerTarget>, main_area: Rect) -> Rect {
+    Rect::new(sidebar.width(), main_area.y, panels.width(), main_area.height)
+}
+
+// The hosts in ~/.ssh/config, for the SSH hosts panel
+fn ssh_host_items() -> Vec<(PickerItem, PickerTarget)> {
+    let Ok(home) = std::env::var("HOME") else { return Vec::new() };
+    let config = std::fs::read_to_string(std::path::Path::new(&home).join(".ssh/config")).unwrap_or_default();
+    panels::ssh_hosts(&config).into_iter()
+        .map(|host| (PickerItem::new(host.clone(), ""), PickerTarget::Ssh(host)))
+        .collect()
+}
+
+// Shortest time between frames
+fn frame_interval(settings: &Settings) -> Duration {
+    Duration::from_secs(1) / settings.general.max_fps.max(1)
+}
+
+// A duration in its largest units, e.g. "42s", "5m", "2h 10m"
+fn idle_text(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h {}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+// Translate a crossterm key event into the terminal key encoder's types
+fn terminal_key(key: &KeyEvent) -> Option<(Key, keyboard::KeyModifiers)> {
+    let code = match key.code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::BackTab => Key::BackTab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::F(n) => Key::F(n),
+        _ => return None,
+    };
+    
+    let mods = keyboard::KeyModifiers {
+        shift: key.modifiers.contains(KeyModifiers::SHIFT),
+        alt: key.modifiers.contains(KeyModifiers::ALT),
+        ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+    };
+    
+    Some((code, mods))
+}
+
+pub fn run(options: StartOptions) -> Result<()> {
     // Simple direct initialization with better error handling
     println!("Starting Matrix Terminal...");
-    match App::new() {
+    match App::new(&options) {
         Ok(mut app) => {
             println!("Matrix Terminal initialized.");
             return app.run();
@@ -744,6 +4434,9 @@ pub fn run() -> Result<()> {
         Err(e) => {
             eprintln!("Error initializing Matrix Terminal: {}", e);
             eprintln!("Detailed error: {:?}", e);
+            if !options.safe_mode {
+                eprintln!("Run matrix --safe-mode to start with the default settings.");
+            }
             
             // If we're not in a TTY, we might be running from a launcher
             if !atty::is(atty::Stream::Stdout) {
@@ -0,0 +1,140 @@
+// Checking the settings file: syntax errors, values of the wrong type, unknown keys and
+// values out of range are reported by key, and the settings are loaded with the
+// defaults in place of whatever is wrong, so a mistake never keeps Matrix from starting.
+
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::config::settings::Settings;
+use crate::ui::keymap::Keymaps;
+use crate::ui::preferences;
+
+// Something wrong in the settings file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    // Where, e.g. "ui.theme"; empty for the file as a whole
+    pub key: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(key: impl Into<String>, message: impl ToString) -> Self {
+        Self { key: key.into(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+// Load a settings file, using the defaults for everything it gets wrong
+pub fn load(path: &Path) -> (Settings, Vec<Problem>) {
+    if !path.exists() {
+        return (Settings::new(), Vec::new());
+    }
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text),
+        Err(e) => (Settings::new(), vec![Problem::new("", format!("can't read {}: {}", path.display(), e))]),
+    }
+}
+
+// Parse settings, keeping every valid value and reporting the rest
+pub fn parse(text: &str) -> (Settings, Vec<Problem>) {
+    let user = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(user)) => user,
+        Ok(_) => return (Settings::new(), vec![Problem::new("", "expected an object of settings")]),
+        Err(e) => return (Settings::new(), vec![Problem::new("", e)]),
+    };
+    let defaults = serde_json::to_value(Settings::new()).unwrap_or_default();
+
+    // Take the user's values one at a time, keeping those that still deserialize
+    let mut problems = Vec::new();
+    let mut merged = defaults.clone();
+    for (key, value) in &user {
+        match defaults.get(key) {
+            None => problems.push(Problem::new(key.as_str(), "unknown setting")),
+            // Sections are taken key by key; maps (profiles, snippets) as a whole
+            Some(Value::Object(section)) if !section.is_empty() => match value {
+                Value::Object(values) => merge_section(&mut merged, key, section, values, &mut problems),
+                _ => problems.push(Problem::new(key.as_str(), "expected a section of settings")),
+            },
+            Some(_) => try_value(&mut merged, &[key], value, &mut problems),
+        }
+    }
+
+    // Values that parse but make no sense go back to their defaults
+    let mut settings: Settings = serde_json::from_value(merged.clone()).unwrap_or_default();
+    let invalid = preferences::invalid_values(&settings);
+    if !invalid.is_empty() {
+        for (section, key, message) in invalid {
+            merged[section][key] = defaults[section][key].clone();
+            problems.push(Problem::new(format!("{}.{}", section, key), message));
+        }
+        settings = serde_json::from_value(merged).unwrap_or_default();
+    }
+
+    // Key bindings that don't parse are left out of the keymap
+    for error in Keymaps::new(&settings.keyboard.bindings, &settings.keyboard.modes).1 {
+        problems.push(Problem::new("keyboard.bindings", error));
+    }
+
+    (settings, problems)
+}
+
+fn merge_section(merged: &mut Value, section: &str, defaults: &Map<String, Value>, values: &Map<String, Value>, problems: &mut Vec<Problem>) {
+    for (key, value) in values {
+        if defaults.contains_key(key) {
+            try_value(merged, &[section, key], value, problems);
+        } else {
+            problems.push(Problem::new(format!("{}.{}", section, key), "unknown setting"));
+        }
+    }
+}
+
+// Put a value in place if the settings still deserialize with it
+fn try_value(merged: &mut Value, path: &[&str], value: &Value, problems: &mut Vec<Problem>) {
+    let mut candidate = merged.clone();
+    let slot = path.iter().fold(&mut candidate, |v, key| &mut v[*key]);
+    *slot = value.clone();
+    match serde_json::from_value::<Settings>(candidate.clone()) {
+        Ok(_) => *merged = candidate,
+        Err(e) => problems.push(Problem::new(path.join("."), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_values_are_reported_and_replaced_by_defaults() {
+        let text = r#"{
+            "general": { "tick_rate_ms": "fast", "max_fps": 30 },
+            "ui": { "theme": "neon", "scrollbar": false, "colour": "red" },
+            "bell": 3,
+            "bookmarks": ["/tmp"]
+        }"#;
+        let (settings, problems) = parse(text);
+        let keys: Vec<&str> = problems.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["bell", "general.tick_rate_ms", "ui.colour", "ui.theme"]);
+
+        let defaults = Settings::new();
+        assert_eq!(settings.general.tick_rate_ms, defaults.general.tick_rate_ms);
+        assert_eq!(settings.general.max_fps, 30);
+        assert_eq!(settings.ui.theme, defaults.ui.theme);
+        assert!(!settings.ui.scrollbar);
+        assert_eq!(settings.bookmarks, ["/tmp"]);
+
+        let (_, problems) = parse("{ \"ui\": ");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].to_string().contains("line 1"));
+    }
+}
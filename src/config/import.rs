@@ -0,0 +1,330 @@
+// Importing settings from another terminal's configuration: tmux, kitty and alacritty
+// options with a Matrix equivalent (scrollback, shell, bell, clipboard, mouse) are
+// translated, the background color picks the closest built-in theme, and everything
+// else (key bindings, palettes, fonts) is listed as not imported, with the reason.
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use ratatui::style::Color;
+use std::path::PathBuf;
+
+use crate::config::settings::{BellSound, Osc52Policy, Settings};
+use crate::terminal::export;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Source {
+    Tmux,
+    Kitty,
+    Alacritty,
+}
+
+impl Source {
+    // Where the program keeps its configuration
+    pub fn default_path(&self) -> PathBuf {
+        let home = PathBuf::from(std::env::var("HOME").unwrap_or_default());
+        match self {
+            Source::Tmux => home.join(".tmux.conf"),
+            Source::Kitty => home.join(".config/kitty/kitty.conf"),
+            Source::Alacritty => home.join(".config/alacritty/alacritty.toml"),
+        }
+    }
+}
+
+// What an import did with each option
+#[derive(Debug, Default)]
+pub struct Report {
+    // "option value -> setting = value"
+    pub mapped: Vec<String>,
+    // "option value: why it wasn't imported"
+    pub unmapped: Vec<String>,
+}
+
+// Translate a configuration file's text into changes to `settings`
+pub fn import(source: Source, text: &str, settings: &mut Settings) -> Result<Report> {
+    let mut importer = Importer { settings, report: Report::default() };
+    match source {
+        Source::Tmux => importer.tmux(text),
+        Source::Kitty => importer.kitty(text),
+        Source::Alacritty => importer.alacritty(text)?,
+    }
+    Ok(importer.report)
+}
+
+struct Importer<'a> {
+    settings: &'a mut Settings,
+    report: Report,
+}
+
+impl Importer<'_> {
+    fn mapped(&mut self, option: &str, setting: &str, value: impl std::fmt::Display) {
+        self.report.mapped.push(format!("{} -> {} = {}", option, setting, value));
+    }
+
+    fn unmapped(&mut self, option: &str, reason: &str) {
+        self.report.unmapped.push(format!("{}: {}", option, reason));
+    }
+
+    fn scrollback(&mut self, option: &str, lines: i64) {
+        match usize::try_from(lines) {
+            Ok(lines) if lines > 0 => {
+                self.settings.scrollback.max_lines = lines;
+                self.mapped(option, "scrollback.max_lines", lines);
+            }
+            _ => self.unmapped(option, "scrollback must be a positive number of lines"),
+        }
+    }
+
+    fn shell(&mut self, option: &str, shell: &str) {
+        self.settings.general.default_shell = shell.to_string();
+        self.mapped(option, "general.default_shell", shell);
+    }
+
+    fn scroll_lines(&mut self, option: &str, multiplier: f64) {
+        let lines = multiplier.round().max(1.0) as usize;
+        self.settings.mouse.scroll_lines = lines;
+        self.mapped(option, "mouse.scroll_lines", lines);
+    }
+
+    // Pick the built-in theme closest to a background color
+    fn background(&mut self, option: &str, color: &str) {
+        match color.parse::<Color>().ok().and_then(export::rgb) {
+            Some(rgb) => {
+                let theme = if export::is_dark(rgb) { "default" } else { "light" };
+                self.settings.ui.theme = theme.to_string();
+                self.mapped(option, "ui.theme", format!("{} (closest built-in theme)", theme));
+            }
+            None => self.unmapped(option, "unknown color"),
+        }
+    }
+
+    fn osc52(&mut self, option: &str, copy: bool, paste: bool) {
+        self.settings.clipboard.osc52 = if copy { Osc52Policy::Allow } else { Osc52Policy::Off };
+        self.settings.clipboard.osc52_read = paste;
+        let policy = if copy { "allow" } else { "off" };
+        self.mapped(option, "clipboard.osc52", policy);
+        self.mapped(option, "clipboard.osc52_read", paste);
+    }
+
+    // tmux: `set`/`setw` commands; one command per line
+    fn tmux(&mut self, text: &str) {
+        for line in text.lines() {
+            let words = words(line);
+            let Some(command) = words.first() else { continue };
+            let line = line.trim();
+            match command.as_str() {
+                "set" | "set-option" | "setw" | "set-window-option" => {
+                    let mut rest = words[1..].iter().skip_while(|word| word.starts_with('-'));
+                    let Some(name) = rest.next() else { continue };
+                    let value = rest.map(String::as_str).collect::<Vec<_>>().join(" ");
+                    self.tmux_option(line, name, &value);
+                }
+                "bind" | "bind-key" | "unbind" | "unbind-key" => self.unmapped(line, "key bindings can't be changed"),
+                "source" | "source-file" => self.unmapped(line, "other files aren't followed; import them separately"),
+                _ => self.unmapped(line, "no Matrix equivalent"),
+            }
+        }
+    }
+
+    fn tmux_option(&mut self, line: &str, name: &str, value: &str) {
+        let on = matches!(value, "on" | "");
+        match name {
+            "history-limit" => match value.parse() {
+                Ok(lines) => self.scrollback(line, lines),
+                Err(_) => self.unmapped(line, "not a number"),
+            },
+            "default-shell" => self.shell(line, value),
+            "status" => {
+                self.settings.ui.status_bar = value != "off";
+                self.mapped(line, "ui.status_bar", value != "off");
+            }
+            "bell-action" => {
+                self.settings.bell.muted = value == "none";
+                self.mapped(line, "bell.muted", value == "none");
+            }
+            "visual-bell" => {
+                self.settings.bell.visual = on;
+                self.mapped(line, "bell.visual", on);
+            }
+            "set-clipboard" => self.osc52(line, value != "off", false),
+            "mouse" => self.unmapped(line, "the mouse is always enabled"),
+            name if is_tmux_color(name) => self.unmapped(line, "colors come from Matrix's themes"),
+            _ => self.unmapped(line, "no Matrix equivalent"),
+        }
+    }
+
+    // kitty: `option value` lines
+    fn kitty(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            match name {
+                "scrollback_lines" => match value.parse() {
+                    Ok(lines) => self.scrollback(line, lines),
+                    Err(_) => self.unmapped(line, "not a number"),
+                },
+                // "." is kitty's login shell, which Matrix starts anyway
+                "shell" if value == "." => self.unmapped(line, "the login shell is already the default"),
+                "shell" => self.shell(line, value),
+                "enable_audio_bell" => {
+                    let sound = if value == "no" { BellSound::Off } else { BellSound::System };
+                    self.settings.bell.sound = sound;
+                    self.mapped(line, "bell.sound", if value == "no" { "off" } else { "system" });
+                }
+                "visual_bell_duration" => {
+                    let visual = value.parse::<f64>().is_ok_and(|secs| secs > 0.0);
+                    self.settings.bell.visual = visual;
+                    self.mapped(line, "bell.visual", visual);
+                }
+                "wheel_scroll_multiplier" => match value.parse() {
+                    Ok(multiplier) => self.scroll_lines(line, multiplier),
+                    Err(_) => self.unmapped(line, "not a number"),
+                },
+                "focus_follows_mouse" => {
+                    self.settings.ui.focus_follows_mouse = value == "yes";
+                    self.mapped(line, "ui.focus_follows_mouse", value == "yes");
+                }
+                "clipboard_control" => {
+                    let copy = value.split_whitespace().any(|v| v.starts_with("write-clipboard"));
+                    let paste = value.split_whitespace().any(|v| v.starts_with("read-clipboard"));
+                    self.osc52(line, copy, paste);
+                }
+                "background" => self.background(line, value),
+                "map" | "mouse_map" => self.unmapped(line, "key bindings can't be changed"),
+                "include" | "globinclude" => self.unmapped(line, "other files aren't followed; import them separately"),
+                name if is_kitty_color(name) => self.unmapped(line, "colors come from Matrix's themes"),
+                _ => self.unmapped(line, "no Matrix equivalent"),
+            }
+        }
+    }
+
+    // alacritty: TOML (the older YAML files have to be migrated first)
+    fn alacritty(&mut self, text: &str) -> Result<()> {
+        let table: toml::Table = match text.parse() {
+            Ok(table) => table,
+            Err(e) => bail!("not an alacritty.toml ({}); convert YAML files with `alacritty migrate`", e.message()),
+        };
+        let mut options = Vec::new();
+        flatten("", &toml::Value::Table(table), &mut options);
+
+        for (name, value) in options {
+            let option = format!("{} = {}", name, value);
+            match (name.as_str(), &value) {
+                ("scrolling.history", toml::Value::Integer(lines)) => self.scrollback(&option, *lines),
+                ("scrolling.multiplier", toml::Value::Integer(multiplier)) => self.scroll_lines(&option, *multiplier as f64),
+                ("shell" | "terminal.shell" | "shell.program" | "terminal.shell.program", toml::Value::String(shell)) => {
+                    self.shell(&option, shell)
+                }
+                ("shell.args" | "terminal.shell.args", _) => self.unmapped(&option, "the shell is started without arguments"),
+                ("colors.primary.background", toml::Value::String(color)) => {
+                    let color = color.replacen("0x", "#", 1);
+                    self.background(&option, &color)
+                }
+                ("bell.duration", toml::Value::Integer(ms)) => {
+                    self.settings.bell.visual = *ms > 0;
+                    self.mapped(&option, "bell.visual", *ms > 0);
+                }
+                ("terminal.osc52", toml::Value::String(mode)) => {
+                    let copy = matches!(mode.as_str(), "OnlyCopy" | "CopyPaste");
+                    let paste = matches!(mode.as_str(), "OnlyPaste" | "CopyPaste");
+                    self.osc52(&option, copy, paste)
+                }
+                (name, _) if name.starts_with("keyboard.") || name.starts_with("mouse.bindings") => {
+                    self.unmapped(&option, "key bindings can't be changed")
+                }
+                (name, _) if name.ends_with("import") => self.unmapped(&option, "other files aren't followed; import them separately"),
+                (name, _) if name.starts_with("colors.") => self.unmapped(&option, "colors come from Matrix's themes"),
+                _ => self.unmapped(&option, "no Matrix equivalent"),
+            }
+        }
+        Ok(())
+    }
+}
+
+// The leaf values of a TOML table, by dotted name; arrays are kept whole
+fn flatten(prefix: &str, value: &toml::Value, options: &mut Vec<(String, toml::Value)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&name, value, options);
+            }
+        }
+        value => options.push((prefix.to_string(), value.clone())),
+    }
+}
+
+// A tmux command line's words, with quotes removed and comments dropped
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, '#') if word.is_none() => break,
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+fn is_tmux_color(name: &str) -> bool {
+    ["-style", "-colour", "-color", "-fg", "-bg", "-attr"].iter().any(|suffix| name.ends_with(suffix))
+}
+
+fn is_kitty_color(name: &str) -> bool {
+    name == "foreground"
+        || name == "cursor"
+        || name.starts_with("selection_")
+        || name.ends_with("_color")
+        || name.ends_with("_foreground")
+        || name.ends_with("_background")
+        || name.strip_prefix("color").is_some_and(|n| n.parse::<u8>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_with_an_equivalent_are_translated_and_the_rest_reported() {
+        let mut settings = Settings::new();
+        let tmux = "set -g history-limit 50000  # lots\nset-option -g default-shell '/bin/zsh'\nbind-key C-a send-prefix\nset -g status-style 'bg=#202020'\nset -g status off\n";
+        let report = import(Source::Tmux, tmux, &mut settings).unwrap();
+        assert_eq!(settings.scrollback.max_lines, 50000);
+        assert_eq!(settings.general.default_shell, "/bin/zsh");
+        assert!(!settings.ui.status_bar);
+        assert_eq!(report.mapped.len(), 3);
+        assert_eq!(report.unmapped, [
+            "bind-key C-a send-prefix: key bindings can't be changed",
+            "set -g status-style 'bg=#202020': colors come from Matrix's themes",
+        ]);
+
+        let kitty = "# kitty\nscrollback_lines 2000\nbackground #fdf6e3\ncolor1 #dc322f\nenable_audio_bell no\nmap ctrl+t new_tab\n";
+        let report = import(Source::Kitty, kitty, &mut settings).unwrap();
+        assert_eq!(settings.scrollback.max_lines, 2000);
+        assert_eq!(settings.ui.theme, "light");
+        assert_eq!(settings.bell.sound, BellSound::Off);
+        assert_eq!(report.unmapped.len(), 2);
+
+        let alacritty = "[scrolling]\nhistory = 7000\n\n[terminal]\nosc52 = \"CopyPaste\"\n\n[colors.primary]\nbackground = \"0x1d1f21\"\n\n[[keyboard.bindings]]\nkey = \"N\"\naction = \"SpawnNewInstance\"\n";
+        let report = import(Source::Alacritty, alacritty, &mut settings).unwrap();
+        assert_eq!(settings.scrollback.max_lines, 7000);
+        assert_eq!(settings.ui.theme, "default");
+        assert_eq!(settings.clipboard.osc52, Osc52Policy::Allow);
+        assert!(settings.clipboard.osc52_read);
+        assert_eq!(report.unmapped.len(), 1);
+        assert!(import(Source::Alacritty, "scrolling:\n  history: 7000\n", &mut settings).is_err());
+    }
+}
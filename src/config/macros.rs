@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::utils::paths;
+
+// Keystroke macros: input typed into a window, recorded under a name to be sent
+// again later. They're kept as the bytes the keys produced, so replaying one
+// doesn't depend on the keyboard layout or shortcuts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Macros {
+    macros: BTreeMap<String, String>,
+}
+
+// A macro being recorded
+#[derive(Debug)]
+pub struct Recording {
+    pub name: String,
+    pub input: String,
+}
+
+impl Macros {
+    // Location of the macros file
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("macros.json")
+    }
+
+    // Load the saved macros, or none if there is no file
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read(&path)?;
+        serde_json::from_slice(&data).with_context(|| format!("invalid macros in {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves half-written macros
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.macros.get(name).map(String::as_str)
+    }
+
+    // Macro names, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.macros.keys().map(String::as_str)
+    }
+
+    pub fn insert(&mut self, name: String, input: String) {
+        self.macros.insert(name, input);
+    }
+
+    // Forget a macro, returning whether there was one
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.macros.remove(name).is_some()
+    }
+}
@@ -1 +1,4 @@
 pub mod settings;
+pub mod macros;
+pub mod check;
+pub mod import;
@@ -1,22 +1,668 @@
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::PathBuf;
 
+use crate::terminal::{redact, smart_select};
+use crate::utils::paths;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Settings {
     pub general: GeneralSettings,
     pub ui: UiSettings,
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+    #[serde(default)]
+    pub keyboard: KeyboardSettings,
+    #[serde(default)]
+    pub clipboard: ClipboardSettings,
+    #[serde(default)]
+    pub mouse: MouseSettings,
+    #[serde(default)]
+    pub session: SessionSettings,
+    #[serde(default)]
+    pub history: HistorySettings,
+    #[serde(default)]
+    pub lock: LockSettings,
+    #[serde(default)]
+    pub redaction: RedactionSettings,
+    #[serde(default)]
+    pub scrollback: ScrollbackSettings,
+    #[serde(default)]
+    pub triggers: Vec<TriggerRule>,
+    // What double-click and hint mode select (URLs, paths, hashes...), first match wins
+    #[serde(default = "smart_select::default_rules")]
+    pub selection_rules: Vec<SelectionRule>,
+    // Status bar segments showing what a command prints (e.g. the kube context)
+    #[serde(default)]
+    pub status_scripts: Vec<StatusScript>,
+    #[serde(default)]
+    pub editor: EditorSettings,
+    #[serde(default)]
+    pub bell: BellSettings,
+    #[serde(default)]
+    pub git: GitSettings,
+    #[serde(default)]
+    pub remote: RemoteSettings,
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+    // Named limits and sandboxes for new windows (`:new --profile <name>`)
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    // Named text to type into a window (`:snippets`), with {{placeholders}} asked for on insert
+    #[serde(default)]
+    pub snippets: BTreeMap<String, String>,
+    // Directories to jump to (`:bookmarks`)
+    #[serde(default)]
+    pub bookmarks: Vec<String>,
+    // Workspace templates set up when Matrix starts in a directory
+    #[serde(default)]
+    pub workspaces: Vec<WorkspaceBinding>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeneralSettings {
     pub tick_rate_ms: u64,
     pub default_shell: String,
+    // Most frames drawn per second; output arriving faster is drawn together
+    #[serde(default = "default_max_fps")]
+    pub max_fps: u32,
+    // Pause a window whose output keeps arriving faster than it can be shown
+    #[serde(default = "default_flow_control")]
+    pub flow_control: bool,
+    // Columns between tab stops, until an application sets its own
+    #[serde(default = "default_tab_width")]
+    pub tab_width: usize,
+    // TERM programs are started with, e.g. "tmux-256color" or "matrix" (see `matrix install-terminfo`)
+    #[serde(default = "default_term")]
+    pub term: String,
+    // Tell programs 24-bit color works (COLORTERM=truecolor)
+    #[serde(default = "default_colorterm")]
+    pub colorterm: bool,
+    // Start shells as login shells, reading the profile files (PATH and the like)
+    #[serde(default)]
+    pub login_shell: bool,
+    // How long to wait for a shell's first prompt before typing its init commands anyway
+    #[serde(default = "default_init_delay_ms")]
+    pub init_delay_ms: u64,
+}
+
+fn default_max_fps() -> u32 {
+    60
+}
+
+fn default_flow_control() -> bool {
+    true
+}
+
+fn default_tab_width() -> usize {
+    8
+}
+
+fn default_init_delay_ms() -> u64 {
+    1000
+}
+
+fn default_term() -> String {
+    String::from("xterm-256color")
+}
+
+fn default_colorterm() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UiSettings {
     pub theme: String,
+    // Preview where a split will go before creating it
+    #[serde(default = "default_split_preview")]
+    pub split_preview: bool,
+    // Directional navigation wraps around at the screen edges
+    #[serde(default)]
+    pub wrap_navigation: bool,
+    // Scrollbar with search, prompt and trigger marks on each window's right edge
+    #[serde(default = "default_scrollbar")]
+    pub scrollbar: bool,
+    // Status line at the bottom of the screen
+    #[serde(default = "default_status_bar")]
+    pub status_bar: bool,
+    // Seconds without input or output after which a window is dimmed; 0 never dims
+    #[serde(default = "default_idle_dim_secs")]
+    pub idle_dim_secs: u64,
+    // Title windows after the program running in them, unless named by the user
+    #[serde(default = "default_auto_title")]
+    pub auto_title: bool,
+    // Switch between light_theme and dark_theme as the system's appearance changes
+    #[serde(default)]
+    pub follow_system_appearance: bool,
+    #[serde(default = "default_light_theme")]
+    pub light_theme: String,
+    #[serde(default = "default_dark_theme")]
+    pub dark_theme: String,
+    // Focus the window under the mouse once it has rested there focus_delay_ms
+    #[serde(default)]
+    pub focus_follows_mouse: bool,
+    #[serde(default = "default_focus_delay_ms")]
+    pub focus_delay_ms: u64,
+    // Colors the terminal can show: auto (detected), truecolor, 256 or 16
+    #[serde(default = "default_color_depth")]
+    pub color_depth: String,
+    // Draw borders and lines in ASCII, for terminals without box-drawing characters
+    #[serde(default)]
+    pub ascii_borders: bool,
+    // How text attributes are drawn: bold text in the bright version of its color,
+    // blinking text blinking slowly (slow) or steady (ignore), and italics underlined
+    // on screen for terminals without an italic font
+    #[serde(default)]
+    pub bold_is_bright: bool,
+    #[serde(default = "default_blink")]
+    pub blink: String,
+    #[serde(default)]
+    pub italic_as_underline: bool,
+    // Color the output of `cat <file>` by the file's syntax, for files the highlighter
+    // knows (needs shell integration to tell where the output starts and ends)
+    #[serde(default)]
+    pub highlight_files: bool,
+    // Sidebar panels open on start (windows, search, bookmarks, ssh), and their width
+    #[serde(default)]
+    pub panels: Vec<String>,
+    #[serde(default = "default_panel_width")]
+    pub panel_width: u16,
+}
+
+fn default_split_preview() -> bool {
+    true
+}
+
+fn default_scrollbar() -> bool {
+    true
+}
+
+fn default_status_bar() -> bool {
+    true
+}
+
+fn default_idle_dim_secs() -> u64 {
+    600
+}
+
+fn default_auto_title() -> bool {
+    true
+}
+
+fn default_light_theme() -> String {
+    String::from("light")
+}
+
+fn default_dark_theme() -> String {
+    String::from("default")
+}
+
+fn default_focus_delay_ms() -> u64 {
+    150
+}
+
+fn default_color_depth() -> String {
+    String::from("auto")
+}
+
+fn default_blink() -> String {
+    String::from("slow")
+}
+
+fn default_panel_width() -> u16 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    // Command used to speak text, e.g. "spd-say" (None disables speech)
+    pub speech_command: Option<String>,
+    // Speak new output lines of the focused window
+    pub announce_output: bool,
+    // Speak focus and mode changes
+    pub announce_ui: bool,
+    // Maximum number of output lines spoken per update
+    pub max_lines: usize,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            speech_command: None,
+            announce_output: true,
+            announce_ui: true,
+            max_lines: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyboardSettings {
+    // Let applications enable the kitty keyboard protocol
+    pub kitty_protocol: bool,
+    // Let applications enable xterm modifyOtherKeys
+    pub modify_other_keys: bool,
+    // Ask the host terminal for unambiguous key reports when it supports them
+    pub enhance_host_keyboard: bool,
+    // Key sequences and the commands they run, e.g. "Ctrl+A w v": "split"
+    pub bindings: BTreeMap<String, String>,
+    // Bindings of the other input modes (copy, resize, search, command), by mode
+    pub modes: BTreeMap<String, BTreeMap<String, String>>,
+    // How long to wait for the next key of a sequence
+    pub chord_timeout_ms: u64,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self {
+            kitty_protocol: true,
+            modify_other_keys: true,
+            enhance_host_keyboard: true,
+            bindings: BTreeMap::new(),
+            modes: BTreeMap::new(),
+            chord_timeout_ms: 1000,
+        }
+    }
+}
+
+// Whether the last session comes back when Matrix starts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    Ask,
+    Always,
+    Never,
+}
+
+// What to do when an application sets the clipboard through OSC 52
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Osc52Policy {
+    Off,
+    Ask,
+    Allow,
+}
+
+// How line breaks in pasted text reach the program
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteNewlines {
+    // Send them as Enter presses
+    Keep,
+    // Join the lines with spaces, so nothing runs until Enter is pressed
+    Join,
+    // End each line with a backslash, continuing a shell command
+    Escape,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    // Whether applications may set the clipboard (off, ask or allow)
+    pub osc52: Osc52Policy,
+    // Let applications read the clipboard back (only honoured with "allow")
+    pub osc52_read: bool,
+    // Largest clipboard payload accepted from an application, in bytes
+    pub max_bytes: usize,
+    // Recent copies kept to choose from with :paste-buffer
+    pub history_size: usize,
+    // What happens to line breaks in text pasted with :paste (keep, join, escape)
+    pub paste_newlines: PasteNewlines,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        Self {
+            osc52: Osc52Policy::Ask,
+            osc52_read: false,
+            max_bytes: 1024 * 1024,
+            history_size: 20,
+            paste_newlines: PasteNewlines::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseSettings {
+    // Lines scrolled per wheel tick
+    pub scroll_lines: usize,
+    // Scroll the content the way the wheel moves, as on a touchpad
+    pub natural_scrolling: bool,
+    // In full-screen programs (alternate screen) the wheel sends arrow keys instead
+    pub alternate_scroll: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            scroll_lines: 3,
+            natural_scrolling: false,
+            alternate_scroll: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    // Save the session periodically and on exit so it can be restored after a crash
+    pub autosave: bool,
+    // Seconds between automatic saves
+    pub autosave_interval_secs: u64,
+    // Scrollback saved per window, in bytes (before compression)
+    pub max_scrollback_bytes: usize,
+    // Whether to bring back the last session on start (ask, always or never)
+    pub restore: RestoreMode,
+    // When always restoring, also run each window's last command again
+    pub rerun_commands: bool,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            autosave: true,
+            autosave_interval_secs: 30,
+            max_scrollback_bytes: 256 * 1024,
+            restore: RestoreMode::Ask,
+            rerun_commands: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    // Remember commands run at shell prompts (needs shell integration, OSC 133)
+    pub record: bool,
+    // Also search the shell's own history file
+    pub import_shell: bool,
+    // Commands kept, and read from the shell's history file
+    pub max_entries: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            record: true,
+            import_shell: false,
+            max_entries: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockSettings {
+    // Lock the screen after this many minutes without keyboard or mouse input (0: never)
+    pub idle_minutes: u64,
+    // Command checking the password given on its standard input (empty: su for the current user)
+    pub auth_command: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    // Regular expressions for secrets masked in exports, saved sessions and the
+    // command history; with a capture group, only the group is masked
+    pub patterns: Vec<String>,
+    // Also mask secrets on screen, except on the line the cursor is on
+    pub mask_scrollback: bool,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            patterns: redact::DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            mask_scrollback: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollbackSettings {
+    // Lines of history kept per window
+    pub max_lines: usize,
+    // Compress older history in memory (lz4)
+    pub compress: bool,
+    // Lines kept uncompressed when compressing
+    pub working_set_lines: usize,
+    // Seconds between recordings of each window's screen for `:timeline`; 0 turns it off
+    pub timeline_interval_secs: u64,
+    // Recordings kept per window
+    pub timeline_snapshots: usize,
+}
+
+impl Default for ScrollbackSettings {
+    fn default() -> Self {
+        Self {
+            max_lines: 100_000,
+            compress: true,
+            working_set_lines: 5_000,
+            timeline_interval_secs: 10,
+            timeline_snapshots: 60,
+        }
+    }
+}
+
+// A regex evaluated on every line of output, and what to do when it matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    pub pattern: String,
+    // Draw matching lines in this color (e.g. "red" or "#ff8800")
+    #[serde(default)]
+    pub highlight: Option<String>,
+    // Show a notification with the matching line
+    #[serde(default)]
+    pub notify: bool,
+    // Mark the window as needing attention until it is focused
+    #[serde(default)]
+    pub mark: bool,
+    // Shell command to run; gets MATRIX_LINE and MATRIX_WINDOW in its environment
+    #[serde(default)]
+    pub run: Option<String>,
+}
+
+// A pattern selected whole by double-click and hint mode, e.g. a ticket ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionRule {
+    pub name: String,
+    pub pattern: String,
+    // Command opening a match, {match} replaced by it (e.g. "open https://tracker/browse/{match}")
+    #[serde(default)]
+    pub open: Option<String>,
+}
+
+// A status bar segment: the last line a command prints, run in the focused
+// window's directory (also in MATRIX_CWD) every interval and when it changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusScript {
+    pub command: String,
+    #[serde(default = "default_script_interval")]
+    pub interval_secs: u64,
+    // Killed if it takes longer, keeping the last output
+    #[serde(default = "default_script_timeout")]
+    pub timeout_ms: u64,
+    // Show it with the indicators on the right rather than on the left
+    #[serde(default)]
+    pub right: bool,
+}
+
+fn default_script_interval() -> u64 {
+    10
+}
+
+fn default_script_timeout() -> u64 {
+    2000
+}
+
+// A workspace template applied when Matrix starts in a directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceBinding {
+    // The directory, or a glob of directories (e.g. "~/work/*"); their subdirectories match too
+    pub dir: String,
+    // Name of a template saved with `:workspace template save`
+    pub template: String,
+}
+
+// Where files opened from output are edited
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorTarget {
+    // In a new split next to the window
+    Split,
+    // As a separate program (e.g. a GUI editor)
+    External,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    // Shell command opening a file; {file}, {line} and {column} are replaced
+    pub command: String,
+    pub open_in: EditorTarget,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            command: String::from("${EDITOR:-vi} +{line} {file}"),
+            open_in: EditorTarget::Split,
+        }
+    }
+}
+
+// Sound played when an application rings the bell
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellSound {
+    // No sound
+    Off,
+    // The host terminal's bell
+    System,
+    // The sound file in `sound_file`, played with `player`
+    File,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BellSettings {
+    // Flash the window's border
+    pub visual: bool,
+    // Sound to play (off, system or file)
+    pub sound: BellSound,
+    // Sound file played when `sound` is "file"
+    pub sound_file: Option<String>,
+    // Shell command playing a sound file; {file} is replaced
+    pub player: String,
+    // Silence the bell in every window
+    pub muted: bool,
+    // Shortest time between two sounds, in milliseconds
+    pub min_interval_ms: u64,
+}
+
+impl Default for BellSettings {
+    fn default() -> Self {
+        Self {
+            visual: true,
+            sound: BellSound::System,
+            sound_file: None,
+            player: String::from(if cfg!(target_os = "macos") { "afplay {file}" } else { "paplay {file}" }),
+            muted: false,
+            min_interval_ms: 200,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitSettings {
+    // Branch, changes and ahead/behind counts of the current window's repository in the status bar
+    pub status: bool,
+    // Border color of windows in a repository with uncommitted changes (e.g. "yellow"), if any
+    pub dirty_border: Option<String>,
+}
+
+impl Default for GitSettings {
+    fn default() -> Self {
+        Self {
+            status: true,
+            dirty_border: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    // Also serve remote control over a WebSocket on localhost (needs the "remote" feature);
+    // matrix ctl uses the local control socket, which is always there
+    pub enabled: bool,
+    // Port on localhost to listen on
+    pub port: u16,
+    // Token clients must present; a random one is kept in the state directory if unset
+    pub token: Option<String>,
+}
+
+impl Default for RemoteSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7681,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSettings {
+    // Serve metrics in the Prometheus text format on localhost
+    pub endpoint: bool,
+    // Port on localhost to serve them on
+    pub port: u16,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: false,
+            port: 9464,
+        }
+    }
+}
+
+// Limits and sandboxing applied to a window's process when it starts; unset fields
+// leave the process as it would otherwise be
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    // Scheduling priority, from -20 (highest) to 19 (lowest)
+    pub nice: Option<i32>,
+    // CPU time in seconds
+    pub cpu_secs: Option<u64>,
+    // Virtual memory in megabytes
+    pub memory_mb: Option<u64>,
+    pub open_files: Option<u64>,
+    // Processes the user may run
+    pub processes: Option<u64>,
+    // Linux cgroup (v2) directory to run in, e.g. /sys/fs/cgroup/matrix
+    pub cgroup: Option<String>,
+    // Command the program runs under, e.g. "firejail --quiet" or "bwrap --ro-bind / / --dev /dev"
+    pub sandbox: Option<String>,
+    // TERM advertised instead of the general setting's
+    pub term: Option<String>,
+    // Start the shell as a login shell, or not, whatever the general setting says
+    pub login_shell: Option<bool>,
+    // Arguments the shell is started with, e.g. ["--norc"]
+    pub shell_args: Vec<String>,
+    // Typed into the shell at its first prompt, e.g. ["source .venv/bin/activate"]
+    pub init_commands: Vec<String>,
 }
 
 impl Default for Settings {
@@ -25,10 +671,57 @@ impl Default for Settings {
             general: GeneralSettings {
                 tick_rate_ms: 250,
                 default_shell: std::env::var("SHELL").unwrap_or_else(|_| String::from("/bin/bash")),
+                max_fps: default_max_fps(),
+                flow_control: default_flow_control(),
+                tab_width: default_tab_width(),
+                term: default_term(),
+                colorterm: default_colorterm(),
+                login_shell: false,
+                init_delay_ms: default_init_delay_ms(),
             },
             ui: UiSettings {
                 theme: String::from("default"),
+                split_preview: default_split_preview(),
+                wrap_navigation: false,
+                scrollbar: default_scrollbar(),
+                status_bar: default_status_bar(),
+                idle_dim_secs: default_idle_dim_secs(),
+                auto_title: default_auto_title(),
+                follow_system_appearance: false,
+                light_theme: default_light_theme(),
+                dark_theme: default_dark_theme(),
+                focus_follows_mouse: false,
+                focus_delay_ms: default_focus_delay_ms(),
+                color_depth: default_color_depth(),
+                ascii_borders: false,
+                bold_is_bright: false,
+                blink: default_blink(),
+                italic_as_underline: false,
+                highlight_files: false,
+                panels: Vec::new(),
+                panel_width: default_panel_width(),
             },
+            accessibility: AccessibilitySettings::default(),
+            keyboard: KeyboardSettings::default(),
+            clipboard: ClipboardSettings::default(),
+            mouse: MouseSettings::default(),
+            session: SessionSettings::default(),
+            history: HistorySettings::default(),
+            lock: LockSettings::default(),
+            redaction: RedactionSettings::default(),
+            scrollback: ScrollbackSettings::default(),
+            triggers: Vec::new(),
+            selection_rules: smart_select::default_rules(),
+            status_scripts: Vec::new(),
+            editor: EditorSettings::default(),
+            bell: BellSettings::default(),
+            git: GitSettings::default(),
+            remote: RemoteSettings::default(),
+            metrics: MetricsSettings::default(),
+            profiles: BTreeMap::new(),
+            snippets: BTreeMap::new(),
+            bookmarks: Vec::new(),
+            workspaces: Vec::new(),
         }
     }
 }
@@ -38,14 +731,21 @@ impl Settings {
         Self::default()
     }
     
-    pub fn load() -> Result<Self> {
-        // TODO: Implement loading from config file
-        // For now, just return default settings
-        Ok(Self::default())
+    // Location of the settings file
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("settings.json")
     }
     
-    pub fn save(&self, _path: &PathBuf) -> Result<()> {
-        // TODO: Implement saving to config file
+    pub fn save(&self, path: &PathBuf) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        
+        // Write to a temporary file first so a crash never leaves half-written settings
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+        
         Ok(())
     }
 }
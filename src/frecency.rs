@@ -0,0 +1,133 @@
+// Frecency: how often and how recently directories were visited and commands run,
+// for ranking the quick-open list. Uses count for more the more recent they are, as
+// in Firefox's address bar or zoxide; the counts are kept on disk across restarts.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utils::paths;
+
+// Entries kept; the lowest ranked go first
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Directory,
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    kind: Kind,
+    text: String,
+    count: u32,
+    // Seconds since the epoch
+    last_used: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Frecency {
+    entries: Vec<Entry>,
+}
+
+impl Frecency {
+    // Location of the frecency file
+    pub fn path() -> PathBuf {
+        paths::state_dir().join("frecency.json")
+    }
+
+    // Load the saved counts, or start with none
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read(&path)?;
+        serde_json::from_slice(&data).with_context(|| format!("invalid frecency data in {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    // Count a use of a directory or command
+    pub fn visit(&mut self, kind: Kind, text: &str) {
+        self.visit_at(kind, text, now());
+    }
+
+    fn visit_at(&mut self, kind: Kind, text: &str, now: u64) {
+        match self.entries.iter_mut().find(|e| e.kind == kind && e.text == text) {
+            Some(entry) => {
+                entry.count = entry.count.saturating_add(1);
+                entry.last_used = now;
+            }
+            None => self.entries.push(Entry { kind, text: text.to_string(), count: 1, last_used: now }),
+        }
+
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.sort_by(|a, b| rank(b, now).total_cmp(&rank(a, now)));
+            self.entries.truncate(MAX_ENTRIES);
+        }
+    }
+
+    // The directories or commands used, with their scores
+    pub fn scores(&self, kind: Kind) -> Vec<(&str, f64)> {
+        let now = now();
+        self.entries.iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| (e.text.as_str(), rank(e, now)))
+            .collect()
+    }
+}
+
+// Score of something used `count` times, the last time `age_secs` ago
+pub fn score(count: u32, age_secs: u64) -> f64 {
+    let weight = match age_secs {
+        0..=3_599 => 4.0,
+        3_600..=86_399 => 2.0,
+        86_400..=604_799 => 0.5,
+        _ => 0.25,
+    };
+    count as f64 * weight
+}
+
+fn rank(entry: &Entry, now: u64) -> f64 {
+    score(entry.count, now.saturating_sub(entry.last_used))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_uses_outrank_old_frequent_ones() {
+        let mut frecency = Frecency::default();
+        let day = 86_400;
+        for _ in 0..5 {
+            frecency.visit_at(Kind::Directory, "/old", 0);
+        }
+        frecency.visit_at(Kind::Directory, "/new", 30 * day);
+        frecency.visit_at(Kind::Command, "make", 30 * day);
+
+        let now = 30 * day + 60;
+        let rank_of = |text: &str| frecency.entries.iter().find(|e| e.text == text).map(|e| rank(e, now));
+        assert_eq!(rank_of("/old"), Some(1.25));
+        assert_eq!(rank_of("/new"), Some(4.0));
+        assert_eq!(frecency.entries.iter().filter(|e| e.kind == Kind::Directory).count(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+// Command history: commands run at shell prompts in any window, as reported by
+// shell integration (OSC 133), kept on disk to be searched and typed again. The
+// shell's own history file can be read in too, so history from before Matrix
+// (or from shells without integration) is searchable as well.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::utils::paths;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    // Title of the window it ran in
+    pub window: String,
+    // The window itself, while it's open (IDs don't survive a restart)
+    #[serde(skip)]
+    pub window_id: Option<Uuid>,
+}
+
+// Where an entry found by a search came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source<'a> {
+    // Run in the window searching
+    ThisWindow,
+    // Run in another window, with this title
+    Window(&'a str),
+    // Read from the shell's history file
+    Shell,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History {
+    // Oldest first
+    entries: VecDeque<HistoryEntry>,
+    // Commands read from the shell's history file, oldest first (not saved again)
+    #[serde(skip)]
+    imported: Vec<String>,
+}
+
+impl History {
+    // Location of the history file
+    pub fn path() -> PathBuf {
+        paths::state_dir().join("history.json")
+    }
+
+    // Load the saved history, or start an empty one if there is none
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read(&path)?;
+        serde_json::from_slice(&data).with_context(|| format!("invalid history in {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a half-written history
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec(self)?)?;
+        fs::rename(&temp, path)?;
+
+        Ok(())
+    }
+
+    // Add a command run in a window, keeping at most `max_entries`
+    pub fn push(&mut self, command: String, window_id: Uuid, window: &str, max_entries: usize) {
+        self.entries.push_back(HistoryEntry { command, window: window.to_string(), window_id: Some(window_id) });
+        while self.entries.len() > max_entries.max(1) {
+            self.entries.pop_front();
+        }
+    }
+
+    // Read the shell's history file ($HISTFILE, ~/.zsh_history or ~/.bash_history)
+    pub fn import_shell(&mut self, max_entries: usize) {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        let candidates = std::env::var_os("HISTFILE").map(PathBuf::from).into_iter()
+            .chain([home.join(".zsh_history"), home.join(".bash_history")]);
+
+        for path in candidates {
+            let Ok(data) = fs::read(&path) else { continue };
+            let text = String::from_utf8_lossy(&data);
+            let mut commands: Vec<String> = text.lines()
+                .filter_map(shell_history_command)
+                .map(str::to_string)
+                .collect();
+            let skip = commands.len().saturating_sub(max_entries);
+            self.imported = commands.split_off(skip);
+            return;
+        }
+    }
+
+    // Distinct commands, newest first: the window's own, then other windows', then
+    // the shell's history file
+    pub fn search_order(&self, window_id: Option<Uuid>) -> Vec<(&str, Source<'_>)> {
+        let own = self.entries.iter().rev()
+            .filter(|e| window_id.is_some() && e.window_id == window_id)
+            .map(|e| (e.command.as_str(), Source::ThisWindow));
+        let others = self.entries.iter().rev()
+            .filter(|e| window_id.is_none() || e.window_id != window_id)
+            .map(|e| (e.command.as_str(), Source::Window(e.window.as_str())));
+        let shell = self.imported.iter().rev().map(|c| (c.as_str(), Source::Shell));
+
+        let mut seen = HashSet::new();
+        own.chain(others).chain(shell).filter(|(command, _)| seen.insert(*command)).collect()
+    }
+}
+
+// The command of a history file line: zsh's extended format starts lines with
+// ": <time>:<duration>;", bash may write "#<time>" lines between commands
+fn shell_history_command(line: &str) -> Option<&str> {
+    let command = match line.strip_prefix(": ") {
+        Some(rest) => rest.split_once(';').map_or(line, |(_, command)| command),
+        None if line.starts_with('#') => return None,
+        None => line,
+    };
+    let command = command.trim();
+    (!command.is_empty()).then_some(command)
+}
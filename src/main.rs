@@ -1,9 +1,194 @@
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
 mod app;
 mod terminal;
 mod ui;
 mod config;
 mod utils;
+mod session;
+mod workspace;
+mod history;
+mod frecency;
+mod metrics;
+mod remote;
+
+/// A terminal multiplexer
+#[derive(Parser)]
+#[command(name = "matrix", version)]
+struct Cli {
+    /// Start with the default settings, ignoring the configuration, macros and saved
+    /// sessions (nothing is written to them), to recover from a broken configuration
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Keep the configuration and state in a matrix-data directory next to the binary
+    /// instead of the platform's directories
+    #[arg(long, global = true)]
+    portable: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a settings file and list its problems; exits with status 1 if there are any
+    CheckConfig {
+        /// The file to check (default: the settings file Matrix uses)
+        file: Option<PathBuf>,
+    },
+    /// Translate another terminal's configuration into Matrix settings, listing what
+    /// could and couldn't be carried over; the settings file's other values are kept
+    Import {
+        /// The program the configuration is for
+        #[arg(long)]
+        from: config::import::Source,
+        /// The configuration file (default: where that program keeps it)
+        file: Option<PathBuf>,
+        /// The settings file to write (default: the settings file Matrix uses)
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Only list what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Install the terminfo entry for TERM=matrix into ~/.terminfo (or $TERMINFO)
+    InstallTerminfo,
+    /// Start with a single window and a guided tour of splits, navigation, copy mode
+    /// and command mode, each step moving on once it's been done
+    Tutorial,
+    /// Control the running Matrix through its local control socket
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum CtlCommand {
+    /// Print a window's last lines
+    CapturePane {
+        /// The window: its number in the layout (from 1) or ID (default: the focused one)
+        #[arg(long)]
+        window: Option<String>,
+        /// How many lines (default: a screenful)
+        #[arg(long)]
+        lines: Option<usize>,
+        /// Print JSON with each line's styles instead of plain text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Wait until a channel is signaled (by `matrix ctl signal` or :signal-channel)
+    WaitFor {
+        channel: String,
+        /// Give up with an error after this many seconds (an hour by default)
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Signal a channel, letting everything waiting on it carry on
+    Signal {
+        channel: String,
+    },
+    /// Print events as JSON lines as they happen (window-created, window-closed,
+    /// focus-changed, output-activity, process-exited)
+    Events {
+        /// Only these events (default: all)
+        events: Vec<String>,
+    },
+}
 
 fn main() -> anyhow::Result<()> {
-    app::run()
-}
\ No newline at end of file
+    let cli = Cli::parse();
+    if cli.portable {
+        utils::paths::set_portable().context("can't find the directory of the binary")?;
+    }
+    match cli.command {
+        Some(Command::CheckConfig { file }) => {
+            let path = file.unwrap_or_else(config::settings::Settings::path);
+            let (_, problems) = config::check::load(&path);
+            if problems.is_empty() {
+                println!("{}: ok", path.display());
+                return Ok(());
+            }
+            eprintln!("{}: {} problems", path.display(), problems.len());
+            for problem in problems {
+                eprintln!("  {}", problem);
+            }
+            std::process::exit(1);
+        }
+        Some(Command::Import { from, file, output, dry_run }) => {
+            let file = file.unwrap_or_else(|| from.default_path());
+            let text = std::fs::read_to_string(&file)
+                .with_context(|| format!("can't read {}", file.display()))?;
+            let output = output.unwrap_or_else(config::settings::Settings::path);
+            let (mut settings, problems) = config::check::load(&output);
+            if !problems.is_empty() {
+                anyhow::bail!("{} has problems; fix them first (see `matrix check-config`)", output.display());
+            }
+
+            let report = config::import::import(from, &text, &mut settings)?;
+            println!("Imported from {}:", file.display());
+            for line in &report.mapped {
+                println!("  {}", line);
+            }
+            if !report.unmapped.is_empty() {
+                println!("Not imported:");
+                for line in &report.unmapped {
+                    println!("  {}", line);
+                }
+            }
+            if !dry_run && !report.mapped.is_empty() {
+                settings.save(&output)?;
+                println!("Saved to {}", output.display());
+            }
+            Ok(())
+        }
+        Some(Command::InstallTerminfo) => {
+            let dir = terminal::terminfo::install()?;
+            println!("Installed the matrix terminfo entry in {}", dir.display());
+            Ok(())
+        }
+        Some(Command::Ctl { command }) => ctl(command),
+        Some(Command::Tutorial) => app::run(app::StartOptions { safe_mode: cli.safe_mode, tutorial: true }),
+        None => app::run(app::StartOptions { safe_mode: cli.safe_mode, tutorial: false }),
+    }
+}
+
+// Send a request to the running Matrix and print the answer
+fn ctl(command: CtlCommand) -> anyhow::Result<()> {
+    match command {
+        CtlCommand::CapturePane { window, lines, json } => {
+            let window = window.map(|w| w.parse::<u64>().map_or_else(|_| serde_json::json!(w), |n| serde_json::json!(n)));
+            let request = serde_json::json!({
+                "method": "capture",
+                "window": window,
+                "lines": lines,
+                "format": if json { "json" } else { "text" },
+            });
+            let result = remote::call(request)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&result["lines"])?);
+            } else {
+                for line in result["lines"].as_array().into_iter().flatten() {
+                    println!("{}", line.as_str().unwrap_or(""));
+                }
+            }
+        }
+        CtlCommand::WaitFor { channel, timeout } => {
+            let mut request = serde_json::json!({ "method": "wait_for", "channel": channel });
+            if let Some(timeout) = timeout {
+                request["timeout_secs"] = serde_json::json!(timeout);
+            }
+            remote::call(request)?;
+        }
+        CtlCommand::Signal { channel } => {
+            remote::call(serde_json::json!({ "method": "signal", "channel": channel }))?;
+        }
+        CtlCommand::Events { events } => {
+            remote::subscribe(&events, |event| println!("{}", event))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+// Counters and gauges for diagnosing long-running sessions: open windows, bytes read
+// from each PTY, frame times and dropped frames. Shown by :stats and, when enabled,
+// served in the Prometheus text format on localhost.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::terminal::window::TerminalWindow;
+
+// Frame timing, updated by the main loop
+#[derive(Default)]
+pub struct Metrics {
+    // Frames drawn
+    pub frames: u64,
+    // Frames that took longer than the frame budget (the tick rate)
+    pub dropped_frames: u64,
+    // Time taken by the last frame
+    pub last_frame: Duration,
+    // Slowest frame so far
+    pub max_frame: Duration,
+    // Time taken by all frames, for the average
+    total_frame: Duration,
+}
+
+impl Metrics {
+    // Record a frame that took `elapsed`, with `budget` to do it in
+    pub fn record_frame(&mut self, elapsed: Duration, budget: Duration) {
+        self.frames += 1;
+        self.last_frame = elapsed;
+        self.max_frame = self.max_frame.max(elapsed);
+        self.total_frame += elapsed;
+        if elapsed > budget {
+            self.dropped_frames += 1;
+        }
+    }
+
+    // Average time per frame
+    pub fn average_frame(&self) -> Duration {
+        match self.frames {
+            0 => Duration::ZERO,
+            frames => self.total_frame / frames as u32,
+        }
+    }
+
+    // Everything in the Prometheus text exposition format
+    pub fn to_prometheus<'a>(&self, windows: impl Iterator<Item = &'a TerminalWindow>) -> String {
+        let mut text = String::new();
+        let mut windows: Vec<_> = windows.collect();
+        windows.sort_by_key(|w| w.id());
+
+        metric(&mut text, "matrix_windows", "gauge", "Open windows");
+        let _ = writeln!(text, "matrix_windows {}", windows.len());
+
+        metric(&mut text, "matrix_pty_read_bytes_total", "counter", "Bytes read from each window's PTY");
+        for window in &windows {
+            let _ = writeln!(
+                text,
+                "matrix_pty_read_bytes_total{{window=\"{}\",title=\"{}\"}} {}",
+                window.id(),
+                label(&window.title),
+                window.bytes_read,
+            );
+        }
+
+        metric(&mut text, "matrix_frames_total", "counter", "Frames drawn");
+        let _ = writeln!(text, "matrix_frames_total {}", self.frames);
+        metric(&mut text, "matrix_dropped_frames_total", "counter", "Frames that took longer than the tick rate");
+        let _ = writeln!(text, "matrix_dropped_frames_total {}", self.dropped_frames);
+        metric(&mut text, "matrix_frame_seconds", "gauge", "Time taken by the last frame");
+        let _ = writeln!(text, "matrix_frame_seconds {}", self.last_frame.as_secs_f64());
+        metric(&mut text, "matrix_frame_seconds_max", "gauge", "Slowest frame");
+        let _ = writeln!(text, "matrix_frame_seconds_max {}", self.max_frame.as_secs_f64());
+
+        text
+    }
+}
+
+// HELP and TYPE lines of a metric
+fn metric(text: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} {}", name, kind);
+}
+
+// Escape a label value
+fn label(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+// Serves the latest metrics snapshot over HTTP on localhost
+pub struct MetricsServer {
+    port: u16,
+    snapshot: Arc<Mutex<String>>,
+}
+
+impl MetricsServer {
+    pub fn start(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("can't listen on port {}", port))?;
+        let port = listener.local_addr()?.port();
+        let snapshot = Arc::new(Mutex::new(String::new()));
+
+        let shared = Arc::clone(&snapshot);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                // Any request gets the metrics; the request itself isn't needed
+                let mut request = [0u8; 1024];
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+                let _ = stream.read(&mut request);
+
+                let body = shared.lock().map(|s| s.clone()).unwrap_or_default();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body,
+                );
+            }
+        });
+
+        Ok(Self { port, snapshot })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // Replace the metrics served
+    pub fn update(&self, text: String) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            *snapshot = text;
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// The local transport, always there: a Unix domain socket in the runtime directory
+// that only the user can reach, carrying one JSON message per line. `matrix ctl`
+// talks to the running Matrix through it, so it needs no token.
+
+use std::path::PathBuf;
+
+use crate::utils::paths;
+
+// Where the running Matrix listens
+pub fn socket_path() -> PathBuf {
+    paths::runtime_dir().join("matrix.sock")
+}
+
+#[cfg(unix)]
+mod unix {
+    use anyhow::{anyhow, bail, Context, Result};
+    use serde_json::{json, Value};
+    use std::fs;
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+    use std::sync::mpsc::Sender;
+    use std::thread;
+
+    use super::super::{serve, Connection, Received, RemoteRequest, Subscribers, MIRROR_INTERVAL};
+    use super::socket_path;
+
+    // Listen on the socket, returning its path
+    pub fn listen(sender: Sender<RemoteRequest>, subscribers: Subscribers) -> Result<PathBuf> {
+        let path = socket_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            // Left behind by a Matrix that didn't exit cleanly, unless it's still running
+            Err(e) if e.kind() == ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).is_ok() {
+                    bail!("another Matrix is listening on {}", path.display());
+                }
+                fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            Err(e) => return Err(e).with_context(|| format!("can't listen on {}", path.display())),
+        };
+        // Only the user may connect
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                let subscribers = subscribers.clone();
+                thread::spawn(move || {
+                    if let Err(e) = LocalConnection::new(stream).and_then(|mut connection| serve(&mut connection, sender, subscribers)) {
+                        eprintln!("Remote control client error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(path)
+    }
+
+    // A client on the socket
+    pub(super) struct LocalConnection {
+        stream: UnixStream,
+        reader: BufReader<UnixStream>,
+        // What arrived of a message whose end hasn't yet
+        line: Vec<u8>,
+    }
+
+    impl LocalConnection {
+        pub(super) fn new(stream: UnixStream) -> Result<Self> {
+            // Stop waiting for messages now and then to push mirror updates and events
+            stream.set_read_timeout(Some(MIRROR_INTERVAL))?;
+            let reader = BufReader::new(stream.try_clone()?);
+            Ok(Self { stream, reader, line: Vec::new() })
+        }
+    }
+
+    impl Connection for LocalConnection {
+        fn receive(&mut self) -> Result<Received> {
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => Ok(Received::Closed),
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&self.line).trim().to_string();
+                    self.line.clear();
+                    Ok(if text.is_empty() { Received::Nothing } else { Received::Message(text) })
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(Received::Nothing),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        fn send(&mut self, message: &Value) -> Result<()> {
+            writeln!(self.stream, "{}", message)?;
+            Ok(())
+        }
+    }
+
+    fn connect() -> Result<UnixStream> {
+        let path = socket_path();
+        UnixStream::connect(&path).with_context(|| format!("can't reach Matrix at {} (is it running?)", path.display()))
+    }
+
+    // Send a request to the running Matrix, returning its result
+    pub fn call(mut request: Value) -> Result<Value> {
+        let stream = connect()?;
+        request["id"] = json!(1);
+        writeln!(&stream, "{}", request)?;
+
+        for line in BufReader::new(&stream).lines() {
+            let reply: Value = serde_json::from_str(&line?)?;
+            if reply.get("id") != Some(&json!(1)) {
+                continue;
+            }
+            return match reply.get("error").and_then(Value::as_str) {
+                Some(error) => Err(anyhow!("{}", error)),
+                None => Ok(reply.get("result").cloned().unwrap_or_default()),
+            };
+        }
+        Err(anyhow!("Matrix closed the connection"))
+    }
+
+    // Subscribe to the events of the running Matrix (all of them if none are named),
+    // handing each to `on_event` until Matrix closes the connection
+    pub fn subscribe(events: &[String], mut on_event: impl FnMut(&Value)) -> Result<()> {
+        let stream = connect()?;
+        let mut request = json!({ "id": 1, "method": "subscribe" });
+        if !events.is_empty() {
+            request["events"] = json!(events);
+        }
+        writeln!(&stream, "{}", request)?;
+
+        for line in BufReader::new(&stream).lines() {
+            let message: Value = serde_json::from_str(&line?)?;
+            if let Some(error) = message.get("error").and_then(Value::as_str) {
+                return Err(anyhow!("{}", error));
+            }
+            if message.get("event").is_some() {
+                on_event(&message);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{call, listen, subscribe};
+
+// Without Unix domain sockets there's no local transport
+#[cfg(not(unix))]
+mod other {
+    use anyhow::{anyhow, Result};
+    use serde_json::Value;
+    use std::path::PathBuf;
+    use std::sync::mpsc::Sender;
+
+    use super::super::{RemoteRequest, Subscribers};
+
+    pub fn listen(_sender: Sender<RemoteRequest>, _subscribers: Subscribers) -> Result<PathBuf> {
+        Err(anyhow!("the local control socket needs Unix domain sockets"))
+    }
+
+    pub fn call(_request: Value) -> Result<Value> {
+        Err(anyhow!("matrix ctl needs Unix domain sockets"))
+    }
+
+    pub fn subscribe(_events: &[String], _on_event: impl FnMut(&Value)) -> Result<()> {
+        Err(anyhow!("matrix ctl needs Unix domain sockets"))
+    }
+}
+
+#[cfg(not(unix))]
+pub use other::{call, listen, subscribe};
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::super::{serve, RemoteRequest, Subscribers};
+    use super::unix::LocalConnection;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn requests_on_the_socket_are_answered_line_by_line() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (sender, requests) = mpsc::channel::<RemoteRequest>();
+        thread::spawn(move || serve(&mut LocalConnection::new(server).unwrap(), sender, Subscribers::default()));
+
+        // A message split across writes is read once it's complete
+        write!(&client, r#"{{"id": 1, "method": "#).unwrap();
+        writeln!(&client, r#""windows"}}"#).unwrap();
+        let request = requests.recv().unwrap();
+        assert_eq!(request.method, "windows");
+        request.reply(Ok(json!([])));
+
+        let mut reply = String::new();
+        BufReader::new(&client).read_line(&mut reply).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&reply).unwrap(), json!({ "id": 1, "result": [] }));
+    }
+}
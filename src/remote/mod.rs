@@ -0,0 +1,606 @@
+// Remote control: scripts and companion tools (stream decks, web dashboards) list
+// windows, read the layout and send input.
+//
+// Matrix always listens on a local socket only the user can reach (local.rs), which
+// `matrix ctl` uses. Built with the "remote" feature and with remote.enabled, it also
+// serves a WebSocket on localhost for clients holding a token (websocket.rs). Both
+// carry the same JSON messages:
+//
+//   {"id": 1, "method": "windows"}
+//   {"id": 2, "method": "layout"}
+//   {"id": 3, "method": "send_input", "window": "<uuid>", "text": "ls\n"}
+//   {"id": 4, "method": "focus", "window": "<uuid>"}
+//   {"id": 5, "method": "capture", "window": 2, "lines": 200, "format": "json"}
+//
+// and get back {"id": 1, "result": ...} or {"id": 1, "error": "..."}. Windows are
+// given by ID or by number in the layout (from 1); the focused one is the default.
+// capture returns a window's last lines (a screenful by default) as text, or with
+// "format": "json" as {"text", "styles"} objects; secrets are masked.
+//
+// Scripts in different windows can wait for each other, like tmux's wait-for:
+//
+//   {"id": 6, "method": "wait_for", "channel": "build", "timeout_secs": 600}
+//   {"id": 7, "method": "signal", "channel": "build"}
+//
+// wait_for is answered once the channel is signaled (by a client or :signal-channel),
+// or with an error after timeout_secs (an hour by default); the client is sent
+// events, mirror updates and other replies in the meantime. A signal nobody was
+// waiting for lets the next wait_for through at once.
+//
+// A client can also mirror a window, read-only, to show it elsewhere:
+//
+//   {"id": 8, "method": "mirror", "window": "<uuid>", "keyframe_secs": 5}
+//   {"id": 9, "method": "unmirror"}
+//
+// While mirroring, Matrix pushes the window's screen as it changes:
+//
+//   {"mirror": "<uuid>", "seq": 7, "keyframe": false, "title": "...", "top": 1200,
+//    "rows": 40, "lines": {"1238": "$ ls", "1239": "Cargo.toml  src"}}
+//
+// Lines are keyed by absolute line number, which stays the same as the screen
+// scrolls, and the screen shows lines top..top+rows. Only lines that changed
+// are sent; a keyframe sends every line and replaces what the client has.
+// Keyframes are sent first and then every keyframe_secs. {"mirror": "<uuid>",
+// "closed": true} is sent when the window closes.
+//
+// A client can subscribe to events, all of them or those listed:
+//
+//   {"id": 10, "method": "subscribe", "events": ["focus-changed", "process-exited"]}
+//   {"id": 11, "method": "unsubscribe"}
+//
+// and is then sent them as they happen:
+//
+//   {"event": "window-created", "window": "<uuid>", "title": "..."}
+//   {"event": "window-closed", "window": "<uuid>"}
+//   {"event": "focus-changed", "window": "<uuid>", "previous": "<uuid>"}
+//   {"event": "output-activity", "window": "<uuid>"}   (at most once a second per window)
+//   {"event": "process-exited", "window": "<uuid>", "code": 0}
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::terminal::window::{TerminalWindow, WindowState};
+use crate::ui::window_manager::{LayoutNode, WindowManager};
+
+mod local;
+#[cfg(feature = "remote")]
+mod websocket;
+
+pub use local::{call, subscribe};
+#[cfg(feature = "remote")]
+pub use websocket::load_or_create_token;
+
+// How long a connection waits for the application to answer a request
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long wait_for waits for its channel, unless the client asks otherwise
+const DEFAULT_WAIT_SECS: u64 = 60 * 60;
+
+// How often a mirrored window is checked for changes
+const MIRROR_INTERVAL: Duration = Duration::from_millis(100);
+
+// Time between keyframes of a mirrored window, unless the client asks otherwise
+const DEFAULT_KEYFRAME_SECS: u64 = 5;
+
+// Least time between output-activity events for a window
+const ACTIVITY_INTERVAL: Duration = Duration::from_secs(1);
+
+// Where events are sent: one channel per subscribed client
+type Subscribers = Arc<Mutex<Vec<Sender<Value>>>>;
+
+// A request from a client, answered by the application
+pub struct RemoteRequest {
+    pub method: String,
+    pub params: Value,
+    reply: Sender<Result<Value>>,
+}
+
+impl RemoteRequest {
+    // Send the result back to the client, returning whether it was still waiting
+    // (it may have disconnected or given up in the meantime)
+    pub fn reply(self, result: Result<Value>) -> bool {
+        self.reply.send(result).is_ok()
+    }
+}
+
+// Clients waiting on channels until something signals them
+#[derive(Default)]
+pub struct Channels {
+    waiting: HashMap<String, Vec<RemoteRequest>>,
+    // Signaled while nobody was waiting; the next wait ends at once
+    signaled: HashSet<String>,
+}
+
+impl Channels {
+    // Answer a wait_for request once its channel is signaled
+    pub fn wait(&mut self, channel: &str, request: RemoteRequest) {
+        if self.signaled.remove(channel) {
+            request.reply(Ok(Value::Null));
+        } else {
+            self.waiting.entry(channel.to_string()).or_default().push(request);
+        }
+    }
+
+    // Signal a channel, returning how many clients stop waiting
+    pub fn signal(&mut self, channel: &str) -> usize {
+        let waiting = self.waiting.remove(channel).unwrap_or_default();
+        let count = waiting.into_iter().map(|request| request.reply(Ok(Value::Null))).filter(|woken| *woken).count();
+        // Clients that timed out don't count; the next wait_for gets the signal instead
+        if count == 0 {
+            self.signaled.insert(channel.to_string());
+        }
+        count
+    }
+}
+
+
+// Where requests from every transport meet, for the application to answer
+pub struct RemoteServer {
+    requests: Receiver<RemoteRequest>,
+    sender: Sender<RemoteRequest>,
+    subscribers: Subscribers,
+    // The local socket, removed when Matrix exits
+    socket: Option<PathBuf>,
+}
+
+impl Default for RemoteServer {
+    fn default() -> Self {
+        let (sender, requests) = mpsc::channel();
+        Self { requests, sender, subscribers: Subscribers::default(), socket: None }
+    }
+}
+
+impl RemoteServer {
+    // Listen on the local socket
+    pub fn listen_local(&mut self) -> Result<()> {
+        self.socket = Some(local::listen(self.sender.clone(), self.subscribers.clone())?);
+        Ok(())
+    }
+
+    // Also listen for WebSocket clients on localhost that present the token,
+    // returning the port listened on
+    #[cfg(feature = "remote")]
+    pub fn listen_websocket(&self, port: u16, token: String) -> Result<u16> {
+        websocket::listen(port, token, self.sender.clone(), self.subscribers.clone())
+    }
+
+    // Take the requests received since the last call
+    pub fn take_requests(&self) -> Vec<RemoteRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    // Send events to the subscribed clients, forgetting those that disconnected
+    pub fn publish(&self, events: Vec<Value>) {
+        if events.is_empty() {
+            return;
+        }
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|subscriber| events.iter().all(|event| subscriber.send(event.clone()).is_ok()));
+        }
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        if let Some(socket) = self.socket.take() {
+            let _ = std::fs::remove_file(socket);
+        }
+    }
+}
+
+// What the windows were like when events were last looked for
+#[derive(Default)]
+pub struct EventWatcher {
+    // Bytes read, whether exited, and the last output-activity event of each window
+    windows: HashMap<uuid::Uuid, (u64, bool, Option<Instant>)>,
+    focused: Option<uuid::Uuid>,
+}
+
+impl EventWatcher {
+    // The events since the last call
+    pub fn events(&mut self, window_manager: &WindowManager) -> Vec<Value> {
+        let mut events = Vec::new();
+        let windows = window_manager.windows();
+
+        self.windows.retain(|id, _| {
+            let open = windows.contains_key(id);
+            if !open {
+                events.push(json!({ "event": "window-closed", "window": id.to_string() }));
+            }
+            open
+        });
+        for (id, window) in windows {
+            let exited = matches!(window.state(), WindowState::Exited(_));
+            let Some((bytes_read, was_exited, last_activity)) = self.windows.get_mut(id) else {
+                events.push(json!({ "event": "window-created", "window": id.to_string(), "title": window.title }));
+                self.windows.insert(*id, (window.bytes_read, exited, None));
+                continue;
+            };
+            if window.bytes_read > *bytes_read && last_activity.is_none_or(|t| t.elapsed() >= ACTIVITY_INTERVAL) {
+                events.push(json!({ "event": "output-activity", "window": id.to_string() }));
+                *bytes_read = window.bytes_read;
+                *last_activity = Some(Instant::now());
+            }
+            if let (WindowState::Exited(code), false) = (window.state(), *was_exited) {
+                events.push(json!({ "event": "process-exited", "window": id.to_string(), "code": code }));
+            }
+            *was_exited = exited;
+        }
+
+        let focused = window_manager.focused_window().map(|w| w.id());
+        if focused != self.focused {
+            events.push(json!({
+                "event": "focus-changed",
+                "window": focused.map(|id| id.to_string()),
+                "previous": self.focused.map(|id| id.to_string()),
+            }));
+            self.focused = focused;
+        }
+        events
+    }
+}
+
+// A client's subscription to events
+struct Subscription {
+    events: Receiver<Value>,
+    // Events sent, all if None
+    wanted: Option<Vec<String>>,
+}
+
+
+// A client connection, whichever transport it came over
+trait Connection {
+    // The next message from the client, waiting at most MIRROR_INTERVAL for one
+    fn receive(&mut self) -> Result<Received>;
+    fn send(&mut self, message: &Value) -> Result<()>;
+}
+
+// What a connection received
+enum Received {
+    Message(String),
+    // Nothing yet; a chance to push mirror updates and events
+    Nothing,
+    Closed,
+}
+
+// Handle one client until it disconnects
+fn serve(connection: &mut impl Connection, sender: Sender<RemoteRequest>, subscribers: Subscribers) -> Result<()> {
+    let mut mirror: Option<Mirror> = None;
+    let mut subscription: Option<Subscription> = None;
+    let mut waits: Vec<Wait> = Vec::new();
+    
+    loop {
+        match connection.receive()? {
+            Received::Message(text) => {
+                let reply = match serde_json::from_str::<Value>(&text) {
+                    // Answered below once the channel is signaled, without holding up the rest
+                    Ok(message) if message.get("method").and_then(Value::as_str) == Some("wait_for") => {
+                        let id = message.get("id").cloned().unwrap_or(Value::Null);
+                        match Wait::start(message, &sender) {
+                            Ok(wait) => {
+                                waits.push(wait);
+                                None
+                            }
+                            Err(e) => Some(json!({ "id": id, "error": e.to_string() })),
+                        }
+                    }
+                    Ok(message) => {
+                        let id = message.get("id").cloned().unwrap_or(Value::Null);
+                        Some(match handle_message(message, &sender, &mut mirror, &mut subscription, &subscribers) {
+                            Ok(result) => json!({ "id": id, "result": result }),
+                            Err(e) => json!({ "id": id, "error": e.to_string() }),
+                        })
+                    }
+                    Err(e) => Some(json!({ "id": null, "error": format!("invalid JSON: {}", e) })),
+                };
+                if let Some(reply) = reply {
+                    connection.send(&reply)?;
+                }
+            }
+            Received::Nothing => {}
+            Received::Closed => return Ok(()),
+        }
+        
+        // Answer the waits that are over
+        let mut ended = Vec::new();
+        waits.retain_mut(|wait| match wait.poll() {
+            Some(reply) => {
+                ended.push(reply);
+                false
+            }
+            None => true,
+        });
+        for reply in ended {
+            connection.send(&reply)?;
+        }
+        
+        // Pass on the events the client subscribed to
+        if let Some(current) = &subscription {
+            for event in current.events.try_iter() {
+                let name = event.get("event").and_then(Value::as_str).unwrap_or("");
+                if current.wanted.as_ref().is_none_or(|wanted| wanted.iter().any(|w| w == name)) {
+                    connection.send(&event)?;
+                }
+            }
+        }
+        
+        // Bring a mirroring client up to date
+        if let Some(current) = &mut mirror {
+            if current.last_check.elapsed() >= MIRROR_INTERVAL {
+                current.last_check = Instant::now();
+                let screen = handle(json!({ "method": "screen", "window": current.window }), &sender);
+                let update = match screen {
+                    Ok(screen) => current.update(&screen),
+                    Err(_) => {
+                        let closed = json!({ "mirror": current.window, "closed": true });
+                        mirror = None;
+                        Some(closed)
+                    }
+                };
+                if let Some(update) = update {
+                    connection.send(&update)?;
+                }
+            }
+        }
+    }
+}
+
+// Answer a client's message, starting and stopping mirroring and subscriptions here
+// and passing everything else to the application
+fn handle_message(
+    message: Value,
+    sender: &Sender<RemoteRequest>,
+    mirror: &mut Option<Mirror>,
+    subscription: &mut Option<Subscription>,
+    subscribers: &Subscribers,
+) -> Result<Value> {
+    match message.get("method").and_then(Value::as_str) {
+        Some("subscribe") => {
+            let wanted = message.get("events").and_then(Value::as_array).map(|events| {
+                events.iter().filter_map(Value::as_str).map(str::to_string).collect()
+            });
+            let (events_sender, events) = mpsc::channel();
+            subscribers.lock().map_err(|_| anyhow!("Matrix is shutting down"))?.push(events_sender);
+            *subscription = Some(Subscription { events, wanted });
+            Ok(Value::Null)
+        }
+        Some("unsubscribe") => {
+            // Dropping the receiver drops the sender from the subscribers at the next event
+            *subscription = None;
+            Ok(Value::Null)
+        }
+        Some("mirror") => {
+            // The screen tells which window is mirrored when none was given
+            let screen = handle(json!({ "method": "screen", "window": message.get("window") }), sender)?;
+            let window = screen.get("window")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("no window to mirror"))?
+                .to_string();
+            let keyframe_secs = message.get("keyframe_secs")
+                .and_then(Value::as_u64)
+                .unwrap_or(DEFAULT_KEYFRAME_SECS)
+                .max(1);
+            *mirror = Some(Mirror::new(window.clone(), Duration::from_secs(keyframe_secs)));
+            Ok(json!({ "mirroring": window }))
+        }
+        Some("unmirror") => {
+            *mirror = None;
+            Ok(Value::Null)
+        }
+        _ => handle(message, sender),
+    }
+}
+
+// Pass a request to the application and wait for its answer
+fn handle(message: Value, sender: &Sender<RemoteRequest>) -> Result<Value> {
+    let answer = send(message, sender)?;
+    answer.recv_timeout(REPLY_TIMEOUT).map_err(|_| anyhow!("no reply from Matrix"))?
+}
+
+// Pass a request to the application, returning where its answer will arrive
+fn send(message: Value, sender: &Sender<RemoteRequest>) -> Result<Receiver<Result<Value>>> {
+    let method = message.get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("missing method"))?
+        .to_string();
+
+    let (reply, answer) = mpsc::channel();
+    sender.send(RemoteRequest { method, params: message, reply })
+        .map_err(|_| anyhow!("Matrix is shutting down"))?;
+    Ok(answer)
+}
+
+// A client's wait_for, answered when the application replies or the time is up
+struct Wait {
+    id: Value,
+    channel: String,
+    answer: Receiver<Result<Value>>,
+    deadline: Instant,
+}
+
+impl Wait {
+    fn start(message: Value, sender: &Sender<RemoteRequest>) -> Result<Self> {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let channel = message.get("channel").and_then(Value::as_str).unwrap_or("").to_string();
+        let secs = message.get("timeout_secs").and_then(Value::as_u64).unwrap_or(DEFAULT_WAIT_SECS);
+        let answer = send(message, sender)?;
+        Ok(Self { id, channel, answer, deadline: Instant::now() + Duration::from_secs(secs) })
+    }
+
+    // The reply to send the client, once the wait is over
+    fn poll(&self) -> Option<Value> {
+        let result = match self.answer.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("Matrix is shutting down")),
+            // Dropping the receiver lets a later signal go to the next wait_for instead
+            Err(TryRecvError::Empty) if Instant::now() >= self.deadline => {
+                Err(anyhow!("timed out waiting for {}", self.channel))
+            }
+            Err(TryRecvError::Empty) => return None,
+        };
+        Some(match result {
+            Ok(result) => json!({ "id": self.id, "result": result }),
+            Err(e) => json!({ "id": self.id, "error": e.to_string() }),
+        })
+    }
+}
+
+// A window mirrored to a client, and what the client was last sent
+struct Mirror {
+    window: String,
+    keyframe_interval: Duration,
+    // Lines the client has, by absolute line number
+    lines: HashMap<u64, String>,
+    title: String,
+    top: u64,
+    rows: usize,
+    seq: u64,
+    // None until the first keyframe is sent
+    last_keyframe: Option<Instant>,
+    last_check: Instant,
+}
+
+impl Mirror {
+    fn new(window: String, keyframe_interval: Duration) -> Self {
+        Self {
+            window,
+            keyframe_interval,
+            lines: HashMap::new(),
+            title: String::new(),
+            top: 0,
+            rows: 0,
+            seq: 0,
+            last_keyframe: None,
+            // Check right away, for the first keyframe
+            last_check: Instant::now() - MIRROR_INTERVAL,
+        }
+    }
+    
+    // The message bringing the client from what it has to a screen, if anything changed
+    fn update(&mut self, screen: &Value) -> Option<Value> {
+        let title = screen.get("title").and_then(Value::as_str).unwrap_or("").to_string();
+        let top = screen.get("top").and_then(Value::as_u64).unwrap_or(0);
+        let shown: Vec<&str> = screen.get("lines")
+            .and_then(Value::as_array)
+            .map(|lines| lines.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        
+        let keyframe = self.last_keyframe.is_none_or(|t| t.elapsed() >= self.keyframe_interval);
+        if keyframe {
+            self.last_keyframe = Some(Instant::now());
+            self.lines.clear();
+        }
+        
+        // Lines the client doesn't have yet or has different
+        let mut changed = Map::new();
+        for (number, line) in (top..).zip(&shown) {
+            if self.lines.get(&number).map(String::as_str) != Some(*line) {
+                changed.insert(number.to_string(), Value::from(*line));
+                self.lines.insert(number, line.to_string());
+            }
+        }
+        // Lines scrolled off the screen can be forgotten; the client drops them too
+        let end = top + shown.len() as u64;
+        self.lines.retain(|number, _| (top..end).contains(number));
+        
+        if !keyframe && changed.is_empty() && title == self.title && top == self.top && shown.len() == self.rows {
+            return None;
+        }
+        self.title = title;
+        self.top = top;
+        self.rows = shown.len();
+        self.seq += 1;
+        
+        Some(json!({
+            "mirror": self.window,
+            "seq": self.seq,
+            "keyframe": keyframe,
+            "title": self.title,
+            "top": self.top,
+            "rows": self.rows,
+            "lines": changed,
+        }))
+    }
+}
+
+// The lines a window shows, for mirroring
+pub fn screen_json(window: &TerminalWindow) -> Value {
+    json!({
+        "window": window.id().to_string(),
+        "title": window.title,
+        "top": window.buffer.visible_range().start,
+        "lines": window.buffer.visible_lines(),
+    })
+}
+
+// A window as listed to clients
+pub fn window_json(window: &TerminalWindow) -> Value {
+    let rect = window.size();
+    json!({
+        "id": window.id().to_string(),
+        "title": window.title,
+        "focused": window.is_focused(),
+        "state": format!("{:?}", window.state()),
+        "group": window.group,
+        "rect": { "x": rect.x, "y": rect.y, "width": rect.width, "height": rect.height },
+    })
+}
+
+// The layout tree as sent to clients
+pub fn layout_json(node: &LayoutNode) -> Value {
+    match node {
+        LayoutNode::Window { id, .. } => json!({ "window": id.to_string() }),
+        LayoutNode::Split { direction, ratio, first, second, .. } => json!({
+            "split": format!("{:?}", direction).to_lowercase(),
+            "ratio": ratio,
+            "first": layout_json(first),
+            "second": layout_json(second),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str) -> (RemoteRequest, Receiver<Result<Value>>) {
+        let (reply, answer) = mpsc::channel();
+        (RemoteRequest { method: method.to_string(), params: Value::Null, reply }, answer)
+    }
+
+    #[test]
+    fn signals_skip_waits_that_timed_out() {
+        let mut channels = Channels::default();
+        let (gone, answer) = request("wait_for");
+        channels.wait("build", gone);
+        drop(answer);
+
+        // Nobody is still waiting, so the signal lets the next wait_for through
+        assert_eq!(channels.signal("build"), 0);
+        let (next, answer) = request("wait_for");
+        channels.wait("build", next);
+        assert!(answer.try_recv().unwrap().is_ok());
+
+        let (waiting, answer) = request("wait_for");
+        channels.wait("build", waiting);
+        assert!(answer.try_recv().is_err());
+        assert_eq!(channels.signal("build"), 1);
+        assert!(answer.try_recv().unwrap().is_ok());
+    }
+
+    #[test]
+    fn waits_end_with_an_error_when_time_is_up() {
+        let (sender, requests) = mpsc::channel();
+        let message = json!({ "id": 3, "method": "wait_for", "channel": "build", "timeout_secs": 0 });
+        let wait = Wait::start(message, &sender).unwrap();
+        // Still with the application, which hasn't been signaled
+        let pending = requests.try_recv().unwrap();
+        assert_eq!(pending.method, "wait_for");
+        assert_eq!(wait.poll(), Some(json!({ "id": 3, "error": "timed out waiting for build" })));
+    }
+}
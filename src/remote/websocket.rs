@@ -0,0 +1,124 @@
+// The WebSocket transport, for companion tools: clients connect to
+// ws://127.0.0.1:<port>/?token=<token> (or send the token as "Authorization: Bearer
+// <token>") and exchange the messages described in mod.rs as text frames.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::thread;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use super::{serve, Connection, Received, RemoteRequest, Subscribers, MIRROR_INTERVAL};
+use crate::utils::paths;
+
+// Listen on localhost, accepting clients that present the token; returns the port
+pub fn listen(port: u16, token: String, sender: Sender<RemoteRequest>, subscribers: Subscribers) -> Result<u16> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("can't listen on port {}", port))?;
+    let port = listener.local_addr()?.port();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            let token = token.clone();
+            let subscribers = subscribers.clone();
+            thread::spawn(move || {
+                if let Err(e) = accept(stream, &token).and_then(|mut socket| serve(&mut socket, sender, subscribers)) {
+                    eprintln!("Remote control client error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(port)
+}
+
+// Location of the token file used when no token is configured
+fn token_path() -> PathBuf {
+    paths::state_dir().join("remote-token")
+}
+
+// The token in the token file, created with a random token if there is none
+pub fn load_or_create_token() -> Result<String> {
+    let path = token_path();
+    if let Ok(token) = fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, &token)?;
+
+    // Only the user may read it
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(token)
+}
+
+
+// Whether a handshake request carries the token, in the query string or as a bearer token
+fn authorized(request: &Request, token: &str) -> bool {
+    let in_query = request.uri().query().is_some_and(|query| {
+        query.split('&').any(|pair| pair.strip_prefix("token=") == Some(token))
+    });
+    let in_header = request.headers().get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| value == token);
+    in_query || in_header
+}
+
+
+// Complete the handshake of a client presenting the token
+// (the handshake callback's error type is tungstenite's, an HTTP response)
+#[allow(clippy::result_large_err)]
+fn accept(stream: TcpStream, token: &str) -> Result<WebSocket<TcpStream>> {
+    let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        if authorized(request, token) {
+            Ok(response)
+        } else {
+            let mut error = ErrorResponse::new(Some(String::from("invalid token")));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    })
+    .map_err(|e| anyhow!("handshake failed: {}", e))?;
+
+    // Stop waiting for messages now and then to push mirror updates and events
+    socket.get_ref().set_read_timeout(Some(MIRROR_INTERVAL))?;
+    Ok(socket)
+}
+
+impl Connection for WebSocket<TcpStream> {
+    fn receive(&mut self) -> Result<Received> {
+        match self.read() {
+            Ok(Message::Text(text)) => Ok(Received::Message(text)),
+            Ok(Message::Close(_)) => Ok(Received::Closed),
+            // Pings are answered by tungstenite
+            Ok(_) => Ok(Received::Nothing),
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Ok(Received::Nothing)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn send(&mut self, message: &Value) -> Result<()> {
+        WebSocket::send(self, Message::Text(message.to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+// Sessions: the layout, window titles, directories, last commands and recent
+// scrollback, saved to disk so a crashed or closed Matrix can be brought back the
+// way it was.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::terminal::redact::Redactor;
+use crate::ui::window_manager::{LayoutNode, SplitDirection, WindowManager};
+use crate::utils::paths;
+
+// Layout tree with windows referred to by their index in the session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SessionLayout {
+    Window(usize),
+    Split {
+        direction: SplitDirection,
+        ratio: f32,
+        first: Box<SessionLayout>,
+        second: Box<SessionLayout>,
+    },
+}
+
+impl SessionLayout {
+    // Convert a layout tree, numbering windows by their position in `ids`
+    pub fn from_layout(node: &LayoutNode, ids: &[Uuid]) -> Option<Self> {
+        match node {
+            LayoutNode::Window { id, .. } => ids.iter().position(|i| i == id).map(SessionLayout::Window),
+            LayoutNode::Split { direction, ratio, first, second, .. } => Some(SessionLayout::Split {
+                direction: *direction,
+                ratio: *ratio,
+                first: Box::new(Self::from_layout(first, ids)?),
+                second: Box::new(Self::from_layout(second, ids)?),
+            }),
+        }
+    }
+
+    // Build a layout tree for the windows restored with the given ids
+    pub fn to_layout(&self, ids: &[Uuid], area: Rect) -> Option<LayoutNode> {
+        match self {
+            SessionLayout::Window(index) => ids.get(*index).map(|id| LayoutNode::window(*id, area)),
+            SessionLayout::Split { direction, ratio, first, second } => Some(LayoutNode::split(
+                *direction,
+                *ratio,
+                first.to_layout(ids, area)?,
+                second.to_layout(ids, area)?,
+                area,
+            )),
+        }
+    }
+}
+
+// A saved window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub title: String,
+    // The title was set by the user rather than following the foreground program
+    #[serde(default)]
+    pub manual_title: bool,
+    // Broadcast group
+    #[serde(default)]
+    pub group: Option<String>,
+    // Directory its shell was in
+    #[serde(default)]
+    pub dir: Option<String>,
+    // Last command run at its prompt, or else what it was started to run
+    #[serde(default)]
+    pub command: Option<String>,
+    // Recent output, lz4 compressed and base64 encoded
+    scrollback: String,
+}
+
+impl SessionWindow {
+    // Decompress the saved output
+    pub fn scrollback(&self) -> Result<String> {
+        let compressed = STANDARD.decode(&self.scrollback)?;
+        let data = lz4_flex::decompress_size_prepended(&compressed)
+            .map_err(|e| anyhow!("corrupt scrollback: {}", e))?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub windows: Vec<SessionWindow>,
+    pub layout: Option<SessionLayout>,
+    // Index of the focused window
+    pub focused: Option<usize>,
+}
+
+impl Session {
+    // Capture the windows and layout, keeping up to `max_scrollback_bytes` of output
+    // per window with secrets masked
+    pub fn capture(window_manager: &WindowManager, max_scrollback_bytes: usize, redactor: &Redactor) -> Self {
+        // Windows in layout order, then any that aren't in the layout
+        let mut ids = window_manager.layout().map(|l| l.window_ids()).unwrap_or_default();
+        let mut others: Vec<Uuid> = window_manager.windows().keys()
+            .filter(|id| !ids.contains(id))
+            .cloned()
+            .collect();
+        others.sort();
+        ids.extend(others);
+
+        let windows = ids.iter()
+            .filter_map(|id| window_manager.windows().get(id))
+            .map(|window| {
+                let text = window.buffer.scrollback_text(max_scrollback_bytes);
+                let text = redactor.redact(&text);
+                SessionWindow {
+                    title: window.title.clone(),
+                    manual_title: !window.auto_title,
+                    group: window.group.clone(),
+                    dir: window.current_dir(),
+                    command: window.last_command.as_ref().or(window.command.as_ref())
+                        .map(|command| redactor.redact(command).into_owned()),
+                    scrollback: STANDARD.encode(lz4_flex::compress_prepend_size(text.as_bytes())),
+                }
+            })
+            .collect();
+
+        let focused = window_manager.focused_window()
+            .and_then(|w| ids.iter().position(|id| *id == w.id()));
+
+        Self {
+            windows,
+            layout: window_manager.layout().and_then(|l| SessionLayout::from_layout(l, &ids)),
+            focused,
+        }
+    }
+
+    // Path of a named session file; a name can't lead out of the sessions directory
+    pub fn path(name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(anyhow!("invalid session name: {}", name));
+        }
+        Ok(paths::state_dir().join("sessions").join(format!("{}.json", name)))
+    }
+
+    // Save the session under a name
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = Self::path(name)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so a crash never leaves a half-written session
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec(self)?)?;
+        fs::rename(&temp, &path)?;
+
+        Ok(())
+    }
+
+    // Load a named session
+    pub fn load(name: &str) -> Result<Self> {
+        let data = fs::read(Self::path(name)?)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_names_stay_in_the_sessions_directory() {
+        assert!(Session::path("work").unwrap().ends_with("sessions/work.json"));
+        for name in ["../../x", "a/b", "a\\b", "..", ""] {
+            assert!(Session::path(name).is_err(), "{} was accepted", name);
+        }
+    }
+}
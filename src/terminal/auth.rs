@@ -0,0 +1,107 @@
+// Checking the user's password to unlock the screen. By default the password is
+// given to su for the current user, so the system's own authentication (PAM on
+// Linux and macOS) decides. A command can be configured instead: it gets the
+// password on standard input and unlocks the screen by exiting successfully.
+
+use anyhow::{anyhow, bail, Result};
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long su may take to ask for the password, and to check it (a wrong
+// password is usually answered after a deliberate delay)
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Whether the password is the current user's; errors mean it couldn't be checked
+pub fn verify_password(password: &str, command: &str) -> Result<bool> {
+    if command.trim().is_empty() {
+        verify_with_su(password)
+    } else {
+        verify_with_command(password, command)
+    }
+}
+
+fn verify_with_command(password: &str, command: &str) -> Result<bool> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may exit without reading it
+        let _ = writeln!(stdin, "{}", password);
+    }
+    Ok(child.wait()?.success())
+}
+
+// su reads the password from its terminal, so it's run in a pseudoterminal of its own
+fn verify_with_su(password: &str) -> Result<bool> {
+    let user = current_user().ok_or_else(|| anyhow!("the current user is not known"))?;
+
+    let pty = native_pty_system().openpty(PtySize { rows: 24, cols: 80, pixel_width: 0, pixel_height: 0 })?;
+    let mut cmd = CommandBuilder::new("su");
+    cmd.args([user.as_str(), "-c", "true"]);
+    let mut child = pty.slave.spawn_command(cmd)?;
+    drop(pty.slave);
+
+    // Read what su writes on a thread, so waiting for the prompt can time out
+    let mut reader = pty.master.try_clone_reader()?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 256];
+        while let Ok(n) = reader.read(&mut buffer) {
+            if n == 0 || sender.send(buffer[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Wait for the prompt ("Password:", in whatever language)
+    let deadline = Instant::now() + PROMPT_TIMEOUT;
+    let mut output = Vec::new();
+    while !output.contains(&b':') {
+        if child.try_wait()?.is_some() {
+            // Finished without asking, e.g. for root: no password was checked at all
+            bail!("su did not ask for a password; set lock.auth_command to check it another way");
+        }
+        match receiver.recv_timeout(Duration::from_millis(50)) {
+            Ok(chunk) => output.extend(chunk),
+            Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => {}
+            Err(_) => {
+                let _ = child.kill();
+                bail!("su did not ask for a password; set lock.auth_command to check it another way");
+            }
+        }
+    }
+
+    let mut writer = pty.master.take_writer()?;
+    writer.write_all(password.as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+
+    let deadline = Instant::now() + CHECK_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.success());
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            bail!("su did not finish checking the password");
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// Name of the user running Matrix
+fn current_user() -> Option<String> {
+    if let Some(user) = std::env::var("USER").ok().filter(|user| !user.is_empty()) {
+        return Some(user);
+    }
+    let output = Command::new("id").arg("-un").output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|user| !user.is_empty())
+}
@@ -1,34 +1,287 @@
 use anyhow::Result;
-use std::collections::VecDeque;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use std::cmp::{min, max};
 use std::ops::Range;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+use crate::terminal::charset::Charset;
+use crate::terminal::clipboard::{self, ClipboardRequest};
+use crate::terminal::export;
+use crate::terminal::keyboard::{KeyboardMode, KITTY_SUPPORTED_FLAGS};
+use crate::terminal::process;
+use crate::terminal::style::{self, CellStyle, StyleRun};
+
+// Maximum depth of the kitty keyboard flag stack
+const KITTY_STACK_LIMIT: usize = 16;
+
+// Most shell commands remembered per buffer
+const MAX_COMMANDS: usize = 1000;
+
+// Most titles saved on the title stack, as in xterm
+const MAX_TITLE_STACK: usize = 10;
+
+// Number of lines compressed together when scrollback is compressed
+const CHUNK_LINES: usize = 1000;
+
+// Longest escape sequence kept waiting for the rest of it (an OSC 52 copy can be long);
+// a longer one is dropped
+const MAX_PENDING_SEQUENCE: usize = 1 << 20;
+
+// A block of old scrollback lines kept lz4 compressed
+struct CompressedChunk {
+    // Lines joined by newlines, compressed
+    data: Vec<u8>,
+    // Number of lines in the chunk
+    lines: usize,
+    // Size of the lines before compression
+    raw_bytes: usize,
+    // Style runs of the lines that have any, by line index in the chunk
+    styles: Vec<(usize, Vec<StyleRun>)>,
+}
+
+impl CompressedChunk {
+    fn compress(lines: Vec<String>, styles: Vec<Vec<StyleRun>>) -> Self {
+        let text = lines.join("\n");
+        Self {
+            data: lz4_flex::compress_prepend_size(text.as_bytes()),
+            lines: lines.len(),
+            raw_bytes: text.len(),
+            styles: styles.into_iter().enumerate().filter(|(_, runs)| !runs.is_empty()).collect(),
+        }
+    }
+    
+    fn decompress(&self) -> Vec<String> {
+        let data = lz4_flex::decompress_size_prepended(&self.data).unwrap_or_default();
+        String::from_utf8_lossy(&data).split('\n').map(String::from).collect()
+    }
+    
+    // Style runs of every line in the chunk
+    fn line_styles(&self) -> Vec<Vec<StyleRun>> {
+        let mut styles = vec![Vec::new(); self.lines];
+        for (index, runs) in &self.styles {
+            styles[*index] = runs.clone();
+        }
+        styles
+    }
+}
+
+// A regular expression match in the buffer
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    // Line number, counting from the oldest line held
+    pub line: usize,
+    // Byte range of the match within the line
+    pub range: Range<usize>,
+    // The whole line
+    pub text: String,
+}
+
+// Kinds of marked lines, shown on the scrollbar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkKind {
+    // A shell prompt (OSC 133 ; A)
+    Prompt,
+    // A search match
+    Search,
+    // A line matched by a trigger
+    Trigger,
+}
+
+// A command run at a shell prompt, as reported by shell integration (OSC 133)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandRun {
+    // Absolute number of the prompt's line (see `visible_range`)
+    pub line: u64,
+    // When the command started running (OSC 133 ; C)
+    pub started: Option<Instant>,
+    // How long it ran, once it finished (OSC 133 ; D)
+    pub duration: Option<Duration>,
+    // Exit status, if the shell reported one
+    pub exit_code: Option<i32>,
+}
+
+impl CommandRun {
+    // Finished without an error
+    pub fn succeeded(&self) -> bool {
+        self.exit_code.unwrap_or(0) == 0
+    }
+    
+    // Status and duration of a finished command, e.g. "✓ 0.4s" or "✗ 2 · 1m 5s"
+    pub fn badge(&self) -> Option<String> {
+        let duration = self.duration?;
+        let time = if duration.as_secs() >= 60 {
+            format!("{}m {}s", duration.as_secs() / 60, duration.as_secs() % 60)
+        } else {
+            format!("{:.1}s", duration.as_secs_f32())
+        };
+        Some(match self.exit_code {
+            Some(code) if code != 0 => format!("✗ {} · {}", code, time),
+            _ => format!("✓ {}", time),
+        })
+    }
+}
+
+// Memory use of a buffer's scrollback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollbackStats {
+    // Lines held, compressed or not
+    pub total_lines: usize,
+    // Lines in the uncompressed working set
+    pub uncompressed_lines: usize,
+    pub compressed_chunks: usize,
+    // Size of the compressed chunks
+    pub compressed_bytes: usize,
+    // Size of the compressed lines before compression
+    pub raw_bytes: usize,
+}
+
+// Lines changed since the renderer last looked
+#[derive(Debug)]
+pub enum Damage {
+    // Any line may have changed (e.g. the screen was cleared)
+    All,
+    // Changed lines by absolute number (see `visible_range`)
+    Lines(HashSet<u64>),
+}
 
 // Terminal buffer to store and manage terminal output
 pub struct TerminalBuffer {
     lines: VecDeque<String>,
+    // Style runs of each line, aligned with `lines` from the front (may be shorter)
+    styles: VecDeque<Vec<StyleRun>>,
+    // Style set by the last SGR sequences, used for new characters
+    style: CellStyle,
     // Current cursor position (row, column)
     cursor_pos: (usize, usize),
     // Maximum number of lines to keep in history
     max_lines: usize,
     // Current viewport scroll position (0 = bottom/newest)
     scroll_offset: usize,
+    // Lines written since the view was scrolled back, kept from moving it
+    unseen_lines: usize,
     // Viewport size (rows, columns)
     viewport_size: (usize, usize),
+    // Total number of lines completed since the buffer was created
+    completed_lines: u64,
+    // Kitty keyboard protocol flag stack (top is active)
+    kitty_flags: Vec<u8>,
+    // xterm modifyOtherKeys level
+    modify_other_keys: u8,
+    // Which keyboard protocols applications may enable
+    kitty_supported: bool,
+    modify_other_keys_supported: bool,
+    // Replies to queries, waiting to be written back to the application
+    responses: Vec<u8>,
+    // Clipboard requests (OSC 52) waiting for the application layer
+    clipboard_requests: Vec<ClipboardRequest>,
+    // Older scrollback, compressed, oldest first
+    compressed: VecDeque<CompressedChunk>,
+    // Lines kept uncompressed when compression is enabled
+    working_set: Option<usize>,
+    // Number of lines dropped from the front of the history so far
+    first_line: u64,
+    // Marked lines by absolute number (first_line + line number), oldest first
+    marks: Vec<(u64, MarkKind)>,
+    // Commands run at shell prompts, oldest first
+    commands: VecDeque<CommandRun>,
+    // Bells rung since the last check
+    bells: usize,
+    // Prompts shown (OSC 133 ; A) since the last check
+    prompts: usize,
+    // Lines changed since the last check
+    damage: Damage,
+    // Columns between the default tab stops
+    tab_width: usize,
+    // Tab stops set by the application (HTS, TBC); None means the default stops
+    tab_stops: Option<BTreeSet<usize>>,
+    // Character sets designated as G0 and G1, and whether G1 is shifted in (SO)
+    charsets: [Charset; 2],
+    shift_out: bool,
+    // The application asked for pasted text to be marked (mode 2004)
+    bracketed_paste: bool,
+    // Where the command typed at the current prompt starts (OSC 133 ; B): absolute line and column
+    command_start: Option<(u64, usize)>,
+    // Commands typed at prompts that started running since the last check
+    typed_commands: Vec<String>,
+    // The command running, and the line its output starts on
+    running_command: Option<(String, u64)>,
+    // Commands that finished since the last check, and the lines of their output
+    finished_commands: Vec<(String, Range<u64>)>,
+    // Working directory the shell last reported (OSC 7)
+    working_dir: Option<String>,
+    // Default text and background colors reported to applications (OSC 10 and 11)
+    default_colors: ((u8, u8, u8), (u8, u8, u8)),
+    // The application wants to hear when the theme turns light or dark (mode 2031)
+    color_scheme_updates: bool,
+    // The application switched to the alternate screen (full-screen programs like less or vim)
+    alternate_screen: bool,
+    // The start of an escape sequence that ended a write, finished by the next one
+    pending: Vec<u8>,
+    // Title the application set (OSC 0 or 2)
+    title: Option<String>,
+    // Titles saved by the application (CSI 22 t), restored last first (CSI 23 t)
+    title_stack: Vec<Option<String>>,
 }
 
 impl TerminalBuffer {
     pub fn new(max_lines: usize) -> Self {
         Self {
             lines: VecDeque::with_capacity(max_lines),
+            styles: VecDeque::new(),
+            style: CellStyle::default(),
             cursor_pos: (0, 0),
             max_lines,
             scroll_offset: 0,
+            unseen_lines: 0,
             viewport_size: (24, 80), // Default terminal size
+            completed_lines: 0,
+            kitty_flags: Vec::new(),
+            modify_other_keys: 0,
+            kitty_supported: true,
+            modify_other_keys_supported: true,
+            responses: Vec::new(),
+            clipboard_requests: Vec::new(),
+            compressed: VecDeque::new(),
+            working_set: None,
+            first_line: 0,
+            marks: Vec::new(),
+            commands: VecDeque::new(),
+            bells: 0,
+            prompts: 0,
+            damage: Damage::All,
+            tab_width: 8,
+            tab_stops: None,
+            charsets: [Charset::Ascii; 2],
+            shift_out: false,
+            bracketed_paste: false,
+            default_colors: (export::DEFAULT_FOREGROUND, export::DEFAULT_BACKGROUND),
+            color_scheme_updates: false,
+            alternate_screen: false,
+            command_start: None,
+            typed_commands: Vec::new(),
+            running_command: None,
+            finished_commands: Vec::new(),
+            working_dir: None,
+            pending: Vec::new(),
+            title: None,
+            title_stack: Vec::new(),
         }
     }
     
     // Write raw data to the buffer (handles basic terminal control sequences)
     pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        // An escape sequence split between reads goes on where it stopped
+        let joined;
+        let data = if self.pending.is_empty() {
+            data
+        } else {
+            joined = [std::mem::take(&mut self.pending), data.to_vec()].concat();
+            &joined[..]
+        };
+        
         // Ensure there's at least one line
         if self.lines.is_empty() {
             self.lines.push_back(String::new());
@@ -37,6 +290,13 @@ impl TerminalBuffer {
         // Current cursor position
         let (mut row, mut col) = self.cursor_pos;
         
+        // Where the output ends, to keep a view scrolled back on the same lines
+        let end = self.first_line + self.total_lines() as u64;
+        
+        // Lines are only compressed after the loop, so absolute line numbers are
+        // first_line + compressed + row throughout
+        let compressed = self.compressed_lines() as u64;
+        
         // Process each byte
         let mut i = 0;
         while i < data.len() {
@@ -45,6 +305,7 @@ impl TerminalBuffer {
                 b'\n' => {
                     row += 1;
                     col = 0;
+                    self.completed_lines += 1;
                     
                     // Add a new line if needed
                     if row >= self.lines.len() {
@@ -53,6 +314,8 @@ impl TerminalBuffer {
                         // Trim history if needed
                         if self.lines.len() > self.max_lines {
                             self.lines.pop_front();
+                            self.styles.pop_front();
+                            self.first_line += 1;
                             row = self.lines.len() - 1;
                         }
                     }
@@ -63,25 +326,41 @@ impl TerminalBuffer {
                     col = 0;
                 }
                 
-                // Tab
+                // Bell
+                0x07 => {
+                    self.bells += 1;
+                }
+                
+                // Shift out (SO) and in (SI): use the G1 or G0 character set
+                0x0e => self.shift_out = true,
+                0x0f => self.shift_out = false,
+                
+                // Tab: move to the next tab stop, or the last column if there is none
                 b'\t' => {
-                    // Replace tab with spaces (every 8 columns)
-                    let spaces = 8 - (col % 8);
-                    for _ in 0..spaces {
-                        if col < self.viewport_size.1 {
-                            // Extend current line if needed
-                            let current_line = &mut self.lines[row];
-                            while current_line.len() <= col {
-                                current_line.push(' ');
-                            }
-                            current_line.replace_range(col..col+1, " ");
-                            col += 1;
-                        }
-                    }
+                    col = self.next_tab_stop(col);
                 }
                 
                 // ESC - basic ANSI escape sequence handling (very simplified)
                 b'\x1b' => {
+                    // A sequence the data ends in the middle of waits for the next write
+                    let start = i;
+                    let unfinished = match data.get(i + 1).copied() {
+                        None => true,
+                        Some(b'[') => !data[i + 2..].iter().any(u8::is_ascii_alphabetic),
+                        Some(b'(' | b')') => i + 2 >= data.len(),
+                        Some(b']') => {
+                            let end = data[i + 2..].iter().position(|&b| b == 0x07 || b == 0x1b).map(|p| i + 2 + p);
+                            end.is_none_or(|end| data[end] == 0x1b && end + 1 == data.len())
+                        }
+                        Some(_) => false,
+                    };
+                    if unfinished {
+                        if data.len() - start <= MAX_PENDING_SEQUENCE {
+                            self.pending = data[start..].to_vec();
+                        }
+                        break;
+                    }
+                    
                     // Check if we have an escape sequence
                     if i + 1 < data.len() && data[i + 1] == b'[' {
                         i += 2; // Skip ESC [
@@ -100,8 +379,14 @@ impl TerminalBuffer {
                                 // Clear screen
                                 'J' => {
                                     if seq == "2" {
+                                        // Marks on the cleared lines go with them
+                                        let cleared = self.first_line + self.compressed_lines() as u64;
+                                        self.marks.retain(|(line, _)| *line < cleared);
+                                        
                                         self.lines.clear();
+                                        self.styles.clear();
                                         self.lines.push_back(String::new());
+                                        self.damage = Damage::All;
                                         row = 0;
                                         col = 0;
                                     }
@@ -116,37 +401,121 @@ impl TerminalBuffer {
                                         }
                                     }
                                 }
+                                // Device attributes (primary and secondary)
+                                'c' => self.handle_device_attributes(&seq),
+                                // Device status and cursor position reports
+                                'n' if !seq.starts_with('>') => {
+                                    self.handle_device_status(&seq, row, col);
+                                }
+                                // Window manipulation: size reports
+                                't' => self.handle_window_report(&seq),
+                                // XTVERSION
+                                'q' if seq.starts_with('>') => {
+                                    self.respond(&format!("\x1bP>|Matrix {}\x1b\\", env!("CARGO_PKG_VERSION")));
+                                }
+                                // Kitty keyboard protocol
+                                'u' => self.handle_kitty_keyboard(&seq),
+                                // xterm modifyOtherKeys (set/reset and query)
+                                'm' | 'n' if seq.starts_with('>') || seq.starts_with('?') => {
+                                    self.handle_modify_other_keys(cmd, &seq);
+                                }
+                                // Colors and attributes (SGR)
+                                'm' => self.style.apply_sgr(&seq),
+                                // Tab clear (TBC): at the cursor, or every stop
+                                'g' => match seq.as_str() {
+                                    "" | "0" => {
+                                        self.custom_tab_stops().remove(&col);
+                                    }
+                                    "3" => self.tab_stops = Some(BTreeSet::new()),
+                                    _ => {}
+                                },
+                                // Bracketed paste on or off
+                                'h' | 'l' if seq == "?2004" => self.bracketed_paste = cmd == 'h',
+                                // Color scheme change notifications on or off
+                                'h' | 'l' if seq == "?2031" => self.color_scheme_updates = cmd == 'h',
+                                // Alternate screen in or out
+                                'h' | 'l' if matches!(seq.as_str(), "?1049" | "?1047" | "?47") => {
+                                    self.alternate_screen = cmd == 'h';
+                                }
+                                // Back to the default tab stops (DECST8C)
+                                'W' if seq == "?5" => self.tab_stops = None,
                                 // We ignore other escape sequences for now
                                 _ => {}
                             }
                         }
+                    } else if i + 2 < data.len() && matches!(data[i + 1], b'(' | b')') {
+                        // Designate the G0 or G1 character set (SCS)
+                        let slot = usize::from(data[i + 1] == b')');
+                        self.charsets[slot] = Charset::from_designator(data[i + 2]);
+                        i += 2;
+                    } else if i + 1 < data.len() && data[i + 1] == b'H' {
+                        // Set a tab stop at the cursor (HTS)
+                        i += 1;
+                        self.custom_tab_stops().insert(col);
+                    } else if i + 1 < data.len() && data[i + 1] == b']' {
+                        i += 2; // Skip ESC ]
+                        
+                        // Operating system command, terminated by BEL or ESC \\
+                        let start = i;
+                        while i < data.len() && data[i] != 0x07 && data[i] != 0x1b {
+                            i += 1;
+                        }
+                        let body = String::from_utf8_lossy(&data[start..i]).into_owned();
+                        
+                        // Skip the backslash of an ESC \\ terminator
+                        if i + 1 < data.len() && data[i] == 0x1b && data[i + 1] == b'\\' {
+                            i += 1;
+                        }
+                        
+                        self.handle_osc(&body, row, col);
                     }
                 }
                 
                 // Normal character
                 _ => {
+                    // ASCII in the active character set, anything else decoded as UTF-8.
+                    // A character the data ends in the middle of waits for the next write.
+                    let (c, len) = if data[i].is_ascii() {
+                        (self.charsets[usize::from(self.shift_out)].map(data[i]), 1)
+                    } else {
+                        match decode_char(&data[i..min(i + 4, data.len())]) {
+                            Some(decoded) => decoded,
+                            None => {
+                                self.pending = data[i..].to_vec();
+                                break;
+                            }
+                        }
+                    };
+                    i += len - 1;
+                    
                     // Make sure we have enough lines
                     while self.lines.len() <= row {
                         self.lines.push_back(String::new());
                     }
                     
-                    // Get current line and make sure it's long enough
-                    let current_line = &mut self.lines[row];
-                    while current_line.len() <= col {
-                        current_line.push(' ');
+                    self.damage_line(self.first_line + compressed + row as u64);
+                    
+                    // Combining characters join the one before them
+                    let width = c.width().unwrap_or(0);
+                    if width == 0 {
+                        attach_char(&mut self.lines[row], col, c);
+                        i += 1;
+                        continue;
                     }
                     
-                    // Replace character at current position
-                    if col < current_line.len() {
-                        // This is safe because we're indexing within a valid char boundary
-                        // (we're only handling ASCII for now)
-                        current_line.replace_range(col..col+1, &(data[i] as char).to_string());
-                    } else {
-                        current_line.push(data[i] as char);
+                    // Replace the character at the current position
+                    let index = put_char(&mut self.lines[row], col, c);
+                    
+                    // Record the character's style
+                    if !self.style.is_default() || row < self.styles.len() {
+                        while self.styles.len() <= row {
+                            self.styles.push_back(Vec::new());
+                        }
+                        style::set_cell_style(&mut self.styles[row], index, self.style);
                     }
                     
-                    // Advance cursor
-                    col += 1;
+                    // Advance cursor, by two columns for wide characters
+                    col += width;
                 }
             }
             
@@ -156,11 +525,229 @@ impl TerminalBuffer {
         // Update cursor position
         self.cursor_pos = (row, col);
         
+        // Scroll lock: a view scrolled back stays on what it shows while new lines
+        // are counted, rather than drifting with the output
+        if self.scroll_offset > 0 {
+            let added = (self.first_line + self.total_lines() as u64).saturating_sub(end) as usize;
+            self.scroll_offset = min(self.scroll_offset + added, self.lines.len().saturating_sub(1));
+            self.unseen_lines += added;
+        }
+        
+        self.compress_old_lines();
+        
         Ok(())
     }
     
+    // Whether the view is scrolled back from the newest output
+    pub fn is_scrolled_back(&self) -> bool {
+        self.scroll_offset > 0
+    }
+    
+    // Lines written below a view scrolled back since it was scrolled
+    pub fn unseen_lines(&self) -> usize {
+        if self.scroll_offset == 0 { 0 } else { self.unseen_lines }
+    }
+    
+    // Column of the first tab stop after `col`, or the last column if there is none
+    fn next_tab_stop(&self, col: usize) -> usize {
+        let last = self.viewport_size.1.saturating_sub(1);
+        let next = match &self.tab_stops {
+            Some(stops) => stops.range(col + 1..).next().copied().unwrap_or(last),
+            None => (col / self.tab_width + 1) * self.tab_width,
+        };
+        min(next, last).max(col)
+    }
+    
+    // The tab stops, turned from the defaults into a set the application can change
+    fn custom_tab_stops(&mut self) -> &mut BTreeSet<usize> {
+        let (width, tab_width) = (self.viewport_size.1, self.tab_width);
+        self.tab_stops.get_or_insert_with(|| (tab_width..width).step_by(tab_width).collect())
+    }
+    
+    // Set the columns between the default tab stops
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
+    }
+    
+    // Note that a line changed
+    fn damage_line(&mut self, line: u64) {
+        if let Damage::Lines(lines) = &mut self.damage {
+            lines.insert(line);
+        }
+    }
+    
+    // Lines changed since the last call
+    pub fn take_damage(&mut self) -> Damage {
+        std::mem::replace(&mut self.damage, Damage::Lines(HashSet::new()))
+    }
+    
+    // Set how many lines of history are kept and, if compressing, how many stay uncompressed
+    pub fn set_scrollback(&mut self, max_lines: usize, working_set: Option<usize>) {
+        self.max_lines = max_lines.max(1);
+        self.working_set = working_set.map(|lines| lines.max(self.viewport_size.0));
+    }
+    
+    // Move the oldest lines beyond the working set into compressed chunks
+    fn compress_old_lines(&mut self) {
+        let Some(working_set) = self.working_set else { return };
+        
+        // Keep whatever is on screen uncompressed
+        let needed = working_set.max(self.scroll_offset + self.viewport_size.0);
+        
+        while self.lines.len() > needed + CHUNK_LINES {
+            let lines: Vec<String> = self.lines.drain(..CHUNK_LINES).collect();
+            let styles = self.styles.drain(..CHUNK_LINES.min(self.styles.len())).collect();
+            self.compressed.push_back(CompressedChunk::compress(lines, styles));
+            self.cursor_pos.0 = self.cursor_pos.0.saturating_sub(CHUNK_LINES);
+        }
+        
+        // Drop the oldest chunks beyond the history limit
+        while self.total_lines() > self.max_lines {
+            match self.compressed.pop_front() {
+                Some(chunk) => self.first_line += chunk.lines as u64,
+                None => break,
+            }
+        }
+    }
+    
+    // Bring compressed lines back until `rows` lines are uncompressed (or nothing is left)
+    fn decompress_lines(&mut self, rows: usize) {
+        while self.lines.len() < rows {
+            let Some(chunk) = self.compressed.pop_back() else { break };
+            let lines = chunk.decompress();
+            self.cursor_pos.0 += lines.len();
+            
+            // Line styles are aligned from the front, so pad them before adding older lines
+            self.styles.resize(self.lines.len(), Vec::new());
+            for (line, runs) in lines.into_iter().zip(chunk.line_styles()).rev() {
+                self.lines.push_front(line);
+                self.styles.push_front(runs);
+            }
+        }
+    }
+    
+    // Number of lines held in compressed chunks
+    fn compressed_lines(&self) -> usize {
+        self.compressed.iter().map(|c| c.lines).sum()
+    }
+    
+    // Total number of lines held, compressed or not
+    pub fn total_lines(&self) -> usize {
+        self.lines.len() + self.compressed_lines()
+    }
+    
+    // First line shown and number of lines shown, numbered as in search results
+    pub fn viewport(&self) -> (usize, usize) {
+        let visible_rows = min(self.viewport_size.0, self.lines.len());
+        let scroll = min(self.scroll_offset, self.lines.len() - visible_rows);
+        let start = self.lines.len() - visible_rows - scroll;
+        (self.compressed_lines() + start, visible_rows)
+    }
+    
+    // Absolute numbers of the lines `visible_lines` returns; unlike search result
+    // numbers they don't change when old lines are dropped
+    pub fn visible_range(&self) -> Range<u64> {
+        let (start, rows) = self.viewport();
+        let start = self.first_line + start as u64;
+        start..start + rows as u64
+    }
+    
+    // Absolute number of the line the cursor is on
+    pub fn cursor_line(&self) -> u64 {
+        self.first_line + (self.compressed_lines() + self.cursor_pos.0) as u64
+    }
+    
+    // Absolute numbers of every line held, compressed or not
+    pub fn line_numbers(&self) -> Range<u64> {
+        self.first_line..self.first_line + self.total_lines() as u64
+    }
+    
+    // A line by absolute number, if it is still held
+    pub fn line_at(&self, line: u64) -> Option<Cow<'_, str>> {
+        let index = usize::try_from(line.checked_sub(self.first_line)?).ok()?;
+        let compressed = self.compressed_lines();
+        if index >= compressed {
+            return self.lines.get(index - compressed).map(|line| Cow::Borrowed(line.as_str()));
+        }
+        self.all_lines().nth(index)
+    }
+    
+    // Width of the viewport in columns
+    pub fn columns(&self) -> usize {
+        self.viewport_size.1
+    }
+    
+    // Scroll just enough to show a line (absolute number)
+    pub fn scroll_into_view(&mut self, line: u64) {
+        let visible = self.visible_range();
+        if line < visible.start {
+            self.scroll_up((visible.start - line) as usize);
+        } else if line >= visible.end {
+            self.scroll_down((line - visible.end + 1) as usize);
+        }
+    }
+    
+    // Mark a line (numbered as in search results)
+    pub fn add_mark(&mut self, line: usize, kind: MarkKind) {
+        // Forget marks on lines that were dropped
+        let first_line = self.first_line;
+        self.marks.retain(|(line, _)| *line >= first_line);
+        
+        self.marks.push((first_line + line as u64, kind));
+    }
+    
+    // Mark the line `lines_back` lines above the cursor's line
+    pub fn mark_recent_line(&mut self, lines_back: usize, kind: MarkKind) {
+        if let Some(line) = self.total_lines().checked_sub(lines_back + 1) {
+            self.add_mark(line, kind);
+        }
+    }
+    
+    // Remove all marks of a kind
+    pub fn clear_marks(&mut self, kind: MarkKind) {
+        self.marks.retain(|(_, k)| *k != kind);
+    }
+    
+    // Marked lines still held, numbered as in search results
+    pub fn marks(&self) -> Vec<(usize, MarkKind)> {
+        self.marks.iter()
+            .filter(|(line, _)| *line >= self.first_line)
+            .map(|(line, kind)| ((line - self.first_line) as usize, *kind))
+            .collect()
+    }
+    
+    // Memory statistics of the scrollback
+    pub fn scrollback_stats(&self) -> ScrollbackStats {
+        ScrollbackStats {
+            total_lines: self.total_lines(),
+            uncompressed_lines: self.lines.len(),
+            compressed_chunks: self.compressed.len(),
+            compressed_bytes: self.compressed.iter().map(|c| c.data.len()).sum(),
+            raw_bytes: self.compressed.iter().map(|c| c.raw_bytes).sum(),
+        }
+    }
+    
     // Get visible lines based on current scroll position and viewport height
     pub fn visible_lines(&self) -> Vec<&str> {
+        self.visible_indices().map(|i| self.lines[i].as_str()).collect()
+    }
+    
+    // Styles of the visible lines, in the order of visible_lines
+    pub fn visible_styles(&self) -> Vec<&[StyleRun]> {
+        self.visible_indices().map(|i| self.styles.get(i).map_or(&[][..], Vec::as_slice)).collect()
+    }
+    
+    // The lines at the bottom of the buffer the application draws on, and their styles,
+    // whatever part of the history is being looked at
+    pub fn screen_lines(&self) -> Vec<(&str, &[StyleRun])> {
+        let start = self.lines.len().saturating_sub(self.viewport_size.0);
+        (start..self.lines.len())
+            .map(|i| (self.lines[i].as_str(), self.styles.get(i).map_or(&[][..], Vec::as_slice)))
+            .collect()
+    }
+    
+    // Indices into the uncompressed lines of those on screen
+    fn visible_indices(&self) -> Range<usize> {
         let buffer_size = self.lines.len();
         
         // Calculate visible range
@@ -173,25 +760,400 @@ impl TerminalBuffer {
         // Calculate range of lines to display
         let start = buffer_size.saturating_sub(visible_rows).saturating_sub(scroll);
         let end = start + visible_rows;
-        let range = start..min(end, buffer_size);
+        start..min(end, buffer_size)
+    }
+    
+    // Handle kitty keyboard protocol sequences (CSI ? u, CSI > f u, CSI < n u, CSI = f ; m u)
+    fn handle_kitty_keyboard(&mut self, seq: &str) {
+        if !self.kitty_supported || seq.is_empty() {
+            return;
+        }
         
-        // Extract lines from the buffer
-        range.map(|i| self.lines[i].as_str()).collect()
+        let (prefix, params) = seq.split_at(1);
+        let mut values = params.split(';').map(|p| p.parse::<u8>().ok());
+        let first = values.next().flatten();
+        let current = self.kitty_flags.last().copied().unwrap_or(0);
+        
+        match prefix {
+            // Query the active flags
+            "?" => {
+                self.respond(&format!("\x1b[?{}u", current));
+            }
+            // Push flags
+            ">" => {
+                if self.kitty_flags.len() >= KITTY_STACK_LIMIT {
+                    self.kitty_flags.remove(0);
+                }
+                self.kitty_flags.push(first.unwrap_or(0) & KITTY_SUPPORTED_FLAGS);
+            }
+            // Pop entries
+            "<" => {
+                let count = first.unwrap_or(1).max(1) as usize;
+                let remaining = self.kitty_flags.len().saturating_sub(count);
+                self.kitty_flags.truncate(remaining);
+            }
+            // Set, add or remove flags on the active entry
+            "=" => {
+                let flags = first.unwrap_or(0) & KITTY_SUPPORTED_FLAGS;
+                let updated = match values.next().flatten().unwrap_or(1) {
+                    2 => current | flags,
+                    3 => current & !flags,
+                    _ => flags,
+                };
+                match self.kitty_flags.last_mut() {
+                    Some(top) => *top = updated,
+                    None => self.kitty_flags.push(updated),
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    // Handle xterm modifyOtherKeys (CSI > 4 ; v m, CSI > 4 n) and its query (CSI ? 4 m)
+    fn handle_modify_other_keys(&mut self, cmd: char, seq: &str) {
+        if !self.modify_other_keys_supported {
+            return;
+        }
+        
+        let mut params = seq[1..].split(';');
+        if params.next() != Some("4") {
+            return;
+        }
+        let value = params.next().and_then(|v| v.parse::<u8>().ok()).unwrap_or(0);
+        
+        match (seq.as_bytes()[0], cmd) {
+            (b'>', 'm') => self.modify_other_keys = value.min(2),
+            (b'>', 'n') => self.modify_other_keys = 0,
+            (b'?', 'm') => self.respond(&format!("\x1b[>4;{}m", self.modify_other_keys)),
+            _ => {}
+        }
+    }
+    
+    // Answer device attribute queries (CSI c, CSI > c)
+    fn handle_device_attributes(&mut self, seq: &str) {
+        match seq {
+            // Primary DA: VT220 with ANSI color
+            "" | "0" => self.respond("\x1b[?62;22c"),
+            // Secondary DA: terminal type, firmware version, ROM cartridge
+            ">" | ">0" => self.respond("\x1b[>1;10;0c"),
+            _ => {}
+        }
+    }
+    
+    // Answer xterm window operations (CSI Ps t): state, size and title reports and
+    // the title stack. Requests to move, resize or iconify the window are ignored,
+    // since a pane can't do them.
+    fn handle_window_report(&mut self, seq: &str) {
+        let (rows, cols) = (self.viewport_size.0 as u16, self.viewport_size.1 as u16);
+        let (cell_width, cell_height) = process::cell_pixels();
+        match seq.split(';').next().unwrap_or("") {
+            // Never iconified
+            "11" => self.respond("\x1b[1t"),
+            // Position of the window
+            "13" => self.respond("\x1b[3;0;0t"),
+            // Text area in pixels
+            "14" => self.respond(&format!("\x1b[4;{};{}t", rows.saturating_mul(cell_height), cols.saturating_mul(cell_width))),
+            // Character cell in pixels
+            "16" => self.respond(&format!("\x1b[6;{};{}t", cell_height, cell_width)),
+            // Text area and screen in characters
+            "18" => self.respond(&format!("\x1b[8;{};{}t", rows, cols)),
+            "19" => self.respond(&format!("\x1b[9;{};{}t", rows, cols)),
+            // Icon name and window title
+            "20" => self.respond(&format!("\x1b]L{}\x1b\\", self.title.as_deref().unwrap_or(""))),
+            "21" => self.respond(&format!("\x1b]l{}\x1b\\", self.title.as_deref().unwrap_or(""))),
+            // Save and restore the title; icon name and title are one, so 0, 1 and 2 all apply
+            "22" => {
+                if self.title_stack.len() == MAX_TITLE_STACK {
+                    self.title_stack.remove(0);
+                }
+                self.title_stack.push(self.title.clone());
+            }
+            "23" => {
+                if let Some(title) = self.title_stack.pop() {
+                    self.title = title;
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    // Title the application set, if any
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    
+    // Answer device status reports (CSI 5 n, CSI 6 n, CSI ? 6 n)
+    fn handle_device_status(&mut self, seq: &str, row: usize, col: usize) {
+        // Cursor position relative to the top of the screen, 1-based
+        let top = self.lines.len().saturating_sub(self.viewport_size.0);
+        let screen_row = row.saturating_sub(top) + 1;
+        let screen_col = min(col, self.viewport_size.1.saturating_sub(1)) + 1;
+        
+        match seq {
+            // Operating status: always OK
+            "5" => self.respond("\x1b[0n"),
+            "6" => self.respond(&format!("\x1b[{};{}R", screen_row, screen_col)),
+            "?6" => self.respond(&format!("\x1b[?{};{}R", screen_row, screen_col)),
+            // Color scheme: dark or light
+            "?996" => self.report_color_scheme(),
+            _ => {}
+        }
+    }
+    
+    // Handle operating system commands (OSC)
+    fn handle_osc(&mut self, body: &str, row: usize, col: usize) {
+        if let Some(request) = clipboard::parse_osc52(body) {
+            self.clipboard_requests.push(request);
+        }
+        
+        // Queries of the default text and background colors
+        if body == "10;?" || body == "11;?" {
+            let (foreground, background) = self.default_colors;
+            let ((r, g, b), number) = if body == "10;?" { (foreground, 10) } else { (background, 11) };
+            // 16 bits per channel, as xterm reports them
+            let reply = format!("\x1b]{};rgb:{:02x}{:02x}/{:02x}{:02x}/{:02x}{:02x}\x1b\\", number, r, r, g, g, b, b);
+            self.respond(&reply);
+        }
+        
+        // The window title (and icon name, which is the same thing here)
+        if let Some(title) = body.strip_prefix("0;").or_else(|| body.strip_prefix("2;")) {
+            // Control characters are dropped so a title reported back can't run commands
+            let title: String = title.chars().filter(|c| !c.is_control()).collect();
+            self.title = (!title.is_empty()).then_some(title);
+        }
+        
+        // The shell's working directory, as a file:// URL
+        if let Some(url) = body.strip_prefix("7;") {
+            let path = url.strip_prefix("file://").and_then(|rest| rest.find('/').map(|i| &rest[i..]));
+            if let Some(path) = path {
+                self.working_dir = Some(percent_decode(path));
+            }
+        }
+        
+        // Shell integration: a prompt starts on this line
+        if body == "133;A" || body.starts_with("133;A;") {
+            self.add_mark(self.compressed_lines() + row, MarkKind::Prompt);
+            self.prompts += 1;
+            
+            // Forget commands whose prompt was dropped
+            let first_line = self.first_line;
+            self.commands.retain(|c| c.line >= first_line);
+            if self.commands.len() >= MAX_COMMANDS {
+                self.commands.pop_front();
+            }
+            self.commands.push_back(CommandRun {
+                line: first_line + (self.compressed_lines() + row) as u64,
+                started: None,
+                duration: None,
+                exit_code: None,
+            });
+        }
+        
+        // The prompt ends and the command is typed from here
+        if body == "133;B" || body.starts_with("133;B;") {
+            self.command_start = Some((self.first_line + (self.compressed_lines() + row) as u64, col));
+        }
+        
+        // The command typed at the prompt starts running
+        if body == "133;C" || body.starts_with("133;C;") {
+            if let Some(command) = self.commands.back_mut().filter(|c| c.started.is_none()) {
+                command.started = Some(Instant::now());
+            }
+            
+            // Its text runs from the end of the prompt to the line the cursor left
+            if let Some((line, start_col)) = self.command_start.take() {
+                let cursor_line = self.first_line + (self.compressed_lines() + row) as u64;
+                let last = if col == 0 && cursor_line > line { cursor_line - 1 } else { cursor_line };
+                let text = (line..=last)
+                    .filter_map(|l| self.line_at(l))
+                    .enumerate()
+                    .map(|(i, text)| {
+                        let skip = if i == 0 { start_col } else { 0 };
+                        text.chars().skip(skip).collect::<String>().trim_end().to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let text = text.trim();
+                if !text.is_empty() {
+                    self.typed_commands.push(text.to_string());
+                    let output_start = if col == 0 { cursor_line } else { cursor_line + 1 };
+                    self.running_command = Some((text.to_string(), output_start));
+                }
+            }
+        }
+        
+        // The command finished, optionally with its exit status; a prompt left
+        // without running anything has nothing to report
+        if let Some(status) = body.strip_prefix("133;D") {
+            let finished = self.commands.back_mut()
+                .filter(|c| c.duration.is_none() && c.started.is_some());
+            if let Some(command) = finished {
+                command.duration = command.started.map(|started| started.elapsed());
+                command.exit_code = status.strip_prefix(';')
+                    .and_then(|s| s.split(';').next())
+                    .and_then(|code| code.parse().ok());
+                let line = command.line;
+                self.damage_line(line);
+            }
+            
+            // Its output ends where the cursor is, or includes the line if the output
+            // didn't end in a newline
+            if let Some((text, start)) = self.running_command.take() {
+                let cursor_line = self.first_line + (self.compressed_lines() + row) as u64;
+                let end = if col == 0 { cursor_line } else { cursor_line + 1 };
+                self.finished_commands.push((text, start..end.max(start)));
+            }
+        }
+    }
+    
+    // The finished command whose prompt is on a line (absolute number, see `visible_range`)
+    pub fn command_at(&self, line: u64) -> Option<&CommandRun> {
+        self.commands.iter().rev()
+            .take_while(|c| c.line >= line)
+            .find(|c| c.line == line && c.duration.is_some())
+    }
+    
+    // The last command that finished
+    pub fn last_command(&self) -> Option<&CommandRun> {
+        self.commands.iter().rev().find(|c| c.duration.is_some())
+    }
+    
+    // Working directory the shell reported, if it reports one
+    pub fn working_dir(&self) -> Option<&str> {
+        self.working_dir.as_deref()
+    }
+    
+    // Take the commands typed at prompts (reported by shell integration) since the last call
+    pub fn take_typed_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.typed_commands)
+    }
+    
+    // Take the commands that finished since the last call, with the lines of their output
+    // (absolute numbers, see `visible_range`)
+    pub fn take_finished_commands(&mut self) -> Vec<(String, Range<u64>)> {
+        std::mem::take(&mut self.finished_commands)
+    }
+    
+    // Number of bells rung since the last call
+    pub fn take_bells(&mut self) -> usize {
+        std::mem::take(&mut self.bells)
+    }
+    
+    // Whether the shell has shown a prompt (shell integration) yet
+    pub fn prompted(&self) -> bool {
+        !self.commands.is_empty()
+    }
+    
+    // Number of prompts shown since the last call
+    pub fn take_prompts(&mut self) -> usize {
+        std::mem::take(&mut self.prompts)
+    }
+    
+    // Take the clipboard requests made since the last call
+    pub fn take_clipboard_requests(&mut self) -> Vec<ClipboardRequest> {
+        std::mem::take(&mut self.clipboard_requests)
+    }
+    
+    // Queue a reply to be written back to the application
+    pub fn respond(&mut self, reply: &str) {
+        self.responses.extend_from_slice(reply.as_bytes());
+    }
+    
+    // Set the colors reported as the defaults, telling the application if it asked
+    // to hear when the theme turns light or dark
+    pub fn set_default_colors(&mut self, foreground: (u8, u8, u8), background: (u8, u8, u8)) {
+        let was_dark = export::is_dark(self.default_colors.1);
+        self.default_colors = (foreground, background);
+        if self.color_scheme_updates && export::is_dark(self.default_colors.1) != was_dark {
+            self.report_color_scheme();
+        }
+    }
+    
+    // Color scheme report: 1 for dark, 2 for light
+    fn report_color_scheme(&mut self) {
+        let scheme = if export::is_dark(self.default_colors.1) { 1 } else { 2 };
+        self.respond(&format!("\x1b[?997;{}n", scheme));
+    }
+    
+    // Take the replies generated since the last call
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+    
+    // Choose which keyboard protocols applications are allowed to enable
+    pub fn set_keyboard_support(&mut self, kitty: bool, modify_other_keys: bool) {
+        self.kitty_supported = kitty;
+        self.modify_other_keys_supported = modify_other_keys;
+    }
+    
+    // Keyboard protocol currently requested by the application
+    pub fn keyboard_mode(&self) -> KeyboardMode {
+        KeyboardMode {
+            kitty_flags: self.kitty_flags.last().copied().unwrap_or(0),
+            modify_other_keys: self.modify_other_keys,
+        }
+    }
+    
+    // Whether a full-screen program has switched to the alternate screen
+    pub fn alternate_screen(&self) -> bool {
+        self.alternate_screen
+    }
+    
+    // Whether pasted text should be sent between bracketed paste markers
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+    
+    // Total number of lines completed so far (never decreases)
+    pub fn completed_lines(&self) -> u64 {
+        self.completed_lines
+    }
+    
+    // Get up to `count` of the most recently completed lines, oldest first
+    pub fn recent_lines(&self, count: usize) -> Vec<&str> {
+        // The last line is still being written, so skip it
+        let completed = self.lines.len().saturating_sub(1);
+        let start = completed.saturating_sub(count);
+        (start..completed).map(|i| self.lines[i].as_str()).collect()
+    }
+    
+    // The most recent lines, joined by newlines, limited to about `max_bytes`
+    pub fn scrollback_text(&self, max_bytes: usize) -> String {
+        let mut size = 0;
+        let start = self.lines.iter()
+            .rposition(|line| {
+                size += line.len() + 1;
+                size > max_bytes
+            })
+            .map_or(0, |i| i + 1);
+        
+        self.lines.range(start..).map(String::as_str).collect::<Vec<_>>().join("\n")
     }
     
     // Scroll the view up (toward older content)
     pub fn scroll_up(&mut self, lines: usize) {
+        if self.scroll_offset == 0 {
+            self.unseen_lines = 0;
+        }
+        
+        // Decompress older history if the view reaches it
+        self.decompress_lines(self.scroll_offset + lines + self.viewport_size.0);
+        
         self.scroll_offset = min(self.scroll_offset + lines, self.lines.len().saturating_sub(1));
     }
     
     // Scroll the view down (toward newer content)
     pub fn scroll_down(&mut self, lines: usize) {
         self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        if self.scroll_offset == 0 {
+            self.unseen_lines = 0;
+        }
     }
     
     // Move to the bottom (most recent content)
     pub fn scroll_to_bottom(&mut self) {
         self.scroll_offset = 0;
+        self.unseen_lines = 0;
     }
     
     // Resize the viewport
@@ -216,8 +1178,8 @@ impl TerminalBuffer {
             return results;
         }
         
-        // Process each line
-        for (line_idx, line) in self.lines.iter().enumerate() {
+        // Process each line (line numbers count from the oldest line held)
+        for (line_idx, line) in self.all_lines().enumerate() {
             let mut start_idx = 0;
             
             while start_idx < line.len() {
@@ -249,11 +1211,225 @@ impl TerminalBuffer {
         results
     }
     
+    // Search for a regular expression, returning each match with its line
+    pub fn search_regex(&self, pattern: &Regex) -> Vec<SearchMatch> {
+        let mut results = Vec::new();
+        
+        for (line_idx, line) in self.all_lines().enumerate() {
+            for found in pattern.find_iter(&line) {
+                if found.range().is_empty() {
+                    continue;
+                }
+                results.push(SearchMatch {
+                    line: line_idx,
+                    range: found.range(),
+                    text: line.to_string(),
+                });
+            }
+        }
+        
+        results
+    }
+    
+    // The last `max` completed lines matching a pattern, and how many match in total
+    pub fn matching_lines(&self, pattern: &Regex, max: usize) -> (Vec<String>, usize) {
+        let mut lines = VecDeque::new();
+        let mut count = 0;
+        
+        // The last line is still being written
+        for line in self.all_lines().take(self.total_lines().saturating_sub(1)) {
+            if pattern.is_match(&line) {
+                count += 1;
+                if lines.len() == max {
+                    lines.pop_front();
+                }
+                lines.push_back(line.into_owned());
+            }
+        }
+        
+        (lines.into(), count)
+    }
+    
+    // Every line held, oldest first, decompressing history one chunk at a time
+    fn all_lines(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        let history = self.compressed.iter()
+            .flat_map(|chunk| chunk.decompress().into_iter().map(Cow::Owned));
+        history.chain(self.lines.iter().map(|line| Cow::Borrowed(line.as_str())))
+    }
+    
+    // Every line held with its style runs, oldest first
+    pub fn styled_lines(&self) -> Vec<(String, Vec<StyleRun>)> {
+        let mut lines = Vec::with_capacity(self.total_lines());
+        for chunk in &self.compressed {
+            lines.extend(chunk.decompress().into_iter().zip(chunk.line_styles()));
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            lines.push((line.clone(), self.styles.get(i).cloned().unwrap_or_default()));
+        }
+        lines
+    }
+    
+    // Scroll so that a line (numbered as in search results) is at the top of the view
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let total = self.total_lines();
+        if line >= total {
+            return;
+        }
+        
+        // Lines from the target to the end must be uncompressed
+        self.decompress_lines(total - line + self.viewport_size.0);
+        
+        let index = line - (total - self.lines.len());
+        let visible_rows = min(self.viewport_size.0, self.lines.len());
+        self.scroll_offset = self.lines.len().saturating_sub(visible_rows).saturating_sub(index);
+    }
+    
     // Clear the buffer
     pub fn clear(&mut self) {
+        self.first_line += self.total_lines() as u64;
+        self.marks.clear();
+        self.compressed.clear();
         self.lines.clear();
+        self.styles.clear();
         self.lines.push_back(String::new());
         self.cursor_pos = (0, 0);
         self.scroll_offset = 0;
+        self.damage = Damage::All;
+    }
+}
+
+// Put a character in a line at a screen column, padding the line with spaces to reach
+// it, and return its index among the line's characters. A wide character takes two
+// columns; what's left of a wide character it half covers becomes a space.
+fn put_char(line: &mut String, col: usize, c: char) -> usize {
+    let width = c.width().unwrap_or(1).max(1);
+    if line.is_ascii() && width == 1 {
+        // Columns are byte offsets
+        while line.len() < col {
+            line.push(' ');
+        }
+        if col < line.len() {
+            line.replace_range(col..col + 1, c.encode_utf8(&mut [0; 4]));
+        } else {
+            line.push(c);
+        }
+        return col;
+    }
+    
+    // The characters covering the columns taken, and the columns of them left over
+    let (mut cell, mut count) = (0, 0);
+    let mut covered: Option<(usize, usize, usize)> = None;
+    let mut end = line.len();
+    let mut trail = 0;
+    for (offset, old) in line.char_indices() {
+        let old_width = old.width().unwrap_or(0);
+        if cell >= col + width && old_width > 0 {
+            end = offset;
+            break;
+        }
+        if covered.is_none() && cell + old_width > col {
+            covered = Some((offset, count, col - cell));
+        }
+        cell += old_width;
+        count += 1;
+        if covered.is_some() {
+            trail = cell.saturating_sub(col + width);
+        }
+    }
+    
+    match covered {
+        Some((start, index, lead)) => {
+            let replacement = format!("{}{}{}", " ".repeat(lead), c, " ".repeat(trail));
+            line.replace_range(start..end, &replacement);
+            index + lead
+        }
+        None => {
+            line.extend(std::iter::repeat_n(' ', col - cell));
+            line.push(c);
+            count + col - cell
+        }
+    }
+}
+
+// Add a zero-width character to the one ending at a screen column
+fn attach_char(line: &mut String, col: usize, c: char) {
+    let mut cell = 0;
+    for (offset, old) in line.char_indices() {
+        let width = old.width().unwrap_or(0);
+        if cell >= col && width > 0 {
+            line.insert(offset, c);
+            return;
+        }
+        cell += width;
+    }
+    line.push(c);
+}
+
+// The first character of some bytes and its length in them; invalid UTF-8 is one
+// replacement character. None if the bytes end in the middle of a character.
+fn decode_char(bytes: &[u8]) -> Option<(char, usize)> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.valid_up_to() > 0 => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+        Err(e) => return e.error_len().map(|len| (char::REPLACEMENT_CHARACTER, len)),
+    };
+    text.chars().next().map(|c| (c, c.len_utf8()))
+}
+
+// Decode the %XX escapes of a URL path
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
     }
-}
\ No newline at end of file
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_sequences_split_between_writes_are_finished_by_the_next() {
+        let mut buffer = TerminalBuffer::new(100);
+        buffer.write(b"copied\x1b]52;c;aGVs").unwrap();
+        assert!(buffer.take_clipboard_requests().is_empty());
+        buffer.write(b"bG8=\x1b").unwrap();
+        assert!(buffer.take_clipboard_requests().is_empty());
+        buffer.write(b"\\ done\x1b[3").unwrap();
+        assert_eq!(buffer.take_clipboard_requests(), vec![ClipboardRequest::Set(String::from("aGVsbG8="))]);
+        buffer.write(b"1mred").unwrap();
+
+        // None of the sequences shows up as text
+        assert_eq!(buffer.visible_lines()[0], "copied donered");
+    }
+
+    #[test]
+    fn wide_characters_are_decoded_and_take_two_columns() {
+        let mut buffer = TerminalBuffer::new(100);
+        let text = "中文".as_bytes();
+        buffer.write(&text[..4]).unwrap();
+        assert_eq!(buffer.cursor_position(), (0, 2));
+        buffer.write(&text[4..]).unwrap();
+        assert_eq!(buffer.cursor_position(), (0, 4));
+        buffer.write(b"!").unwrap();
+
+        // Decoded whole although the second character was split between writes
+        assert_eq!(buffer.visible_lines()[0], "中文!");
+        assert_eq!(buffer.cursor_position(), (0, 5));
+    }
+}
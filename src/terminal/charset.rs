@@ -0,0 +1,67 @@
+// Character sets applications can switch between with SCS (ESC ( 0, ESC ) B, ...)
+// and SO/SI. Only DEC Special Graphics differs from ASCII; curses programs use it
+// for line drawing.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Ascii,
+    // DEC Special Graphics: box drawing and a few symbols in place of 0x5f-0x7e
+    DecGraphics,
+}
+
+impl Charset {
+    // The character set an SCS final byte designates; unknown sets are treated as ASCII
+    pub fn from_designator(byte: u8) -> Self {
+        match byte {
+            b'0' => Charset::DecGraphics,
+            _ => Charset::Ascii,
+        }
+    }
+
+    // The character a byte stands for in this set
+    pub fn map(self, byte: u8) -> char {
+        match self {
+            Charset::Ascii => byte as char,
+            Charset::DecGraphics => dec_graphics(byte),
+        }
+    }
+}
+
+fn dec_graphics(byte: u8) -> char {
+    match byte {
+        b'_' => '\u{a0}',
+        b'`' => '◆',
+        b'a' => '▒',
+        b'b' => '␉',
+        b'c' => '␌',
+        b'd' => '␍',
+        b'e' => '␊',
+        b'f' => '°',
+        b'g' => '±',
+        b'h' => '␤',
+        b'i' => '␋',
+        b'j' => '┘',
+        b'k' => '┐',
+        b'l' => '┌',
+        b'm' => '└',
+        b'n' => '┼',
+        b'o' => '⎺',
+        b'p' => '⎻',
+        b'q' => '─',
+        b'r' => '⎼',
+        b's' => '⎽',
+        b't' => '├',
+        b'u' => '┤',
+        b'v' => '┴',
+        b'w' => '┬',
+        b'x' => '│',
+        b'y' => '≤',
+        b'z' => '≥',
+        b'{' => 'π',
+        b'|' => '≠',
+        b'}' => '£',
+        b'~' => '·',
+        _ => byte as char,
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use crate::config::settings::PasteNewlines;
+
+// A clipboard operation requested by an application through OSC 52
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardRequest {
+    // Set the clipboard to the (still base64 encoded) payload
+    Set(String),
+    // Report the clipboard contents back to the application
+    Query,
+}
+
+// Parse the body of an OSC 52 sequence ("52;<targets>;<payload>")
+pub fn parse_osc52(body: &str) -> Option<ClipboardRequest> {
+    let mut parts = body.splitn(3, ';');
+    if parts.next() != Some("52") {
+        return None;
+    }
+
+    // Targets (c, p, s, ...) all map to the one clipboard we have
+    let _targets = parts.next()?;
+
+    match parts.next()? {
+        "?" => Some(ClipboardRequest::Query),
+        payload => Some(ClipboardRequest::Set(payload.to_string())),
+    }
+}
+
+// Decode an OSC 52 payload, refusing anything over `max_bytes` once decoded
+pub fn decode_payload(payload: &str, max_bytes: usize) -> Result<String> {
+    // Base64 grows the data by 4/3, so we can reject oversized payloads before decoding
+    if payload.len() / 4 * 3 > max_bytes + 2 {
+        return Err(anyhow!("clipboard payload exceeds {} bytes", max_bytes));
+    }
+
+    let data = STANDARD.decode(payload.trim())?;
+    if data.len() > max_bytes {
+        return Err(anyhow!("clipboard payload exceeds {} bytes", max_bytes));
+    }
+
+    Ok(String::from_utf8(data)?)
+}
+
+// Build the OSC 52 reply an application receives for a clipboard query
+pub fn encode_reply(text: &str) -> String {
+    format!("\x1b]52;c;{}\x1b\\", STANDARD.encode(text))
+}
+
+// Bytes sent to a program for pasted text: line breaks become Enter presses (or
+// are joined or escaped), and with bracketed paste the text is marked so the
+// program doesn't run it as typed commands
+pub fn paste_data(text: &str, newlines: PasteNewlines, bracketed: bool) -> String {
+    let text = text.replace("\r\n", "\n");
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    let text = match newlines {
+        PasteNewlines::Keep => text.replace('\n', "\r"),
+        PasteNewlines::Join => text.split('\n').map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" "),
+        PasteNewlines::Escape => text.replace('\n', " \\\r"),
+    };
+
+    if bracketed {
+        // The text can't end the paste early
+        format!("\x1b[200~{}\x1b[201~", text.replace("\x1b[201~", ""))
+    } else {
+        text
+    }
+}
+
+// Copies kept in the history unless configured otherwise
+const DEFAULT_HISTORY: usize = 20;
+
+// The clipboard shared by all windows, with a history of recent copies
+#[derive(Debug)]
+pub struct Clipboard {
+    // Recent copies, newest (the current contents) first
+    history: VecDeque<String>,
+    // Most copies kept
+    history_size: usize,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self {
+            history: VecDeque::new(),
+            history_size: DEFAULT_HISTORY,
+        }
+    }
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Current clipboard contents, if anything was copied
+    pub fn get(&self) -> Option<&str> {
+        self.history.front().map(String::as_str)
+    }
+
+    // Recent copies, newest first
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().map(String::as_str)
+    }
+
+    // Keep up to `size` copies (at least the current one)
+    pub fn set_history_size(&mut self, size: usize) {
+        self.history_size = size.max(1);
+        self.history.truncate(self.history_size);
+    }
+
+    // Replace the clipboard contents and forward them to the host terminal's clipboard;
+    // the previous contents stay in the history
+    pub fn set(&mut self, text: String) -> Result<()> {
+        Self::forward(&text)?;
+
+        // Copying an earlier entry again moves it to the front
+        self.history.retain(|entry| *entry != text);
+        self.history.push_front(text);
+        self.history.truncate(self.history_size);
+        Ok(())
+    }
+
+    // Add text to the end of the current contents instead of replacing them
+    pub fn append(&mut self, text: &str) -> Result<()> {
+        let Some(current) = self.history.front_mut() else {
+            return self.set(text.to_string());
+        };
+        if !current.is_empty() && !current.ends_with('\n') {
+            current.push('\n');
+        }
+        current.push_str(text);
+        Self::forward(current)
+    }
+
+    // Make an entry of the history the current contents again
+    pub fn restore(&mut self, index: usize) -> Result<()> {
+        match self.history.get(index).cloned() {
+            Some(text) => self.set(text),
+            None => Err(anyhow!("no clipboard entry {}", index)),
+        }
+    }
+
+    // The host terminal owns the system clipboard, so hand the text on with OSC 52
+    fn forward(text: &str) -> Result<()> {
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+// Copy mode: move a cursor over a window's scrollback with the keyboard, select
+// text as a stream of lines or as a rectangular block, and copy it. Lines are
+// numbered absolutely (see TerminalBuffer::visible_range), so a selection stays
+// put while output arrives.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::ops::Range;
+use unicode_width::UnicodeWidthChar;
+
+use crate::terminal::buffer::TerminalBuffer;
+
+// What a key pressed in copy mode asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyAction {
+    None,
+    // Copy this text and leave copy mode
+    Copy(String),
+    // Add this text to the end of the clipboard and leave copy mode
+    Append(String),
+    // Leave copy mode without copying
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyMode {
+    // Cursor: absolute line number and column (in characters)
+    pub cursor: (u64, usize),
+    // Where the selection started, if one is being made
+    pub anchor: Option<(u64, usize)>,
+    // The selection is a rectangle of columns rather than a stream of text
+    pub block: bool,
+}
+
+impl CopyMode {
+    // Start with the cursor at the start of the last line shown
+    pub fn new(buffer: &TerminalBuffer) -> Self {
+        let visible = buffer.visible_range();
+        Self {
+            cursor: (visible.end.saturating_sub(1).max(visible.start), 0),
+            anchor: None,
+            block: false,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, buffer: &mut TerminalBuffer) -> CopyAction {
+        let lines = buffer.line_numbers();
+        let last = lines.end.saturating_sub(1).max(lines.start);
+        let page = buffer.visible_range().count().max(1) as u64;
+        let (line, col) = self.cursor;
+        let line_length = buffer.line_at(line).map_or(0, |text| text.chars().count());
+        // The cursor may go past the end of short lines, for block selections
+        let max_col = line_length.max(buffer.columns()).saturating_sub(1);
+
+        self.cursor = match (key.code, key.modifiers) {
+            (KeyCode::Char('v'), KeyModifiers::CONTROL) => {
+                // Block selection, starting here if nothing is selected yet
+                self.block = !self.block;
+                self.anchor.get_or_insert(self.cursor);
+                self.cursor
+            }
+            (KeyCode::Char('v') | KeyCode::Char(' '), _) => {
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                };
+                self.block = false;
+                self.cursor
+            }
+            (KeyCode::Char('y') | KeyCode::Enter, _) => {
+                return match self.selected_text(buffer) {
+                    Some(text) => CopyAction::Copy(text),
+                    None => CopyAction::Exit,
+                };
+            }
+            (KeyCode::Char('a'), _) => {
+                return match self.selected_text(buffer) {
+                    Some(text) => CopyAction::Append(text),
+                    None => CopyAction::Exit,
+                };
+            }
+            (KeyCode::Esc | KeyCode::Char('q'), _) => return CopyAction::Exit,
+            (KeyCode::Left | KeyCode::Char('h'), _) => (line, col.saturating_sub(1)),
+            (KeyCode::Right | KeyCode::Char('l'), _) => (line, (col + 1).min(max_col)),
+            (KeyCode::Up | KeyCode::Char('k'), _) => (line.saturating_sub(1).max(lines.start), col),
+            (KeyCode::Down | KeyCode::Char('j'), _) => ((line + 1).min(last), col),
+            (KeyCode::PageUp, _) => (line.saturating_sub(page).max(lines.start), col),
+            (KeyCode::PageDown, _) => ((line + page).min(last), col),
+            (KeyCode::Home | KeyCode::Char('0'), _) => (line, 0),
+            (KeyCode::End | KeyCode::Char('$'), _) => (line, line_length.saturating_sub(1)),
+            (KeyCode::Char('g'), _) => (lines.start, 0),
+            (KeyCode::Char('G'), _) => (last, 0),
+            _ => self.cursor,
+        };
+
+        buffer.scroll_into_view(self.cursor.0);
+        CopyAction::None
+    }
+
+    // Characters of a line that are selected
+    pub fn selected_columns(&self, line: u64, text: &str, buffer: &TerminalBuffer) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let (start, end) = if anchor <= self.cursor { (anchor, self.cursor) } else { (self.cursor, anchor) };
+        if line < start.0 || line > end.0 {
+            return None;
+        }
+
+        let length = text.chars().count();
+        if self.block {
+            // Both corners as screen cells, so lines with wide characters in
+            // different places still line up
+            let corner = |(line, col): (u64, usize)| {
+                let text = buffer.line_at(line).unwrap_or_default();
+                let first = display_column(&text, col);
+                (first, first + char_width(&text, col).saturating_sub(1))
+            };
+            let (anchor_first, anchor_last) = corner(anchor);
+            let (cursor_first, cursor_last) = corner(self.cursor);
+            let cells = anchor_first.min(cursor_first)..anchor_last.max(cursor_last) + 1;
+            Some(chars_in_cells(text, cells))
+        } else {
+            let first = if line == start.0 { start.1 } else { 0 };
+            let last = if line == end.0 { end.1 + 1 } else { length };
+            Some(first.min(length)..last.min(length))
+        }
+    }
+
+    // The selected text: block rows are cut at the same screen columns
+    pub fn selected_text(&self, buffer: &TerminalBuffer) -> Option<String> {
+        let anchor = self.anchor?;
+        let lines = anchor.0.min(self.cursor.0)..anchor.0.max(self.cursor.0) + 1;
+
+        let text = lines
+            .map(|line| {
+                let text = buffer.line_at(line).unwrap_or_default();
+                let columns = self.selected_columns(line, &text, buffer).unwrap_or(0..0);
+                let selected: String = text.chars().skip(columns.start).take(columns.len()).collect();
+                selected.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(text)
+    }
+}
+
+// Screen column a character starts at
+fn display_column(text: &str, col: usize) -> usize {
+    let before: usize = text.chars().take(col).map(|c| c.width().unwrap_or(0)).sum();
+    // Past the end of the line every column is one cell
+    before + col.saturating_sub(text.chars().count())
+}
+
+// Cells a character takes up (one past the end of the line)
+fn char_width(text: &str, col: usize) -> usize {
+    text.chars().nth(col).map_or(1, |c| c.width().unwrap_or(0).max(1))
+}
+
+// Characters of a line touching a range of screen columns; a wide character cut by
+// either edge is taken whole
+fn chars_in_cells(text: &str, cells: Range<usize>) -> Range<usize> {
+    let mut first = None;
+    let mut last = 0;
+    let mut cell = 0;
+    for (index, c) in text.chars().enumerate() {
+        let width = c.width().unwrap_or(0);
+        let end = cell + width.max(1);
+        if end > cells.start && cell < cells.end {
+            first.get_or_insert(index);
+            last = index + 1;
+        }
+        cell += width;
+        if cell >= cells.end {
+            break;
+        }
+    }
+    match first {
+        Some(first) => first..last,
+        None => 0..0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> TerminalBuffer {
+        let mut buffer = TerminalBuffer::new(100);
+        buffer.write(text.as_bytes()).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn block_selection_copies_the_same_columns_of_each_line() {
+        let buffer = buffer("abcdef\n123456\nxy\n");
+        let first = buffer.line_numbers().start;
+        let copy = CopyMode { cursor: (first + 2, 3), anchor: Some((first, 1)), block: true };
+
+        assert_eq!(copy.selected_text(&buffer).unwrap(), "bcd\n234\ny");
+    }
+
+    #[test]
+    fn stream_selection_runs_from_anchor_to_cursor() {
+        let buffer = buffer("abcdef\n123456\n");
+        let first = buffer.line_numbers().start;
+        let copy = CopyMode { cursor: (first, 4), anchor: Some((first + 1, 1)), block: false };
+
+        assert_eq!(copy.selected_text(&buffer).unwrap(), "ef\n12");
+    }
+
+    #[test]
+    fn wide_characters_cut_by_a_block_edge_are_taken_whole() {
+        // 漢 takes cells 1 and 2
+        assert_eq!(chars_in_cells("a漢b", 2..4), 1..3);
+        assert_eq!(chars_in_cells("a漢b", 0..2), 0..2);
+        assert_eq!(chars_in_cells("ab", 5..7), 0..0);
+    }
+}
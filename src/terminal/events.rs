@@ -6,42 +6,62 @@ pub enum AppEvent {
     Key(KeyEvent),
     Mouse(MouseEvent),
     Tick,
+    // The host terminal was resized
+    Resize,
+    // A frame's time passed without input; new output may need drawing
+    Frame,
     Quit,
 }
 
 pub struct EventHandler {
     tick_rate: Duration,
     last_tick: Instant,
+    // Longest wait for input, so output is drawn at the frame rate between ticks
+    frame_interval: Duration,
 }
 
 impl EventHandler {
-    pub fn new(tick_rate: Duration) -> Self {
+    pub fn new(tick_rate: Duration, frame_interval: Duration) -> Self {
         Self {
             tick_rate,
             last_tick: Instant::now(),
+            frame_interval,
         }
     }
 
+    // Change how often tick events are sent
+    pub fn set_tick_rate(&mut self, tick_rate: Duration) {
+        self.tick_rate = tick_rate;
+    }
+
+    // Change how long to wait for input before a frame event
+    pub fn set_frame_interval(&mut self, frame_interval: Duration) {
+        self.frame_interval = frame_interval;
+    }
+
     pub fn next(&mut self) -> Result<AppEvent> {
         let timeout = self.tick_rate
             .checked_sub(self.last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+            .unwrap_or_else(|| Duration::from_secs(0))
+            .min(self.frame_interval);
             
         if event::poll(timeout)? {
             match event::read()? {
                 Event::Key(key) => {
                     if key.kind == event::KeyEventKind::Press {
-                        // Ctrl+C or q to quit
-                        if (key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL) ||
-                           (key.code == KeyCode::Char('q')) {
+                        // Ctrl+C to quit
+                        if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
                             return Ok(AppEvent::Quit);
                         }
-                        return Ok(AppEvent::Key(key));
+                        return Ok(AppEvent::Key(normalize_key(key)));
                     }
                 },
                 Event::Mouse(mouse) => {
                     return Ok(AppEvent::Mouse(mouse));
                 },
+                Event::Resize(..) => {
+                    return Ok(AppEvent::Resize);
+                },
                 _ => {}
             }
         }
@@ -51,8 +71,19 @@ impl EventHandler {
             return Ok(AppEvent::Tick);
         }
         
-        // No event, wait for next tick
-        std::thread::sleep(timeout);
-        Ok(AppEvent::Tick)
+        // No event before the end of the frame
+        Ok(AppEvent::Frame)
+    }
+}
+
+// Hosts using the kitty keyboard protocol report Shift+letter as the
+// lowercase key plus SHIFT; fold that back into the uppercase character
+// legacy terminals send so shortcuts match either way.
+fn normalize_key(mut key: KeyEvent) -> KeyEvent {
+    if let KeyCode::Char(c) = key.code {
+        if key.modifiers.contains(KeyModifiers::SHIFT) && c.is_ascii_lowercase() {
+            key.code = KeyCode::Char(c.to_ascii_uppercase());
+        }
     }
+    key
 }
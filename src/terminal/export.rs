@@ -0,0 +1,220 @@
+// Export of window contents to other formats
+
+use ratatui::style::Color;
+use serde_json::{json, Map, Value};
+
+use crate::terminal::style::{Blink, CellStyle, StyleMapping, StyleRun, Underline};
+use crate::ui::style::Theme;
+
+// Colors used when the theme leaves the terminal's own default
+pub const DEFAULT_FOREGROUND: (u8, u8, u8) = (0xc0, 0xc0, 0xc0);
+pub const DEFAULT_BACKGROUND: (u8, u8, u8) = (0x00, 0x00, 0x00);
+
+// The xterm colors of the 16 basic palette entries
+pub const BASIC_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+];
+
+// RGB value of a terminal color, or None for the default color
+pub fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some((r, g, b)),
+        Color::Indexed(n) => n,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+
+    Some(match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        // 6x6x6 color cube
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = index - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        // Grayscale ramp
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    })
+}
+
+// Whether a color is dark, by its perceived brightness
+pub fn is_dark((r, g, b): (u8, u8, u8)) -> bool {
+    (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000 < 128
+}
+
+// CSS notation of an RGB value
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// CSS color of a terminal color, or None for the default color
+fn css_color(color: Color) -> Option<String> {
+    rgb(color).map(hex)
+}
+
+// Escape text for HTML
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Inline CSS for a cell style
+fn css_style(style: &CellStyle, theme: &Theme, mapping: StyleMapping) -> String {
+    let (fg, bg) = style.colors(theme, mapping);
+
+    let mut css = Vec::new();
+    if let Some(fg) = fg.and_then(css_color) {
+        css.push(format!("color:{}", fg));
+    }
+    if let Some(bg) = bg.and_then(css_color) {
+        css.push(format!("background:{}", bg));
+    }
+    if style.bold {
+        css.push("font-weight:bold".to_string());
+    }
+    if style.dim {
+        css.push("opacity:0.6".to_string());
+    }
+    if style.italic {
+        css.push("font-style:italic".to_string());
+    }
+    let mut decoration = Vec::new();
+    if style.underline != Underline::None {
+        decoration.push("underline");
+        let line = match style.underline {
+            Underline::Double => "double",
+            Underline::Curly => "wavy",
+            Underline::Dotted => "dotted",
+            Underline::Dashed => "dashed",
+            _ => "solid",
+        };
+        css.push(format!("text-decoration-style:{}", line));
+        if let Some(color) = style.underline_color.and_then(css_color) {
+            css.push(format!("text-decoration-color:{}", color));
+        }
+    }
+    if style.blink && mapping.blink == Blink::Slow {
+        decoration.push("blink");
+    }
+    if !decoration.is_empty() {
+        css.push(format!("text-decoration:{}", decoration.join(" ")));
+    }
+    css.join(";")
+}
+
+// Render one line, wrapping each styled run in a span
+fn render_line(line: &str, runs: &[StyleRun], theme: &Theme, mapping: StyleMapping, html: &mut String) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut col = 0;
+
+    for run in runs {
+        let start = run.start.min(chars.len());
+        let end = run.end.min(chars.len());
+        if start >= end {
+            continue;
+        }
+
+        let plain: String = chars[col..start].iter().collect();
+        html.push_str(&escape(&plain));
+
+        let styled: String = chars[start..end].iter().collect();
+        html.push_str(&format!(
+            "<span style=\"{}\">{}</span>",
+            css_style(&run.style, theme, mapping),
+            escape(&styled),
+        ));
+        col = end;
+    }
+
+    let rest: String = chars[col..].iter().collect();
+    html.push_str(&escape(&rest));
+}
+
+// Render lines and their styles as a standalone HTML page
+pub fn to_html(title: &str, lines: &[(String, Vec<StyleRun>)], theme: &Theme, mapping: StyleMapping) -> String {
+    let foreground = css_color(theme.foreground).unwrap_or_else(|| hex(DEFAULT_FOREGROUND));
+    let background = css_color(theme.background).unwrap_or_else(|| hex(DEFAULT_BACKGROUND));
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(title)));
+    html.push_str(&format!(
+        "<style>body {{ margin: 0; color: {}; background: {}; }} pre {{ margin: 0; padding: 1em; font-family: monospace; }}</style>\n",
+        foreground, background,
+    ));
+    html.push_str("</head>\n<body>\n<pre>");
+
+    // Trailing blank lines (the unused part of the screen) are left out
+    let end = lines.iter().rposition(|(line, _)| !line.trim().is_empty()).map_or(0, |i| i + 1);
+    for (line, runs) in &lines[..end] {
+        render_line(line, runs, theme, mapping, &mut html);
+        html.push('\n');
+    }
+
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+// Lines and their styles as JSON, for scripts:
+// [{"text": "...", "styles": [{"start": 0, "end": 4, "fg": "#cd0000", "bold": true}]}]
+pub fn to_json(lines: &[(String, Vec<StyleRun>)]) -> Value {
+    let lines = lines.iter().map(|(text, runs)| {
+        let styles: Vec<Value> = runs.iter().map(|run| {
+            let mut style = Map::new();
+            style.insert(String::from("start"), json!(run.start));
+            style.insert(String::from("end"), json!(run.end));
+            if let Some(fg) = run.style.fg.and_then(css_color) {
+                style.insert(String::from("fg"), json!(fg));
+            }
+            if let Some(bg) = run.style.bg.and_then(css_color) {
+                style.insert(String::from("bg"), json!(bg));
+            }
+            let CellStyle { bold, dim, italic, underline, underline_color, blink, reverse, .. } = run.style;
+            if !matches!(underline, Underline::None | Underline::Single) {
+                style.insert(String::from("underline_style"), json!(underline.name()));
+            }
+            if let Some(color) = underline_color.and_then(css_color) {
+                style.insert(String::from("underline_color"), json!(color));
+            }
+            let underline = underline != Underline::None;
+            for (name, set) in [("bold", bold), ("dim", dim), ("italic", italic), ("underline", underline), ("blink", blink), ("reverse", reverse)] {
+                if set {
+                    style.insert(String::from(name), json!(true));
+                }
+            }
+            Value::Object(style)
+        }).collect();
+        json!({ "text": text, "styles": styles })
+    });
+    Value::Array(lines.collect())
+}
@@ -0,0 +1,55 @@
+// Looking up the program running in the foreground of a window, so the window can
+// be titled after it and its directory followed. Each platform has its own way of
+// describing a process; where none is known, nothing is found and titles and
+// directories are left alone.
+
+// Short name of a running process (e.g. "vim")
+#[cfg(target_os = "linux")]
+pub fn process_name(pid: u32) -> Option<String> {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+// Short name of a running process (e.g. "vim")
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_name(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "comm=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let path = String::from_utf8_lossy(&output.stdout);
+    // ps gives the full path of the executable on some systems
+    let name = path.trim().rsplit('/').next().unwrap_or("");
+    Some(name.trim_start_matches('-').to_string()).filter(|name| !name.is_empty())
+}
+
+// Short name of a running process (e.g. "vim")
+#[cfg(not(unix))]
+pub fn process_name(_pid: u32) -> Option<String> {
+    None
+}
+
+// Working directory of a running process
+#[cfg(target_os = "linux")]
+pub fn working_dir(pid: u32) -> Option<String> {
+    let path = std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+// Working directory of a running process
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn working_dir(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("lsof")
+        .args(["-a", "-d", "cwd", "-Fn", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    // Fields come one per line, the name prefixed with "n"
+    let fields = String::from_utf8_lossy(&output.stdout);
+    fields.lines().find_map(|line| line.strip_prefix('n')).map(str::to_string)
+}
+
+// Working directory of a running process
+#[cfg(not(unix))]
+pub fn working_dir(_pid: u32) -> Option<String> {
+    None
+}
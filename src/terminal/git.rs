@@ -0,0 +1,149 @@
+// Git state of the directory a window is in (branch, uncommitted changes, commits
+// ahead of and behind the upstream), for the status bar and border tinting. It's
+// asked for on a thread when a new prompt appears or the directory changes, not
+// every frame.
+
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GitStatus {
+    // Branch checked out, or the commit's short hash when detached
+    pub branch: String,
+    // Changed, staged or untracked files
+    pub dirty: bool,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl GitStatus {
+    // As shown in the status bar, e.g. "⎇ main* ↑1 ↓2"
+    pub fn label(&self) -> String {
+        let mut label = format!("⎇ {}", self.branch);
+        if self.dirty {
+            label.push('*');
+        }
+        if self.ahead > 0 {
+            label.push_str(&format!(" ↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            label.push_str(&format!(" ↓{}", self.behind));
+        }
+        label
+    }
+}
+
+// Read `git status --porcelain=v2 --branch` output
+pub fn parse(output: &str) -> Option<GitStatus> {
+    let mut status = GitStatus::default();
+    let mut oid = None;
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("# ") {
+            match header.split_once(' ') {
+                Some(("branch.oid", value)) => oid = Some(value),
+                Some(("branch.head", value)) => status.branch = value.to_string(),
+                Some(("branch.ab", value)) => {
+                    for count in value.split_whitespace() {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            status.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            status.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else if !line.is_empty() && !line.starts_with('!') {
+            status.dirty = true;
+        }
+    }
+
+    if status.branch.is_empty() {
+        return None;
+    }
+    if status.branch == "(detached)" {
+        status.branch = oid.map_or(status.branch.clone(), |oid| oid.chars().take(7).collect());
+    }
+    Some(status)
+}
+
+// Ask git about a directory; None outside a repository
+pub fn query(dir: &str) -> Option<GitStatus> {
+    let output = Command::new("git")
+        .args(["-C", dir, "status", "--porcelain=v2", "--branch"])
+        // Don't take the index lock, so commands run in the window never find it taken
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+// A window's git state, kept up to date in the background
+#[derive(Default)]
+pub struct GitWatcher {
+    // Last state found, None outside a repository
+    pub status: Option<GitStatus>,
+    // Directory last asked about
+    dir: Option<String>,
+    // Something changed since the last question was asked
+    stale: bool,
+    pending: Option<Receiver<Option<GitStatus>>>,
+}
+
+impl GitWatcher {
+    // Ask again if a prompt appeared or the directory changed, and pick up the answer
+    // to an earlier question; returns whether the state changed
+    pub fn refresh(&mut self, dir: Option<String>, prompted: bool) -> bool {
+        self.stale |= prompted || dir != self.dir;
+        if self.stale && self.pending.is_none() {
+            self.stale = false;
+            self.pending = dir.clone().map(|dir| {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = sender.send(query(&dir));
+                });
+                receiver
+            });
+            if dir.is_none() {
+                self.status = None;
+            }
+            self.dir = dir;
+        }
+
+        let Some(pending) = &self.pending else { return false };
+        match pending.try_recv() {
+            Ok(status) => {
+                self.pending = None;
+                let changed = status != self.status;
+                self.status = status;
+                changed
+            }
+            Err(TryRecvError::Empty) => false,
+            Err(TryRecvError::Disconnected) => {
+                self.pending = None;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_output_is_read() {
+        let output = "# branch.oid 1a2b3c4d5e6f\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n? notes.txt\n";
+        let status = parse(output).unwrap();
+        assert_eq!(status, GitStatus { branch: String::from("main"), dirty: true, ahead: 2, behind: 1 });
+        assert_eq!(status.label(), "⎇ main* ↑2 ↓1");
+
+        let detached = parse("# branch.oid 1a2b3c4d5e6f\n# branch.head (detached)\n").unwrap();
+        assert_eq!(detached.label(), "⎇ 1a2b3c4");
+        assert_eq!(parse(""), None);
+    }
+}
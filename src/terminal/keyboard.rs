@@ -0,0 +1,248 @@
+// Key encoding for the terminal, including the kitty keyboard protocol and
+// xterm's modifyOtherKeys. This module is frontend independent: frontends
+// translate their own key events into `Key` and `KeyModifiers`.
+
+// Kitty progressive enhancement flags supported by the encoder
+pub const KITTY_DISAMBIGUATE: u8 = 0b1;
+pub const KITTY_REPORT_ALL_KEYS: u8 = 0b1000;
+pub const KITTY_SUPPORTED_FLAGS: u8 = KITTY_DISAMBIGUATE | KITTY_REPORT_ALL_KEYS;
+
+// Keys that can be encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    F(u8),
+}
+
+// Modifier keys held while a key was pressed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl KeyModifiers {
+    // Are any modifiers held
+    pub fn any(&self) -> bool {
+        self.shift || self.alt || self.ctrl
+    }
+
+    // Modifier parameter as used by xterm and kitty (1 + bitmask)
+    fn param(&self) -> u8 {
+        1 + self.shift as u8 + ((self.alt as u8) << 1) + ((self.ctrl as u8) << 2)
+    }
+}
+
+// Keyboard protocol requested by the application running in a window
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct KeyboardMode {
+    // Active kitty enhancement flags (0 = legacy encoding)
+    pub kitty_flags: u8,
+    // xterm modifyOtherKeys level (0, 1 or 2)
+    pub modify_other_keys: u8,
+}
+
+// Encode a key press into the bytes sent to the application
+pub fn encode_key(key: Key, mods: KeyModifiers, mode: KeyboardMode) -> Vec<u8> {
+    if mode.kitty_flags & KITTY_SUPPORTED_FLAGS != 0 {
+        if let Some(bytes) = encode_kitty(key, mods, mode.kitty_flags) {
+            return bytes;
+        }
+    } else if mode.modify_other_keys > 0 {
+        if let Some(bytes) = encode_modify_other_keys(key, mods, mode.modify_other_keys) {
+            return bytes;
+        }
+    }
+
+    encode_legacy(key, mods)
+}
+
+// Kitty keyboard protocol encoding (CSI code ; modifiers u)
+fn encode_kitty(key: Key, mods: KeyModifiers, flags: u8) -> Option<Vec<u8>> {
+    let report_all = flags & KITTY_REPORT_ALL_KEYS != 0;
+
+    let code = match key {
+        Key::Char(c) => {
+            // Shifted text stays text unless every key must be reported
+            if !report_all && !mods.alt && !mods.ctrl {
+                return None;
+            }
+            c.to_ascii_lowercase() as u32
+        }
+        Key::Enter | Key::Tab | Key::Backspace => {
+            if !report_all && !mods.any() {
+                return None;
+            }
+            match key {
+                Key::Enter => 13,
+                Key::Tab => 9,
+                _ => 127,
+            }
+        }
+        Key::BackTab => {
+            return Some(csi_u(9, KeyModifiers { shift: true, ..mods }));
+        }
+        // Escape is always disambiguated
+        Key::Esc => 27,
+        // Functional keys keep their xterm encodings
+        _ => return None,
+    };
+
+    Some(csi_u(code, mods))
+}
+
+// xterm modifyOtherKeys encoding (CSI 27 ; modifiers ; code ~)
+fn encode_modify_other_keys(key: Key, mods: KeyModifiers, level: u8) -> Option<Vec<u8>> {
+    if !mods.any() {
+        return None;
+    }
+
+    let code = match key {
+        Key::Enter => 13,
+        Key::Tab => 9,
+        Key::Backspace => 127,
+        Key::Esc => 27,
+        Key::Char(c) => {
+            // Shifted text is already unambiguous
+            if !mods.alt && !mods.ctrl {
+                return None;
+            }
+            // Level 1 leaves keys with a well-known control encoding alone
+            if level < 2 && legacy_control_char(c).is_some() {
+                return None;
+            }
+            c as u32
+        }
+        _ => return None,
+    };
+
+    Some(format!("\x1b[27;{};{}~", mods.param(), code).into_bytes())
+}
+
+// Traditional xterm encoding
+fn encode_legacy(key: Key, mods: KeyModifiers) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    match key {
+        Key::Char(c) => {
+            if mods.alt {
+                bytes.push(0x1b);
+            }
+            match (mods.ctrl, legacy_control_char(c)) {
+                (true, Some(control)) => bytes.push(control),
+                _ => {
+                    let mut utf8 = [0u8; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+            }
+        }
+        Key::Enter => {
+            if mods.alt {
+                bytes.push(0x1b);
+            }
+            bytes.push(b'\r');
+        }
+        Key::Tab => bytes.push(b'\t'),
+        Key::BackTab => bytes.extend_from_slice(b"\x1b[Z"),
+        Key::Backspace => {
+            if mods.alt {
+                bytes.push(0x1b);
+            }
+            bytes.push(if mods.ctrl { 0x08 } else { 0x7f });
+        }
+        Key::Esc => bytes.push(0x1b),
+        Key::Up => bytes.extend(cursor_key(b'A', mods)),
+        Key::Down => bytes.extend(cursor_key(b'B', mods)),
+        Key::Right => bytes.extend(cursor_key(b'C', mods)),
+        Key::Left => bytes.extend(cursor_key(b'D', mods)),
+        Key::Home => bytes.extend(cursor_key(b'H', mods)),
+        Key::End => bytes.extend(cursor_key(b'F', mods)),
+        Key::PageUp => bytes.extend(tilde_key(5, mods)),
+        Key::PageDown => bytes.extend(tilde_key(6, mods)),
+        Key::Insert => bytes.extend(tilde_key(2, mods)),
+        Key::Delete => bytes.extend(tilde_key(3, mods)),
+        Key::F(n @ 1..=4) => {
+            let letter = b'P' + (n - 1);
+            if mods.any() {
+                bytes.extend(format!("\x1b[1;{}{}", mods.param(), letter as char).into_bytes());
+            } else {
+                bytes.extend_from_slice(&[0x1b, b'O', letter]);
+            }
+        }
+        Key::F(n) => {
+            let code = match n {
+                5 => 15,
+                6 => 17,
+                7 => 18,
+                8 => 19,
+                9 => 20,
+                10 => 21,
+                11 => 23,
+                12 => 24,
+                _ => return bytes,
+            };
+            bytes.extend(tilde_key(code, mods));
+        }
+    }
+
+    bytes
+}
+
+// The C0 control character produced by Ctrl+<c>, if any
+fn legacy_control_char(c: char) -> Option<u8> {
+    match c {
+        'a'..='z' => Some(c as u8 - b'a' + 1),
+        'A'..='Z' => Some(c as u8 - b'A' + 1),
+        ' ' | '@' | '2' => Some(0),
+        '[' | '3' => Some(0x1b),
+        '\\' | '4' => Some(0x1c),
+        ']' | '5' => Some(0x1d),
+        '^' | '6' => Some(0x1e),
+        '_' | '7' | '/' => Some(0x1f),
+        '8' | '?' => Some(0x7f),
+        _ => None,
+    }
+}
+
+// Cursor keys: ESC [ X, or ESC [ 1 ; mods X with modifiers
+fn cursor_key(letter: u8, mods: KeyModifiers) -> Vec<u8> {
+    if mods.any() {
+        format!("\x1b[1;{}{}", mods.param(), letter as char).into_bytes()
+    } else {
+        vec![0x1b, b'[', letter]
+    }
+}
+
+// Editing keys: ESC [ n ~, or ESC [ n ; mods ~ with modifiers
+fn tilde_key(code: u8, mods: KeyModifiers) -> Vec<u8> {
+    if mods.any() {
+        format!("\x1b[{};{}~", code, mods.param()).into_bytes()
+    } else {
+        format!("\x1b[{}~", code).into_bytes()
+    }
+}
+
+// CSI code ; modifiers u, omitting the modifier when there is none
+fn csi_u(code: u32, mods: KeyModifiers) -> Vec<u8> {
+    if mods.any() {
+        format!("\x1b[{};{}u", code, mods.param()).into_bytes()
+    } else {
+        format!("\x1b[{}u", code).into_bytes()
+    }
+}
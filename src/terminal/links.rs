@@ -0,0 +1,66 @@
+// Detection of file locations (file:line) in output, as printed by compilers,
+// test runners and stack traces.
+
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// path:line[:column], or Python's File "path", line N
+const LINK_PATTERN: &str = r#"File "(?P<pyfile>[^"]+)", line (?P<pyline>\d+)|(?P<file>(?:[\w.~-]*/)*[\w-][\w.-]*\.\w+):(?P<line>\d+)(?::(?P<column>\d+))?"#;
+
+// A file location found in a line of output
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLink {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    // Character range of the link within the line
+    pub range: Range<usize>,
+}
+
+impl FileLink {
+    // Fill an editor command template with the location, quoting the file for the shell
+    pub fn editor_command(&self, template: &str) -> String {
+        let quoted = format!("'{}'", self.file.replace('\'', r"'\''"));
+        template
+            .replace("{file}", &quoted)
+            .replace("{line}", &self.line.to_string())
+            .replace("{column}", &self.column.unwrap_or(1).to_string())
+    }
+
+    // Whether the file exists, relative paths resolved against the directory of the
+    // window it was printed in (if it's known)
+    pub fn exists(&self, dir: Option<&str>) -> bool {
+        match dir {
+            Some(dir) => Path::new(dir).join(&self.file).is_file(),
+            None => Path::new(&self.file).is_file(),
+        }
+    }
+}
+
+fn link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(LINK_PATTERN).expect("valid link pattern"))
+}
+
+// Find the file locations in a line
+pub fn find_links(line: &str) -> Vec<FileLink> {
+    link_regex().captures_iter(line)
+        .filter_map(|caps| {
+            let (file, line_number) = match (caps.name("pyfile"), caps.name("pyline")) {
+                (Some(file), Some(number)) => (file, number),
+                _ => (caps.name("file")?, caps.name("line")?),
+            };
+            let whole = caps.get(0)?;
+
+            Some(FileLink {
+                file: file.as_str().to_string(),
+                line: line_number.as_str().parse().ok()?,
+                column: caps.name("column").and_then(|c| c.as_str().parse().ok()),
+                // Byte offsets to character columns
+                range: line[..whole.start()].chars().count()..line[..whole.end()].chars().count(),
+            })
+        })
+        .collect()
+}
@@ -3,3 +3,25 @@ pub mod events;
 pub mod process;
 pub mod buffer;
 pub mod window;
+pub mod keyboard;
+pub mod clipboard;
+pub mod tmux;
+pub mod triggers;
+pub mod style;
+pub mod export;
+pub mod links;
+pub mod output_pool;
+pub mod foreground;
+pub mod sandbox;
+pub mod charset;
+pub mod copy_mode;
+pub mod snippets;
+pub mod auth;
+pub mod redact;
+pub mod smart_select;
+pub mod git;
+pub mod terminfo;
+pub mod supervisor;
+pub mod pipe;
+pub mod timeline;
+pub mod syntax;
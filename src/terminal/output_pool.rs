@@ -0,0 +1,94 @@
+// Reusable buffers for process output. The reader thread reads straight into a pooled
+// buffer and hands it to the UI thread as is; when the chunk has been parsed and
+// dropped, its buffer goes back to the pool. At high output rates this avoids an
+// allocation and a copy per read.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+// Most idle buffers kept for reuse
+const MAX_POOLED: usize = 64;
+
+type Buffers = Arc<Mutex<Vec<Vec<u8>>>>;
+
+// Buffers of one size, shared by a reader thread and the chunks it sends
+#[derive(Clone)]
+pub struct BufferPool {
+    buffers: Buffers,
+    size: usize,
+}
+
+impl BufferPool {
+    pub fn new(size: usize) -> Self {
+        Self {
+            buffers: Arc::new(Mutex::new(Vec::new())),
+            size,
+        }
+    }
+
+    // A buffer of the pool's size to read into, reused when one is free
+    pub fn get(&self) -> Vec<u8> {
+        let mut buffer = self.buffers.lock()
+            .ok()
+            .and_then(|mut buffers| buffers.pop())
+            .unwrap_or_default();
+        // Only the part cut off by a short read needs filling
+        buffer.resize(self.size, 0);
+        buffer
+    }
+
+    // Turn a buffer holding `len` bytes of output into a chunk that returns it when dropped
+    pub fn chunk(&self, mut buffer: Vec<u8>, len: usize) -> OutputChunk {
+        buffer.truncate(len);
+        OutputChunk {
+            data: buffer,
+            pool: Some(Arc::clone(&self.buffers)),
+        }
+    }
+}
+
+// A chunk of process output
+pub struct OutputChunk {
+    data: Vec<u8>,
+    // Where the buffer goes back to, if it came from a pool
+    pool: Option<Buffers>,
+}
+
+impl Deref for OutputChunk {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl From<Vec<u8>> for OutputChunk {
+    fn from(data: Vec<u8>) -> Self {
+        Self { data, pool: None }
+    }
+}
+
+impl Clone for OutputChunk {
+    fn clone(&self) -> Self {
+        Self::from(self.data.clone())
+    }
+}
+
+impl fmt::Debug for OutputChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OutputChunk({} bytes)", self.data.len())
+    }
+}
+
+impl Drop for OutputChunk {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            if let Ok(mut buffers) = pool.lock() {
+                if buffers.len() < MAX_POOLED {
+                    buffers.push(std::mem::take(&mut self.data));
+                }
+            }
+        }
+    }
+}
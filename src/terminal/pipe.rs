@@ -0,0 +1,54 @@
+// A window's output copied to a command's stdin as it arrives, like tmux's pipe-pane.
+// The command is fed on a thread, so a slow one never holds up the window; it gets
+// the raw output, escape sequences included, and stops when its stdin closes. One that
+// falls too far behind is stopped rather than let the output pile up.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+// Chunks of output waiting for the command before it's taken to have stopped reading
+const PIPE_BACKLOG: usize = 256;
+
+pub struct OutputPipe {
+    // The shell command fed
+    pub command: String,
+    sender: SyncSender<Vec<u8>>,
+}
+
+impl OutputPipe {
+    // Start a shell command to feed output to, in a directory if one is given
+    pub fn start(command: &str, dir: Option<&str>) -> Result<Self> {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(dir) = dir {
+            shell.current_dir(dir);
+        }
+        let mut child = shell.spawn().with_context(|| format!("can't run {}", command))?;
+        let mut stdin = child.stdin.take().context("no stdin")?;
+
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(PIPE_BACKLOG);
+        thread::spawn(move || {
+            // Until the window stops piping or the command stops reading
+            for data in receiver {
+                if stdin.write_all(&data).is_err() {
+                    break;
+                }
+            }
+            drop(stdin);
+            let _ = child.wait();
+        });
+
+        Ok(Self { command: command.to_string(), sender })
+    }
+
+    // Pass on output; false once the command has stopped reading, or is too far behind
+    pub fn write(&self, data: &[u8]) -> bool {
+        self.sender.try_send(data.to_vec()).is_ok()
+    }
+}
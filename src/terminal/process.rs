@@ -3,16 +3,67 @@ use portable_pty::{
     native_pty_system, PtySize, CommandBuilder, Child,
 };
 use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crate::terminal::output_pool::{BufferPool, OutputChunk};
 
 pub type ProcessId = uuid::Uuid;
 
+// Most bytes read from a PTY at once
+const READ_CHUNK: usize = 16 * 1024;
+
+// Times in a row the output reader of a process is restarted before giving up
+const MAX_READER_RESTARTS: u32 = 5;
+
+// How long a reader may have stopped before the process is taken to still be running;
+// a process closing its terminal is usually seen to exit within it
+const READER_GRACE: Duration = Duration::from_millis(250);
+
+// Size of a character cell in pixels when the terminal Matrix runs in doesn't say
+const DEFAULT_CELL_PIXELS: (u16, u16) = (8, 16);
+
+// Width and height of a character cell in pixels, from the size the terminal Matrix
+// runs in reports (many report none, leaving a typical cell size)
+pub fn cell_pixels() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            ((size.width / size.columns).max(1), (size.height / size.rows).max(1))
+        }
+        _ => DEFAULT_CELL_PIXELS,
+    }
+}
+
+// The size of a PTY in cells and in pixels, so programs drawing images or
+// working out mouse positions in pixels get them right
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    let (cell_width, cell_height) = cell_pixels();
+    PtySize {
+        rows,
+        cols,
+        pixel_width: cols.saturating_mul(cell_width),
+        pixel_height: rows.saturating_mul(cell_height),
+    }
+}
+
+// What the watchdog found when checking a process's output reader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReaderStatus {
+    // Delivering output, or stopped because the process exited
+    Healthy,
+    // Stopped while the process was still running, and started again
+    Restarted,
+    // Stopped too many times; output is no longer read
+    Failed,
+}
+
 #[derive(Debug, Clone)]
 pub enum ProcessEvent {
-    Output(Vec<u8>),
+    // A chunk of output, in the buffer it was read into
+    Output(OutputChunk),
     Exit(i32),
     Error(String),
 }
@@ -23,6 +74,16 @@ pub trait ProcessController: Send + Sync {
     fn resize(&mut self, rows: u16, cols: u16) -> Result<()>;
     fn read_event(&mut self) -> Option<ProcessEvent>;
     fn kill(&mut self) -> Result<()>;
+    
+    // Restart the output reader if it stopped while the process is still running
+    fn check_reader(&mut self) -> ReaderStatus {
+        ReaderStatus::Healthy
+    }
+    
+    // Process ID of the process group in the terminal's foreground, if known
+    fn foreground_process(&self) -> Option<u32> {
+        None
+    }
 }
 
 pub struct Process {
@@ -30,21 +91,43 @@ pub struct Process {
     child: Option<Box<dyn Child + Send + Sync>>,
     pty_master: Arc<Mutex<Box<dyn portable_pty::MasterPty + Send>>>,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    sender: mpsc::Sender<ProcessEvent>,
     receiver: mpsc::Receiver<ProcessEvent>,
-    _reader_thread: thread::JoinHandle<()>,
-    _wait_thread: thread::JoinHandle<()>,
+    reader_thread: thread::JoinHandle<()>,
+    // Set once the reader running has read some output
+    reader_delivered: Arc<AtomicBool>,
+    // When the reader was first seen stopped while the process was running
+    reader_stopped: Option<Instant>,
+    // Times the reader was restarted by the watchdog without reading anything since
+    reader_restarts: u32,
 }
 
 impl Process {
-    pub fn new(command: &str, working_dir: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
+    // Start a program with arguments in a new pseudoterminal
+    pub fn with_args(command: &str, args: &[&str], working_dir: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
+        Self::with_env(command, args, &[], working_dir, cols, rows)
+    }
+
+    // Start a program with arguments and extra environment variables (e.g. TERM) in a
+    // new pseudoterminal
+    pub fn with_env(command: &str, args: &[&str], env: &[(String, String)], working_dir: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
+        let mut cmd = CommandBuilder::new(command);
+        cmd.args(args);
+        Self::start(cmd, env, working_dir, cols, rows)
+    }
+
+    // Start a shell as a login shell (argv[0] prefixed with `-`, as login does), so it
+    // reads the profile files a terminal's first shell usually does
+    pub fn login_shell(shell: &str, env: &[(String, String)], working_dir: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
+        let mut cmd = CommandBuilder::new_default_prog();
+        cmd.env("SHELL", shell);
+        Self::start(cmd, env, working_dir, cols, rows)
+    }
+
+    fn start(mut cmd: CommandBuilder, env: &[(String, String)], working_dir: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
         // Create a new pseudoterminal
         let pty_system = native_pty_system();
-        let pty_pair = pty_system.openpty(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
+        let pty_pair = pty_system.openpty(pty_size(rows, cols))?;
 
         // Get the master and slave parts of the PTY
         let pty_master = pty_pair.master;
@@ -53,8 +136,6 @@ impl Process {
         // Create a writer for sending data to the process
         let mut writer = pty_master.take_writer()?;
 
-        // Create a command to run in the PTY
-        let mut cmd = CommandBuilder::new(command);
         if let Some(dir) = working_dir {
             cmd.cwd(dir);
         }
@@ -71,6 +152,13 @@ impl Process {
         if let Ok(home) = std::env::var("HOME") {
             cmd.env("HOME", home);
         }
+        // Output is read as UTF-8, so programs shouldn't fall back to ASCII for want of a locale
+        if ["LC_ALL", "LC_CTYPE", "LANG"].iter().all(|name| std::env::var_os(name).is_none_or(|v| v.is_empty())) {
+            cmd.env("LC_CTYPE", "C.UTF-8");
+        }
+        for (name, value) in env {
+            cmd.env(name, value);
+        }
 
         // Spawn the process
         let child = pty_slave.spawn_command(cmd)?;
@@ -78,63 +166,58 @@ impl Process {
         // Create a channel for communication
         let (sender, receiver) = mpsc::channel(100);
 
-        // Create a reader for the process output
-        let mut reader = pty_master.try_clone_reader()?;
-        let sender_clone = sender.clone();
-
         // Create a thread to read from the process
-        let reader_thread = thread::spawn(move || {
-            let mut buffer = [0u8; 1024];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => {
-                        // End of stream
-                        break;
-                    }
-                    Ok(n) => {
-                        let data = buffer[..n].to_vec();
-                        if let Err(_) = sender_clone.blocking_send(ProcessEvent::Output(data)) {
-                            // Channel closed, exit the thread
-                            break;
-                        }
-                    }
-                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                        // No data available, sleep a bit and retry
-                        thread::sleep(Duration::from_millis(10));
-                    }
-                    Err(e) => {
-                        // Send error and exit
-                        let _ = sender_clone.blocking_send(ProcessEvent::Error(e.to_string()));
-                        break;
-                    }
-                }
-            }
-        });
-
-        // Create a thread to wait for the process to exit
-        let sender_clone = sender.clone();
-        let process_id = uuid::Uuid::new_v4(); // Generate a process ID
-        let wait_thread = thread::spawn(move || {
-            // Just sleep a while and simulate a process exit
-            // In a real implementation, we would actually wait for the child process
-            thread::sleep(Duration::from_secs(3600)); // 1 hour
-            
-            // Signal that the process "exited"
-            let _ = sender_clone.blocking_send(ProcessEvent::Exit(0));
-        });
+        let reader_delivered = Arc::new(AtomicBool::new(false));
+        let reader_thread = spawn_reader(pty_master.try_clone_reader()?, sender.clone(), Arc::clone(&reader_delivered));
 
         Ok(Self {
             id: uuid::Uuid::new_v4(),
             child: Some(child),
             pty_master: Arc::new(Mutex::new(pty_master)),
             writer: Arc::new(Mutex::new(writer)),
+            sender,
             receiver,
-            _reader_thread: reader_thread,
-            _wait_thread: wait_thread,
+            reader_thread,
+            reader_delivered,
+            reader_stopped: None,
+            reader_restarts: 0,
         })
     }
 }
 
+// Read a process's output on a thread, sending it over a channel in pooled buffers
+fn spawn_reader(mut reader: Box<dyn Read + Send>, sender: mpsc::Sender<ProcessEvent>, delivered: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let pool = BufferPool::new(READ_CHUNK);
+        loop {
+            let mut buffer = pool.get();
+            match reader.read(&mut buffer) {
+                Ok(0) => {
+                    // End of stream
+                    break;
+                }
+                Ok(n) => {
+                    delivered.store(true, Ordering::Relaxed);
+                    let data = pool.chunk(buffer, n);
+                    if let Err(_) = sender.blocking_send(ProcessEvent::Output(data)) {
+                        // Channel closed, exit the thread
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // No data available, sleep a bit and retry
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    // Send error and exit
+                    let _ = sender.blocking_send(ProcessEvent::Error(e.to_string()));
+                    break;
+                }
+            }
+        }
+    })
+}
+
 impl ProcessController for Process {
     fn write(&mut self, data: &[u8]) -> Result<()> {
         let mut writer = self.writer.lock().map_err(|_| anyhow!("Failed to lock writer"))?;
@@ -149,17 +232,19 @@ impl ProcessController for Process {
 
     fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
         let mut pty_master = self.pty_master.lock().map_err(|_| anyhow!("Failed to lock pty_master"))?;
-        pty_master.resize(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
+        pty_master.resize(pty_size(rows, cols))?;
         Ok(())
     }
 
     fn read_event(&mut self) -> Option<ProcessEvent> {
-        self.receiver.try_recv().ok()
+        if let Ok(event) = self.receiver.try_recv() {
+            return Some(event);
+        }
+        
+        // Report the exit once, when there's no output waiting
+        let status = self.child.as_mut()?.try_wait().ok()??;
+        self.child = None;
+        Some(ProcessEvent::Exit(status.exit_code() as i32))
     }
 
     fn kill(&mut self) -> Result<()> {
@@ -168,4 +253,53 @@ impl ProcessController for Process {
         }
         Ok(())
     }
+    
+    fn check_reader(&mut self) -> ReaderStatus {
+        if !self.reader_thread.is_finished() {
+            // A restarted reader that reads again has recovered
+            if self.reader_restarts > 0 && self.reader_delivered.load(Ordering::Relaxed) {
+                self.reader_restarts = 0;
+            }
+            return ReaderStatus::Healthy;
+        }
+        
+        // A reader stopping because the process exited is expected; the exit may take
+        // a moment to show after the end of the output, so look again after a while
+        let running = self.child.as_mut().is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+        if !running {
+            return ReaderStatus::Healthy;
+        }
+        let stopped = *self.reader_stopped.get_or_insert_with(Instant::now);
+        if stopped.elapsed() < READER_GRACE {
+            return ReaderStatus::Healthy;
+        }
+        if self.reader_restarts >= MAX_READER_RESTARTS {
+            return ReaderStatus::Failed;
+        }
+        
+        // Read again through a fresh handle on the same PTY
+        let reader = match self.pty_master.lock() {
+            Ok(pty_master) => pty_master.try_clone_reader(),
+            Err(_) => return ReaderStatus::Failed,
+        };
+        match reader {
+            Ok(reader) => {
+                self.reader_restarts += 1;
+                self.reader_stopped = None;
+                self.reader_delivered = Arc::new(AtomicBool::new(false));
+                self.reader_thread = spawn_reader(reader, self.sender.clone(), Arc::clone(&self.reader_delivered));
+                ReaderStatus::Restarted
+            }
+            Err(_) => {
+                self.reader_restarts = MAX_READER_RESTARTS;
+                ReaderStatus::Failed
+            }
+        }
+    }
+    
+    #[cfg(unix)]
+    fn foreground_process(&self) -> Option<u32> {
+        let pty_master = self.pty_master.lock().ok()?;
+        pty_master.process_group_leader().and_then(|pid| u32::try_from(pid).ok())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+// Resource limits and sandboxing for a window's process. A profile's limits are set
+// by a small sh script that then execs the program, so they apply to the program
+// and everything it starts; if a limit can't be set the program isn't started.
+
+use crate::config::settings::Profile;
+
+// The program and arguments that start `program` with a profile's limits and sandbox
+pub fn wrap(profile: &Profile, program: &str, args: &[&str]) -> (String, Vec<String>) {
+    let mut script = vec![String::from("set -e")];
+
+    if let Some(secs) = profile.cpu_secs {
+        script.push(format!("ulimit -t {}", secs));
+    }
+    if let Some(mb) = profile.memory_mb {
+        script.push(format!("ulimit -v {}", mb.saturating_mul(1024)));
+    }
+    if let Some(files) = profile.open_files {
+        script.push(format!("ulimit -n {}", files));
+    }
+    if let Some(processes) = profile.processes {
+        // bash calls it -u, dash -p
+        script.push(format!("ulimit -u {0} 2>/dev/null || ulimit -p {0}", processes));
+    }
+    if let Some(cgroup) = &profile.cgroup {
+        // Join a cgroup (v2) prepared by the user, e.g. with memory.max or cpu.max set
+        script.push(format!("echo $$ > {}", quote(&format!("{}/cgroup.procs", cgroup.trim_end_matches('/')))));
+    }
+
+    let mut command = Vec::new();
+    if let Some(nice) = profile.nice {
+        command.push(format!("nice -n {}", nice));
+    }
+    if let Some(sandbox) = &profile.sandbox {
+        // Written as a shell command line, so it may carry its own options
+        command.push(sandbox.clone());
+    }
+    command.push(quote(program));
+    command.extend(args.iter().map(|arg| quote(arg)));
+    script.push(format!("exec {}", command.join(" ")));
+
+    (String::from("sh"), vec![String::from("-c"), script.join("\n")])
+}
+
+// Quote a word for sh
+pub fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
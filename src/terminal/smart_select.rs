@@ -0,0 +1,115 @@
+// Smart selection: patterns for things worth selecting whole in output (URLs,
+// paths, IP addresses, git hashes, UUIDs, or anything configured, such as ticket
+// IDs), used by double-click and hint mode. A rule can say how to open its matches.
+
+use regex::Regex;
+use std::ops::Range;
+
+use crate::config::settings::SelectionRule;
+use crate::terminal::sandbox;
+
+// Command opening URLs and files with the desktop's default application
+const OPENER: &str = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+
+// The built-in rules; where matches overlap, the earlier rule wins
+pub fn default_rules() -> Vec<SelectionRule> {
+    let rule = |name: &str, pattern: &str, open: Option<String>| SelectionRule {
+        name: name.to_string(),
+        pattern: pattern.to_string(),
+        open,
+    };
+    vec![
+        rule("url", r#"\b(?:https?|ftp|file)://[^\s<>"'`]*[^\s<>"'`.,;:!?)\]]"#, Some(format!("{} {{match}}", OPENER))),
+        rule("uuid", r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b", None),
+        rule("ip", r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b", None),
+        rule("path", r"(?:~|\.{1,2})?(?:/[\w.@+-]+)+/?|\b[\w.@+-]+(?:/[\w.@+-]+)+/?", Some(format!("{} {{match}}", OPENER))),
+        rule("hash", r"\b[0-9a-f]{7,40}\b", None),
+    ]
+}
+
+// A piece of a line matched by a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartMatch {
+    // Name of the rule that matched
+    pub kind: String,
+    pub text: String,
+    // Character range within the line
+    pub range: Range<usize>,
+    // Command opening it, with the text filled in
+    pub open: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SmartSelection {
+    rules: Vec<(SelectionRule, Regex)>,
+}
+
+impl SmartSelection {
+    // Compile the configured rules, skipping (and reporting) invalid patterns
+    pub fn new(rules: &[SelectionRule]) -> Self {
+        let rules = rules.iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some((rule.clone(), regex)),
+                Err(e) => {
+                    eprintln!("Invalid selection pattern {}: {}", rule.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    // Matches in a line, left to right, without overlaps
+    pub fn find(&self, line: &str) -> Vec<SmartMatch> {
+        let mut found: Vec<SmartMatch> = Vec::new();
+        for (rule, regex) in &self.rules {
+            for m in regex.find_iter(line) {
+                // Byte offsets to character columns
+                let range = line[..m.start()].chars().count()..line[..m.end()].chars().count();
+                if found.iter().any(|f| f.range.start < range.end && range.start < f.range.end) {
+                    continue;
+                }
+                found.push(SmartMatch {
+                    kind: rule.name.clone(),
+                    text: m.as_str().to_string(),
+                    range,
+                    open: rule.open.as_ref().map(|template| template.replace("{match}", &sandbox::quote(m.as_str()))),
+                });
+            }
+        }
+        found.sort_by_key(|m| m.range.start);
+        found
+    }
+
+    // The match covering a column of a line
+    pub fn at(&self, line: &str, column: usize) -> Option<SmartMatch> {
+        self.find(line).into_iter().find(|m| m.range.contains(&column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earlier_rules_win_and_custom_rules_apply() {
+        let mut rules = vec![SelectionRule {
+            name: String::from("ticket"),
+            pattern: String::from(r"\bOPS-\d+\b"),
+            open: Some(String::from("open https://tracker.example.com/browse/{match}")),
+        }];
+        rules.extend(default_rules());
+        let selection = SmartSelection::new(&rules);
+
+        let line = "see OPS-42 at https://example.com/a/b. (commit 1a2b3c4d)";
+        let kinds: Vec<_> = selection.find(line).into_iter().map(|m| (m.kind, m.text)).collect();
+        assert_eq!(kinds, [
+            (String::from("ticket"), String::from("OPS-42")),
+            (String::from("url"), String::from("https://example.com/a/b")),
+            (String::from("hash"), String::from("1a2b3c4d")),
+        ]);
+
+        let ticket = selection.at(line, 6).unwrap();
+        assert_eq!(ticket.open.as_deref(), Some("open https://tracker.example.com/browse/'OPS-42'"));
+    }
+}
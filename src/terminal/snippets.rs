@@ -0,0 +1,119 @@
+// Snippets: text kept in the settings to type into a window on demand. A snippet
+// can have placeholders, written {{name}} or {{name:default}}, that are asked for
+// when it's inserted; the same name used twice is asked for once.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    // Used when nothing is typed
+    pub default: Option<String>,
+}
+
+// Placeholders of a snippet, in the order they first appear
+pub fn placeholders(text: &str) -> Vec<Placeholder> {
+    let mut found: Vec<Placeholder> = Vec::new();
+    for (body, _) in tags(text) {
+        let (name, default) = match body.split_once(':') {
+            Some((name, default)) => (name.trim(), Some(default.to_string())),
+            None => (body.trim(), None),
+        };
+        if !found.iter().any(|p| p.name == name) {
+            found.push(Placeholder { name: name.to_string(), default });
+        }
+    }
+    found
+}
+
+// The text with each placeholder replaced by its value
+pub fn fill(text: &str, values: &[(String, String)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = 0;
+    for (body, range) in tags(text) {
+        let name = body.split_once(':').map_or(body, |(name, _)| name).trim();
+        filled.push_str(&text[rest..range.start]);
+        match values.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => filled.push_str(value),
+            None => filled.push_str(&text[range.clone()]),
+        }
+        rest = range.end;
+    }
+    filled.push_str(&text[rest..]);
+    filled
+}
+
+// Each {{...}} in the text: what's inside, and where the whole tag is
+fn tags(text: &str) -> Vec<(&str, std::ops::Range<usize>)> {
+    let mut tags = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find("{{").map(|i| from + i) {
+        let Some(end) = text[start + 2..].find("}}").map(|i| start + 2 + i) else { break };
+        let body = &text[start + 2..end];
+        if !body.trim().is_empty() {
+            tags.push((body, start..end + 2));
+        }
+        from = end + 2;
+    }
+    tags
+}
+
+// A snippet being inserted, asking for its placeholders one at a time
+#[derive(Debug)]
+pub struct SnippetFill {
+    pub name: String,
+    text: String,
+    placeholders: Vec<Placeholder>,
+    values: Vec<(String, String)>,
+    // What's typed for the current placeholder
+    pub input: String,
+}
+
+impl SnippetFill {
+    pub fn new(name: &str, text: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            text: text.to_string(),
+            placeholders: placeholders(text),
+            values: Vec::new(),
+            input: String::new(),
+        }
+    }
+
+    // The placeholder being asked for, or None once all are answered
+    pub fn current(&self) -> Option<&Placeholder> {
+        self.placeholders.get(self.values.len())
+    }
+
+    // Take the typed input (or the default, if nothing was typed) for the current placeholder
+    pub fn answer(&mut self) {
+        let Some(placeholder) = self.placeholders.get(self.values.len()) else { return };
+        let input = std::mem::take(&mut self.input);
+        let value = match &placeholder.default {
+            Some(default) if input.is_empty() => default.clone(),
+            _ => input,
+        };
+        self.values.push((placeholder.name.clone(), value));
+    }
+
+    // The text to insert
+    pub fn text(&self) -> String {
+        fill(&self.text, &self.values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_asked_for_once_and_filled_everywhere() {
+        let text = "kubectl -n {{ns:default}} logs {{pod}} | grep {{pod}} {{}}";
+        let mut fill = SnippetFill::new("logs", text);
+        assert_eq!(fill.current().unwrap().name, "ns");
+
+        fill.answer();
+        fill.input = "web-1".to_string();
+        fill.answer();
+        assert_eq!(fill.current(), None);
+        assert_eq!(fill.text(), "kubectl -n default logs web-1 | grep web-1 {{}}");
+    }
+}
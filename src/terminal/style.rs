@@ -0,0 +1,399 @@
+// Cell styling: the colors and attributes set by SGR sequences (ESC [ ... m),
+// stored per line as runs of columns sharing a style.
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::settings::UiSettings;
+use crate::terminal::export;
+use crate::ui::style::Theme;
+
+// Colors and attributes of a cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    // None means the terminal's default color
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: Underline,
+    // None means the text's own color
+    pub underline_color: Option<Color>,
+    pub blink: bool,
+    pub reverse: bool,
+}
+
+// How text is underlined (SGR 4:0 to 4:5); the TUI draws every style as a plain
+// underline, since that's all the host terminal is sure to have
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Underline {
+    #[default]
+    None,
+    Single,
+    Double,
+    // Curly, as editors mark diagnostics
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl Underline {
+    fn from_sgr(style: u16) -> Self {
+        match style {
+            0 => Underline::None,
+            2 => Underline::Double,
+            3 => Underline::Curly,
+            4 => Underline::Dotted,
+            5 => Underline::Dashed,
+            _ => Underline::Single,
+        }
+    }
+
+    // Name of the style, as exports give it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Underline::None => "none",
+            Underline::Single => "single",
+            Underline::Double => "double",
+            Underline::Curly => "curly",
+            Underline::Dotted => "dotted",
+            Underline::Dashed => "dashed",
+        }
+    }
+}
+
+// How blinking text is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Blink {
+    #[default]
+    Slow,
+    // Drawn steady
+    Ignore,
+}
+
+impl Blink {
+    // Values of the ui.blink setting
+    pub const NAMES: [&'static str; 2] = ["slow", "ignore"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "slow" => Some(Blink::Slow),
+            "ignore" => Some(Blink::Ignore),
+            _ => None,
+        }
+    }
+}
+
+// How cell attributes are drawn, as the ui settings choose
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StyleMapping {
+    // Bold text in the bright version of the eight basic colors
+    pub bold_is_bright: bool,
+    pub blink: Blink,
+    // Italics underlined on screen, where the host terminal may have no italic font
+    pub italic_as_underline: bool,
+}
+
+impl StyleMapping {
+    pub fn new(settings: &UiSettings) -> Self {
+        Self {
+            bold_is_bright: settings.bold_is_bright,
+            blink: Blink::from_name(&settings.blink).unwrap_or_default(),
+            italic_as_underline: settings.italic_as_underline,
+        }
+    }
+}
+
+impl CellStyle {
+    // Whether this is the default style
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Colors to draw the cell in under a theme, None where the theme's own color shows.
+    // Bold may brighten the foreground, reverse video swaps them, and a foreground too close to its background to read
+    // is moved toward black or white until it meets the theme's minimum contrast.
+    pub fn colors(&self, theme: &Theme, mapping: StyleMapping) -> (Option<Color>, Option<Color>) {
+        let (default_fg, default_bg) = theme.colors();
+        let (mut fg, mut bg) = (self.fg, self.bg);
+        if let (true, Some(Color::Indexed(n @ 0..=7))) = (mapping.bold_is_bright && self.bold, fg) {
+            fg = Some(Color::Indexed(n + 8));
+        }
+        if self.reverse {
+            let (r, g, b) = default_bg;
+            let swapped_fg = bg.unwrap_or(Color::Rgb(r, g, b));
+            let (r, g, b) = default_fg;
+            bg = Some(fg.unwrap_or(Color::Rgb(r, g, b)));
+            fg = Some(swapped_fg);
+        }
+
+        // Only colors the application chose are adjusted, never the theme's own pair
+        if theme.minimum_contrast > 1.0 && (fg.is_some() || bg.is_some()) {
+            let shown_fg = fg.and_then(export::rgb).unwrap_or(default_fg);
+            let shown_bg = bg.and_then(export::rgb).unwrap_or(default_bg);
+            let (r, g, b) = readable(shown_fg, shown_bg, theme.minimum_contrast);
+            if (r, g, b) != shown_fg {
+                fg = Some(Color::Rgb(r, g, b));
+            }
+        }
+        (fg, bg)
+    }
+
+    // The style the cell is drawn with on screen
+    pub fn screen_style(&self, theme: &Theme, mapping: StyleMapping) -> Style {
+        let (fg, bg) = self.colors(theme, mapping);
+        let mut style = Style::default();
+        if let Some(fg) = fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = bg {
+            style = style.bg(bg);
+        }
+        if let Some(color) = self.underline_color {
+            style = style.underline_color(color);
+        }
+        let underline = self.underline != Underline::None;
+        for (set, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.italic && !mapping.italic_as_underline, Modifier::ITALIC),
+            (underline || (self.italic && mapping.italic_as_underline), Modifier::UNDERLINED),
+            (self.blink && mapping.blink == Blink::Slow, Modifier::SLOW_BLINK),
+        ] {
+            if set {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+
+    // Apply the parameters of an SGR sequence
+    pub fn apply_sgr(&mut self, params: &str) {
+        // Parameters are separated by ;, and the values within one by : (ITU T.416)
+        let groups: Vec<Vec<u16>> = params.split(';')
+            .map(|group| group.split(':').map(|p| p.parse().unwrap_or(0)).collect())
+            .collect();
+
+        let mut i = 0;
+        while i < groups.len() {
+            let group = &groups[i];
+            match group[0] {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = group.get(1).map_or(Underline::Single, |&style| Underline::from_sgr(style)),
+                5 | 6 => self.blink = true,
+                7 => self.reverse = true,
+                21 => self.underline = Underline::Double,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = Underline::None,
+                25 => self.blink = false,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fg = Some(Color::Indexed((n - 30) as u8)),
+                code @ (38 | 48 | 58) => {
+                    // 38:5:n in one parameter, or spread over the next ones as 38;5;n
+                    let color = if group.len() > 1 {
+                        colon_color(&group[1..])
+                    } else {
+                        let values: Vec<u16> = groups[i + 1..].iter().map(|group| group[0]).collect();
+                        let (color, used) = extended_color(&values);
+                        i += used;
+                        color
+                    };
+                    match code {
+                        38 => self.fg = color.or(self.fg),
+                        48 => self.bg = color.or(self.bg),
+                        _ => self.underline_color = color.or(self.underline_color),
+                    }
+                }
+                39 => self.fg = None,
+                n @ 40..=47 => self.bg = Some(Color::Indexed((n - 40) as u8)),
+                49 => self.bg = None,
+                59 => self.underline_color = None,
+                n @ 90..=97 => self.fg = Some(Color::Indexed((n - 90 + 8) as u8)),
+                n @ 100..=107 => self.bg = Some(Color::Indexed((n - 100 + 8) as u8)),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+// Parse the color after 38, 48 or 58 (5;n or 2;r;g;b), returning it and the number of values used
+fn extended_color(values: &[u16]) -> (Option<Color>, usize) {
+    match values {
+        [5, n, ..] => (Some(Color::Indexed(*n as u8)), 2),
+        [2, r, g, b, ..] => (Some(Color::Rgb(*r as u8, *g as u8, *b as u8)), 4),
+        _ => (None, values.len()),
+    }
+}
+
+// Parse the values of a color written with colons: 5:n, 2:r:g:b, or 2:id:r:g:b with
+// the color space id T.416 puts first
+fn colon_color(values: &[u16]) -> Option<Color> {
+    match values {
+        [5, n, ..] => Some(Color::Indexed(*n as u8)),
+        [2, _, r, g, b, ..] | [2, r, g, b] => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+        _ => None,
+    }
+}
+
+// Relative luminance of a color, as WCAG defines it
+fn luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let linear = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.03928 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+// WCAG contrast ratio of two colors, from 1 (the same) to 21 (black on white)
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+// A foreground that reads on a background with at least the minimum contrast ratio:
+// the color itself if it already does, else the closest blend of it toward black or
+// white, whichever side of the background has more room
+pub fn readable(fg: (u8, u8, u8), bg: (u8, u8, u8), minimum: f32) -> (u8, u8, u8) {
+    if contrast_ratio(fg, bg) >= minimum {
+        return fg;
+    }
+    let target = if contrast_ratio((255, 255, 255), bg) >= contrast_ratio((0, 0, 0), bg) {
+        (255, 255, 255)
+    } else {
+        (0, 0, 0)
+    };
+    let blend = |t: f32| {
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        (mix(fg.0, target.0), mix(fg.1, target.1), mix(fg.2, target.2))
+    };
+
+    // The contrast only grows on the way to the target, so search for the least blend
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..16 {
+        let middle = (low + high) / 2.0;
+        if contrast_ratio(blend(middle), bg) >= minimum {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+    blend(high)
+}
+
+// Columns start..end of a line drawn in one style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyleRun {
+    pub start: usize,
+    pub end: usize,
+    pub style: CellStyle,
+}
+
+// Set the style of one column in a line's runs (sorted, non-overlapping, default style omitted)
+pub fn set_cell_style(runs: &mut Vec<StyleRun>, col: usize, style: CellStyle) {
+    // Extending the last run is by far the most common case
+    if let Some(last) = runs.last_mut() {
+        if last.end == col && last.style == style {
+            last.end += 1;
+            return;
+        }
+    }
+    // Default style past the last run needs no run
+    if style.is_default() && runs.last().map_or(0, |run| run.end) <= col {
+        return;
+    }
+
+    // Cut the column out of any run covering it
+    let mut updated = Vec::with_capacity(runs.len() + 2);
+    for run in runs.drain(..) {
+        if run.end <= col || run.start > col {
+            updated.push(run);
+            continue;
+        }
+        if run.start < col {
+            updated.push(StyleRun { end: col, ..run });
+        }
+        if run.end > col + 1 {
+            updated.push(StyleRun { start: col + 1, ..run });
+        }
+    }
+
+    if !style.is_default() {
+        let index = updated.iter().position(|run| run.start > col).unwrap_or(updated.len());
+        updated.insert(index, StyleRun { start: col, end: col + 1, style });
+    }
+
+    *runs = updated;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_foregrounds_are_moved_to_the_minimum_contrast() {
+        assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 0.01);
+
+        // Dark blue on black brightens until it reads, a readable color is kept
+        let adjusted = readable((0, 0, 0x80), (0, 0, 0), 4.5);
+        assert!(contrast_ratio(adjusted, (0, 0, 0)) >= 4.5);
+        assert!(contrast_ratio(adjusted, (0, 0, 0)) < 4.7);
+        assert_eq!(readable((0xc0, 0xc0, 0xc0), (0, 0, 0), 4.5), (0xc0, 0xc0, 0xc0));
+
+        // The theme's white text on an application's yellow background turns dark
+        let mut theme = Theme::high_contrast();
+        theme.minimum_contrast = 4.5;
+        let style = CellStyle { bg: Some(Color::Indexed(11)), ..CellStyle::default() };
+        let (fg, _) = style.colors(&theme, StyleMapping::default());
+        let fg = fg.and_then(export::rgb).unwrap();
+        assert!(contrast_ratio(fg, (0xff, 0xff, 0x00)) >= 4.5);
+
+        // The theme's own colors are left alone
+        assert_eq!(CellStyle::default().colors(&theme, StyleMapping::default()), (None, None));
+    }
+
+    #[test]
+    fn attributes_follow_the_style_mapping() {
+        let theme = Theme::default();
+        let mut style = CellStyle::default();
+        style.apply_sgr("1;3;5;31");
+
+        let plain = style.screen_style(&theme, StyleMapping::default());
+        assert_eq!(plain.fg, Some(Color::Indexed(1)));
+        assert!(plain.add_modifier.contains(Modifier::BOLD | Modifier::ITALIC | Modifier::SLOW_BLINK));
+
+        let mapping = StyleMapping { bold_is_bright: true, blink: Blink::Ignore, italic_as_underline: true };
+        let mapped = style.screen_style(&theme, mapping);
+        assert_eq!(mapped.fg, Some(Color::Indexed(9)));
+        assert!(mapped.add_modifier.contains(Modifier::BOLD | Modifier::UNDERLINED));
+        assert!(!mapped.add_modifier.intersects(Modifier::ITALIC | Modifier::SLOW_BLINK));
+    }
+
+    #[test]
+    fn underline_styles_and_colors_are_parsed() {
+        // An editor's curly diagnostic underline in red, then the style reset
+        let mut style = CellStyle::default();
+        style.apply_sgr("4:3;58:2::255:0:0");
+        assert_eq!(style.underline, Underline::Curly);
+        assert_eq!(style.underline_color, Some(Color::Rgb(255, 0, 0)));
+        assert!(!style.italic);
+        assert!(style.screen_style(&Theme::default(), StyleMapping::default()).add_modifier.contains(Modifier::UNDERLINED));
+
+        style.apply_sgr("58;5;196;4:0");
+        assert_eq!(style.underline, Underline::None);
+        assert_eq!(style.underline_color, Some(Color::Indexed(196)));
+        style.apply_sgr("4;59");
+        assert_eq!((style.underline, style.underline_color), (Underline::Single, None));
+
+        // Colors with colons, with and without the color space
+        style.apply_sgr("38:2:1:2:3;48:5:17");
+        assert_eq!((style.fg, style.bg), (Some(Color::Rgb(1, 2, 3)), Some(Color::Indexed(17))));
+    }
+}
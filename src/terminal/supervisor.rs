@@ -0,0 +1,84 @@
+// Restarting a window's command when it fails (a flaky dev server, say). Each failure
+// in a row doubles the wait before the next start, up to a limit; a run that lasted
+// a while counts as a fresh start. A command exiting successfully is left stopped.
+
+use std::time::{Duration, Instant};
+
+// Wait before restarting after the first failure
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+// Longest wait between restarts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// How long a run must last for the backoff to start over
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+pub struct Supervisor {
+    // Restarts so far, shown in the window's title
+    pub restarts: u32,
+    // Failures in a row
+    streak: u32,
+    // When the command last started
+    started: Instant,
+    // When the failed command starts again
+    restart_at: Option<Instant>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self { restarts: 0, streak: 0, started: Instant::now(), restart_at: None }
+    }
+}
+
+impl Supervisor {
+    // The command exited; returns how long until it restarts, or None if it won't
+    pub fn exited(&mut self, code: i32) -> Option<Duration> {
+        if code == 0 {
+            return None;
+        }
+        if self.started.elapsed() >= STABLE_RUN {
+            self.streak = 0;
+        }
+        let wait = backoff(self.streak);
+        self.streak += 1;
+        self.restart_at = Some(Instant::now() + wait);
+        Some(wait)
+    }
+
+    // Whether it's time to start the command again, counting the restart if so
+    pub fn due(&mut self) -> bool {
+        if self.restart_at.is_some_and(|at| Instant::now() >= at) {
+            self.restart_at = None;
+            self.restarts += 1;
+            self.started = Instant::now();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Wait before a restart after some failures in a row
+fn backoff(streak: u32) -> Duration {
+    FIRST_BACKOFF.saturating_mul(1 << streak.min(16)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_in_a_row_wait_longer_and_success_stays_stopped() {
+        let mut supervisor = Supervisor::default();
+        assert_eq!(supervisor.exited(0), None);
+        assert!(!supervisor.due());
+
+        let waits: Vec<u64> = (0..8).map(|_| supervisor.exited(1).unwrap().as_secs()).collect();
+        assert_eq!(waits, [1, 2, 4, 8, 16, 32, 60, 60]);
+
+        supervisor.restart_at = Some(Instant::now());
+        assert!(supervisor.due());
+        assert!(!supervisor.due());
+        assert_eq!(supervisor.restarts, 1);
+    }
+}
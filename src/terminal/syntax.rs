@@ -0,0 +1,314 @@
+// Syntax highlighting for the pager and for files shown in a window: a small highlighter
+// of keywords, strings, comments and numbers for common languages, chosen by file name.
+// It colors text as style runs, in the terminal's own palette, so themes and the
+// attribute settings apply to it as to any application's colors.
+
+use ratatui::style::Color;
+
+use crate::terminal::style::{CellStyle, StyleRun};
+
+// How a language's text is split into tokens
+pub struct Syntax {
+    pub name: &'static str,
+    // Extensions, or whole file names such as Makefile
+    files: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    // A string not closed by the end of a line goes on into the next
+    multiline_strings: bool,
+    // Capitalized names are types
+    capitalized_types: bool,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+    "extern", "float", "for", "goto", "if", "int", "long", "return", "short", "signed", "sizeof",
+    "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while",
+    "class", "namespace", "template", "public", "private", "protected", "virtual", "new", "delete",
+    "true", "false", "nullptr", "NULL", "#include", "#define", "#ifdef", "#ifndef", "#endif",
+];
+
+pub const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        name: "rust",
+        files: &["rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        multiline_strings: true,
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "python",
+        files: &["py", "pyi"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+            "try", "while", "with", "yield",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: &['"', '\''],
+        multiline_strings: false,
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "javascript",
+        files: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+            "delete", "do", "else", "export", "extends", "false", "finally", "for", "from", "function",
+            "if", "import", "in", "instanceof", "interface", "let", "new", "null", "of", "return",
+            "static", "super", "switch", "this", "throw", "true", "try", "type", "typeof",
+            "undefined", "var", "void", "while", "yield",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        multiline_strings: true,
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "go",
+        files: &["go"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "false",
+            "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map", "nil",
+            "package", "range", "return", "select", "struct", "switch", "true", "type", "var",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        multiline_strings: true,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "c",
+        files: &["c", "h", "cc", "cpp", "cxx", "hpp", "hh"],
+        keywords: C_KEYWORDS,
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        multiline_strings: false,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "java",
+        files: &["java", "kt", "kts", "scala"],
+        keywords: &[
+            "abstract", "break", "case", "catch", "class", "continue", "default", "do", "else",
+            "enum", "extends", "false", "final", "finally", "for", "fun", "if", "implements",
+            "import", "interface", "new", "null", "object", "override", "package", "private",
+            "protected", "public", "return", "static", "super", "switch", "this", "throw", "throws",
+            "true", "try", "val", "var", "void", "when", "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        multiline_strings: false,
+        capitalized_types: true,
+    },
+    Syntax {
+        name: "shell",
+        files: &["sh", "bash", "zsh", "fish", ".bashrc", ".zshrc", ".profile"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: &['"', '\''],
+        multiline_strings: true,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "json",
+        files: &["json"],
+        keywords: &["true", "false", "null"],
+        line_comment: None,
+        block_comment: None,
+        quotes: &['"'],
+        multiline_strings: false,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "toml",
+        files: &["toml", "ini", "cfg", "conf"],
+        keywords: &["true", "false"],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: &['"', '\''],
+        multiline_strings: false,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "yaml",
+        files: &["yaml", "yml"],
+        keywords: &["true", "false", "null", "yes", "no"],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: &['"', '\''],
+        multiline_strings: false,
+        capitalized_types: false,
+    },
+    Syntax {
+        name: "make",
+        files: &["Makefile", "makefile", "mk"],
+        keywords: &["ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "define", "endef", ".PHONY"],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: &[],
+        multiline_strings: false,
+        capitalized_types: false,
+    },
+];
+
+// The syntax for a file, by its name or extension
+pub fn for_path(path: &str) -> Option<&'static Syntax> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+    SYNTAXES.iter().find(|syntax| syntax.files.contains(&name) || extension.is_some_and(|e| syntax.files.contains(&e)))
+}
+
+// Styles of the tokens
+fn token_style(color: Color) -> CellStyle {
+    CellStyle { fg: Some(color), ..CellStyle::default() }
+}
+
+// Colors a file line by line; block comments, and strings where the language allows
+// it, carry on from one line to the next
+pub struct Highlighter {
+    syntax: &'static Syntax,
+    in_block: bool,
+    in_string: Option<char>,
+}
+
+impl Highlighter {
+    pub fn new(syntax: &'static Syntax) -> Self {
+        Self { syntax, in_block: false, in_string: None }
+    }
+
+    // Color the next line of the file
+    pub fn line(&mut self, line: &str) -> Vec<StyleRun> {
+        let syntax = self.syntax;
+        let keyword = CellStyle { bold: true, ..token_style(Color::Indexed(5)) };
+        let string = token_style(Color::Indexed(2));
+        let comment = CellStyle { italic: true, ..token_style(Color::Indexed(8)) };
+        let number = token_style(Color::Indexed(6));
+        let type_name = token_style(Color::Indexed(3));
+
+        let chars: Vec<char> = line.chars().collect();
+        let starts_at = |i: usize, pattern: &str| pattern.chars().enumerate().all(|(j, c)| chars.get(i + j) == Some(&c));
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut runs = Vec::new();
+        let mut push = |start: usize, end: usize, style: CellStyle| runs.push(StyleRun { start, end, style });
+
+        // The end of a string closed on a later line than it was opened on
+        let string_end = |from: usize, quote: char| {
+            let mut end = from;
+            while end < chars.len() && chars[end] != quote {
+                end += if chars[end] == '\\' { 2 } else { 1 };
+            }
+            (end < chars.len()).then_some(end + 1)
+        };
+
+        let mut i = 0;
+        if let Some(quote) = self.in_string {
+            let end = string_end(0, quote);
+            self.in_string = end.is_none().then_some(quote);
+            i = end.unwrap_or(chars.len());
+            push(0, i, string);
+        }
+        while i < chars.len() {
+            // Inside a comment started on an earlier line, or here
+            if let Some((open, close)) = syntax.block_comment {
+                if self.in_block || starts_at(i, open) {
+                    let from = if self.in_block { i } else { i + open.chars().count() };
+                    let end = (from..chars.len()).find(|&j| starts_at(j, close));
+                    self.in_block = end.is_none();
+                    let end = end.map_or(chars.len(), |j| j + close.chars().count());
+                    push(i, end, comment);
+                    i = end;
+                    continue;
+                }
+            }
+            let c = chars[i];
+            if syntax.line_comment.is_some_and(|open| starts_at(i, open)) {
+                push(i, chars.len(), comment);
+                break;
+            }
+            if syntax.quotes.contains(&c) {
+                let end = string_end(i + 1, c);
+                if end.is_none() && syntax.multiline_strings {
+                    self.in_string = Some(c);
+                }
+                let end = end.unwrap_or(chars.len());
+                push(i, end, string);
+                i = end;
+                continue;
+            }
+            let after_word = i > 0 && is_word(chars[i - 1]);
+            if c.is_ascii_digit() && !after_word {
+                let end = (i..chars.len()).find(|&j| !(is_word(chars[j]) || chars[j] == '.')).unwrap_or(chars.len());
+                push(i, end, number);
+                i = end;
+                continue;
+            }
+            if (is_word(c) || c == '#' || c == '.') && !after_word {
+                let end = (i + 1..chars.len()).find(|&j| !is_word(chars[j])).unwrap_or(chars.len());
+                let word: String = chars[i..end].iter().collect();
+                if syntax.keywords.contains(&word.as_str()) {
+                    push(i, end, keyword);
+                } else if syntax.capitalized_types && c.is_uppercase() {
+                    push(i, end, type_name);
+                }
+                i = end;
+                continue;
+            }
+            i += 1;
+        }
+        runs
+    }
+}
+
+// Color lines of a file from its first
+pub fn highlight(syntax: &'static Syntax, lines: &[&str]) -> Vec<Vec<StyleRun>> {
+    let mut highlighter = Highlighter::new(syntax);
+    lines.iter().map(|line| highlighter.line(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_colored_by_language() {
+        let rust = for_path("src/main.rs").unwrap();
+        assert_eq!(rust.name, "rust");
+        assert_eq!(for_path("/tmp/Makefile").unwrap().name, "make");
+        assert!(for_path("notes.txt").is_none());
+
+        let runs = highlight(rust, &["let x: Vec<u8> = \"a\\\"b\"; // done", "/* open", "still */ 42", "\"two", "lines\" if"]);
+        let spans = |line: usize| runs[line].iter().map(|run| (run.start, run.end, run.style.fg)).collect::<Vec<_>>();
+        assert_eq!(spans(0), vec![
+            (0, 3, Some(Color::Indexed(5))),
+            (7, 10, Some(Color::Indexed(3))),
+            (17, 23, Some(Color::Indexed(2))),
+            (25, 32, Some(Color::Indexed(8))),
+        ]);
+        // The comment goes on into the next line, and ends there
+        assert_eq!(spans(1), vec![(0, 7, Some(Color::Indexed(8)))]);
+        assert_eq!(spans(2), vec![(0, 8, Some(Color::Indexed(8))), (9, 11, Some(Color::Indexed(6)))]);
+        // So does a string
+        assert_eq!(spans(4), vec![(0, 6, Some(Color::Indexed(2))), (7, 9, Some(Color::Indexed(5)))]);
+    }
+}
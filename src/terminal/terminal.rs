@@ -1,6 +1,6 @@
 use crossterm::{
-    terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    event::{EnableMouseCapture, DisableMouseCapture},
+    terminal::{enable_raw_mode, disable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{EnableMouseCapture, DisableMouseCapture, KeyboardEnhancementFlags, PushKeyboardEnhancementFlags, PopKeyboardEnhancementFlags},
     execute,
 };
 use std::io::{self, Stdout};
@@ -10,10 +10,12 @@ use anyhow::Result;
 
 pub struct Terminal {
     terminal: TuiTerminal<CrosstermBackend<Stdout>>,
+    // Did we enable keyboard enhancements on the host terminal
+    keyboard_enhanced: bool,
 }
 
 impl Terminal {
-    pub fn new() -> Result<Self> {
+    pub fn new(enhance_keyboard: bool) -> Result<Self> {
         // Initialize terminal with robust error handling
         // First enable raw mode
         match enable_raw_mode() {
@@ -36,6 +38,14 @@ impl Terminal {
             }
         };
         
+        // Ask the host terminal to report keys unambiguously (e.g. Ctrl+I vs Tab)
+        let keyboard_enhanced = enhance_keyboard
+            && matches!(supports_keyboard_enhancement(), Ok(true))
+            && execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            ).is_ok();
+        
         // Create the terminal backend
         let backend = CrosstermBackend::new(io::stdout());
         
@@ -58,21 +68,25 @@ impl Terminal {
         }
         
         // Return the initialized terminal
-        Ok(Self { terminal })
+        Ok(Self { terminal, keyboard_enhanced })
     }
     
-    pub fn draw<F>(&mut self, render_fn: F) -> Result<()>
+    // Draw a frame, returning what was drawn
+    pub fn draw<F>(&mut self, render_fn: F) -> Result<&ratatui::buffer::Buffer>
     where
         F: FnOnce(&mut ratatui::Frame),
     {
-        self.terminal.draw(render_fn)?;
-        Ok(())
+        let frame = self.terminal.draw(render_fn)?;
+        Ok(frame.buffer)
     }
 }
 
 impl Drop for Terminal {
     fn drop(&mut self) {
         // Restore terminal
+        if self.keyboard_enhanced {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
         let _ = disable_raw_mode();
         let _ = execute!(
             io::stdout(),
@@ -0,0 +1,28 @@
+// The terminfo entry for TERM=matrix. Programs only find it where it's installed, so
+// it's compiled with tic into the user's terminfo directory on request; hosts reached
+// over ssh need it installed too, or keep TERM at xterm-256color.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub const SOURCE: &str = include_str!("../../terminfo/matrix.terminfo");
+
+// Compile the entry into $TERMINFO or ~/.terminfo, returning the directory
+pub fn install() -> Result<PathBuf> {
+    let dir = match std::env::var_os("TERMINFO") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").context("HOME isn't set")?).join(".terminfo"),
+    };
+
+    let source = std::env::temp_dir().join(format!("matrix-{}.terminfo", std::process::id()));
+    fs::write(&source, SOURCE)?;
+    let status = Command::new("tic").arg("-x").arg("-o").arg(&dir).arg(&source).status();
+    let _ = fs::remove_file(&source);
+    match status {
+        Ok(status) if status.success() => Ok(dir),
+        Ok(status) => bail!("tic failed ({})", status),
+        Err(e) => bail!("can't run tic ({}); is ncurses installed?", e),
+    }
+}
@@ -0,0 +1,134 @@
+// Timeline of what a window showed: its screen is recorded every so often while output
+// arrives, and the recordings can be scrubbed through later (`:timeline`). A recording
+// only holds the lines that weren't on the screen the time before; the others, even
+// when they've scrolled, are shared with it.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::terminal::style::StyleRun;
+
+// One line of a recorded screen
+#[derive(Debug, PartialEq)]
+pub struct ScreenLine {
+    pub text: String,
+    pub runs: Vec<StyleRun>,
+}
+
+// The screen at one moment
+pub struct Snapshot {
+    pub time: Instant,
+    pub lines: Vec<Arc<ScreenLine>>,
+}
+
+pub struct Timeline {
+    snapshots: VecDeque<Snapshot>,
+    // Time between recordings; zero records nothing
+    interval: Duration,
+    // Recordings kept, the oldest dropped first
+    capacity: usize,
+    last_recorded: Option<Instant>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10), 60)
+    }
+}
+
+impl Timeline {
+    pub fn new(interval: Duration, capacity: usize) -> Self {
+        Self { snapshots: VecDeque::new(), interval, capacity, last_recorded: None }
+    }
+
+    // Change how often the screen is recorded and how many recordings are kept
+    pub fn configure(&mut self, interval: Duration, capacity: usize) {
+        self.interval = interval;
+        self.capacity = capacity;
+        if interval.is_zero() {
+            self.snapshots.clear();
+        }
+        while self.snapshots.len() > capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    // Whether it's time to record the screen again
+    pub fn due(&self) -> bool {
+        !self.interval.is_zero() && self.capacity > 0 && self.last_recorded.is_none_or(|last| last.elapsed() >= self.interval)
+    }
+
+    // Record the screen; false if it hadn't changed since the last recording, or the
+    // timeline is turned off
+    pub fn record(&mut self, screen: &[(&str, &[StyleRun])]) -> bool {
+        if self.interval.is_zero() {
+            return false;
+        }
+        self.last_recorded = Some(Instant::now());
+
+        let previous: HashMap<&str, &Arc<ScreenLine>> = self.snapshots.back()
+            .map(|snapshot| snapshot.lines.iter().map(|line| (line.text.as_str(), line)).collect())
+            .unwrap_or_default();
+        let lines: Vec<Arc<ScreenLine>> = screen.iter()
+            .map(|(text, runs)| match previous.get(text).filter(|line| line.runs == *runs) {
+                Some(line) => Arc::clone(line),
+                None => Arc::new(ScreenLine { text: text.to_string(), runs: runs.to_vec() }),
+            })
+            .collect();
+        if self.snapshots.back().is_some_and(|snapshot| snapshot.lines == lines) {
+            return false;
+        }
+
+        self.snapshots.push_back(Snapshot { time: Instant::now(), lines });
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    // A recording, the oldest first
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_share_unchanged_lines() {
+        let mut timeline = Timeline::new(Duration::from_secs(1), 2);
+        assert!(timeline.due());
+        assert!(timeline.record(&[("$ make", &[]), ("building", &[])]));
+        assert!(!timeline.due());
+
+        // The same screen again isn't kept
+        assert!(!timeline.record(&[("$ make", &[]), ("building", &[])]));
+        assert_eq!(timeline.len(), 1);
+
+        // A line that scrolled up is still the one recorded before
+        assert!(timeline.record(&[("building", &[]), ("done", &[])]));
+        let (first, second) = (timeline.get(0).unwrap(), timeline.get(1).unwrap());
+        assert!(Arc::ptr_eq(&first.lines[1], &second.lines[0]));
+        assert_eq!(second.lines[1].text, "done");
+
+        // Only the newest recordings are kept
+        assert!(timeline.record(&[("$ ", &[])]));
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline.get(0).unwrap().lines[1].text, "done");
+
+        timeline.configure(Duration::ZERO, 2);
+        assert!(timeline.is_empty() && !timeline.due());
+        assert!(!timeline.record(&[("$ ", &[])]));
+    }
+}
@@ -0,0 +1,325 @@
+// tmux control mode (tmux -CC) client. A control-mode tmux runs in a hidden
+// gateway process; its panes are mapped onto native Matrix windows whose
+// process controller forwards input and output through the gateway.
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::terminal::process::{Process, ProcessController, ProcessEvent, ProcessId};
+
+// Format used to list panes: pane id, window id and pane title
+const LIST_PANES: &str = "list-panes -s -F \"#{pane_id} #{window_id} #{pane_title}\"";
+
+// A notification line sent by tmux in control mode
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    // Start of a command reply (flags = 1 when the command came from this client)
+    Begin { flags: u32 },
+    // End of a command reply, successful or not
+    End { error: bool },
+    // Output of a pane
+    Output { pane: String, data: Vec<u8> },
+    // Windows were added, closed or relaid out
+    WindowsChanged,
+    // The attached session changed
+    SessionChanged,
+    // Control mode is over
+    Exit(Option<String>),
+    // A line of a command reply, or a notification we don't use
+    Line(String),
+}
+
+// Parse one control-mode line
+pub fn parse_line(line: &str) -> Notification {
+    let mut words = line.splitn(2, ' ');
+    let keyword = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("");
+
+    match keyword {
+        "%begin" => Notification::Begin {
+            flags: rest.split(' ').nth(2).and_then(|f| f.parse().ok()).unwrap_or(0),
+        },
+        "%end" => Notification::End { error: false },
+        "%error" => Notification::End { error: true },
+        "%output" => {
+            let (pane, data) = rest.split_once(' ').unwrap_or((rest, ""));
+            Notification::Output { pane: pane.to_string(), data: unescape_output(data) }
+        }
+        "%window-add" | "%window-close" | "%unlinked-window-close" | "%layout-change" => {
+            Notification::WindowsChanged
+        }
+        "%session-changed" => Notification::SessionChanged,
+        "%exit" => Notification::Exit(if rest.is_empty() { None } else { Some(rest.to_string()) }),
+        _ => Notification::Line(line.to_string()),
+    }
+}
+
+// Undo tmux's escaping of pane output (characters below space and backslash as \ooo)
+pub fn unescape_output(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let value = (bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0');
+            output.push(value);
+            i += 4;
+        } else {
+            output.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    output
+}
+
+// A pane reported by tmux
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneInfo {
+    pub pane_id: String,
+    pub window_id: String,
+    pub title: String,
+}
+
+// Events the application has to act on
+#[derive(Debug, Clone)]
+pub enum TmuxEvent {
+    // The full list of panes in the session
+    Panes(Vec<PaneInfo>),
+    // The control client exited
+    Exited(Option<String>),
+}
+
+// What a command we sent is waiting for
+#[derive(Debug, Clone)]
+enum Pending {
+    ListPanes,
+    Capture(String),
+    Ignore,
+}
+
+// A pane's link to its Matrix window
+struct PaneLink {
+    window_id: Uuid,
+    output: mpsc::UnboundedSender<ProcessEvent>,
+}
+
+pub struct TmuxSession {
+    // The process running tmux in control mode
+    gateway: Process,
+    // Incomplete line received from the gateway
+    partial_line: Vec<u8>,
+    // Commands written to tmux, waiting for their reply
+    pending: VecDeque<Pending>,
+    // Lines of the reply currently being received
+    reply: Option<Vec<String>>,
+    // Panes mapped onto Matrix windows
+    panes: HashMap<String, PaneLink>,
+    // Commands queued by pane controllers (input, resize, kill)
+    command_tx: mpsc::UnboundedSender<String>,
+    command_rx: mpsc::UnboundedReceiver<String>,
+}
+
+impl TmuxSession {
+    // Attach to (or create) a tmux session, locally or on a host over ssh
+    pub fn attach(host: Option<&str>, cols: u16, rows: u16) -> Result<Self> {
+        let gateway = match host {
+            Some(host) => Process::with_args("ssh", &["-t", host, "tmux -CC new-session -A"], None, cols, rows)?,
+            None => Process::with_args("tmux", &["-CC", "new-session", "-A"], None, cols, rows)?,
+        };
+
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+
+        Ok(Self {
+            gateway,
+            partial_line: Vec::new(),
+            pending: VecDeque::new(),
+            reply: None,
+            panes: HashMap::new(),
+            command_tx,
+            command_rx,
+        })
+    }
+
+    // Send a command to tmux
+    fn send_command(&mut self, command: &str, pending: Pending) -> Result<()> {
+        self.gateway.write(format!("{}\n", command).as_bytes())?;
+        self.pending.push_back(pending);
+        Ok(())
+    }
+
+    // Exchange data with tmux and return the events the application must handle
+    pub fn poll(&mut self) -> Result<Vec<TmuxEvent>> {
+        let mut events = Vec::new();
+
+        // Forward commands from the pane controllers
+        while let Ok(command) = self.command_rx.try_recv() {
+            self.send_command(&command, Pending::Ignore)?;
+        }
+
+        while let Some(event) = self.gateway.read_event() {
+            match event {
+                ProcessEvent::Output(data) => {
+                    self.partial_line.extend_from_slice(&data);
+
+                    // Handle every complete line
+                    while let Some(end) = self.partial_line.iter().position(|&b| b == b'\n') {
+                        let line: Vec<u8> = self.partial_line.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line).into_owned();
+                        self.handle_line(line.trim_end_matches(['\r', '\n']), &mut events)?;
+                    }
+                }
+                ProcessEvent::Exit(_) => events.push(TmuxEvent::Exited(None)),
+                ProcessEvent::Error(e) => events.push(TmuxEvent::Exited(Some(e))),
+            }
+        }
+
+        Ok(events)
+    }
+
+    // Handle one line from tmux
+    fn handle_line(&mut self, line: &str, events: &mut Vec<TmuxEvent>) -> Result<()> {
+        // Control mode starts inside a DCS sequence, which we don't need
+        let line = line.trim_start_matches("\x1bP1000p");
+
+        // Lines inside a reply block belong to the reply
+        if let Some(reply) = &mut self.reply {
+            if !line.starts_with("%end") && !line.starts_with("%error") {
+                reply.push(line.to_string());
+                return Ok(());
+            }
+        }
+
+        match parse_line(line) {
+            Notification::Begin { flags } => {
+                // Only replies to our own commands are collected
+                if flags & 1 != 0 {
+                    self.reply = Some(Vec::new());
+                }
+            }
+            Notification::End { error } => {
+                if let Some(lines) = self.reply.take() {
+                    let pending = self.pending.pop_front().unwrap_or(Pending::Ignore);
+                    if !error {
+                        self.handle_reply(pending, lines, events);
+                    }
+                }
+            }
+            Notification::Output { pane, data } => {
+                if let Some(link) = self.panes.get(&pane) {
+                    let _ = link.output.send(ProcessEvent::Output(data.into()));
+                }
+            }
+            Notification::WindowsChanged | Notification::SessionChanged => {
+                self.send_command(LIST_PANES, Pending::ListPanes)?;
+            }
+            Notification::Exit(reason) => events.push(TmuxEvent::Exited(reason)),
+            Notification::Line(_) => {}
+        }
+
+        Ok(())
+    }
+
+    // Handle the reply to one of our commands
+    fn handle_reply(&mut self, pending: Pending, lines: Vec<String>, events: &mut Vec<TmuxEvent>) {
+        match pending {
+            Pending::ListPanes => {
+                let panes = lines.iter()
+                    .filter_map(|line| {
+                        let mut fields = line.splitn(3, ' ');
+                        Some(PaneInfo {
+                            pane_id: fields.next()?.to_string(),
+                            window_id: fields.next()?.to_string(),
+                            title: fields.next().unwrap_or("").to_string(),
+                        })
+                    })
+                    .collect();
+                events.push(TmuxEvent::Panes(panes));
+            }
+            Pending::Capture(pane) => {
+                // Show what the pane displayed before we attached
+                if let Some(link) = self.panes.get(&pane) {
+                    let data = lines.join("\r\n").into_bytes();
+                    let _ = link.output.send(ProcessEvent::Output(data.into()));
+                }
+            }
+            Pending::Ignore => {}
+        }
+    }
+
+    // Connect a tmux pane to a Matrix window and return the window's process controller
+    pub fn connect_pane(&mut self, pane_id: &str, window_id: Uuid) -> Result<TmuxPane> {
+        let (output_tx, output_rx) = mpsc::unbounded_channel();
+        self.panes.insert(pane_id.to_string(), PaneLink { window_id, output: output_tx });
+
+        // Fetch the current contents of the pane, with colors
+        self.send_command(&format!("capture-pane -p -e -t {}", pane_id), Pending::Capture(pane_id.to_string()))?;
+
+        Ok(TmuxPane {
+            pane_id: pane_id.to_string(),
+            commands: self.command_tx.clone(),
+            output: output_rx,
+        })
+    }
+
+    // Forget a pane that tmux no longer reports, returning its window
+    pub fn disconnect_pane(&mut self, pane_id: &str) -> Option<Uuid> {
+        self.panes.remove(pane_id).map(|link| link.window_id)
+    }
+
+    // Ids of the panes currently mapped onto windows
+    pub fn pane_ids(&self) -> Vec<String> {
+        self.panes.keys().cloned().collect()
+    }
+
+    // Windows showing panes of this session
+    pub fn window_ids(&self) -> Vec<Uuid> {
+        self.panes.values().map(|link| link.window_id).collect()
+    }
+
+    // Detach from tmux, leaving the session running
+    pub fn detach(&mut self) -> Result<()> {
+        self.send_command("detach-client", Pending::Ignore)
+    }
+}
+
+// Process controller for a window showing a tmux pane
+pub struct TmuxPane {
+    pane_id: String,
+    commands: mpsc::UnboundedSender<String>,
+    output: mpsc::UnboundedReceiver<ProcessEvent>,
+}
+
+impl TmuxPane {
+    fn command(&self, command: String) -> Result<()> {
+        self.commands.send(command).map_err(|_| anyhow::anyhow!("tmux session closed"))
+    }
+}
+
+impl ProcessController for TmuxPane {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        // Send the raw bytes as hex so nothing is interpreted as a key name
+        let hex: Vec<String> = data.iter().map(|b| format!("{:02x}", b)).collect();
+        self.command(format!("send-keys -t {} -H {}", self.pane_id, hex.join(" ")))
+    }
+
+    fn process_id(&self) -> ProcessId {
+        // Pane ids (%N) are unique within the tmux server
+        ProcessId::from_u128(self.pane_id.trim_start_matches('%').parse().unwrap_or(0))
+    }
+
+    fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
+        self.command(format!("resize-pane -t {} -x {} -y {}", self.pane_id, cols, rows))
+    }
+
+    fn read_event(&mut self) -> Option<ProcessEvent> {
+        self.output.try_recv().ok()
+    }
+
+    fn kill(&mut self) -> Result<()> {
+        self.command(format!("kill-pane -t {}", self.pane_id))
+    }
+}
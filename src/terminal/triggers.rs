@@ -0,0 +1,138 @@
+use ratatui::style::Color;
+use regex::Regex;
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use uuid::Uuid;
+
+use crate::config::settings::TriggerRule;
+use crate::terminal::buffer::TerminalBuffer;
+
+// Most new lines checked per window and update, so a flood of output can't stall the UI
+const MAX_LINES_PER_UPDATE: usize = 500;
+
+// A trigger rule with its pattern compiled
+struct Trigger {
+    pattern: Regex,
+    rule: TriggerRule,
+}
+
+// Something a trigger asks the application to do
+#[derive(Debug, Clone)]
+pub enum TriggerEvent {
+    // Show a notification
+    Notify { text: String },
+    // Mark a window as needing attention
+    Mark { window: Uuid },
+    // A line matched, `lines_back` lines above the window's cursor
+    Hit { window: Uuid, lines_back: usize },
+}
+
+// Evaluates trigger rules on the output of every window
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+    // Number of completed lines already checked for each window
+    seen_lines: HashMap<Uuid, u64>,
+    // Hook processes that may still be running
+    children: Vec<Child>,
+}
+
+impl Triggers {
+    // Compile the configured rules, skipping (and reporting) invalid patterns
+    pub fn new(rules: &[TriggerRule]) -> Self {
+        let triggers = rules.iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(pattern) => Some(Trigger { pattern, rule: rule.clone() }),
+                Err(e) => {
+                    eprintln!("Invalid trigger pattern {}: {}", rule.pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            triggers,
+            seen_lines: HashMap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    // Patterns whose matching lines are drawn in a color
+    pub fn highlights(&self) -> Vec<(Regex, Color)> {
+        self.triggers.iter()
+            .filter_map(|t| {
+                let color = t.rule.highlight.as_deref()?;
+                match color.parse::<Color>() {
+                    Ok(color) => Some((t.pattern.clone(), color)),
+                    Err(_) => {
+                        eprintln!("Unknown trigger color: {}", color);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Check the lines a window completed since the last call
+    pub fn scan(&mut self, window: Uuid, title: &str, buffer: &TerminalBuffer) -> Vec<TriggerEvent> {
+        let completed = buffer.completed_lines();
+        let seen = self.seen_lines.insert(window, completed).unwrap_or(0);
+
+        let mut events = Vec::new();
+        if self.triggers.is_empty() || completed <= seen {
+            return events;
+        }
+
+        // Reap hooks that have finished
+        self.children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let new_lines = ((completed - seen) as usize).min(MAX_LINES_PER_UPDATE);
+        let recent = buffer.recent_lines(new_lines);
+        let count = recent.len();
+        for (index, line) in recent.into_iter().enumerate() {
+            let mut hit = false;
+            for trigger in &self.triggers {
+                if !trigger.pattern.is_match(line) {
+                    continue;
+                }
+                hit = true;
+
+                let rule = &trigger.rule;
+                if rule.notify {
+                    events.push(TriggerEvent::Notify {
+                        text: format!("{}: {}", title, line.trim()),
+                    });
+                }
+                if rule.mark {
+                    events.push(TriggerEvent::Mark { window });
+                }
+                if let Some(command) = &rule.run {
+                    // Hooks get the line and window title in the environment
+                    let spawned = Command::new("sh")
+                        .arg("-c")
+                        .arg(command)
+                        .env("MATRIX_LINE", line)
+                        .env("MATRIX_WINDOW", title)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn();
+                    match spawned {
+                        Ok(child) => self.children.push(child),
+                        Err(e) => eprintln!("Error running trigger hook: {}", e),
+                    }
+                }
+            }
+            
+            if hit {
+                events.push(TriggerEvent::Hit { window, lines_back: count - index });
+            }
+        }
+
+        events
+    }
+
+    // Forget windows that were closed
+    pub fn retain_windows(&mut self, is_open: impl Fn(&Uuid) -> bool) {
+        self.seen_lines.retain(|id, _| is_open(id));
+    }
+}
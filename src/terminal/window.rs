@@ -1,15 +1,40 @@
 use anyhow::Result;
 use ratatui::layout::Rect;
-use ratatui::style::{Color, Style};
-use ratatui::text::Text;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use regex::Regex;
 use ratatui::widgets::{Block, Borders, BorderType, Paragraph, Wrap};
-use std::time::Duration;
+use ratatui::Frame;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
-use tokio::task::JoinHandle;
-use tokio::sync::mpsc;
 
-use crate::terminal::buffer::TerminalBuffer;
-use crate::terminal::process::{Process, ProcessController, ProcessEvent};
+use crate::terminal::copy_mode::CopyMode;
+use crate::terminal::foreground;
+use crate::terminal::git::GitWatcher;
+use crate::terminal::buffer::{Damage, MarkKind, TerminalBuffer};
+use crate::terminal::links::{self, FileLink};
+use crate::terminal::process::{Process, ProcessController, ProcessEvent, ReaderStatus};
+use crate::terminal::pipe::OutputPipe;
+use crate::terminal::redact::Redactor;
+use crate::terminal::style::{StyleMapping, StyleRun};
+use crate::terminal::syntax;
+use crate::terminal::supervisor::Supervisor;
+use crate::terminal::timeline::{Snapshot, Timeline};
+use crate::ui::style::Theme;
+
+// Most output parsed per update, so one busy window can't hold up the others;
+// the rest waits in the channel, and once that fills, the reader stops reading
+const OUTPUT_BUDGET: usize = 256 * 1024;
+
+// Most matching lines a filter keeps, the newest, for as tall a window as there may be
+const FILTER_TAIL: usize = 500;
+
+// How long a window may keep using its whole budget before flow control pauses it
+const FLOOD_LIMIT: Duration = Duration::from_secs(2);
 
 // The different states a terminal window can be in
 #[derive(Debug, Clone, PartialEq)]
@@ -24,19 +49,53 @@ pub enum WindowState {
     Error(String),
 }
 
-// Events that can happen in a terminal window
-#[derive(Debug, Clone)]
-pub enum WindowEvent {
-    // Process has output new data
-    Output(Vec<u8>),
-    // Process has exited
-    Exit(i32),
-    // An error occurred
-    Error(String),
-    // Resize event
-    Resize(u16, u16),
-    // Request to focus this window
-    Focus,
+// How long the border flashes when the bell rings
+const BELL_FLASH: Duration = Duration::from_millis(300);
+
+// Longest output of cat that's colored, and how many files' colors are kept
+const MAX_HIGHLIGHTED_LINES: u64 = 10_000;
+const MAX_HIGHLIGHTED_FILES: usize = 20;
+
+// A file shown with cat, colored by its syntax
+struct HighlightedFile {
+    // Absolute numbers of the output's lines
+    lines: Range<u64>,
+    runs: Vec<Vec<StyleRun>>,
+}
+
+// A live filter showing only the lines that match a pattern. The buffer is searched
+// once when it's set; after that only lines completed since are tested.
+struct OutputFilter {
+    pattern: Regex,
+    // Most recent matching lines
+    lines: VecDeque<String>,
+    // Number of matching lines since the filter was set, including the buffer's
+    matches: usize,
+    // Completed line count when the filter was last applied
+    seen_lines: u64,
+}
+
+impl OutputFilter {
+    // The newest matching lines that fit in a number of rows
+    fn shown(&self, rows: usize) -> impl Iterator<Item = &String> {
+        self.lines.iter().skip(self.lines.len().saturating_sub(rows))
+    }
+}
+
+// Rendered lines kept between frames, so only changed lines are styled again
+#[derive(Default)]
+struct LineCache {
+    // Lines by absolute line number
+    lines: HashMap<u64, Line<'static>>,
+    // What the lines were styled with (highlight patterns and colors)
+    key: String,
+}
+
+// Commands typed into a window's shell once it's ready for them
+struct InitCommands {
+    commands: Vec<String>,
+    // Typed by then even if no prompt was seen, for shells without shell integration
+    deadline: Instant,
 }
 
 pub struct TerminalWindow {
@@ -44,6 +103,23 @@ pub struct TerminalWindow {
     id: Uuid,
     // Window title
     pub title: String,
+    // What the window was started to run, or had typed into its shell by a workspace
+    // template; None for a plain shell
+    pub command: Option<String>,
+    // Last command run at its shell prompt (shell integration)
+    pub last_command: Option<String>,
+    // Environment variables its process starts with (TERM, COLORTERM)
+    pub env: Vec<(String, String)>,
+    // Waiting for the shell's first prompt
+    init_commands: Option<InitCommands>,
+    // Program, arguments and directory last started with arguments, for restarting it
+    spawned: Option<(String, Vec<String>, Option<String>)>,
+    // Restarts the program when it fails
+    pub supervisor: Option<Supervisor>,
+    // Command the output is copied to
+    pub pipe: Option<OutputPipe>,
+    // The title follows the program in the foreground, unless the user named the window
+    pub auto_title: bool,
     // Terminal buffer to store content
     pub buffer: TerminalBuffer,
     // Current state of the window
@@ -54,10 +130,62 @@ pub struct TerminalWindow {
     size: Rect,
     // Is this window focused
     focused: bool,
-    // Event receiver from process
-    event_rx: Option<mpsc::UnboundedReceiver<WindowEvent>>,
-    // Process monitor task
-    _process_task: Option<JoinHandle<()>>,
+    // Pattern whose matches are highlighted
+    pub highlight: Option<Regex>,
+    // Filter limiting the view to matching lines
+    filter: Option<OutputFilter>,
+    // Lines matching these patterns are drawn in the given color
+    pub line_highlights: Vec<(Regex, Color)>,
+    // Secrets masked on screen, except on the cursor's line
+    pub redactor: Option<Redactor>,
+    // Marked as needing attention (e.g. by a trigger)
+    pub marked: bool,
+    // Git state of the directory the window is in
+    pub git: GitWatcher,
+    // Border color while that repository has uncommitted changes
+    pub dirty_border: Option<Color>,
+    // How the attributes applications set are drawn
+    pub style_mapping: StyleMapping,
+    // Color files shown with cat by their syntax, unless raw text was asked for
+    pub highlight_files: bool,
+    pub raw_output: bool,
+    highlighted_files: Vec<HighlightedFile>,
+    // Group the window belongs to, for broadcasting input to related windows
+    pub group: Option<String>,
+    // Receiving broadcast input
+    pub broadcasting: bool,
+    // The bell makes no sound for this window
+    pub bell_muted: bool,
+    // Keystrokes are not passed to the process
+    pub read_only: bool,
+    // When input was last sent to the process, and output last read from it
+    pub last_input: Instant,
+    pub last_output: Instant,
+    // Unused for longer than the idle threshold, and drawn dimmed
+    pub idle: bool,
+    // Pause reading output that keeps arriving faster than it can be shown
+    pub flow_control: bool,
+    // Output is left unread until resumed; the process blocks once the PTY's buffer fills
+    pub output_paused: bool,
+    // Since when every update has used the whole output budget
+    flooding_since: Option<Instant>,
+    // Cursor and selection while the window is in copy mode
+    pub copy_mode: Option<CopyMode>,
+    // When the bell last rang, for the visual bell
+    bell_rang: Option<Instant>,
+    // Recordings of the screen, and the one shown while scrubbing through them
+    pub timeline: Timeline,
+    timeline_position: Option<usize>,
+    // Bytes read from the process so far
+    pub bytes_read: u64,
+    // Output reader restarts and failures not yet reported
+    reader_events: Vec<ReaderStatus>,
+    // The output reader stopped for good
+    reader_failed: bool,
+    // Output or a state change arrived since the window was last drawn
+    damaged: bool,
+    // Lines rendered by the last frame (filled in while rendering)
+    line_cache: RefCell<LineCache>,
 }
 
 impl TerminalWindow {
@@ -68,105 +196,473 @@ impl TerminalWindow {
         Self {
             id: Uuid::new_v4(),
             title: title.to_string(),
+            command: None,
+            last_command: None,
+            env: Vec::new(),
+            init_commands: None,
+            spawned: None,
+            supervisor: None,
+            pipe: None,
+            auto_title: true,
             buffer: TerminalBuffer::new(buffer_size),
             state: WindowState::Ready,
             process: None,
             size,
             focused: false,
-            event_rx: None,
-            _process_task: None,
+            highlight: None,
+            filter: None,
+            line_highlights: Vec::new(),
+            redactor: None,
+            marked: false,
+            git: GitWatcher::default(),
+            dirty_border: None,
+            style_mapping: StyleMapping::default(),
+            highlight_files: false,
+            raw_output: false,
+            highlighted_files: Vec::new(),
+            group: None,
+            broadcasting: false,
+            bell_muted: false,
+            read_only: false,
+            last_input: Instant::now(),
+            last_output: Instant::now(),
+            idle: false,
+            flow_control: false,
+            output_paused: false,
+            flooding_since: None,
+            copy_mode: None,
+            bell_rang: None,
+            timeline: Timeline::default(),
+            timeline_position: None,
+            bytes_read: 0,
+            reader_events: Vec::new(),
+            reader_failed: false,
+            damaged: true,
+            line_cache: RefCell::default(),
         }
     }
     
     // Start a new process in this window
     pub fn spawn_process(&mut self, command: &str, working_dir: Option<&str>) -> Result<()> {
         // Create a new process
-        let process = Process::new(
+        let process = Process::with_env(
             command,
+            &[],
+            &self.env,
             working_dir,
             self.size.width,
             self.size.height.saturating_sub(2), // Subtract border height
         )?;
         
-        // Set up channel for process events
-        let (tx, rx) = mpsc::unbounded_channel();
-        
-        // Clone what we need for the background task
-        let tx_clone = tx.clone();
-        
-        // Start a background task to monitor the process
-        let process_task = tokio::spawn(async move {
-            // Simulate some output
-            let output = b"Welcome to Matrix Terminal!\nThis is a simulated shell.\n> ";
-            let _ = tx_clone.send(WindowEvent::Output(output.to_vec()));
-            
-            // Keep the task alive to simulate a running process
-            loop {
-                tokio::time::sleep(Duration::from_secs(1000)).await;
-            }
-        });
-        
         // Create a process controller
         let process_controller = Box::new(process);
         
         self.process = Some(process_controller);
-        self.event_rx = Some(rx);
-        self._process_task = Some(process_task);
         self.state = WindowState::Running;
         
         Ok(())
     }
     
-    // Update the window state based on events
+    // Start a shell in this window as a login shell
+    pub fn spawn_login_shell(&mut self, shell: &str, working_dir: Option<&str>) -> Result<()> {
+        let process = Process::login_shell(shell, &self.env, working_dir, self.size.width, self.size.height.saturating_sub(2))?;
+        self.process = Some(Box::new(process));
+        self.state = WindowState::Running;
+        
+        Ok(())
+    }
+    
+    // Start a program with arguments in this window
+    pub fn spawn_with_args(&mut self, command: &str, args: &[&str], working_dir: Option<&str>) -> Result<()> {
+        let process = Process::with_env(command, args, &self.env, working_dir, self.size.width, self.size.height.saturating_sub(2))?;
+        self.process = Some(Box::new(process));
+        self.spawned = Some((command.to_string(), args.iter().map(|arg| arg.to_string()).collect(), working_dir.map(str::to_string)));
+        self.state = WindowState::Running;
+        
+        Ok(())
+    }
+    
+    // Drive this window with an existing process controller (e.g. a remote pane)
+    pub fn attach_process(&mut self, process: Box<dyn ProcessController + Send>) -> Result<()> {
+        self.process = Some(process);
+        self.state = WindowState::Running;
+        
+        // Let the process know how big the window is
+        self.resize(self.size)
+    }
+    
+    // Update the window state based on events from the process
     pub fn update(&mut self) -> Result<()> {
-        if let Some(rx) = &mut self.event_rx {
-            // Try to receive events without blocking
-            if let Ok(event) = rx.try_recv() {
+        // A supervised program that failed starts again once its wait is over
+        if matches!(self.state, WindowState::Exited(_)) && self.supervisor.as_mut().is_some_and(Supervisor::due) {
+            if let Some((program, args, dir)) = self.spawned.clone() {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                self.spawn_with_args(&program, &args, dir.as_deref())?;
+                self.damaged = true;
+            }
+        }
+        
+        if let Some(process) = &mut self.process {
+            // Take what the process produced since the last update, up to the budget
+            let mut budget = if self.output_paused { 0 } else { OUTPUT_BUDGET };
+            while budget > 0 {
+                let Some(event) = process.read_event() else { break };
+                self.damaged = true;
                 match event {
-                    WindowEvent::Output(data) => {
+                    ProcessEvent::Output(data) => {
+                        budget = budget.saturating_sub(data.len());
+                        self.bytes_read += data.len() as u64;
+                        self.last_output = Instant::now();
                         self.buffer.write(&data)?;
+                        if self.pipe.as_ref().is_some_and(|pipe| !pipe.write(&data)) {
+                            self.pipe = None;
+                        }
                     }
-                    WindowEvent::Exit(code) => {
+                    ProcessEvent::Exit(code) => {
                         self.state = WindowState::Exited(code);
+                        if let Some(wait) = self.supervisor.as_mut().and_then(|supervisor| supervisor.exited(code)) {
+                            let notice = format!("\r\n[exited with {}, restarting in {}s]\r\n", code, wait.as_secs());
+                            self.buffer.write(notice.as_bytes())?;
+                        }
                     }
-                    WindowEvent::Error(err) => {
+                    ProcessEvent::Error(err) => {
                         self.state = WindowState::Error(err);
                     }
-                    WindowEvent::Resize(rows, cols) => {
-                        if let Some(process) = &mut self.process {
-                            process.resize(rows, cols)?;
-                        }
-                        self.buffer.resize(rows as usize, cols as usize);
-                    }
-                    WindowEvent::Focus => {
-                        self.focused = true;
+                }
+            }
+            
+            // Output that keeps outrunning the screen is paused until the user resumes it
+            if budget == 0 && !self.output_paused {
+                let since = *self.flooding_since.get_or_insert_with(Instant::now);
+                if self.flow_control && since.elapsed() >= FLOOD_LIMIT {
+                    self.output_paused = true;
+                    self.damaged = true;
+                }
+            } else {
+                self.flooding_since = None;
+            }
+            
+            // Forget the rendering of lines that changed
+            match self.buffer.take_damage() {
+                Damage::All => self.line_cache.get_mut().lines.clear(),
+                Damage::Lines(lines) => {
+                    let cache = &mut self.line_cache.get_mut().lines;
+                    for line in lines {
+                        cache.remove(&line);
                     }
                 }
             }
+            
+            // Answer any queries the application made
+            let responses = self.buffer.take_responses();
+            if !responses.is_empty() {
+                process.write(&responses)?;
+            }
+            
+            // Type the initial commands at the first prompt
+            let ready = |init: &mut InitCommands| self.buffer.prompted() || Instant::now() >= init.deadline;
+            if let Some(init) = self.init_commands.take_if(ready) {
+                for command in init.commands {
+                    process.write(format!("{}\r", command).as_bytes())?;
+                }
+            }
+            
+            // Bring back a reader that stopped while the process is still running,
+            // rather than leaving the window frozen
+            match process.check_reader() {
+                ReaderStatus::Restarted => {
+                    self.state = WindowState::Running;
+                    self.damaged = true;
+                    self.reader_events.push(ReaderStatus::Restarted);
+                }
+                ReaderStatus::Failed if !self.reader_failed => {
+                    self.reader_failed = true;
+                    self.reader_events.push(ReaderStatus::Failed);
+                }
+                _ => {}
+            }
         }
         
+        // Color the files shown by commands that just finished
+        self.highlight_finished_commands();
+        
+        // Keep a filtered view up to date
+        self.refresh_filter();
+        
         Ok(())
     }
     
+    // Highlight the output of finished commands that showed a file with cat
+    fn highlight_finished_commands(&mut self) {
+        for (command, lines) in self.buffer.take_finished_commands() {
+            if !self.highlight_files || lines.end - lines.start > MAX_HIGHLIGHTED_LINES {
+                continue;
+            }
+            let Some(syntax) = cat_file(&command).and_then(syntax::for_path) else { continue };
+            let text: Vec<String> = lines.clone().filter_map(|line| self.buffer.line_at(line).map(|text| text.into_owned())).collect();
+            let text: Vec<&str> = text.iter().map(String::as_str).collect();
+            let runs = syntax::highlight(syntax, &text);
+            
+            if self.highlighted_files.len() >= MAX_HIGHLIGHTED_FILES {
+                self.highlighted_files.remove(0);
+            }
+            self.highlighted_files.push(HighlightedFile { lines, runs });
+            self.damaged = true;
+        }
+        
+        // Forget files whose lines have left the history
+        let first = self.buffer.line_numbers().start;
+        self.highlighted_files.retain(|file| file.lines.end > first);
+    }
+    
+    // Syntax colors of a line of a file shown with cat, unless showing raw text
+    fn highlighted_runs(&self, line: u64) -> Option<&[StyleRun]> {
+        if self.raw_output {
+            return None;
+        }
+        let file = self.highlighted_files.iter().rev().find(|file| file.lines.contains(&line))?;
+        file.runs.get((line - file.lines.start) as usize).map(Vec::as_slice)
+    }
+    
+    // Type commands into the shell once it's ready: at its first prompt, or after `delay`
+    pub fn queue_init_commands(&mut self, commands: &[String], delay: Duration) {
+        if commands.is_empty() {
+            return;
+        }
+        match &mut self.init_commands {
+            Some(init) => init.commands.extend_from_slice(commands),
+            None => self.init_commands = Some(InitCommands { commands: commands.to_vec(), deadline: Instant::now() + delay }),
+        }
+    }
+    
+    // Whether the window changed since the last call, clearing the flag
+    pub fn take_damage(&mut self) -> bool {
+        std::mem::take(&mut self.damaged)
+    }
+    
+    // Reader restarts and failures since the last call
+    pub fn take_reader_events(&mut self) -> Vec<ReaderStatus> {
+        std::mem::take(&mut self.reader_events)
+    }
+    
+    // Show only lines matching a pattern, or everything again with None
+    pub fn set_filter(&mut self, pattern: Option<Regex>) {
+        self.filter = pattern.map(|pattern| {
+            let (lines, matches) = self.buffer.matching_lines(&pattern, FILTER_TAIL);
+            OutputFilter { pattern, lines: lines.into(), matches, seen_lines: self.buffer.completed_lines() }
+        });
+    }
+    
+    // Number of lines matching the active filter
+    pub fn filter_matches(&self) -> Option<usize> {
+        self.filter.as_ref().map(|f| f.matches)
+    }
+    
+    // Test the lines completed since the filter was last applied
+    fn refresh_filter(&mut self) {
+        let completed = self.buffer.completed_lines();
+        let Some(filter) = &mut self.filter else { return };
+        if filter.seen_lines == completed {
+            return;
+        }
+        
+        let new_lines = completed.saturating_sub(filter.seen_lines) as usize;
+        for line in self.buffer.recent_lines(new_lines) {
+            if filter.pattern.is_match(line) {
+                filter.matches += 1;
+                if filter.lines.len() == FILTER_TAIL {
+                    filter.lines.pop_front();
+                }
+                filter.lines.push_back(line.to_string());
+            }
+        }
+        filter.seen_lines = completed;
+    }
+    
     // Send input to the process
     pub fn send_input(&mut self, data: &[u8]) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("window is read-only"));
+        }
         if let Some(process) = &mut self.process {
             process.write(data)?;
         }
+        self.last_input = Instant::now();
         Ok(())
     }
     
+    // Read output again after flow control paused it
+    pub fn resume_output(&mut self) {
+        self.output_paused = false;
+        self.flooding_since = None;
+        self.damaged = true;
+    }
+    
+    // Title the window as the program asked (OSC 0 or 2), or else after the
+    // program in its foreground
+    pub fn refresh_title(&mut self) {
+        if !self.auto_title {
+            return;
+        }
+        let name = self.buffer.title().map(str::to_string).or_else(|| {
+            self.process.as_ref()
+                .and_then(|process| process.foreground_process())
+                .and_then(foreground::process_name)
+        });
+        if let Some(name) = name.filter(|name| *name != self.title) {
+            self.title = name;
+            self.damaged = true;
+        }
+    }
+    
+    // Directory the window's shell is in: as the shell reported it, or else the
+    // foreground program's
+    pub fn current_dir(&self) -> Option<String> {
+        self.buffer.working_dir().map(str::to_string).or_else(|| {
+            self.process.as_ref()
+                .and_then(|process| process.foreground_process())
+                .and_then(foreground::working_dir)
+        })
+    }
+    
+    // A line as shown: with secrets masked, if they're masked on screen
+    fn masked<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(line),
+            None => Cow::Borrowed(line),
+        }
+    }
+    
+    // Time since the window last had input or output
+    pub fn idle_for(&self) -> Duration {
+        self.last_input.elapsed().min(self.last_output.elapsed())
+    }
+    
     // Render the window to a ratatui frame
-    pub fn render<'a>(&self) -> Paragraph<'a> {
-        // Get the visible content from the buffer
-        let content = self.buffer.visible_lines();
-        let content_text = content.join("\n");
+    pub fn render<'a>(&self, theme: &Theme) -> Paragraph<'a> {
+        // Highlight search matches, or else underline file locations
+        let highlight = self.filter.as_ref().map(|f| &f.pattern).or(self.highlight.as_ref());
+        let highlight_style = Style::default().fg(Color::Black).bg(theme.selected);
+        let link_style = Style::default().add_modifier(Modifier::UNDERLINED);
+        let style_line = |line: &str, runs: &[StyleRun]| {
+            // The application's colors, with the matches or locations over them
+            let mut styled = cell_styled_line(line, runs, theme, self.style_mapping);
+            match highlight {
+                Some(pattern) => {
+                    for found in pattern.find_iter(line).filter(|found| !found.range().is_empty()) {
+                        let start = line[..found.start()].chars().count();
+                        let end = start + found.as_str().chars().count();
+                        styled = style_chars(styled, start..end, highlight_style);
+                    }
+                }
+                None => {
+                    for link in links::find_links(line) {
+                        styled = style_chars(styled, link.range, link_style);
+                    }
+                }
+            }
+            
+            // Color whole lines matched by triggers
+            if let Some((_, color)) = self.line_highlights.iter().find(|(p, _)| p.is_match(line)) {
+                styled.patch_style(Style::default().fg(*color));
+            }
+            styled
+        };
+        
+        let content_text = match (self.timeline_snapshot(), &self.filter) {
+            // A recording of the screen, shown as it was
+            (Some(snapshot), _) => Text::from(snapshot.lines.iter().map(|line| style_line(&self.masked(&line.text), &line.runs)).collect::<Vec<_>>()),
+            // Filtered lines change as a whole, so they aren't cached
+            (None, Some(filter)) => Text::from(filter.shown(self.size.height.saturating_sub(2) as usize).map(|line| style_line(&self.masked(line), &[])).collect::<Vec<_>>()),
+            (None, None) => {
+                let mut cache = self.line_cache.borrow_mut();
+                
+                // Restyle everything if the highlighting changed
+                let key = format!(
+                    "{:?} {:?} {:?} {:?} {} {:?} {} {} {}",
+                    highlight.map(Regex::as_str),
+                    self.line_highlights.iter().map(|(p, c)| (p.as_str(), c)).collect::<Vec<_>>(),
+                    theme.selected,
+                    theme.colors(),
+                    theme.minimum_contrast,
+                    self.style_mapping,
+                    self.redactor.is_some(),
+                    self.raw_output,
+                    self.highlighted_files.last().map_or(0, |file| file.lines.start),
+                );
+                if cache.key != key {
+                    cache.lines.clear();
+                    cache.key = key;
+                }
+                
+                let style_numbered_line = |number: u64, line: &str, runs: &[StyleRun]| {
+                    let mut styled = style_line(line, self.highlighted_runs(number).unwrap_or(runs));
+                    
+                    // Show how the command run at a prompt went
+                    if let Some(command) = self.buffer.command_at(number) {
+                        let color = if command.succeeded() { Color::Green } else { Color::Red };
+                        let badge = format!("  {}", command.badge().unwrap_or_default());
+                        styled.spans.push(Span::styled(badge, Style::default().fg(color).add_modifier(Modifier::DIM)));
+                    }
+                    styled
+                };
+                
+                // The line being typed on isn't masked, nor cached until the cursor leaves it
+                let unmasked = self.redactor.as_ref().map(|_| self.buffer.cursor_line());
+                
+                // Only lines not rendered before, or changed since, are styled
+                let range = self.buffer.visible_range();
+                let lines = range.clone()
+                    .zip(self.buffer.visible_lines())
+                    .zip(self.buffer.visible_styles())
+                    .map(|((number, line), runs)| {
+                        if Some(number) == unmasked {
+                            return style_numbered_line(number, line, runs);
+                        }
+                        cache.lines.entry(number)
+                            .or_insert_with(|| style_numbered_line(number, &self.masked(line), runs))
+                            .clone()
+                    })
+                    .collect::<Vec<_>>();
+                
+                // Keep only what is on screen
+                cache.lines.retain(|number, _| range.contains(number));
+                
+                // Show the copy mode selection and cursor over the cached lines
+                let lines = match &self.copy_mode {
+                    Some(copy) => {
+                        let selected_style = Style::default().fg(Color::Black).bg(theme.selected);
+                        range.zip(self.buffer.visible_lines()).zip(lines)
+                            .map(|((number, text), mut styled)| {
+                                if let Some(columns) = copy.selected_columns(number, text, &self.buffer) {
+                                    styled = style_chars(styled, columns, selected_style);
+                                }
+                                if copy.cursor.0 == number {
+                                    let col = copy.cursor.1;
+                                    styled = style_chars(styled, col..col + 1, Style::default().add_modifier(Modifier::REVERSED));
+                                }
+                                styled
+                            })
+                            .collect()
+                    }
+                    None => lines,
+                };
+                Text::from(lines)
+            }
+        };
         
         // Create a styled block for the window
-        let border_style = if self.focused {
-            Style::default().fg(Color::Cyan)
+        let border_style = if self.bell_rang.is_some_and(|t| t.elapsed() < BELL_FLASH) {
+            Style::default().fg(theme.focused_border).add_modifier(Modifier::REVERSED)
+        } else if self.broadcasting {
+            Style::default().fg(theme.broadcast_border).add_modifier(Modifier::BOLD)
+        } else if let Some(color) = self.dirty_border.filter(|_| self.git.status.as_ref().is_some_and(|s| s.dirty)) {
+            let style = Style::default().fg(color);
+            if self.focused { style.add_modifier(Modifier::BOLD) } else { style }
+        } else if self.focused {
+            Style::default().fg(theme.focused_border)
         } else {
-            Style::default().fg(Color::Gray)
+            Style::default().fg(theme.border)
         };
         
         let status_indicator = match &self.state {
@@ -176,7 +672,44 @@ impl TerminalWindow {
             WindowState::Error(_) => "[Error]",
         };
         
-        let title = format!("{} {}", self.title, status_indicator);
+        let mut marker = String::new();
+        if self.marked {
+            marker.push_str("● ");
+        }
+        if self.bell_muted {
+            marker.push_str("🔕 ");
+        }
+        if self.read_only {
+            marker.push_str("🔒 ");
+        }
+        if self.output_paused {
+            marker.push_str("⏸ ");
+        }
+        if self.pipe.is_some() {
+            marker.push_str("⇢ ");
+        }
+        if let Some(supervisor) = self.supervisor.as_ref().filter(|s| s.restarts > 0) {
+            marker.push_str(&format!("↻{} ", supervisor.restarts));
+        }
+        let group = match (&self.group, self.broadcasting) {
+            (Some(group), true) => format!(" [{} ⇉]", group),
+            (Some(group), false) => format!(" [{}]", group),
+            (None, true) => String::from(" [⇉]"),
+            (None, false) => String::new(),
+        };
+        let mut title = match &self.filter {
+            Some(filter) => format!("{}{}{} {} [filter /{}/: {} matches]", marker, self.title, group, status_indicator, filter.pattern, filter.matches),
+            None => format!("{}{}{} {}", marker, self.title, group, status_indicator),
+        };
+        if let (Some(position), Some(snapshot)) = (self.timeline_position, self.timeline_snapshot()) {
+            let age = snapshot.time.elapsed().as_secs();
+            let age = match age {
+                0..=59 => format!("{}s", age),
+                60..=3599 => format!("{}m {}s", age / 60, age % 60),
+                _ => format!("{}h {}m", age / 3600, age / 60 % 60),
+            };
+            title.push_str(&format!(" [timeline {}/{}: {} ago]", position + 1, self.timeline.len(), age));
+        }
         
         let block = Block::default()
             .borders(Borders::ALL)
@@ -184,13 +717,186 @@ impl TerminalWindow {
             .title(title)
             .border_style(border_style);
         
+        // Idle windows fade into the background until they are used again
+        let mut style = Style::default().fg(theme.foreground).bg(theme.background);
+        if self.idle && !self.focused {
+            style = style.add_modifier(Modifier::DIM);
+        }
+        
         // Create paragraph with the content
-        Paragraph::new(Text::from(content_text))
+        Paragraph::new(content_text)
             .block(block)
-            .style(Style::default().fg(Color::White))
+            .style(style)
             .wrap(Wrap { trim: false })
     }
     
+    // Record the screen for the timeline, if it's time to
+    pub fn record_timeline(&mut self) {
+        // Not while scrubbing, which would move the recordings under it
+        if self.timeline_position.is_none() && self.timeline.due() {
+            self.timeline.record(&self.buffer.screen_lines());
+        }
+    }
+    
+    // Show the timeline's recordings, starting from the screen as it is now; false if
+    // the timeline is turned off
+    pub fn open_timeline(&mut self) -> bool {
+        self.timeline.record(&self.buffer.screen_lines());
+        if self.timeline.is_empty() {
+            return false;
+        }
+        self.timeline_position = Some(self.timeline.len() - 1);
+        self.damaged = true;
+        true
+    }
+    
+    // Move through the recordings, back for negative steps; clamped to the oldest and newest
+    pub fn scrub_timeline(&mut self, steps: isize) {
+        if let Some(position) = &mut self.timeline_position {
+            let last = self.timeline.len().saturating_sub(1) as isize;
+            *position = (*position as isize + steps).clamp(0, last) as usize;
+            self.damaged = true;
+        }
+    }
+    
+    // Back to the live screen
+    pub fn close_timeline(&mut self) {
+        if self.timeline_position.take().is_some() {
+            self.damaged = true;
+        }
+    }
+    
+    // The recording being shown instead of the screen, if any
+    pub fn timeline_snapshot(&self) -> Option<&Snapshot> {
+        self.timeline.get(self.timeline_position?)
+    }
+    
+    // Flash the border (the visual bell)
+    pub fn flash(&mut self) {
+        self.bell_rang = Some(Instant::now());
+    }
+    
+    // Column of the right border used as a scrollbar, if the history is longer than the view
+    fn scrollbar_track(&self) -> Option<Rect> {
+        // Filtered lines aren't a position in the history
+        if self.filter.is_some() || self.size.width < 2 || self.size.height < 3 {
+            return None;
+        }
+        
+        let (_, visible) = self.buffer.viewport();
+        if self.buffer.total_lines() <= visible {
+            return None;
+        }
+        
+        Some(Rect::new(self.size.x + self.size.width - 1, self.size.y + 1, 1, self.size.height - 2))
+    }
+    
+    // Draw the scrollbar: the view's position in the history, and marked lines
+    pub fn render_scrollbar(&self, f: &mut Frame, theme: &Theme) {
+        let Some(track) = self.scrollbar_track() else { return };
+        let total = self.buffer.total_lines();
+        let height = track.height as usize;
+        let row_of = |line: usize| (line * height / total).min(height - 1) as u16;
+        
+        let (start, visible) = self.buffer.viewport();
+        let thumb_color = if self.focused { theme.focused_border } else { theme.border };
+        let buffer = f.buffer_mut();
+        for row in row_of(start)..=row_of(start + visible.saturating_sub(1)) {
+            buffer.get_mut(track.x, track.y + row).set_symbol("┃").set_fg(thumb_color);
+        }
+        
+        for (line, kind) in self.buffer.marks() {
+            let color = match kind {
+                MarkKind::Prompt => theme.title,
+                MarkKind::Search => theme.selected,
+                MarkKind::Trigger => theme.command,
+            };
+            buffer.get_mut(track.x, track.y + row_of(line)).set_symbol("◆").set_fg(color);
+        }
+    }
+    
+    // Where the "new lines" pill goes: on the bottom border, at the right, while the
+    // view is scrolled back and output arrived below it
+    fn scroll_pill_area(&self) -> Option<(Rect, String)> {
+        let unseen = self.buffer.unseen_lines();
+        if unseen == 0 || self.filter.is_some() || self.size.height < 3 {
+            return None;
+        }
+        
+        let text = format!(" ↓ {} new line{} (P) ", unseen, if unseen == 1 { "" } else { "s" });
+        let width = text.chars().count() as u16;
+        if width + 4 > self.size.width {
+            return None;
+        }
+        let x = self.size.x + self.size.width - width - 2;
+        Some((Rect::new(x, self.size.y + self.size.height - 1, width, 1), text))
+    }
+    
+    // Draw the "new lines" pill of a view scrolled back
+    pub fn render_scroll_pill(&self, f: &mut Frame, theme: &Theme) {
+        let Some((area, text)) = self.scroll_pill_area() else { return };
+        let style = Style::default().fg(theme.background).bg(theme.title).add_modifier(Modifier::BOLD);
+        f.render_widget(Paragraph::new(text).style(style), area);
+    }
+    
+    // Whether a point is on the "new lines" pill
+    pub fn scroll_pill_at(&self, column: u16, row: u16) -> bool {
+        self.scroll_pill_area().is_some_and(|(area, _)| area.intersects(Rect::new(column, row, 1, 1)))
+    }
+    
+    // Line of the history (numbered as in search results) at a point on the scrollbar
+    pub fn scrollbar_line_at(&self, column: u16, row: u16) -> Option<usize> {
+        let track = self.scrollbar_track()?;
+        if !track.intersects(Rect::new(column, row, 1, 1)) {
+            return None;
+        }
+        
+        Some((row - track.y) as usize * self.buffer.total_lines() / track.height as usize)
+    }
+    
+    // The lines shown in the window: the visible part of the buffer, or the filtered lines
+    pub fn content(&self) -> Vec<&str> {
+        match &self.filter {
+            Some(filter) => filter.shown(self.size.height.saturating_sub(2) as usize).map(String::as_str).collect(),
+            None => self.buffer.visible_lines(),
+        }
+    }
+    
+    // Where the lines are shown: the window inside its border
+    pub fn content_area(&self) -> Rect {
+        Rect::new(self.size.x + 1, self.size.y + 1, self.size.width.saturating_sub(2), self.size.height.saturating_sub(2))
+    }
+    
+    // The line shown at a screen position, and the column within it
+    pub fn line_at(&self, column: u16, row: u16) -> Option<(String, usize)> {
+        let inner = self.content_area();
+        if !inner.intersects(Rect::new(column, row, 1, 1)) {
+            return None;
+        }
+        
+        let line = self.content().get((row - inner.y) as usize)?.to_string();
+        Some((line, (column - inner.x) as usize))
+    }
+    
+    // File location shown at a screen position, if any
+    pub fn link_at(&self, column: u16, row: u16) -> Option<FileLink> {
+        let (line, col) = self.line_at(column, row)?;
+        links::find_links(&line).into_iter().find(|link| link.range.contains(&col))
+    }
+    
+    // File locations shown in the window, top to bottom, without duplicates
+    pub fn visible_links(&self) -> Vec<FileLink> {
+        let mut found: Vec<FileLink> = Vec::new();
+        for line in self.content() {
+            for link in links::find_links(line) {
+                if !found.iter().any(|l| l.file == link.file && l.line == link.line && l.column == link.column) {
+                    found.push(link);
+                }
+            }
+        }
+        found
+    }
+    
     // Resize the window
     pub fn resize(&mut self, size: Rect) -> Result<()> {
         self.size = size;
@@ -247,10 +953,78 @@ impl TerminalWindow {
         }
         
         self.process = None;
-        self._process_task = None;
-        self.event_rx = None;
         self.state = WindowState::Exited(-1);
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// The file a command shows, if it's `cat` with a single file
+fn cat_file(command: &str) -> Option<&str> {
+    let mut words = command.split_whitespace();
+    if words.next()? != "cat" {
+        return None;
+    }
+    let mut files = words.filter(|word| !word.starts_with('-'));
+    let file = files.next()?;
+    files.next().is_none().then_some(file.trim_matches(['"', '\'']))
+}
+
+// A line in the colors and attributes the application gave it
+pub fn cell_styled_line(line: &str, runs: &[StyleRun], theme: &Theme, mapping: StyleMapping) -> Line<'static> {
+    if runs.is_empty() {
+        return Line::from(line.to_string());
+    }
+    
+    let chars: Vec<char> = line.chars().collect();
+    let mut spans = Vec::new();
+    let mut col = 0;
+    for run in runs {
+        let start = run.start.clamp(col, chars.len());
+        let end = run.end.min(chars.len());
+        if start >= end {
+            continue;
+        }
+        if start > col {
+            spans.push(Span::raw(chars[col..start].iter().collect::<String>()));
+        }
+        spans.push(Span::styled(chars[start..end].iter().collect::<String>(), run.style.screen_style(theme, mapping)));
+        col = end;
+    }
+    if col < chars.len() {
+        spans.push(Span::raw(chars[col..].iter().collect::<String>()));
+    }
+    
+    Line::from(spans)
+}
+
+// Patch a style onto some characters of a styled line, padding the line with
+// spaces if they lie past its end
+pub fn style_chars(line: Line<'static>, chars: Range<usize>, style: Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut index = 0;
+    for span in line.spans {
+        let mut run = String::new();
+        let mut run_selected = false;
+        for c in span.content.chars() {
+            let selected = chars.contains(&index);
+            if selected != run_selected && !run.is_empty() {
+                let run_style = if run_selected { span.style.patch(style) } else { span.style };
+                spans.push(Span::styled(std::mem::take(&mut run), run_style));
+            }
+            run_selected = selected;
+            run.push(c);
+            index += 1;
+        }
+        if !run.is_empty() {
+            let run_style = if run_selected { span.style.patch(style) } else { span.style };
+            spans.push(Span::styled(run, run_style));
+        }
+    }
+    
+    if chars.end > index {
+        spans.push(Span::raw(" ".repeat(chars.start.saturating_sub(index))));
+        spans.push(Span::styled(" ".repeat(chars.end - index.max(chars.start)), style));
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use uuid::Uuid;
+
+use crate::config::settings::AccessibilitySettings;
+use crate::terminal::buffer::TerminalBuffer;
+
+// Sends output and UI state to an external speech command (e.g. speech-dispatcher's spd-say)
+pub struct Announcer {
+    // Program and arguments of the speech command
+    command: Vec<String>,
+    // Speak new output lines of the focused window
+    announce_output: bool,
+    // Speak focus and mode changes
+    announce_ui: bool,
+    // Maximum number of lines spoken per update
+    max_lines: usize,
+    // Number of completed lines already spoken for each window
+    seen_lines: HashMap<Uuid, u64>,
+    // Last window announced as focused
+    last_focused: Option<Uuid>,
+    // Speech processes that may still be running
+    children: Vec<Child>,
+}
+
+impl Announcer {
+    // Create an announcer from the accessibility settings
+    pub fn new(settings: &AccessibilitySettings) -> Self {
+        let command = settings.speech_command
+            .as_deref()
+            .map(|cmd| cmd.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+
+        Self {
+            command,
+            announce_output: settings.announce_output,
+            announce_ui: settings.announce_ui,
+            max_lines: settings.max_lines,
+            seen_lines: HashMap::new(),
+            last_focused: None,
+            children: Vec::new(),
+        }
+    }
+
+    // Is a speech command configured
+    pub fn is_enabled(&self) -> bool {
+        !self.command.is_empty()
+    }
+
+    // Speak a piece of text
+    pub fn speak(&mut self, text: &str) {
+        // Reap speech processes that have finished
+        self.children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let text = text.trim();
+        if !self.is_enabled() || text.is_empty() {
+            return;
+        }
+
+        let spawned = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match spawned {
+            Ok(child) => self.children.push(child),
+            Err(e) => eprintln!("Error running speech command: {}", e),
+        }
+    }
+
+    // Speak a UI state change (focus, mode) if enabled
+    pub fn announce_ui(&mut self, text: &str) {
+        if self.announce_ui {
+            self.speak(text);
+        }
+    }
+
+    // Announce a change of the focused window
+    pub fn focus_changed(&mut self, id: Uuid, title: &str, buffer: &TerminalBuffer) {
+        if self.last_focused == Some(id) {
+            return;
+        }
+
+        self.last_focused = Some(id);
+
+        // Don't read out everything that happened while the window was in the background
+        self.seen_lines.insert(id, buffer.completed_lines());
+
+        self.announce_ui(&format!("Focused {}", title));
+    }
+
+    // Speak the lines completed in a window since the last update
+    pub fn announce_new_output(&mut self, id: Uuid, buffer: &TerminalBuffer) {
+        let completed = buffer.completed_lines();
+        let seen = self.seen_lines.insert(id, completed).unwrap_or(completed);
+
+        if !self.announce_output || completed <= seen {
+            return;
+        }
+
+        let new_lines = (completed - seen) as usize;
+        let text = buffer.recent_lines(new_lines.min(self.max_lines)).join("\n");
+        self.speak(&text);
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::settings::{BellSettings, BellSound};
+
+// Plays the bell's sound, at most once per interval however often applications ring it
+#[derive(Default)]
+pub struct Bell {
+    // When a sound was last played
+    last_played: Option<Instant>,
+    // Players that may still be running
+    children: Vec<Child>,
+}
+
+impl Bell {
+    // Play the configured sound unless the bell is muted or has just been played
+    pub fn ring(&mut self, settings: &BellSettings) {
+        // Reap players that have finished
+        self.children.retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let interval = Duration::from_millis(settings.min_interval_ms);
+        if settings.muted || self.last_played.is_some_and(|t| t.elapsed() < interval) {
+            return;
+        }
+
+        match (settings.sound, &settings.sound_file) {
+            (BellSound::Off, _) => return,
+            (BellSound::File, Some(file)) => {
+                let quoted = format!("'{}'", file.replace('\'', r"'\''"));
+                let spawned = Command::new("sh")
+                    .arg("-c")
+                    .arg(settings.player.replace("{file}", &quoted))
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+                match spawned {
+                    Ok(child) => self.children.push(child),
+                    Err(e) => eprintln!("Error playing bell sound: {}", e),
+                }
+            }
+            // The host terminal's own bell (also used when no sound file is set)
+            _ => {
+                let mut stdout = std::io::stdout();
+                let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+            }
+        }
+
+        self.last_played = Some(Instant::now());
+    }
+}
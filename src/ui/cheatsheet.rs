@@ -0,0 +1,261 @@
+// Cheatsheet of the keys in effect: the built-in shortcuts and the key sequences bound
+// in the settings, by mode, with the ones the settings override left out. It's rebuilt
+// whenever the bindings change, can be searched, and can be written out as Markdown.
+
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::ui::keymap::{self, Chord, Keymaps, Mode};
+use crate::ui::style::Theme;
+
+// Keys handled without a binding, by mode
+const BUILT_IN: &[(Mode, &str, &str)] = &[
+    (Mode::Normal, "Ctrl+N", "New window"),
+    (Mode::Normal, "Ctrl+H", "Split horizontally"),
+    (Mode::Normal, "Ctrl+V", "Split vertically"),
+    (Mode::Normal, "Ctrl+W", "Close the window"),
+    (Mode::Normal, "Ctrl+Tab", "Next window"),
+    (Mode::Normal, "Ctrl+Shift+Tab", "Previous window"),
+    (Mode::Normal, "Ctrl+Arrows", "Window in that direction"),
+    (Mode::Normal, "Ctrl+Alt+Arrows", "Grow the window in that direction"),
+    (Mode::Normal, "Ctrl+Z", "Zoom the window"),
+    (Mode::Normal, "Shift+PageUp/PageDown", "Scroll the history"),
+    (Mode::Normal, "P", "Back to the newest output, when scrolled back"),
+    (Mode::Normal, "Ctrl+G", "Grid layout"),
+    (Mode::Normal, "Ctrl+Shift+H", "Horizontal layout"),
+    (Mode::Normal, "Ctrl+Shift+V", "Vertical layout"),
+    (Mode::Normal, "Ctrl+M", "Main and stack layout"),
+    (Mode::Normal, "Ctrl+B", "Toggle the sidebar"),
+    (Mode::Normal, "Ctrl+Q", "Resume paused output"),
+    (Mode::Normal, "Ctrl+Shift+O", "Quick open"),
+    (Mode::Normal, "Alt+C", "Copy mode"),
+    (Mode::Normal, "Alt+E", "Hints"),
+    (Mode::Normal, "Alt+=", "Resize mode"),
+    (Mode::Normal, "Alt+L", "Lock the screen"),
+    (Mode::Normal, "Alt+B", "Bookmarked directories"),
+    (Mode::Normal, "Alt+H", "Command history"),
+    (Mode::Normal, "Alt+S", "Snippets"),
+    (Mode::Normal, "Alt+R", "Record a macro"),
+    (Mode::Normal, "Alt+P", "Play the last macro"),
+    (Mode::Normal, "Alt+V", "Paste from the copy history"),
+    (Mode::Normal, ":", "Command mode"),
+    (Mode::Copy, "Arrows, hjkl", "Move the cursor"),
+    (Mode::Copy, "PageUp/PageDown", "Move a page"),
+    (Mode::Copy, "0 / $", "Line start / end"),
+    (Mode::Copy, "g / G", "Top / bottom"),
+    (Mode::Copy, "v, Space", "Start or clear a selection"),
+    (Mode::Copy, "Ctrl+V", "Block selection"),
+    (Mode::Copy, "y, Enter", "Copy and leave"),
+    (Mode::Copy, "a", "Append to the clipboard and leave"),
+    (Mode::Copy, "Esc, q", "Leave copy mode"),
+    (Mode::Resize, "Arrows", "Move the border 2%"),
+    (Mode::Resize, "Shift+Arrows", "Move the border 10%"),
+    (Mode::Resize, "Esc, Enter", "Leave resize mode"),
+    (Mode::Search, "Enter", "List the matches"),
+    (Mode::Search, "Esc", "Cancel"),
+    (Mode::Command, "Enter", "Run the command"),
+    (Mode::Command, "Esc", "Cancel"),
+];
+
+// One key, or sequence of keys, and what it does
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub mode: Mode,
+    pub keys: String,
+    pub action: String,
+    // Bound in the settings rather than built in
+    pub bound: bool,
+}
+
+// Every key in effect, by mode: the settings' bindings first, then the built-in keys
+// they don't take over
+pub fn entries(keymaps: &Keymaps) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for mode in Mode::ALL {
+        let bindings = keymaps.get(mode).bindings();
+        for (chords, command) in bindings {
+            entries.push(Entry {
+                mode,
+                keys: keymap::sequence_name(chords),
+                action: format!(":{}", command),
+                bound: true,
+            });
+        }
+
+        let overridden = |keys: &str| Chord::parse(keys).is_some_and(|chord| bindings.iter().any(|(chords, _)| chords[..] == [chord]));
+        for (_, keys, action) in BUILT_IN.iter().filter(|(m, keys, _)| *m == mode && !overridden(keys)) {
+            entries.push(Entry { mode, keys: keys.to_string(), action: action.to_string(), bound: false });
+        }
+    }
+    entries
+}
+
+// The entries as a Markdown document, a table per mode
+pub fn to_markdown(entries: &[Entry]) -> String {
+    let mut markdown = String::from("# Matrix keys\n");
+    for mode in Mode::ALL {
+        let rows: Vec<&Entry> = entries.iter().filter(|entry| entry.mode == mode).collect();
+        if rows.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("\n## {} mode\n\n| Keys | Action |\n| --- | --- |\n", mode.name()));
+        for entry in rows {
+            let action = if entry.bound { format!("`{}`", entry.action) } else { entry.action.clone() };
+            markdown.push_str(&format!("| `{}` | {} |\n", entry.keys.replace('|', "\\|"), action.replace('|', "\\|")));
+        }
+    }
+    markdown
+}
+
+// Overlay showing the cheatsheet, narrowed to what's typed
+pub struct Cheatsheet {
+    entries: Vec<Entry>,
+    query: String,
+    // First line shown
+    scroll: usize,
+}
+
+impl Cheatsheet {
+    pub fn new(keymaps: &Keymaps) -> Self {
+        Self { entries: entries(keymaps), query: String::new(), scroll: 0 }
+    }
+
+    // Show the keys again after the bindings changed
+    pub fn update(&mut self, keymaps: &Keymaps) {
+        self.entries = entries(keymaps);
+    }
+
+    // Entries whose keys, action or mode contain what's typed
+    fn matching(&self) -> Vec<&Entry> {
+        let query = self.query.to_lowercase();
+        self.entries.iter()
+            .filter(|entry| {
+                query.is_empty()
+                    || entry.keys.to_lowercase().contains(&query)
+                    || entry.action.to_lowercase().contains(&query)
+                    || entry.mode.name() == query
+            })
+            .collect()
+    }
+
+    // Lines the matching entries take, with a heading and a blank line for each mode
+    fn line_count(&self) -> usize {
+        let matching = self.matching();
+        let modes = Mode::ALL.iter().filter(|mode| matching.iter().any(|entry| entry.mode == **mode)).count();
+        matching.len() + modes * 2
+    }
+
+    // Type to search, scroll with the arrows; false once it's closed
+    pub fn handle_key(&mut self, code: crossterm::event::KeyCode) -> bool {
+        use crossterm::event::KeyCode;
+
+        match code {
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.scroll = 0;
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.scroll = 0;
+            }
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll = (self.scroll + 1).min(self.line_count()),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = (self.scroll + 10).min(self.line_count()),
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.scroll = 0;
+            }
+            KeyCode::Esc | KeyCode::Enter => return false,
+            _ => {}
+        }
+        true
+    }
+
+    // Render the overlay centered in an area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = 72.min(area.width);
+        let height = (area.height * 4 / 5).max(6).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let matching = self.matching();
+        let key_width = matching.iter().map(|entry| entry.keys.chars().count()).max().unwrap_or(0).min(24);
+        let mut lines = Vec::new();
+        let mut mode = None;
+        for entry in &matching {
+            if mode != Some(entry.mode) {
+                if mode.is_some() {
+                    lines.push(Line::from(""));
+                }
+                mode = Some(entry.mode);
+                lines.push(Line::styled(
+                    format!("{} mode", entry.mode.name().to_uppercase()),
+                    Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
+                ));
+            }
+            let action_style = if entry.bound { Style::default().fg(theme.command) } else { Style::default() };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:width$}", entry.keys, width = key_width), Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(format!("  {}", entry.action), action_style),
+            ]));
+        }
+        if matching.is_empty() {
+            lines.push(Line::from("  No keys match"));
+        }
+
+        // Keep the last page in view
+        let visible = rect.height.saturating_sub(2) as usize;
+        let lines = lines.split_off(self.scroll.min(lines.len().saturating_sub(visible)));
+
+        let title = if self.query.is_empty() { String::from("Keys") } else { format!("Keys matching: {}_", self.query) };
+        let paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(title)
+                    .title(Title::from("Type to search  ↑↓: scroll  Esc: close").position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn bindings_take_over_built_in_keys() {
+        let bindings = BTreeMap::from([
+            (String::from("Ctrl+A w v"), String::from("split")),
+            (String::from("Ctrl+G"), String::from(":layout main")),
+        ]);
+        let modes = BTreeMap::from([(String::from("copy"), BTreeMap::from([(String::from("x"), String::from("copy-mode"))]))]);
+        let entries = entries(&Keymaps::new(&bindings, &modes).0);
+
+        let normal: Vec<&Entry> = entries.iter().filter(|entry| entry.mode == Mode::Normal).collect();
+        assert_eq!(normal[0].keys, "Ctrl+A w v");
+        assert_eq!(normal[1], &Entry { mode: Mode::Normal, keys: String::from("Ctrl+G"), action: String::from(":layout main"), bound: true });
+        assert!(!normal.iter().any(|entry| entry.action == "Grid layout"));
+        assert!(normal.iter().any(|entry| entry.keys == "Ctrl+N" && !entry.bound));
+        assert!(entries.iter().any(|entry| entry.mode == Mode::Copy && entry.keys == "x"));
+
+        let markdown = to_markdown(&entries);
+        assert!(markdown.contains("## normal mode\n\n| Keys | Action |\n| --- | --- |\n| `Ctrl+A w v` | `:split` |\n"));
+        assert!(markdown.contains("| `Shift+PageUp/PageDown` | Scroll the history |\n"));
+    }
+}
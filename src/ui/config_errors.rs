@@ -0,0 +1,63 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::config::check::Problem;
+use crate::config::settings::Settings;
+use crate::ui::style::Theme;
+
+// Overlay listing what's wrong with the settings file, shown on start until dismissed
+pub struct ConfigErrors {
+    problems: Vec<Problem>,
+}
+
+impl ConfigErrors {
+    pub fn new(problems: Vec<Problem>) -> Self {
+        Self { problems }
+    }
+
+    // Render the overlay centered in an area, sized to the problems
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = (area.width * 4 / 5).max(20).min(area.width);
+        let height = (self.problems.len() as u16 + 4).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = vec![
+            Line::from("These settings are invalid; the defaults are used instead:"),
+            Line::from(""),
+        ];
+        for problem in &self.problems {
+            lines.push(Line::from(match problem.key.as_str() {
+                "" => vec![Span::raw("  "), Span::raw(problem.message.as_str())],
+                key => vec![
+                    Span::raw("  "),
+                    Span::styled(key, Style::default().fg(theme.command).add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(": {}", problem.message)),
+                ],
+            }));
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(format!("Settings problems ({})", Settings::path().display()))
+                    .title(Title::from("Esc or Enter: close").position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+}
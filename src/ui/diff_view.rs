@@ -0,0 +1,240 @@
+// Diff of what two windows show (`:diff <window>`): the current window's lines against
+// another's, side by side or as a unified diff, in an overlay. Handy for comparing the
+// same command's output in two places.
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::ui::style::Theme;
+
+// A line of one side or the other, or of both
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+// The lines to remove from `old` and add to get `new`, by longest common subsequence;
+// screens are small enough for its quadratic table
+pub fn diff(old: &[&str], new: &[&str]) -> Vec<Change> {
+    let (n, m) = (old.len(), new.len());
+
+    // common[i][j]: lines old[i..] and new[j..] have in common
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    // Removals come before the additions replacing them
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            changes.push(Change::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    changes
+}
+
+// The changes as rows of two columns, each run of removed lines next to the added
+// lines that follow it
+fn side_by_side(changes: &[Change]) -> Vec<(Option<&Change>, Option<&Change>)> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        if let Change::Same(_) = changes[i] {
+            rows.push((Some(&changes[i]), Some(&changes[i])));
+            i += 1;
+            continue;
+        }
+        let removed_end = i + changes[i..].iter().take_while(|c| matches!(c, Change::Removed(_))).count();
+        let added_end = removed_end + changes[removed_end..].iter().take_while(|c| matches!(c, Change::Added(_))).count();
+        let (removed, added) = (&changes[i..removed_end], &changes[removed_end..added_end]);
+        for row in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(row), added.get(row)));
+        }
+        i = added_end;
+    }
+    rows
+}
+
+// Overlay showing the diff of two windows
+pub struct DiffView {
+    // Titles of the windows compared, current one first
+    old_title: String,
+    new_title: String,
+    changes: Vec<Change>,
+    unified: bool,
+    // First line shown
+    scroll: usize,
+}
+
+impl DiffView {
+    // Compare the lines of two windows; trailing blanks don't count as a difference
+    pub fn new(old_title: &str, old: &[&str], new_title: &str, new: &[&str]) -> Self {
+        let trim = |lines: &[&str]| -> Vec<String> {
+            let mut lines: Vec<String> = lines.iter().map(|line| line.trim_end().to_string()).collect();
+            while lines.last().is_some_and(String::is_empty) {
+                lines.pop();
+            }
+            lines
+        };
+        let (old, new) = (trim(old), trim(new));
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        Self {
+            old_title: old_title.to_string(),
+            new_title: new_title.to_string(),
+            changes: diff(&old, &new),
+            unified: false,
+            scroll: 0,
+        }
+    }
+
+    // Lines changed on either side
+    pub fn changed(&self) -> usize {
+        self.changes.iter().filter(|change| !matches!(change, Change::Same(_))).count()
+    }
+
+    fn line_count(&self) -> usize {
+        if self.unified { self.changes.len() } else { side_by_side(&self.changes).len() }
+    }
+
+    // Scroll, or switch between side by side and unified; false once it's closed
+    pub fn handle_key(&mut self, code: crossterm::event::KeyCode) -> bool {
+        use crossterm::event::KeyCode;
+
+        match code {
+            KeyCode::Tab | KeyCode::Char('u') => {
+                self.unified = !self.unified;
+                self.scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = (self.scroll + 1).min(self.line_count()),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = (self.scroll + 10).min(self.line_count()),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => return false,
+            _ => {}
+        }
+        true
+    }
+
+    // Render the overlay centered in an area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = (area.width * 9 / 10).max(20).min(area.width);
+        let height = (area.height * 4 / 5).max(6).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let removed_style = Style::default().fg(Color::Red);
+        let added_style = Style::default().fg(Color::Green);
+        let style_of = |change: &Change| match change {
+            Change::Same(_) => Style::default(),
+            Change::Removed(_) => removed_style,
+            Change::Added(_) => added_style,
+        };
+        let text_of = |change: &Change| match change {
+            Change::Same(text) | Change::Removed(text) | Change::Added(text) => text.clone(),
+        };
+
+        let mut lines: Vec<Line> = if self.unified {
+            self.changes.iter()
+                .map(|change| {
+                    let sign = match change {
+                        Change::Same(_) => ' ',
+                        Change::Removed(_) => '-',
+                        Change::Added(_) => '+',
+                    };
+                    Line::styled(format!("{}{}", sign, text_of(change)), style_of(change))
+                })
+                .collect()
+        } else {
+            // Each side gets half, less the divider
+            let column = (rect.width.saturating_sub(2) as usize).saturating_sub(3) / 2;
+            let cell = |change: Option<&Change>| match change {
+                Some(change) => {
+                    let text: String = text_of(change).chars().take(column).collect();
+                    Span::styled(format!("{:column$}", text, column = column), style_of(change))
+                }
+                None => Span::raw(" ".repeat(column)),
+            };
+            side_by_side(&self.changes).into_iter()
+                .map(|(old, new)| Line::from(vec![cell(old), Span::styled(" │ ", Style::default().fg(theme.border)), cell(new)]))
+                .collect()
+        };
+        if self.changed() == 0 {
+            lines.insert(0, Line::styled("  The windows show the same lines", Style::default().add_modifier(Modifier::BOLD)));
+        }
+
+        // Keep the last page in view
+        let visible = rect.height.saturating_sub(2) as usize;
+        let lines = lines.split_off(self.scroll.min(lines.len().saturating_sub(visible)));
+
+        let title = format!("Diff: {} ↔ {} ({} lines changed)", self.old_title, self.new_title, self.changed());
+        let footer = if self.unified { "Tab: side by side  ↑↓: scroll  Esc: close" } else { "Tab: unified  ↑↓: scroll  Esc: close" };
+        let paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(title)
+                    .title(Title::from(footer).position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_lines_pair_up_side_by_side() {
+        let changes = diff(&["NAME", "web 1/1", "db 1/1", "cache 2/2"], &["NAME", "web 0/1", "db 1/1", "queue 1/1"]);
+        assert_eq!(changes, vec![
+            Change::Same(String::from("NAME")),
+            Change::Removed(String::from("web 1/1")),
+            Change::Added(String::from("web 0/1")),
+            Change::Same(String::from("db 1/1")),
+            Change::Removed(String::from("cache 2/2")),
+            Change::Added(String::from("queue 1/1")),
+        ]);
+
+        let rows = side_by_side(&changes);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1], (Some(&changes[1]), Some(&changes[2])));
+
+        // A line only one side has is next to nothing
+        let changes = diff(&["a"], &["a", "b"]);
+        assert_eq!(side_by_side(&changes)[1], (None, Some(&Change::Added(String::from("b")))));
+        assert_eq!(DiffView::new("x", &["a  ", ""], "y", &["a"]).changed(), 0);
+    }
+}
@@ -0,0 +1,158 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+use unicode_width::UnicodeWidthStr;
+
+use crate::terminal::smart_select::SmartMatch;
+use crate::ui::style::Theme;
+
+// Keys used for labels, easiest to reach first
+const LABEL_KEYS: &str = "asdfjklghqwertyuiopzxcvbnm";
+
+// What choosing a hint does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HintAction {
+    Copy,
+    Paste,
+    Open,
+}
+
+impl HintAction {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "copy" => Some(HintAction::Copy),
+            "paste" => Some(HintAction::Paste),
+            "open" => Some(HintAction::Open),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HintAction::Copy => "copy",
+            HintAction::Paste => "paste",
+            HintAction::Open => "open",
+        }
+    }
+
+    // The next action, for cycling with Tab
+    pub fn next(self) -> Self {
+        match self {
+            HintAction::Copy => HintAction::Paste,
+            HintAction::Paste => HintAction::Open,
+            HintAction::Open => HintAction::Copy,
+        }
+    }
+}
+
+// A match shown in a window, with its label and screen position
+pub struct Hint {
+    pub label: String,
+    pub found: SmartMatch,
+    pub x: u16,
+    pub y: u16,
+}
+
+// Labels over the URLs, paths, hashes... shown in a window, chosen by typing one
+pub struct Hints {
+    hints: Vec<Hint>,
+    pub action: HintAction,
+    // Label typed so far
+    typed: String,
+}
+
+impl Hints {
+    // Label the matches on the lines shown in `area`, top to bottom
+    pub fn new(lines: &[&str], area: Rect, matches: impl Fn(&str) -> Vec<SmartMatch>, action: HintAction) -> Self {
+        let mut found = Vec::new();
+        for (row, line) in lines.iter().enumerate().take(area.height as usize) {
+            for m in matches(line) {
+                // Columns on screen, counting wide characters twice
+                let before: String = line.chars().take(m.range.start).collect();
+                let x = area.x + before.width() as u16;
+                if x < area.right() {
+                    found.push((m, x, area.y + row as u16));
+                }
+            }
+        }
+
+        let labels = labels(found.len());
+        let hints = found.into_iter().zip(labels)
+            .map(|((found, x, y), label)| Hint { label, found, x, y })
+            .collect();
+        Self { hints, action, typed: String::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    // Type a label character; returns the chosen match once a label is complete
+    pub fn push_char(&mut self, c: char) -> Option<SmartMatch> {
+        self.typed.push(c);
+        if let Some(hint) = self.hints.iter().find(|h| h.label == self.typed) {
+            return Some(hint.found.clone());
+        }
+        // A character no label continues with is ignored
+        if !self.hints.iter().any(|h| h.label.starts_with(&self.typed)) {
+            self.typed.pop();
+        }
+        None
+    }
+
+    pub fn pop_char(&mut self) {
+        self.typed.pop();
+    }
+
+    // Draw the labels still matching what's typed over the window
+    pub fn render(&self, f: &mut Frame, screen: Rect, theme: &Theme) {
+        let label_style = Style::default().fg(Color::Black).bg(theme.command).add_modifier(Modifier::BOLD);
+        for hint in self.hints.iter().filter(|h| h.label.starts_with(&self.typed)) {
+            let width = (hint.label.len() as u16).min(screen.right().saturating_sub(hint.x));
+            let rect = Rect::new(hint.x, hint.y, width, 1).intersection(screen);
+            f.render_widget(Paragraph::new(Span::styled(hint.label.as_str(), label_style)), rect);
+        }
+    }
+}
+
+// `count` labels, none the start of another: single keys while they suffice, else pairs
+fn labels(count: usize) -> Vec<String> {
+    let keys: Vec<char> = LABEL_KEYS.chars().collect();
+    if count <= keys.len() {
+        return keys.iter().take(count).map(|c| c.to_string()).collect();
+    }
+    keys.iter()
+        .flat_map(|a| keys.iter().map(move |b| format!("{}{}", a, b)))
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::smart_select::{default_rules, SmartSelection};
+
+    #[test]
+    fn typing_a_label_chooses_its_match() {
+        let selection = SmartSelection::new(&default_rules());
+        let lines = ["clone https://example.com/repo", "at 1a2b3c4d in src/main.rs"];
+        let mut hints = Hints::new(&lines, Rect::new(1, 1, 40, 2), |line| selection.find(line), HintAction::Copy);
+
+        let placed: Vec<_> = hints.hints.iter().map(|h| (h.label.as_str(), h.x, h.y)).collect();
+        assert_eq!(placed, [("a", 7, 1), ("s", 4, 2), ("d", 16, 2)]);
+
+        // Keys no label starts with are ignored
+        assert_eq!(hints.push_char('z'), None);
+        assert_eq!(hints.push_char('d').map(|m| m.text), Some(String::from("src/main.rs")));
+    }
+
+    #[test]
+    fn many_matches_get_two_key_labels() {
+        let labels = labels(30);
+        assert_eq!(labels.len(), 30);
+        assert!(labels.iter().all(|l| l.len() == 2));
+        assert!(labels.iter().enumerate().all(|(i, l)| !labels[i + 1..].contains(l)));
+    }
+}
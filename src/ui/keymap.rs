@@ -0,0 +1,318 @@
+// Key sequences bound to commands, e.g. "Ctrl+A w v" to `split`: keys typed are
+// collected while they start some binding, and once a sequence is complete its command
+// runs as if typed after ':'. While keys are pending, a box lists what can follow.
+// Each input mode (normal, copy, resize...) has its own bindings.
+
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::ui::style::Theme;
+
+// A key with its modifiers, as bindings are written: "Ctrl+A", "Alt+x", "w", "Enter"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Chord {
+    pub fn from_event(key: &KeyEvent) -> Self {
+        // Shift is in the character itself ("A", "?"), not kept apart
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        // Ctrl+letters match however the terminal reports the letter's case
+        let code = match key.code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Self { code, modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT) }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // "Ctrl++" binds the plus key
+        if text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let (key, names) = parts.split_last()?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in names {
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            name if name.len() > 1 && name.starts_with('f') => KeyCode::F(name[1..].parse().ok()?),
+            _ => {
+                let mut chars = key.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                KeyCode::Char(c)
+            }
+        };
+
+        Some(Self::from_event(&KeyEvent::new(code, modifiers)))
+    }
+
+    // The key as a binding would write it
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for (modifier, prefix) in [(KeyModifiers::CONTROL, "Ctrl+"), (KeyModifiers::ALT, "Alt+"), (KeyModifiers::SHIFT, "Shift+")] {
+            if self.modifiers.contains(modifier) {
+                name.push_str(prefix);
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => name.push_str("Space"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => name.push(c.to_ascii_uppercase()),
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::F(n) => name.push_str(&format!("F{}", n)),
+            KeyCode::PageUp => name.push_str("PageUp"),
+            KeyCode::PageDown => name.push_str("PageDown"),
+            code => name.push_str(&format!("{:?}", code)),
+        }
+        name
+    }
+}
+
+// Keys typed so far, written as in bindings
+pub fn sequence_name(keys: &[Chord]) -> String {
+    keys.iter().map(Chord::name).collect::<Vec<_>>().join(" ")
+}
+
+// What the keys typed so far amount to
+#[derive(Debug, PartialEq)]
+pub enum Lookup<'a> {
+    // No binding starts with them
+    None,
+    // Longer bindings start with them; the command is what they're bound to themselves,
+    // run if nothing follows in time
+    Pending(Option<&'a str>),
+    // A binding, complete
+    Command(&'a str),
+}
+
+// What typed keys are for; each mode has its own key bindings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    // Keys go to the focused window
+    Normal,
+    // Keys move a cursor over the scrollback to select text
+    Copy,
+    // Keys move the focused window's borders
+    Resize,
+    // Keys type a pattern to search the focused window for
+    Search,
+    // Keys type a command
+    Command,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] = [Mode::Normal, Mode::Copy, Mode::Resize, Mode::Search, Mode::Command];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Copy => "copy",
+            Mode::Resize => "resize",
+            Mode::Search => "search",
+            Mode::Command => "command",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+// The bindings of every mode
+pub struct Keymaps {
+    // In the order of Mode::ALL
+    maps: Vec<Keymap>,
+}
+
+impl Keymaps {
+    // Normal mode's bindings and the other modes' tables, by mode name; bindings that
+    // don't parse and unknown modes are returned as errors
+    pub fn new(normal: &BTreeMap<String, String>, modes: &BTreeMap<String, BTreeMap<String, String>>) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        for name in modes.keys().filter(|name| Mode::from_name(name).is_none()) {
+            errors.push(format!("unknown mode {:?}", name));
+        }
+
+        let empty = BTreeMap::new();
+        let maps = Mode::ALL.iter()
+            .map(|mode| {
+                // Normal mode's bindings can be in either place
+                let mut table = modes.get(mode.name()).unwrap_or(&empty).clone();
+                if *mode == Mode::Normal {
+                    table.extend(normal.iter().map(|(keys, command)| (keys.clone(), command.clone())));
+                }
+                let (keymap, mode_errors) = Keymap::new(&table);
+                errors.extend(mode_errors);
+                keymap
+            })
+            .collect();
+        (Self { maps }, errors)
+    }
+
+    pub fn get(&self, mode: Mode) -> &Keymap {
+        let index = Mode::ALL.iter().position(|m| *m == mode).unwrap_or(0);
+        &self.maps[index]
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Vec<Chord>, String)>,
+}
+
+impl Keymap {
+    // The bindings of a settings table (keys to command); those that don't parse are
+    // returned as errors
+    pub fn new(table: &BTreeMap<String, String>) -> (Self, Vec<String>) {
+        let mut bindings = Vec::new();
+        let mut errors = Vec::new();
+        for (keys, command) in table {
+            let chords: Option<Vec<Chord>> = keys.split_whitespace().map(Chord::parse).collect();
+            match chords {
+                Some(chords) if !chords.is_empty() => bindings.push((chords, command.trim_start_matches(':').to_string())),
+                _ => errors.push(format!("invalid key binding {:?}", keys)),
+            }
+        }
+        (Self { bindings }, errors)
+    }
+
+    // Each binding's keys and command
+    pub fn bindings(&self) -> &[(Vec<Chord>, String)] {
+        &self.bindings
+    }
+
+    pub fn lookup(&self, keys: &[Chord]) -> Lookup<'_> {
+        let exact = self.bindings.iter().find(|(chords, _)| chords == keys).map(|(_, command)| command.as_str());
+        let longer = self.bindings.iter().any(|(chords, _)| chords.len() > keys.len() && chords.starts_with(keys));
+        match (exact, longer) {
+            (_, true) => Lookup::Pending(exact),
+            (Some(command), false) => Lookup::Command(command),
+            (None, false) => Lookup::None,
+        }
+    }
+
+    // The keys that can follow those typed, and what each leads to: a command, or
+    // more keys
+    pub fn continuations(&self, keys: &[Chord]) -> Vec<(String, String)> {
+        let mut next: Vec<(String, String)> = Vec::new();
+        for (chords, command) in self.bindings.iter().filter(|(chords, _)| chords.len() > keys.len() && chords.starts_with(keys)) {
+            let key = chords[keys.len()].name();
+            let leads_to = if chords.len() == keys.len() + 1 { command.clone() } else { String::from("…") };
+            match next.iter_mut().find(|(k, _)| *k == key) {
+                // A key that both completes a binding and starts longer ones shows the command
+                Some(entry) if chords.len() == keys.len() + 1 => entry.1 = leads_to,
+                Some(_) => {}
+                None => next.push((key, leads_to)),
+            }
+        }
+        next
+    }
+}
+
+// The keys that can follow those typed, in a box above the bottom of an area
+pub fn render_hints(f: &mut Frame, area: Rect, pending: &str, hints: &[(String, String)], theme: &Theme) {
+    if hints.is_empty() {
+        return;
+    }
+    let key_width = hints.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = hints.iter()
+        .map(|(key, command)| Line::from(vec![
+            Span::styled(format!(" {:width$}", key, width = key_width), Style::default().fg(theme.command).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("  {}", command), Style::default().fg(theme.foreground)),
+        ]))
+        .collect();
+
+    let width = lines.iter().map(|line| line.width() as u16).max().unwrap_or(0).max(pending.len() as u16 + 4) + 3;
+    let height = lines.len() as u16 + 2;
+    let rect = Rect::new(
+        area.right().saturating_sub(width + 1).max(area.x),
+        area.bottom().saturating_sub(height + 1).max(area.y),
+        width,
+        height,
+    ).intersection(area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Plain)
+        .border_style(Style::default().fg(theme.focused_border))
+        .title(format!(" {} ", pending));
+    f.render_widget(Clear, rect);
+    f.render_widget(Paragraph::new(lines).block(block).style(Style::default().bg(theme.background)), rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_wait_for_their_last_key() {
+        let table = BTreeMap::from([
+            (String::from("Ctrl+A w v"), String::from("split")),
+            (String::from("Ctrl+a w h"), String::from(":split h")),
+            (String::from("Ctrl+A z"), String::from("zoom")),
+            (String::from("Ctrl+Foo"), String::from("nothing")),
+        ]);
+        let modes = BTreeMap::from([
+            (String::from("resize"), BTreeMap::from([(String::from("Left"), String::from("resize left 5"))])),
+            (String::from("insert"), BTreeMap::new()),
+        ]);
+        let (keymaps, errors) = Keymaps::new(&table, &modes);
+        assert_eq!(errors, vec![String::from("unknown mode \"insert\""), String::from("invalid key binding \"Ctrl+Foo\"")]);
+        let keymap = keymaps.get(Mode::Normal);
+
+        let key = |code, modifiers| Chord::from_event(&KeyEvent::new(code, modifiers));
+        let prefix = key(KeyCode::Char('A'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        let w = key(KeyCode::Char('w'), KeyModifiers::NONE);
+        assert_eq!(keymap.lookup(&[prefix]), Lookup::Pending(None));
+        assert_eq!(keymap.lookup(&[prefix, w, key(KeyCode::Char('h'), KeyModifiers::NONE)]), Lookup::Command("split h"));
+        assert_eq!(keymap.lookup(&[prefix, key(KeyCode::Char('x'), KeyModifiers::NONE)]), Lookup::None);
+        assert_eq!(keymap.lookup(&[w]), Lookup::None);
+
+        assert_eq!(keymap.continuations(&[prefix]), vec![(String::from("w"), String::from("…")), (String::from("z"), String::from("zoom"))]);
+        assert_eq!(sequence_name(&[prefix, w]), "Ctrl+A w");
+        assert_eq!(Chord::parse("Alt+Enter").map(|c| c.name()), Some(String::from("Alt+Enter")));
+
+        // Each mode has its own bindings
+        let left = key(KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(keymaps.get(Mode::Resize).lookup(&[left]), Lookup::Command("resize left 5"));
+        assert_eq!(keymap.lookup(&[left]), Lookup::None);
+        assert_eq!(keymaps.get(Mode::Resize).lookup(&[prefix]), Lookup::None);
+    }
+}
@@ -1,4 +1,7 @@
 use ratatui::layout::{Layout, Constraint, Direction, Rect};
+use uuid::Uuid;
+
+use crate::ui::window_manager::{LayoutNode, SplitDirection};
 
 pub fn create_main_layout(area: Rect) -> Vec<Rect> {
     Layout::default()
@@ -9,4 +12,234 @@ pub fn create_main_layout(area: Rect) -> Vec<Rect> {
         ])
         .split(area)
         .to_vec()
-}
\ No newline at end of file
+}
+
+// Layout tree math, kept free of window state so it can be tested on its own
+
+// Smallest area (width, height) in which every window of a layout gets at least one cell
+pub fn min_size(node: &LayoutNode) -> (u16, u16) {
+    match node {
+        LayoutNode::Window { .. } => (1, 1),
+        LayoutNode::Split { direction, first, second, .. } => {
+            let (first, second) = (min_size(first), min_size(second));
+            match direction {
+                SplitDirection::Horizontal => (first.0.saturating_add(second.0), first.1.max(second.1)),
+                SplitDirection::Vertical => (first.0.max(second.0), first.1.saturating_add(second.1)),
+            }
+        }
+    }
+}
+
+// Cells given to the first side of a split of `length` cells: `ratio` of them,
+// moved if needed so each side keeps the minimum it needs whenever there is room
+pub fn split_length(length: u16, ratio: f32, min_first: u16, min_second: u16) -> u16 {
+    let ratio = if ratio.is_nan() { 0.5 } else { ratio.clamp(0.0, 1.0) };
+    let wanted = ((length as f32 * ratio).floor() as u16).min(length);
+
+    if min_first.saturating_add(min_second) > length {
+        // Too small for both; keep the ratio
+        return wanted;
+    }
+    wanted.clamp(min_first, length - min_second)
+}
+
+// Divide a rectangle between the two sides of a split, the first side getting `first_length` cells
+pub fn split_rect(rect: Rect, direction: SplitDirection, first_length: u16) -> (Rect, Rect) {
+    match direction {
+        SplitDirection::Horizontal => {
+            let first_length = first_length.min(rect.width);
+            (
+                Rect::new(rect.x, rect.y, first_length, rect.height),
+                Rect::new(rect.x + first_length, rect.y, rect.width - first_length, rect.height),
+            )
+        }
+        SplitDirection::Vertical => {
+            let first_length = first_length.min(rect.height);
+            (
+                Rect::new(rect.x, rect.y, rect.width, first_length),
+                Rect::new(rect.x, rect.y + first_length, rect.width, rect.height - first_length),
+            )
+        }
+    }
+}
+
+// The layout with only the windows `keep` accepts; a split that loses one side is
+// replaced by the other side, and None is left if no window is kept
+pub fn retain_windows(node: LayoutNode, keep: &impl Fn(&Uuid) -> bool) -> Option<LayoutNode> {
+    match node {
+        LayoutNode::Window { id, .. } => keep(&id).then_some(node),
+        LayoutNode::Split { direction, ratio, first, second, rect } => {
+            match (retain_windows(*first, keep), retain_windows(*second, keep)) {
+                (Some(first), Some(second)) => Some(LayoutNode::split(direction, ratio, first, second, rect)),
+                (Some(only), None) | (None, Some(only)) => Some(only),
+                (None, None) => None,
+            }
+        }
+    }
+}
+
+// The layout without a window, its sibling taking the place of the split that held it
+pub fn without_window(node: LayoutNode, id: &Uuid) -> Option<LayoutNode> {
+    retain_windows(node, &|window| window != id)
+}
+
+// The first window of the subtree next to a window, which takes its place when it is removed
+pub fn sibling_window(node: &LayoutNode, id: &Uuid) -> Option<Uuid> {
+    match node {
+        LayoutNode::Window { .. } => None,
+        LayoutNode::Split { first, second, .. } => {
+            let is_window = |node: &LayoutNode| matches!(node, LayoutNode::Window { id: window, .. } if window == id);
+            if is_window(first) {
+                second.window_ids().first().copied()
+            } else if is_window(second) {
+                first.window_ids().first().copied()
+            } else {
+                sibling_window(first, id).or_else(|| sibling_window(second, id))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::sample::Index;
+
+    // Layouts of up to 16 windows with any split directions and ratios
+    fn layouts() -> impl Strategy<Value = LayoutNode> {
+        let window = Just(LayoutNode::window(Uuid::nil(), Rect::default()));
+        window
+            .prop_recursive(5, 16, 2, |inner| {
+                (any::<bool>(), 0.0f32..=1.0, inner.clone(), inner).prop_map(|(horizontal, ratio, first, second)| {
+                    let direction = if horizontal { SplitDirection::Horizontal } else { SplitDirection::Vertical };
+                    LayoutNode::split(direction, ratio, first, second, Rect::default())
+                })
+            })
+            .prop_map(|mut layout| {
+                number_windows(&mut layout, &mut 0);
+                layout
+            })
+    }
+
+    // Give the windows distinct IDs
+    fn number_windows(node: &mut LayoutNode, next: &mut u128) {
+        match node {
+            LayoutNode::Window { id, .. } => {
+                *next += 1;
+                *id = Uuid::from_u128(*next);
+            }
+            LayoutNode::Split { first, second, .. } => {
+                number_windows(first, next);
+                number_windows(second, next);
+            }
+        }
+    }
+
+    fn areas() -> impl Strategy<Value = Rect> {
+        (0u16..50, 0u16..50, 0u16..250, 0u16..120).prop_map(|(x, y, width, height)| Rect::new(x, y, width, height))
+    }
+
+    // Every window's rectangle
+    fn window_rects(layout: &LayoutNode) -> Vec<Rect> {
+        layout.window_ids().iter().filter_map(|id| layout.window_rect(id)).collect()
+    }
+
+    // Splits whose sides are both windows: (window, its sibling, direction, ratio, window is the first side)
+    fn window_pairs(node: &LayoutNode) -> Vec<(Uuid, Uuid, SplitDirection, f32, bool)> {
+        match node {
+            LayoutNode::Window { .. } => Vec::new(),
+            LayoutNode::Split { direction, ratio, first, second, .. } => match (first.as_ref(), second.as_ref()) {
+                (LayoutNode::Window { id: a, .. }, LayoutNode::Window { id: b, .. }) => {
+                    vec![(*a, *b, *direction, *ratio, true), (*b, *a, *direction, *ratio, false)]
+                }
+                _ => {
+                    let mut pairs = window_pairs(first);
+                    pairs.extend(window_pairs(second));
+                    pairs
+                }
+            },
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn windows_tile_the_area(mut layout in layouts(), area in areas()) {
+            layout.calculate_layout(area);
+            let rects = window_rects(&layout);
+
+            for rect in &rects {
+                prop_assert_eq!(rect.intersection(area), *rect, "{:?} outside {:?}", rect, area);
+            }
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    prop_assert_eq!(a.intersection(*b).area(), 0, "{:?} overlaps {:?}", a, b);
+                }
+            }
+            let covered: u32 = rects.iter().map(|r| r.area() as u32).sum();
+            prop_assert_eq!(covered, area.area() as u32);
+        }
+
+        #[test]
+        fn no_window_is_empty_when_there_is_room(mut layout in layouts(), area in areas()) {
+            let (width, height) = min_size(&layout);
+            prop_assume!(area.width >= width && area.height >= height);
+
+            layout.calculate_layout(area);
+            for rect in window_rects(&layout) {
+                prop_assert!(rect.width > 0 && rect.height > 0, "empty window {:?} in {:?}", rect, area);
+            }
+        }
+
+        #[test]
+        fn split_length_keeps_minimums(length in any::<u16>(), ratio in any::<f32>(), min_first in 0u16..100, min_second in 0u16..100) {
+            let first = split_length(length, ratio, min_first, min_second);
+            prop_assert!(first <= length);
+            if min_first + min_second <= length {
+                prop_assert!(first >= min_first && length - first >= min_second);
+            }
+        }
+
+        #[test]
+        fn removing_a_split_off_window_restores_the_layout(
+            mut layout in layouts(),
+            target in any::<Index>(),
+            horizontal in any::<bool>(),
+            ratio in 0.0f32..=1.0,
+            new_first in any::<bool>(),
+        ) {
+            let area = Rect::new(0, 0, 200, 100);
+            layout.calculate_layout(area);
+            let ids = layout.window_ids();
+            let target = *target.get(&ids);
+            let direction = if horizontal { SplitDirection::Horizontal } else { SplitDirection::Vertical };
+
+            let mut split = layout.clone();
+            let new_id = Uuid::new_v4();
+            split.split_window(&target, direction, new_id, ratio, new_first).unwrap();
+            prop_assert_eq!(split.window_ids().len(), ids.len() + 1);
+
+            let mut restored = without_window(split, &new_id).unwrap();
+            restored.calculate_layout(area);
+            prop_assert_eq!(restored, layout);
+        }
+
+        #[test]
+        fn splitting_a_removed_window_back_restores_the_layout(mut layout in layouts(), pick in any::<Index>()) {
+            let pairs = window_pairs(&layout);
+            prop_assume!(!pairs.is_empty());
+            let (removed, sibling, direction, ratio, removed_first) = *pick.get(&pairs);
+
+            let area = Rect::new(0, 0, 200, 100);
+            layout.calculate_layout(area);
+
+            let mut restored = without_window(layout.clone(), &removed).unwrap();
+            prop_assert!(!restored.contains(&removed));
+            prop_assert_eq!(restored.window_ids().len(), layout.window_ids().len() - 1);
+
+            restored.split_window(&sibling, direction, removed, ratio, removed_first).unwrap();
+            restored.calculate_layout(area);
+            prop_assert_eq!(restored, layout);
+        }
+    }
+}
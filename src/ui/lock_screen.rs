@@ -0,0 +1,144 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::terminal::auth;
+use crate::ui::style::Theme;
+
+// Screen hiding every window until the user's password is typed
+#[derive(Default)]
+pub struct LockScreen {
+    // Password typed so far
+    input: String,
+    // Why the last attempt failed
+    message: Option<String>,
+    // Result of the check running in the background
+    checking: Option<Receiver<Result<bool, String>>>,
+}
+
+impl LockScreen {
+    pub fn push_char(&mut self, c: char) {
+        if self.checking.is_none() {
+            self.input.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.input.clear();
+    }
+
+    // Type the password; Ctrl+C and Esc only clear it, as nothing gets past the lock
+    pub fn handle_key(&mut self, key: KeyEvent, command: &str) {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.clear(),
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.push_char(c),
+            KeyCode::Backspace => self.pop_char(),
+            KeyCode::Enter => self.submit(command),
+            KeyCode::Esc => self.clear(),
+            _ => {}
+        }
+    }
+
+    // Check the typed password in the background (su may take a few seconds)
+    pub fn submit(&mut self, command: &str) {
+        if self.checking.is_some() || self.input.is_empty() {
+            return;
+        }
+        let password = std::mem::take(&mut self.input);
+        let command = command.to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = auth::verify_password(&password, &command).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+        self.checking = Some(receiver);
+        self.message = None;
+    }
+
+    // Whether the check finished with the right password
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.checking else { return false };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => Err(String::from("the check stopped")),
+        };
+        self.checking = None;
+        match result {
+            Ok(true) => true,
+            Ok(false) => {
+                self.message = Some(String::from("Wrong password"));
+                false
+            }
+            Err(e) => {
+                self.message = Some(format!("Can't check the password: {}", e));
+                false
+            }
+        }
+    }
+
+    // Cover the whole screen, with the password prompt in the middle
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(Style::default().bg(theme.background)), area);
+
+        let width = area.width.min(50);
+        let height = 6.min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let status = match (&self.checking, &self.message) {
+            (Some(_), _) => String::from("Checking…"),
+            (None, Some(message)) => message.clone(),
+            (None, None) => String::new(),
+        };
+        let lines = vec![
+            Line::from(format!("Password: {}", "•".repeat(self.input.chars().count()))),
+            Line::from(""),
+            Line::styled(status, Style::default().add_modifier(Modifier::DIM)),
+        ];
+        let prompt = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.foreground).bg(theme.background))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title("🔒 Matrix is locked"),
+            );
+        f.render_widget(prompt, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrl_c_clears_the_password_and_stays_locked() {
+        let mut lock_screen = LockScreen::default();
+        for c in "hunter2".chars() {
+            lock_screen.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), "false");
+        }
+        assert_eq!(lock_screen.input, "hunter2");
+
+        lock_screen.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), "false");
+        assert!(lock_screen.input.is_empty());
+        assert!(lock_screen.checking.is_none());
+        assert!(!lock_screen.poll());
+    }
+}
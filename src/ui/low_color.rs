@@ -0,0 +1,147 @@
+// Drawing for terminals with fewer colors or without box-drawing characters: once a
+// frame is drawn, its colors are mapped to the nearest ones the terminal has and its
+// lines redrawn in ASCII, so Matrix stays usable on a console or an old terminal.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+
+use crate::terminal::export::{self, BASIC_COLORS};
+
+// Colors the terminal Matrix runs in can show
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    // Values of the ui.color_depth setting
+    pub const NAMES: [&'static str; 4] = ["auto", "truecolor", "256", "16"];
+
+    // The depth a setting names; "auto" detects it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(detect()),
+            "truecolor" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+}
+
+// The depth of the terminal Matrix runs in, from COLORTERM and TERM as most programs
+// tell: truecolor if COLORTERM says so, 256 colors for *-256color terminals, and
+// otherwise the 16 colors every color terminal has
+pub fn detect() -> ColorDepth {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+// Bring a drawn frame down to what the terminal can show
+pub fn degrade(buffer: &mut Buffer, depth: ColorDepth, ascii_borders: bool) {
+    if depth == ColorDepth::TrueColor && !ascii_borders {
+        return;
+    }
+    for cell in buffer.content.iter_mut() {
+        cell.fg = reduce(cell.fg, depth);
+        cell.bg = reduce(cell.bg, depth);
+        if ascii_borders {
+            if let Some(ascii) = ascii_symbol(cell.symbol()) {
+                cell.set_symbol(ascii);
+            }
+        }
+    }
+}
+
+// The nearest color a terminal of some depth has
+fn reduce(color: Color, depth: ColorDepth) -> Color {
+    match (depth, color) {
+        (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256((r, g, b))),
+        // The 16 basic colors, which 16-color terminals such as the Linux console have
+        (ColorDepth::Ansi16, Color::Rgb(..) | Color::Indexed(_)) => match export::rgb(color) {
+            Some(rgb) => NAMED_COLORS[nearest(&BASIC_COLORS, rgb)],
+            None => color,
+        },
+        _ => color,
+    }
+}
+
+// The named colors, in palette order
+const NAMED_COLORS: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow,
+    Color::Blue, Color::Magenta, Color::Cyan, Color::Gray,
+    Color::DarkGray, Color::LightRed, Color::LightGreen, Color::LightYellow,
+    Color::LightBlue, Color::LightMagenta, Color::LightCyan, Color::White,
+];
+
+// Index of the nearest 256-color palette entry, in the color cube or the grayscale ramp
+fn nearest_256((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |v: u8| if v < 48 { 0 } else if v < 115 { 1 } else { (v - 35) / 40 };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let gray = if average > 238 { 255 } else { 232 + (average.saturating_sub(3) / 10) as u8 };
+
+    let distance = |index: u8| export::rgb(Color::Indexed(index)).map_or(u32::MAX, |c| distance(c, (r, g, b)));
+    if distance(gray) < distance(cube) { gray } else { cube }
+}
+
+// Index of the nearest of some colors
+fn nearest(colors: &[(u8, u8, u8)], rgb: (u8, u8, u8)) -> usize {
+    (0..colors.len()).min_by_key(|&i| distance(colors[i], rgb)).unwrap_or(0)
+}
+
+// Squared distance between two colors
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+// ASCII in place of a box-drawing or other symbol Matrix draws
+fn ascii_symbol(symbol: &str) -> Option<&'static str> {
+    let mut chars = symbol.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(match c {
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' => "-",
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '‖' => "|",
+        '\u{2500}'..='\u{257f}' => "+",
+        '◆' | '●' => "*",
+        '█' | '▌' | '▐' => "#",
+        '↓' => "v",
+        '↑' => "^",
+        '⇢' | '→' => ">",
+        '…' => ".",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_map_to_the_nearest_the_terminal_has() {
+        assert_eq!(reduce(Color::Rgb(0, 180, 45), ColorDepth::Ansi16), Color::Green);
+        assert_eq!(reduce(Color::Indexed(196), ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(reduce(Color::Rgb(0xff, 0x00, 0x00), ColorDepth::Ansi256), Color::Indexed(196));
+        assert_eq!(reduce(Color::Rgb(0x80, 0x80, 0x80), ColorDepth::Ansi256), Color::Indexed(244));
+        assert_eq!(reduce(Color::Rgb(1, 2, 3), ColorDepth::TrueColor), Color::Rgb(1, 2, 3));
+        assert_eq!(reduce(Color::Reset, ColorDepth::Ansi16), Color::Reset);
+
+        assert_eq!(ascii_symbol("┌"), Some("+"));
+        assert_eq!(ascii_symbol("─"), Some("-"));
+        assert_eq!(ascii_symbol("┃"), Some("|"));
+        assert_eq!(ascii_symbol("a"), None);
+    }
+}
@@ -3,3 +3,23 @@ pub mod style;
 pub mod widgets;
 pub mod window_manager;
 pub mod sidebar;
+pub mod accessibility;
+pub mod picker;
+pub mod notifications;
+pub mod preferences;
+pub mod bell;
+pub mod status_bar;
+pub mod lock_screen;
+pub mod config_errors;
+pub mod screenshot;
+pub mod hints;
+pub mod status_scripts;
+pub mod low_color;
+pub mod panels;
+pub mod keymap;
+pub mod theme_files;
+pub mod theme_editor;
+pub mod cheatsheet;
+pub mod tutorial;
+pub mod diff_view;
+pub mod pager;
@@ -0,0 +1,78 @@
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::ui::style::Theme;
+
+// Most notifications shown at once
+const MAX_VISIBLE: usize = 3;
+
+// A message shown briefly in the corner of the screen
+struct Notification {
+    text: String,
+    created: Instant,
+}
+
+// Transient notifications ("toasts")
+pub struct Notifications {
+    messages: VecDeque<Notification>,
+    // How long a notification stays on screen
+    duration: Duration,
+}
+
+impl Notifications {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            duration,
+        }
+    }
+
+    // Show a notification
+    pub fn push(&mut self, text: &str) {
+        self.messages.push_back(Notification {
+            text: text.to_string(),
+            created: Instant::now(),
+        });
+        while self.messages.len() > MAX_VISIBLE {
+            self.messages.pop_front();
+        }
+    }
+
+    // Drop notifications that have been shown long enough
+    pub fn expire(&mut self) {
+        let duration = self.duration;
+        self.messages.retain(|n| n.created.elapsed() < duration);
+    }
+
+    // Render the notifications stacked in the top right corner
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.min(50);
+        let mut y = area.y;
+
+        for notification in &self.messages {
+            let height = 3;
+            if y + height > area.y + area.height {
+                break;
+            }
+
+            let rect = Rect::new(area.x + area.width - width, y, width, height);
+            let paragraph = Paragraph::new(notification.text.as_str())
+                .style(Style::default().fg(theme.foreground).bg(theme.background))
+                .wrap(Wrap { trim: true })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Plain)
+                        .border_style(Style::default().fg(theme.command)),
+                );
+
+            f.render_widget(Clear, rect);
+            f.render_widget(paragraph, rect);
+            y += height;
+        }
+    }
+}
@@ -0,0 +1,301 @@
+// Built-in pager (`:view <file>`, or `:view` for the current window's history): the
+// lines in an overlay over the whole screen, with line numbers, searching, and files
+// colored by their syntax, so logs can be read without running less in a window.
+// Files are colored as they're scrolled to, and only their start is read if they're big.
+
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+use regex::Regex;
+
+use crate::terminal::style::{StyleMapping, StyleRun};
+use crate::terminal::syntax::{self, Highlighter};
+use crate::terminal::window::{cell_styled_line, style_chars};
+use crate::ui::style::Theme;
+
+// Most of a file read into the pager
+const MAX_FILE_BYTES: u64 = 16 << 20;
+
+pub struct Pager {
+    title: String,
+    lines: Vec<String>,
+    // Styles of the lines colored so far
+    runs: RefCell<Vec<Vec<StyleRun>>>,
+    // Colors the rest of a file's lines when they're shown
+    highlighter: RefCell<Option<Highlighter>>,
+    // Language the lines are colored as
+    syntax: Option<&'static str>,
+    // Only the start of the file was read
+    truncated: bool,
+    // First line shown
+    top: usize,
+    // Lines that fit, as of the last render
+    page: Cell<usize>,
+    numbers: bool,
+    search: Option<Regex>,
+    // Lines matching the search
+    matches: Vec<usize>,
+    // Search being typed
+    input: Option<String>,
+    message: Option<String>,
+}
+
+impl Pager {
+    // Lines with the styles they were shown in, e.g. a window's history
+    pub fn new(title: &str, lines: Vec<(String, Vec<StyleRun>)>) -> Self {
+        let (lines, runs) = lines.into_iter().unzip();
+        Self {
+            title: title.to_string(),
+            lines,
+            runs: RefCell::new(runs),
+            highlighter: RefCell::new(None),
+            syntax: None,
+            truncated: false,
+            top: 0,
+            page: Cell::new(1),
+            numbers: true,
+            search: None,
+            matches: Vec::new(),
+            input: None,
+            message: None,
+        }
+    }
+
+    // A file, colored by its syntax if it's one the highlighter knows
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)?.take(MAX_FILE_BYTES + 1).read_to_end(&mut data)?;
+        let truncated = data.len() as u64 > MAX_FILE_BYTES;
+        data.truncate(MAX_FILE_BYTES as usize);
+
+        let text = String::from_utf8_lossy(&data).replace('\t', "    ");
+        let lines = text.lines().map(str::to_string).collect();
+        let mut pager = Self::new(&path.to_string_lossy(), Vec::new());
+        pager.lines = lines;
+        pager.truncated = truncated;
+        if let Some(syntax) = syntax::for_path(&path.to_string_lossy()) {
+            pager.syntax = Some(syntax.name);
+            pager.highlighter = RefCell::new(Some(Highlighter::new(syntax)));
+        }
+        Ok(pager)
+    }
+
+    // Color the lines up to `end`, if they're a file's not colored yet
+    fn highlight_to(&self, end: usize) {
+        let mut runs = self.runs.borrow_mut();
+        match self.highlighter.borrow_mut().as_mut() {
+            Some(highlighter) => {
+                while runs.len() < end {
+                    let line = &self.lines[runs.len()];
+                    runs.push(highlighter.line(line));
+                }
+            }
+            None => runs.resize(self.lines.len(), Vec::new()),
+        }
+    }
+
+    // Show the last page, for output where the newest lines matter most
+    pub fn go_to_end(&mut self) {
+        self.top = usize::MAX;
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn last_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.page.get())
+    }
+
+    fn scroll(&mut self, lines: isize) {
+        self.top = (self.top.min(self.last_top()) as isize + lines).clamp(0, self.last_top() as isize) as usize;
+    }
+
+    // Search for a pattern, showing the first match from the top of the page on
+    fn set_search(&mut self, pattern: &str) {
+        match Regex::new(pattern) {
+            Ok(regex) => {
+                self.matches = (0..self.lines.len()).filter(|&i| regex.is_match(&self.lines[i])).collect();
+                self.search = Some(regex);
+                self.message = Some(format!("{} matching lines", self.matches.len()));
+                self.next_match(true);
+            }
+            Err(e) => self.message = Some(format!("Invalid pattern: {}", e)),
+        }
+    }
+
+    // Go to the next match below the top line, or the one above it; wraps around
+    fn next_match(&mut self, forward: bool) {
+        let top = self.top.min(self.last_top());
+        let found = if forward {
+            self.matches.iter().find(|&&line| line > top).or(self.matches.first())
+        } else {
+            self.matches.iter().rev().find(|&&line| line < top).or(self.matches.last())
+        };
+        if let Some(&line) = found {
+            self.top = line.min(self.last_top());
+        }
+    }
+
+    // Scroll, search and toggle the line numbers; false once it's closed
+    pub fn handle_key(&mut self, code: crossterm::event::KeyCode) -> bool {
+        use crossterm::event::KeyCode;
+
+        if let Some(input) = &mut self.input {
+            match code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let pattern = std::mem::take(input);
+                    self.input = None;
+                    if !pattern.is_empty() {
+                        self.set_search(&pattern);
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        let page = self.page.get() as isize;
+        self.message = None;
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => self.scroll(1),
+            KeyCode::PageUp | KeyCode::Char('b') => self.scroll(-page),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll(page),
+            KeyCode::Home | KeyCode::Char('g') => self.top = 0,
+            KeyCode::End | KeyCode::Char('G') => self.top = self.last_top(),
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('n') => self.next_match(true),
+            KeyCode::Char('N') => self.next_match(false),
+            KeyCode::Char('#') => self.numbers = !self.numbers,
+            KeyCode::Esc if self.search.is_some() => {
+                self.search = None;
+                self.matches.clear();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return false,
+            _ => {}
+        }
+        true
+    }
+
+    // Render the pager over an area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme, mapping: StyleMapping) {
+        let page = area.height.saturating_sub(2) as usize;
+        self.page.set(page.max(1));
+        let top = self.top.min(self.last_top());
+        let end = (top + page).min(self.lines.len());
+        self.highlight_to(end);
+        let runs = self.runs.borrow();
+
+        let gutter = self.lines.len().max(1).to_string().len();
+        let number_style = Style::default().fg(theme.border);
+        let match_style = Style::default().fg(Color::Black).bg(theme.selected);
+        let lines: Vec<Line> = (top..end)
+            .map(|i| {
+                let line = &self.lines[i];
+                let mut styled = cell_styled_line(line, &runs[i], theme, mapping);
+                if let Some(search) = &self.search {
+                    for found in search.find_iter(line).filter(|found| !found.range().is_empty()) {
+                        let start = line[..found.start()].chars().count();
+                        styled = style_chars(styled, start..start + found.as_str().chars().count(), match_style);
+                    }
+                }
+                if self.numbers {
+                    styled.spans.insert(0, Span::styled(format!("{:>gutter$} ", i + 1, gutter = gutter), number_style));
+                }
+                styled
+            })
+            .collect();
+
+        let mut title = format!("{} ({}-{} of {} lines", self.title, top + 1, end, self.lines.len());
+        if let Some(syntax) = self.syntax {
+            title.push_str(&format!(", {}", syntax));
+        }
+        if self.truncated {
+            title.push_str(&format!(", only the first {} MiB", MAX_FILE_BYTES >> 20));
+        }
+        title.push(')');
+        let footer = match (&self.input, &self.message) {
+            (Some(input), _) => format!("/{}_", input),
+            (None, Some(message)) => message.clone(),
+            (None, None) => String::from("↑↓ PgUp/PgDn: scroll  /: search  n/N: next/previous  #: line numbers  q: close"),
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(title)
+                    .title(Title::from(footer).position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn search_moves_between_matching_lines() {
+        let lines = (1..=50).map(|i| (if i % 20 == 0 { format!("ERROR {}", i) } else { format!("ok {}", i) }, Vec::new())).collect();
+        let mut pager = Pager::new("log", lines);
+        pager.page.set(10);
+
+        for c in "/ERROR".chars() {
+            pager.handle_key(KeyCode::Char(c));
+        }
+        pager.handle_key(KeyCode::Enter);
+        assert_eq!(pager.matches, vec![19, 39]);
+        assert_eq!(pager.top, 19);
+
+        // The last match can only be brought up as far as the last page
+        pager.handle_key(KeyCode::Char('n'));
+        assert_eq!(pager.top, 39.min(pager.last_top()));
+        pager.handle_key(KeyCode::Char('N'));
+        assert_eq!(pager.top, 19);
+
+        pager.handle_key(KeyCode::Char('G'));
+        pager.handle_key(KeyCode::Down);
+        assert_eq!(pager.top, 40);
+
+        // Esc clears the search before it closes the pager
+        assert!(pager.handle_key(KeyCode::Esc));
+        assert!(!pager.handle_key(KeyCode::Esc));
+    }
+
+    #[test]
+    fn files_are_colored_as_they_are_shown() {
+        let path = std::env::temp_dir().join(format!("matrix-pager-{}.rs", std::process::id()));
+        let text: String = (0..1000).map(|i| format!("let x{} = {};\n", i, i)).collect();
+        std::fs::write(&path, text).unwrap();
+        let pager = Pager::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(pager.line_count(), 1000);
+        assert!(pager.runs.borrow().is_empty());
+        pager.highlight_to(30);
+        let runs = pager.runs.borrow();
+        assert_eq!(runs.len(), 30);
+        assert_eq!(runs[29][0].style.fg, Some(Color::Indexed(5)));
+    }
+}
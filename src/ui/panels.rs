@@ -0,0 +1,297 @@
+// Tool panels docked in the sidebar, next to its icons: the window list, search
+// results, bookmarks and SSH hosts. Each panel is a titled list the app fills in; the
+// dock stacks the open panels, and its width and the panels' heights can be dragged
+// at their borders. A new kind of panel only needs a name and its items.
+
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::ui::picker::PickerItem;
+use crate::ui::style::Theme;
+
+// Widths the dock can be dragged to
+pub const MIN_WIDTH: u16 = 16;
+pub const MAX_WIDTH: u16 = 80;
+
+// Rows a panel takes when opened below others
+const DEFAULT_ROWS: u16 = 10;
+// Rows a panel keeps when its neighbor is dragged over it
+const MIN_ROWS: u16 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanelKind {
+    Windows,
+    Search,
+    Bookmarks,
+    SshHosts,
+}
+
+impl PanelKind {
+    pub const ALL: [PanelKind; 4] = [PanelKind::Windows, PanelKind::Search, PanelKind::Bookmarks, PanelKind::SshHosts];
+
+    // Name used by :panel and the ui.panels setting
+    pub fn name(self) -> &'static str {
+        match self {
+            PanelKind::Windows => "windows",
+            PanelKind::Search => "search",
+            PanelKind::Bookmarks => "bookmarks",
+            PanelKind::SshHosts => "ssh",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            PanelKind::Windows => "Windows",
+            PanelKind::Search => "Search",
+            PanelKind::Bookmarks => "Bookmarks",
+            PanelKind::SshHosts => "SSH Hosts",
+        }
+    }
+}
+
+// An open panel and what's listed in it; `T` is what choosing an item does
+struct Panel<T> {
+    kind: PanelKind,
+    // Height, unless it's the last panel, which takes what's left
+    rows: u16,
+    // First item shown
+    scroll: usize,
+    items: Vec<(PickerItem, T)>,
+}
+
+// A border being dragged
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Drag {
+    // The dock's right edge
+    Width,
+    // The bottom border of a panel
+    Height(usize),
+}
+
+pub struct Panels<T> {
+    open: Vec<Panel<T>>,
+    width: u16,
+    drag: Option<Drag>,
+}
+
+impl<T> Panels<T> {
+    pub fn new(width: u16) -> Self {
+        Self {
+            open: Vec::new(),
+            width: width.clamp(MIN_WIDTH, MAX_WIDTH),
+            drag: None,
+        }
+    }
+
+    // Width of the dock; none when no panel is open
+    pub fn width(&self) -> u16 {
+        if self.open.is_empty() { 0 } else { self.width }
+    }
+
+    pub fn set_width(&mut self, width: u16) {
+        self.width = width.clamp(MIN_WIDTH, MAX_WIDTH);
+    }
+
+    pub fn is_open(&self, kind: PanelKind) -> bool {
+        self.open.iter().any(|panel| panel.kind == kind)
+    }
+
+    // Open a panel below the others; nothing if it's already open
+    pub fn open(&mut self, kind: PanelKind) {
+        if !self.is_open(kind) {
+            self.open.push(Panel { kind, rows: DEFAULT_ROWS, scroll: 0, items: Vec::new() });
+        }
+    }
+
+    // Open or close a panel; true if it's now open
+    pub fn toggle(&mut self, kind: PanelKind) -> bool {
+        match self.open.iter().position(|panel| panel.kind == kind) {
+            Some(index) => {
+                self.open.remove(index);
+                self.drag = None;
+                false
+            }
+            None => {
+                self.open(kind);
+                true
+            }
+        }
+    }
+
+    // The open panels, top to bottom
+    pub fn open_kinds(&self) -> Vec<PanelKind> {
+        self.open.iter().map(|panel| panel.kind).collect()
+    }
+
+    // Replace what a panel lists, if it's open
+    pub fn set_items(&mut self, kind: PanelKind, items: Vec<(PickerItem, T)>) {
+        if let Some(panel) = self.open.iter_mut().find(|panel| panel.kind == kind) {
+            panel.scroll = panel.scroll.min(items.len().saturating_sub(1));
+            panel.items = items;
+        }
+    }
+
+    // Where each open panel goes in the dock
+    fn areas(&self, dock: Rect) -> Vec<Rect> {
+        let mut areas = Vec::new();
+        let mut y = dock.y;
+        for (index, panel) in self.open.iter().enumerate() {
+            let left = dock.bottom().saturating_sub(y);
+            // Later panels keep a few rows each
+            let reserved = (self.open.len() - index - 1) as u16 * MIN_ROWS;
+            let height = if index + 1 == self.open.len() {
+                left
+            } else {
+                panel.rows.min(left.saturating_sub(reserved)).max(MIN_ROWS.min(left))
+            };
+            areas.push(Rect::new(dock.x, y, dock.width, height));
+            y += height;
+        }
+        areas
+    }
+
+    // The panel at a position, and its area
+    fn panel_at(&self, dock: Rect, column: u16, row: u16) -> Option<(usize, Rect)> {
+        self.areas(dock).into_iter().enumerate()
+            .find(|(_, area)| column >= area.x && column < area.right() && row >= area.y && row < area.bottom())
+    }
+
+    // What the item at a position does
+    pub fn item_at(&self, dock: Rect, column: u16, row: u16) -> Option<&T> {
+        let (index, area) = self.panel_at(dock, column, row)?;
+        // Rows between the borders hold items
+        if row <= area.y || row + 1 >= area.bottom() {
+            return None;
+        }
+        let panel = &self.open[index];
+        panel.items.get(panel.scroll + (row - area.y - 1) as usize).map(|(_, target)| target)
+    }
+
+    // Scroll the panel at a position
+    pub fn scroll(&mut self, dock: Rect, column: u16, row: u16, up: bool, lines: usize) {
+        let Some((index, _)) = self.panel_at(dock, column, row) else { return };
+        let panel = &mut self.open[index];
+        panel.scroll = if up {
+            panel.scroll.saturating_sub(lines)
+        } else {
+            (panel.scroll + lines).min(panel.items.len().saturating_sub(1))
+        };
+    }
+
+    // Start dragging the border at a position; false if there's none there
+    pub fn start_drag(&mut self, dock: Rect, column: u16, row: u16) -> bool {
+        self.drag = if column + 1 == dock.right() {
+            Some(Drag::Width)
+        } else {
+            // Any panel's bottom border but the last one's
+            let areas = self.areas(dock);
+            (0..areas.len().saturating_sub(1)).find(|&i| row + 1 == areas[i].bottom()).map(Drag::Height)
+        };
+        self.drag.is_some()
+    }
+
+    // Move the border being dragged to a position; false if nothing is being dragged
+    pub fn drag_to(&mut self, dock: Rect, column: u16, row: u16) -> bool {
+        match self.drag {
+            Some(Drag::Width) => self.set_width((column + 1).saturating_sub(dock.x)),
+            Some(Drag::Height(index)) => {
+                let top = self.areas(dock)[index].y;
+                self.open[index].rows = (row + 1).saturating_sub(top).max(MIN_ROWS);
+            }
+            None => return false,
+        }
+        true
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    pub fn render(&self, f: &mut Frame, dock: Rect, theme: &Theme) {
+        for (panel, area) in self.open.iter().zip(self.areas(dock)) {
+            if area.height == 0 {
+                continue;
+            }
+            let border = if self.drag.is_some() { theme.focused_border } else { theme.border };
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain)
+                .border_style(Style::default().fg(border))
+                .title(Span::styled(
+                    format!("{} ({})", panel.kind.title(), panel.items.len()),
+                    Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
+                ));
+
+            let lines: Vec<Line> = panel.items.iter().skip(panel.scroll)
+                .map(|(item, _)| Line::from(vec![
+                    Span::styled(item.label.clone(), Style::default().fg(theme.title)),
+                    Span::raw(" "),
+                    Span::styled(item.detail.clone(), Style::default().fg(theme.foreground).add_modifier(Modifier::DIM)),
+                ]))
+                .collect();
+
+            f.render_widget(Clear, area);
+            f.render_widget(Paragraph::new(lines).block(block).style(Style::default().bg(theme.background)), area);
+        }
+    }
+}
+
+// Hosts named in an SSH client configuration, without wildcard patterns
+pub fn ssh_hosts(config: &str) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for line in config.lines() {
+        let line = line.trim();
+        let Some((keyword, value)) = line.split_once(|c: char| c.is_whitespace() || c == '=') else { continue };
+        if !keyword.eq_ignore_ascii_case("host") {
+            continue;
+        }
+        for host in value.split_whitespace() {
+            let host = host.trim_matches('"');
+            if !host.contains(['*', '?', '!']) && !hosts.iter().any(|h| h == host) {
+                hosts.push(host.to_string());
+            }
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panels_stack_and_ssh_hosts_are_listed() {
+        let mut panels: Panels<usize> = Panels::new(30);
+        assert_eq!(panels.width(), 0);
+        panels.open(PanelKind::Windows);
+        panels.open(PanelKind::Search);
+        panels.set_items(PanelKind::Windows, vec![(PickerItem::new("a", ""), 1), (PickerItem::new("b", ""), 2)]);
+
+        let dock = Rect::new(3, 0, 30, 40);
+        assert_eq!(panels.width(), 30);
+        assert_eq!(panels.areas(dock), vec![Rect::new(3, 0, 30, 10), Rect::new(3, 10, 30, 30)]);
+        assert_eq!(panels.item_at(dock, 5, 2), Some(&2));
+        assert_eq!(panels.item_at(dock, 5, 0), None);
+
+        // Dragging the first panel's bottom border down, then the dock's edge
+        assert!(panels.start_drag(dock, 10, 9));
+        panels.drag_to(dock, 10, 19);
+        assert_eq!(panels.areas(dock)[1], Rect::new(3, 20, 30, 20));
+        assert!(panels.start_drag(dock, 32, 25));
+        panels.drag_to(dock, 42, 25);
+        assert_eq!(panels.width(), 40);
+
+        assert!(!panels.toggle(PanelKind::Windows));
+        assert_eq!(panels.open_kinds(), vec![PanelKind::Search]);
+
+        let config = "Host web db\n  HostName 10.0.0.1\nHost *.internal !bastion\nhost=\"jump\"\nMatch all\nHost web\n";
+        assert_eq!(ssh_hosts(config), vec!["web", "db", "jump"]);
+    }
+}
@@ -0,0 +1,135 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState};
+use ratatui::Frame;
+
+use crate::ui::style::Theme;
+
+// An entry in a picker
+#[derive(Debug, Clone)]
+pub struct PickerItem {
+    // Main text
+    pub label: String,
+    // Secondary text shown after the label (e.g. a preview)
+    pub detail: String,
+}
+
+impl PickerItem {
+    pub fn new(label: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+// A popup list the user filters by typing and picks an entry from
+pub struct Picker {
+    title: String,
+    items: Vec<PickerItem>,
+    // Text typed to filter the items
+    query: String,
+    // Indices of the items matching the query
+    matches: Vec<usize>,
+    // Position of the selection in `matches`
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(title: &str, items: Vec<PickerItem>) -> Self {
+        let matches = (0..items.len()).collect();
+        Self {
+            title: title.to_string(),
+            items,
+            query: String::new(),
+            matches,
+            selected: 0,
+        }
+    }
+
+    // Add a character to the filter
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.update_matches();
+    }
+
+    // Remove the last character of the filter
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.update_matches();
+    }
+
+    // Move the selection down, wrapping around
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    // Move the selection up, wrapping around
+    pub fn select_prev(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    // Index (in the original item list) of the selected item
+    pub fn selected_index(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    // Recompute the items matching the filter (case-insensitive substring)
+    fn update_matches(&mut self) {
+        let query = self.query.to_lowercase();
+        self.matches = self.items.iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                item.label.to_lowercase().contains(&query) || item.detail.to_lowercase().contains(&query)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.selected = 0;
+    }
+
+    // Render the picker centered in an area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = (area.width * 4 / 5).max(20).min(area.width);
+        let height = (area.height * 3 / 5).max(5).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let items: Vec<ListItem> = self.matches.iter()
+            .map(|&i| {
+                let item = &self.items[i];
+                ListItem::new(Line::from(vec![
+                    Span::styled(item.label.clone(), Style::default().fg(theme.title)),
+                    Span::raw("  "),
+                    Span::styled(item.detail.clone(), Style::default().fg(theme.foreground)),
+                ]))
+            })
+            .collect();
+
+        let title = format!("{} ({}/{}) > {}", self.title, self.matches.len(), self.items.len(), self.query);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(title),
+            )
+            .style(Style::default().bg(theme.background))
+            .highlight_style(Style::default().fg(Color::Black).bg(theme.selected).add_modifier(Modifier::BOLD));
+
+        let mut state = ListState::default();
+        state.select(if self.matches.is_empty() { None } else { Some(self.selected) });
+
+        f.render_widget(Clear, rect);
+        f.render_stateful_widget(list, rect, &mut state);
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{anyhow, bail, Result};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState};
+use ratatui::Frame;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::config::settings::Settings;
+use crate::terminal::style::Blink;
+use crate::ui::keymap::Keymaps;
+use crate::ui::low_color::ColorDepth;
+use crate::ui::panels;
+use crate::ui::style::Theme;
+
+// An editable setting: its section, key and what it does
+struct Preference {
+    section: &'static str,
+    key: &'static str,
+    description: &'static str,
+}
+
+const fn pref(section: &'static str, key: &'static str, description: &'static str) -> Preference {
+    Preference { section, key, description }
+}
+
+// The settings shown, grouped by section
+const PREFERENCES: &[Preference] = &[
+    pref("general", "tick_rate_ms", "Milliseconds between UI updates (10-10000)"),
+    pref("general", "default_shell", "Shell started in new windows"),
+    pref("general", "max_fps", "Most redraws per second (1-240)"),
+    pref("general", "flow_control", "Pause windows flooded with output (Ctrl+Q resumes)"),
+    pref("general", "tab_width", "Columns between tab stops (1-32)"),
+    pref("general", "login_shell", "Start shells as login shells, reading profile files"),
+    pref("general", "init_delay_ms", "Milliseconds to wait for a prompt before typing init commands"),
+    pref("general", "term", "TERM programs are started with (xterm-256color, tmux-256color, matrix...)"),
+    pref("general", "colorterm", "Tell programs 24-bit color works (COLORTERM=truecolor)"),
+    pref("ui", "theme", "Color theme (default, high-contrast, light, or a file in themes/)"),
+    pref("ui", "follow_system_appearance", "Switch theme with the system's light or dark mode"),
+    pref("ui", "light_theme", "Theme used in light mode"),
+    pref("ui", "dark_theme", "Theme used in dark mode"),
+    pref("ui", "focus_follows_mouse", "Focus the window under the mouse"),
+    pref("ui", "focus_delay_ms", "Milliseconds the mouse rests on a window before it's focused"),
+    pref("ui", "split_preview", "Preview where a split goes before creating it"),
+    pref("ui", "wrap_navigation", "Directional navigation wraps at screen edges"),
+    pref("ui", "scrollbar", "Show a scrollbar on each window"),
+    pref("ui", "status_bar", "Show a status line at the bottom of the screen"),
+    pref("ui", "idle_dim_secs", "Dim windows idle this many seconds (0: never)"),
+    pref("ui", "auto_title", "Title windows after the program running in them"),
+    pref("ui", "color_depth", "Colors the terminal shows (auto, truecolor, 256, 16)"),
+    pref("ui", "ascii_borders", "Draw borders in ASCII instead of box-drawing characters"),
+    pref("ui", "bold_is_bright", "Draw bold text in the bright version of its color"),
+    pref("ui", "blink", "Blinking text (slow, ignore)"),
+    pref("ui", "italic_as_underline", "Underline italic text instead of slanting it"),
+    pref("ui", "highlight_files", "Color files shown with cat by their syntax (shell integration)"),
+    pref("ui", "panel_width", "Width of the sidebar's panels"),
+    pref("scrollback", "max_lines", "Lines of history kept per window"),
+    pref("scrollback", "compress", "Compress older history in memory"),
+    pref("scrollback", "working_set_lines", "Lines kept uncompressed when compressing"),
+    pref("scrollback", "timeline_interval_secs", "Seconds between screen recordings for the timeline (0: off)"),
+    pref("scrollback", "timeline_snapshots", "Screen recordings kept per window"),
+    pref("keyboard", "kitty_protocol", "Let applications enable the kitty keyboard protocol"),
+    pref("keyboard", "modify_other_keys", "Let applications enable modifyOtherKeys"),
+    pref("keyboard", "enhance_host_keyboard", "Ask the host terminal for unambiguous keys (on restart)"),
+    pref("keyboard", "chord_timeout_ms", "Milliseconds to wait for the next key of a sequence"),
+    pref("keyboard", "bindings", "Key sequences and their commands, as JSON: {\"Ctrl+A w v\": \"split\"}"),
+    pref("keyboard", "modes", "Bindings of the other modes by mode, as JSON: {\"copy\": {\"v\": \"select\"}}"),
+    pref("clipboard", "osc52", "Applications setting the clipboard (off, ask, allow)"),
+    pref("clipboard", "osc52_read", "Let applications read the clipboard"),
+    pref("clipboard", "max_bytes", "Largest clipboard payload from an application"),
+    pref("clipboard", "history_size", "Recent copies kept for :paste-buffer"),
+    pref("clipboard", "paste_newlines", "Line breaks in pasted text (keep, join, escape)"),
+    pref("mouse", "scroll_lines", "Lines scrolled per wheel tick"),
+    pref("mouse", "natural_scrolling", "Invert the scroll wheel direction"),
+    pref("mouse", "alternate_scroll", "Wheel sends arrow keys to full-screen programs"),
+    pref("session", "autosave", "Save the session periodically and on exit"),
+    pref("session", "autosave_interval_secs", "Seconds between automatic saves"),
+    pref("session", "max_scrollback_bytes", "Scrollback saved per window, in bytes"),
+    pref("session", "restore", "Restore the last session on start (ask, always, never)"),
+    pref("session", "rerun_commands", "Run each window's last command again when always restoring"),
+    pref("history", "record", "Remember commands run at shell prompts (shell integration)"),
+    pref("history", "import_shell", "Also search the shell's history file (on restart)"),
+    pref("history", "max_entries", "Commands kept in the history"),
+    pref("lock", "idle_minutes", "Lock the screen after this many idle minutes (0: never)"),
+    pref("lock", "auth_command", "Command checking the password on stdin (empty: su)"),
+    pref("redaction", "mask_scrollback", "Mask secrets on screen once the cursor leaves their line"),
+    pref("editor", "command", "Editor command; {file}, {line} and {column} are replaced"),
+    pref("editor", "open_in", "Where files open (split, external)"),
+    pref("bell", "visual", "Flash a window's border when it rings the bell"),
+    pref("bell", "sound", "Bell sound (off, system, file)"),
+    pref("bell", "player", "Command playing the bell's sound file; {file} is replaced"),
+    pref("bell", "muted", "Silence the bell in every window"),
+    pref("git", "status", "Show the current window's git branch and changes in the status bar"),
+    pref("accessibility", "announce_output", "Speak new output of the focused window"),
+    pref("accessibility", "announce_ui", "Speak focus and mode changes"),
+];
+
+// The current value of a setting as JSON
+fn value_of(settings: &Settings, preference: &Preference) -> Value {
+    serde_json::to_value(settings)
+        .ok()
+        .and_then(|v| v.get(preference.section)?.get(preference.key).cloned())
+        .unwrap_or(Value::Null)
+}
+
+// A setting's value as shown and edited
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// Checks serde can't do on its own
+fn validate(preference: &Preference, value: &Value) -> Result<()> {
+    let text = value.as_str().unwrap_or("");
+    match (preference.section, preference.key) {
+        ("general", "tick_rate_ms") if !(10..=10_000).contains(&value.as_u64().unwrap_or(0)) => {
+            bail!("tick rate must be between 10 and 10000 ms")
+        }
+        ("general", "max_fps") if !(1..=240).contains(&value.as_u64().unwrap_or(0)) => {
+            bail!("frame rate must be between 1 and 240")
+        }
+        ("general", "tab_width") if !(1..=32).contains(&value.as_u64().unwrap_or(0)) => {
+            bail!("tab width must be between 1 and 32")
+        }
+        ("general", "default_shell" | "term") | ("editor", "command") if text.trim().is_empty() => {
+            bail!("{} can't be empty", preference.key)
+        }
+        ("ui", "theme" | "light_theme" | "dark_theme") => Theme::load(text).map(|_| ()).map_err(|e| anyhow!("{:#}", e)),
+        ("ui", "color_depth") if ColorDepth::from_name(text).is_none() => {
+            bail!("unknown color depth {} (expected one of {})", text, ColorDepth::NAMES.join(", "))
+        }
+        ("ui", "blink") if Blink::from_name(text).is_none() => {
+            bail!("unknown blink setting {} (expected one of {})", text, Blink::NAMES.join(", "))
+        }
+        ("ui", "panel_width") if !(panels::MIN_WIDTH as u64..=panels::MAX_WIDTH as u64).contains(&value.as_u64().unwrap_or(0)) => {
+            bail!("panel width must be between {} and {}", panels::MIN_WIDTH, panels::MAX_WIDTH)
+        }
+        ("scrollback", "max_lines") | ("session", "autosave_interval_secs") | ("clipboard", "history_size") | ("history", "max_entries") | ("keyboard", "chord_timeout_ms") if value.as_u64() == Some(0) => {
+            bail!("{} must be at least 1", preference.key)
+        }
+        ("keyboard", "bindings" | "modes") => {
+            let errors = if preference.key == "bindings" {
+                let bindings: BTreeMap<String, String> = serde_json::from_value(value.clone())?;
+                Keymaps::new(&bindings, &BTreeMap::new()).1
+            } else {
+                let modes: BTreeMap<String, BTreeMap<String, String>> = serde_json::from_value(value.clone())?;
+                Keymaps::new(&BTreeMap::new(), &modes).1
+            };
+            match errors.first() {
+                Some(error) => bail!("{}", error),
+                None => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+// Settings whose values don't pass the checks: section, key and why
+pub fn invalid_values(settings: &Settings) -> Vec<(&'static str, &'static str, String)> {
+    PREFERENCES.iter()
+        .filter_map(|preference| {
+            let error = validate(preference, &value_of(settings, preference)).err()?;
+            Some((preference.section, preference.key, error.to_string()))
+        })
+        .collect()
+}
+
+// Settings with one value changed, parsed like the current value and validated
+fn with_value(settings: &Settings, preference: &Preference, text: &str) -> Result<Settings> {
+    let value = match value_of(settings, preference) {
+        Value::Bool(_) => Value::Bool(text.trim().parse().map_err(|_| anyhow!("expected true or false"))?),
+        Value::Number(_) => Value::Number(text.trim().parse::<u64>().map_err(|_| anyhow!("expected a whole number"))?.into()),
+        Value::Object(_) => serde_json::from_str(text).map_err(|e| anyhow!("expected a JSON object: {}", e))?,
+        _ => Value::String(text.to_string()),
+    };
+    validate(preference, &value)?;
+
+    let mut all = serde_json::to_value(settings)?;
+    all[preference.section][preference.key] = value;
+    Ok(serde_json::from_value(all)?)
+}
+
+// Overlay listing the settings for editing
+#[derive(Default)]
+pub struct Preferences {
+    // Index of the selected preference
+    selected: usize,
+    // Text being typed for the selected preference
+    editing: Option<String>,
+    // Result of the last change, or why it was rejected
+    message: Option<String>,
+}
+
+impl Preferences {
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % PREFERENCES.len();
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = (self.selected + PREFERENCES.len() - 1) % PREFERENCES.len();
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(text) = &mut self.editing {
+            text.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(text) = &mut self.editing {
+            text.pop();
+        }
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.message = None;
+    }
+
+    // Enter on the selected preference: toggle a switch, start editing, or finish editing.
+    // Returns the changed settings when a value was accepted.
+    pub fn activate(&mut self, settings: &Settings) -> Option<Settings> {
+        let preference = &PREFERENCES[self.selected];
+        let current = value_of(settings, preference);
+
+        let text = match self.editing.take() {
+            Some(text) => text,
+            None if current.is_boolean() => (!current.as_bool().unwrap_or(false)).to_string(),
+            None => {
+                self.editing = Some(display(&current));
+                self.message = None;
+                return None;
+            }
+        };
+
+        match with_value(settings, preference, &text) {
+            Ok(changed) => {
+                self.message = Some(format!("{}.{} = {}", preference.section, preference.key, text));
+                Some(changed)
+            }
+            Err(e) => {
+                // Keep the text so it can be corrected
+                if !current.is_boolean() {
+                    self.editing = Some(text);
+                }
+                self.message = Some(format!("Invalid value: {}", e));
+                None
+            }
+        }
+    }
+
+    // Render the overlay centered in an area
+    pub fn render(&self, f: &mut Frame, area: Rect, settings: &Settings, theme: &Theme) {
+        let width = (area.width * 4 / 5).max(20).min(area.width);
+        let height = (area.height * 4 / 5).max(5).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut items = Vec::new();
+        let mut selected_row = 0;
+        let mut section = "";
+        for (i, preference) in PREFERENCES.iter().enumerate() {
+            // Section headings
+            if preference.section != section {
+                section = preference.section;
+                items.push(ListItem::new(Line::from(Span::styled(
+                    format!("[{}]", section),
+                    Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
+                ))));
+            }
+
+            let value = match (&self.editing, i == self.selected) {
+                (Some(text), true) => format!("{}_", text),
+                _ => display(&value_of(settings, preference)),
+            };
+            if i == self.selected {
+                selected_row = items.len();
+            }
+            items.push(ListItem::new(Line::from(vec![
+                Span::raw(format!("  {:<24}", preference.key)),
+                Span::styled(format!("{:<28}", value), Style::default().fg(theme.command)),
+                Span::raw(preference.description),
+            ])));
+        }
+
+        let footer = match &self.message {
+            Some(message) => message.clone(),
+            None => String::from("Enter: edit/toggle  Esc: cancel/close"),
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(format!("Preferences ({})", Settings::path().display()))
+                    .title(Title::from(footer).position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background))
+            .highlight_style(Style::default().fg(Color::Black).bg(theme.selected));
+
+        let mut state = ListState::default();
+        state.select(Some(selected_row));
+
+        f.render_widget(Clear, rect);
+        f.render_stateful_widget(list, rect, &mut state);
+    }
+}
@@ -0,0 +1,158 @@
+// Screenshots: the frame last drawn (windows, borders, sidebar, status bar)
+// rasterized into a PNG with a built-in bitmap font, so no display or GUI is needed.
+// Box-drawing characters are drawn as lines, as the font doesn't have them.
+
+use anyhow::Result;
+use embedded_graphics::mono_font::iso_8859_1::{FONT_9X18, FONT_9X18_BOLD};
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+use ratatui::buffer::Buffer;
+use ratatui::style::Modifier;
+use std::convert::Infallible;
+
+use crate::terminal::export::{self, DEFAULT_BACKGROUND, DEFAULT_FOREGROUND};
+use crate::ui::style::Theme;
+
+// Size of a cell in pixels, that of the font
+const CELL_WIDTH: u32 = 9;
+const CELL_HEIGHT: u32 = 18;
+
+type Rgb = (u8, u8, u8);
+
+// RGB image being drawn
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self { width, height, pixels: vec![0; (width * height * 3) as usize] }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, (r, g, b): Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                let i = ((row * self.width + col) * 3) as usize;
+                self.pixels[i..i + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.fill(point.x as u32, point.y as u32, 1, 1, (color.r(), color.g(), color.b()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Which sides of the cell a box-drawing character reaches: left, right, up, down
+fn box_arms(c: char) -> Option<[bool; 4]> {
+    Some(match c {
+        '─' | '━' | '═' => [true, true, false, false],
+        '│' | '┃' | '║' => [false, false, true, true],
+        '┌' | '┏' | '╔' | '╭' => [false, true, false, true],
+        '┐' | '┓' | '╗' | '╮' => [true, false, false, true],
+        '└' | '┗' | '╚' | '╰' => [false, true, true, false],
+        '┘' | '┛' | '╝' | '╯' => [true, false, true, false],
+        '├' | '┣' | '╠' => [false, true, true, true],
+        '┤' | '┫' | '╣' => [true, false, true, true],
+        '┬' | '┳' | '╦' => [true, true, false, true],
+        '┴' | '┻' | '╩' => [true, true, true, false],
+        '┼' | '╋' | '╬' => [true, true, true, true],
+        _ => return None,
+    })
+}
+
+// Halfway between two colors, for dim text
+fn blend(a: Rgb, b: Rgb) -> Rgb {
+    let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+    (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+// Rasterize a frame into a PNG image
+pub fn to_png(buffer: &Buffer, theme: &Theme) -> Result<Vec<u8>> {
+    let area = buffer.area;
+    let default_fg = export::rgb(theme.foreground).unwrap_or(DEFAULT_FOREGROUND);
+    let default_bg = export::rgb(theme.background).unwrap_or(DEFAULT_BACKGROUND);
+
+    let mut canvas = Canvas::new(area.width as u32 * CELL_WIDTH, area.height as u32 * CELL_HEIGHT);
+    for row in 0..area.height {
+        for col in 0..area.width {
+            let cell = buffer.get(area.x + col, area.y + row);
+            let mut fg = export::rgb(cell.fg).unwrap_or(default_fg);
+            let mut bg = export::rgb(cell.bg).unwrap_or(default_bg);
+            if cell.modifier.contains(Modifier::REVERSED) {
+                std::mem::swap(&mut fg, &mut bg);
+            }
+            if cell.modifier.contains(Modifier::DIM) {
+                fg = blend(fg, bg);
+            }
+
+            let x = col as u32 * CELL_WIDTH;
+            let y = row as u32 * CELL_HEIGHT;
+            canvas.fill(x, y, CELL_WIDTH, CELL_HEIGHT, bg);
+
+            let Some(c) = cell.symbol().chars().next() else { continue };
+            if c == ' ' || cell.modifier.contains(Modifier::HIDDEN) {
+                continue;
+            }
+            if c == '█' {
+                canvas.fill(x, y, CELL_WIDTH, CELL_HEIGHT, fg);
+            } else if let Some([left, right, up, down]) = box_arms(c) {
+                let (mid_x, mid_y) = (x + CELL_WIDTH / 2, y + CELL_HEIGHT / 2);
+                if left {
+                    canvas.fill(x, mid_y, CELL_WIDTH / 2 + 1, 1, fg);
+                }
+                if right {
+                    canvas.fill(mid_x, mid_y, CELL_WIDTH - CELL_WIDTH / 2, 1, fg);
+                }
+                if up {
+                    canvas.fill(mid_x, y, 1, CELL_HEIGHT / 2 + 1, fg);
+                }
+                if down {
+                    canvas.fill(mid_x, mid_y, 1, CELL_HEIGHT - CELL_HEIGHT / 2, fg);
+                }
+            } else {
+                let font = if cell.modifier.contains(Modifier::BOLD) { &FONT_9X18_BOLD } else { &FONT_9X18 };
+                let mut style = MonoTextStyleBuilder::new()
+                    .font(font)
+                    .text_color(Rgb888::new(fg.0, fg.1, fg.2));
+                if cell.modifier.contains(Modifier::UNDERLINED) {
+                    style = style.underline();
+                }
+                let mut glyph = [0; 4];
+                let point = Point::new(x as i32, y as i32);
+                let _ = Text::with_baseline(c.encode_utf8(&mut glyph), point, style.build(), Baseline::Top)
+                    .draw(&mut canvas);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, canvas.width, canvas.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&canvas.pixels)?;
+    Ok(png)
+}
@@ -0,0 +1,53 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::ui::style::Theme;
+
+// Status line at the bottom of the screen: information about the focused window on the
+// left, indicators (broadcast, bell) on the right
+#[derive(Default)]
+pub struct StatusBar {
+    left: Vec<String>,
+    right: Vec<String>,
+}
+
+impl StatusBar {
+    // Add a segment on the left
+    pub fn push_left(&mut self, text: impl Into<String>) {
+        self.left.push(text.into());
+    }
+
+    // Add an indicator on the right
+    pub fn push_right(&mut self, text: impl Into<String>) {
+        self.right.push(text.into());
+    }
+
+    // Draw the status line across an area's last row
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        if area.height == 0 {
+            return;
+        }
+        let rect = Rect::new(area.x, area.y + area.height - 1, area.width, 1);
+
+        let style = Style::default().fg(Color::Black).bg(theme.title);
+        let left = format!(" {}", self.left.join(" │ "));
+        let right = format!("{} ", self.right.join("  "));
+
+        // Right-aligned indicators take precedence over the left segments
+        let width = rect.width as usize;
+        let right_width = right.chars().count().min(width);
+        let left_width = width - right_width;
+        let left: String = left.chars().take(left_width).collect();
+        let padding = left_width - left.chars().count();
+
+        let line = Line::from(vec![
+            Span::styled(left, style.add_modifier(Modifier::BOLD)),
+            Span::styled(" ".repeat(padding), style),
+            Span::styled(right.chars().take(right_width).collect::<String>(), style),
+        ]);
+        f.render_widget(Paragraph::new(line), rect);
+    }
+}
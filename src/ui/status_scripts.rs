@@ -0,0 +1,138 @@
+// Status bar segments from external commands (kube context, git branch...). Each
+// runs on a thread in the focused window's directory, every so often and when that
+// directory changes; the bar shows the last line it printed, so a slow script never
+// holds up drawing, and one taking longer than its timeout is killed.
+
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::settings::StatusScript;
+
+// How often a running script is checked for having finished
+const WAIT_STEP: Duration = Duration::from_millis(20);
+
+// How a script run ended
+enum RunResult {
+    // Its last line of output, if it succeeded and printed anything
+    Finished(Option<String>),
+    TimedOut,
+}
+
+struct Segment {
+    script: StatusScript,
+    // Last line of the last successful run's output
+    output: Option<String>,
+    // When and in which directory the script last started
+    last_run: Option<(Instant, Option<String>)>,
+    // Result of the run in progress
+    running: Option<Receiver<RunResult>>,
+}
+
+#[derive(Default)]
+pub struct StatusScripts {
+    segments: Vec<Segment>,
+}
+
+impl StatusScripts {
+    pub fn new(scripts: &[StatusScript]) -> Self {
+        let segments = scripts.iter()
+            .map(|script| Segment { script: script.clone(), output: None, last_run: None, running: None })
+            .collect();
+        Self { segments }
+    }
+
+    // Collect finished runs and start those that are due; returns whether any output changed
+    pub fn refresh(&mut self, dir: Option<&str>) -> bool {
+        let mut changed = false;
+        for segment in &mut self.segments {
+            if let Some(running) = &segment.running {
+                match running.try_recv() {
+                    // A timed out run keeps the last output; a failing one clears it
+                    Ok(RunResult::Finished(output)) => {
+                        changed |= output != segment.output;
+                        segment.output = output;
+                        segment.running = None;
+                    }
+                    Ok(RunResult::TimedOut) | Err(TryRecvError::Disconnected) => segment.running = None,
+                    Err(TryRecvError::Empty) => continue,
+                }
+            }
+
+            let due = match &segment.last_run {
+                Some((start, last_dir)) => {
+                    start.elapsed() >= Duration::from_secs(segment.script.interval_secs) || last_dir.as_deref() != dir
+                }
+                None => true,
+            };
+            if due {
+                let timeout = Duration::from_millis(segment.script.timeout_ms);
+                segment.running = Some(run(&segment.script.command, dir, timeout));
+                segment.last_run = Some((Instant::now(), dir.map(str::to_string)));
+            }
+        }
+        changed
+    }
+
+    // The segments with output, as (text, shown on the right)
+    pub fn outputs(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.segments.iter()
+            .filter_map(|segment| segment.output.as_deref().map(|text| (text, segment.script.right)))
+    }
+}
+
+// Run a command on a thread, sending back the last non-empty line it printed
+fn run(command: &str, dir: Option<&str>, timeout: Duration) -> Receiver<RunResult> {
+    let (sender, receiver) = mpsc::channel();
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    if let Some(dir) = dir {
+        shell.current_dir(dir).env("MATRIX_CWD", dir);
+    }
+
+    thread::spawn(move || {
+        let Ok(mut child) = shell.spawn() else {
+            let _ = sender.send(RunResult::Finished(None));
+            return;
+        };
+        // Read the output while waiting, so a script printing a lot isn't stuck on a full pipe
+        let stdout = child.stdout.take();
+        let reader = thread::spawn(move || stdout.and_then(|stdout| std::io::read_to_string(stdout).ok()));
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if start.elapsed() < timeout => thread::sleep(WAIT_STEP),
+                Ok(None) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    let _ = sender.send(RunResult::TimedOut);
+                    return;
+                }
+                Err(_) => break None,
+            }
+        };
+
+        let output = reader.join().ok().flatten();
+        let output = status.filter(|status| status.success())
+            .and(output)
+            .and_then(|output| output.lines().map(str::trim).rfind(|line| !line.is_empty()).map(str::to_string));
+        let _ = sender.send(RunResult::Finished(output));
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_printing_more_than_a_pipe_holds_finish() {
+        let result = run("seq 1 100000; echo done", None, Duration::from_secs(5)).recv().unwrap();
+        assert!(matches!(result, RunResult::Finished(Some(line)) if line == "done"));
+    }
+}
@@ -1,25 +1,130 @@
+use anyhow::Result;
 use ratatui::style::{Color, Style, Modifier};
 
+use crate::terminal::export;
+use crate::ui::theme_files;
+
+#[derive(Clone)]
 pub struct Theme {
     pub background: Color,
     pub foreground: Color,
     pub cursor: Color,
     pub selected: Color,
     pub border: Color,
+    pub focused_border: Color,
+    // Border of windows receiving broadcast input
+    pub broadcast_border: Color,
     pub title: Color,
+    pub command: Color,
+    // Contrast ratio application colors are kept readable at (1 leaves them as they are)
+    pub minimum_contrast: f32,
 }
 
 impl Default for Theme {
     fn default() -> Self {
         Self {
-            background: Color::Black,
+            background: Color::Reset,
             foreground: Color::White,
             cursor: Color::Cyan,
             selected: Color::LightCyan,
             border: Color::Gray,
+            focused_border: Color::Cyan,
+            broadcast_border: Color::LightRed,
             title: Color::Green,
+            command: Color::Yellow,
+            minimum_contrast: 1.0,
+        }
+    }
+}
+
+impl Theme {
+    // Names of the built-in themes
+    pub const NAMES: [&'static str; 3] = ["default", "high-contrast", "light"];
+
+    // High-contrast theme: pure black and white with a bright focus color
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color::Black,
+            foreground: Color::White,
+            cursor: Color::LightYellow,
+            selected: Color::LightYellow,
+            border: Color::White,
+            focused_border: Color::LightYellow,
+            broadcast_border: Color::LightMagenta,
+            title: Color::White,
+            command: Color::LightYellow,
+            minimum_contrast: 4.5,
+        }
+    }
+
+    // Light theme: dark text on a light background
+    pub fn light() -> Self {
+        Self {
+            background: Color::Rgb(0xfa, 0xfa, 0xfa),
+            foreground: Color::Rgb(0x28, 0x28, 0x28),
+            cursor: Color::Blue,
+            selected: Color::LightBlue,
+            border: Color::DarkGray,
+            focused_border: Color::Blue,
+            broadcast_border: Color::Red,
+            title: Color::Rgb(0x00, 0x80, 0x00),
+            command: Color::Magenta,
+            minimum_contrast: 1.0,
         }
     }
+
+    // Look up a built-in theme by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "light" => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    // Look up a theme by name: a built-in one, or else a file in the themes directory
+    pub fn load(name: &str) -> Result<Self> {
+        match Self::from_name(name) {
+            Some(theme) => Ok(theme),
+            None => theme_files::load(name),
+        }
+    }
+
+    // Names of the colors a theme sets, as theme files name them
+    pub const SLOTS: [&'static str; 9] = [
+        "background", "foreground", "cursor", "selected", "border",
+        "focused_border", "broadcast_border", "title", "command",
+    ];
+
+    // One of the theme's colors, by its name in SLOTS
+    pub fn slot(&self, name: &str) -> Option<Color> {
+        self.clone().slot_mut(name).copied()
+    }
+
+    pub fn slot_mut(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "background" => &mut self.background,
+            "foreground" => &mut self.foreground,
+            "cursor" => &mut self.cursor,
+            "selected" => &mut self.selected,
+            "border" => &mut self.border,
+            "focused_border" => &mut self.focused_border,
+            "broadcast_border" => &mut self.broadcast_border,
+            "title" => &mut self.title,
+            "command" => &mut self.command,
+            _ => return None,
+        })
+    }
+
+    // Text and background colors as RGB, as reported to applications asking (OSC 10 and 11)
+    pub fn colors(&self) -> ((u8, u8, u8), (u8, u8, u8)) {
+        (
+            export::rgb(self.foreground).unwrap_or(export::DEFAULT_FOREGROUND),
+            export::rgb(self.background).unwrap_or(export::DEFAULT_BACKGROUND),
+        )
+    }
+
 }
 
 pub fn default_title_style() -> Style {
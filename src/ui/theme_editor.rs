@@ -0,0 +1,251 @@
+// Overlay for editing the colors of the running theme: each color is changed a red,
+// green or blue step at a time or typed in, shown right away, and the result saved
+// as a theme file under a name of its own.
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::terminal::export;
+use crate::ui::style::Theme;
+
+// What the editor did with a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorAction {
+    None,
+    // A color of the theme changed
+    Changed,
+    // Save the theme under a name
+    Save(String),
+    Close,
+}
+
+// Text being typed in the editor
+enum Input {
+    // A color for the selected slot
+    Color(String),
+    // The name to save the theme as
+    Name(String),
+}
+
+#[derive(Default)]
+pub struct ThemeEditor {
+    // Index into Theme::SLOTS
+    selected: usize,
+    // Red, green or blue
+    channel: usize,
+    input: Option<Input>,
+    // Result of the last change, or why it was rejected
+    message: Option<String>,
+}
+
+impl ThemeEditor {
+    // Edit the selected color with a key
+    pub fn handle_key(&mut self, code: crossterm::event::KeyCode, theme: &mut Theme) -> EditorAction {
+        use crossterm::event::KeyCode;
+
+        if let Some(input) = &mut self.input {
+            let text = match input {
+                Input::Color(text) | Input::Name(text) => text,
+            };
+            match code {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Enter => return self.finish_input(theme),
+                _ => {}
+            }
+            return EditorAction::None;
+        }
+
+        let step = match code {
+            KeyCode::Up => {
+                self.selected = (self.selected + Theme::SLOTS.len() - 1) % Theme::SLOTS.len();
+                return EditorAction::None;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % Theme::SLOTS.len();
+                return EditorAction::None;
+            }
+            KeyCode::Left => {
+                self.channel = (self.channel + 2) % 3;
+                return EditorAction::None;
+            }
+            KeyCode::Right => {
+                self.channel = (self.channel + 1) % 3;
+                return EditorAction::None;
+            }
+            KeyCode::Char('+' | '=') => 1,
+            KeyCode::Char('-') => -1,
+            KeyCode::PageUp => 16,
+            KeyCode::PageDown => -16,
+            KeyCode::Enter | KeyCode::Char('#') => {
+                let current = theme.slot(self.slot()).map(|color| color.to_string()).unwrap_or_default();
+                self.input = Some(Input::Color(current));
+                self.message = None;
+                return EditorAction::None;
+            }
+            KeyCode::Char('s') => {
+                self.input = Some(Input::Name(String::new()));
+                self.message = None;
+                return EditorAction::None;
+            }
+            KeyCode::Esc => return EditorAction::Close,
+            _ => return EditorAction::None,
+        };
+
+        // Step one channel of the color, starting from the RGB it's shown as
+        let slot = self.slot();
+        let Some(color) = theme.slot_mut(slot) else { return EditorAction::None };
+        let (r, g, b) = shown_rgb(slot, *color);
+        let mut rgb = [r, g, b];
+        rgb[self.channel] = (rgb[self.channel] as i32 + step).clamp(0, 255) as u8;
+        *color = Color::Rgb(rgb[0], rgb[1], rgb[2]);
+        self.message = None;
+        EditorAction::Changed
+    }
+
+    // Tell how saving went
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    fn slot(&self) -> &'static str {
+        Theme::SLOTS[self.selected]
+    }
+
+    // Enter on typed text: set the color, or save under the name
+    fn finish_input(&mut self, theme: &mut Theme) -> EditorAction {
+        match self.input.take() {
+            Some(Input::Color(text)) => match text.trim().parse::<Color>() {
+                Ok(parsed) => {
+                    if let Some(color) = theme.slot_mut(self.slot()) {
+                        *color = parsed;
+                    }
+                    EditorAction::Changed
+                }
+                Err(_) => {
+                    self.message = Some(format!("Invalid color {} (expected a name, #rrggbb or 0-255)", text.trim()));
+                    self.input = Some(Input::Color(text));
+                    EditorAction::None
+                }
+            },
+            Some(Input::Name(name)) => EditorAction::Save(name.trim().to_string()),
+            None => EditorAction::None,
+        }
+    }
+
+    // Render the overlay centered in an area, in the colors being edited
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = 78.min(area.width);
+        let height = (Theme::SLOTS.len() as u16 + 4).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = Vec::new();
+        for (i, slot) in Theme::SLOTS.iter().enumerate() {
+            let color = theme.slot(slot).unwrap_or(Color::Reset);
+            let selected = i == self.selected;
+            let value = match (&self.input, selected) {
+                (Some(Input::Color(text)), true) => format!("{}_", text),
+                _ => color.to_string(),
+            };
+
+            let mut spans = vec![
+                Span::styled(
+                    format!(" {:<18}", slot),
+                    if selected { Style::default().fg(Color::Black).bg(theme.selected) } else { Style::default() },
+                ),
+                Span::raw(" "),
+                Span::styled("██████", Style::default().fg(color)),
+                Span::styled(format!(" {:<14}", value), Style::default().fg(theme.command)),
+            ];
+            let (r, g, b) = shown_rgb(slot, color);
+            for (channel, (label, level)) in [("R", r), ("G", g), ("B", b)].into_iter().enumerate() {
+                let style = if selected && channel == self.channel {
+                    Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!(" {} {:>3}", label, level), style));
+            }
+            lines.push(Line::from(spans));
+        }
+        if let Some(Input::Name(name)) = &self.input {
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!(" Save as: {}_", name)));
+        }
+
+        let footer = match &self.message {
+            Some(message) => message.clone(),
+            None => String::from("←→: channel  +/-, PgUp/PgDn: change  Enter: type  s: save  Esc: close"),
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title("Theme Editor")
+                    .title(Title::from(footer).position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+}
+
+// The RGB a theme color is shown as; the terminal's own colors stand in for Reset
+fn shown_rgb(slot: &str, color: Color) -> (u8, u8, u8) {
+    export::rgb(color).unwrap_or(if slot == "background" {
+        export::DEFAULT_BACKGROUND
+    } else {
+        export::DEFAULT_FOREGROUND
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode;
+
+    #[test]
+    fn colors_step_by_channel_and_can_be_typed() {
+        let mut editor = ThemeEditor::default();
+        let mut theme = Theme::light();
+
+        // The background's green, up one and then a page
+        editor.handle_key(KeyCode::Right, &mut theme);
+        assert_eq!(editor.handle_key(KeyCode::Char('+'), &mut theme), EditorAction::Changed);
+        assert_eq!(editor.handle_key(KeyCode::PageUp, &mut theme), EditorAction::Changed);
+        assert_eq!(theme.background, Color::Rgb(0xfa, 0xff, 0xfa));
+
+        // A named color typed for the foreground
+        editor.handle_key(KeyCode::Down, &mut theme);
+        editor.handle_key(KeyCode::Enter, &mut theme);
+        for _ in 0..20 {
+            editor.handle_key(KeyCode::Backspace, &mut theme);
+        }
+        for c in "light-red".chars() {
+            editor.handle_key(KeyCode::Char(c), &mut theme);
+        }
+        assert_eq!(editor.handle_key(KeyCode::Enter, &mut theme), EditorAction::Changed);
+        assert_eq!(theme.foreground, Color::LightRed);
+
+        editor.handle_key(KeyCode::Char('s'), &mut theme);
+        for c in "dusk".chars() {
+            editor.handle_key(KeyCode::Char(c), &mut theme);
+        }
+        assert_eq!(editor.handle_key(KeyCode::Enter, &mut theme), EditorAction::Save(String::from("dusk")));
+    }
+}
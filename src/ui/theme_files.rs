@@ -0,0 +1,157 @@
+// Themes kept as files in the themes directory next to the settings, e.g.
+// themes/dusk.json, chosen by name like the built-in ones. A file names a built-in
+// theme to start from and the colors it changes:
+//
+//   { "base": "light", "background": "#1d2021", "title": "light-green", "border": "244" }
+//
+// "minimum_contrast" sets the WCAG contrast ratio application colors are kept readable
+// at, e.g. 4.5; 1 leaves them alone.
+//
+// The files are watched, so a theme is shown again as soon as it's saved.
+
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::Color;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::ui::style::Theme;
+use crate::utils::paths;
+
+// How often the themes directory is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Directory holding theme files
+pub fn dir() -> PathBuf {
+    paths::config_dir().join("themes")
+}
+
+// The file a theme is kept in
+pub fn path(name: &str) -> PathBuf {
+    dir().join(format!("{}.json", name))
+}
+
+// Names of the themes in the themes directory
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = modified_times(&dir()).into_keys()
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+// Load the theme kept in a file
+pub fn load(name: &str) -> Result<Theme> {
+    let path = path(name);
+    if !path.exists() {
+        bail!("unknown theme {} (no built-in theme or {})", name, path.display());
+    }
+    let text = fs::read_to_string(&path)?;
+    parse(&text).with_context(|| format!("invalid theme in {}", path.display()))
+}
+
+// Read a theme file's contents
+pub fn parse(text: &str) -> Result<Theme> {
+    let value: Value = serde_json::from_str(text)?;
+    let entries = value.as_object().ok_or_else(|| anyhow!("expected an object of colors"))?;
+
+    let base = entries.get("base").map(|base| base.as_str().ok_or_else(|| anyhow!("base must be a theme name")));
+    let mut theme = match base.transpose()? {
+        Some(name) => Theme::from_name(name).ok_or_else(|| anyhow!("unknown base theme {}", name))?,
+        None => Theme::default(),
+    };
+
+    if let Some(value) = entries.get("minimum_contrast") {
+        theme.minimum_contrast = value.as_f64()
+            .filter(|ratio| (1.0..=21.0).contains(ratio))
+            .ok_or_else(|| anyhow!("minimum_contrast must be a ratio from 1 to 21"))? as f32;
+    }
+    for (key, value) in entries.iter().filter(|(key, _)| !["base", "minimum_contrast"].contains(&key.as_str())) {
+        let slot = theme.slot_mut(key)
+            .ok_or_else(|| anyhow!("unknown color {} (expected one of {})", key, Theme::SLOTS.join(", ")))?;
+        let text = value.as_str().ok_or_else(|| anyhow!("{} must be a color name, #rrggbb or 0-255", key))?;
+        *slot = text.parse::<Color>().map_err(|_| anyhow!("invalid color for {}: {}", key, text))?;
+    }
+    Ok(theme)
+}
+
+// Save a theme to a file, with every color spelled out
+pub fn save(name: &str, theme: &Theme) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        bail!("invalid theme name {}", name);
+    }
+    if Theme::NAMES.contains(&name) {
+        bail!("{} is a built-in theme", name);
+    }
+
+    let mut colors: serde_json::Map<String, Value> = Theme::SLOTS.iter()
+        .filter_map(|slot| Some((slot.to_string(), Value::String(theme.slot(slot)?.to_string()))))
+        .collect();
+    if theme.minimum_contrast > 1.0 {
+        colors.insert(String::from("minimum_contrast"), serde_json::json!(theme.minimum_contrast));
+    }
+    let path = path(name);
+    fs::create_dir_all(dir())?;
+    fs::write(&path, serde_json::to_string_pretty(&colors)?)?;
+    Ok(path)
+}
+
+// Theme files and when they were last written
+fn modified_times(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let Ok(entries) = fs::read_dir(dir) else { return HashMap::new() };
+    entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some((path.clone(), fs::metadata(&path).ok()?.modified().ok()?)))
+        .collect()
+}
+
+// Notices theme files being written
+pub struct ThemeWatcher {
+    modified: HashMap<PathBuf, SystemTime>,
+    last_check: Instant,
+}
+
+impl ThemeWatcher {
+    pub fn new() -> Self {
+        Self { modified: modified_times(&dir()), last_check: Instant::now() }
+    }
+
+    // Names of the themes whose files were written since the last check
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_times(&dir());
+        let changed = modified.iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(time))
+            .filter_map(|(path, _)| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        self.modified = modified;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_files_change_colors_over_a_base() {
+        let theme = parse(r##"{ "base": "light", "background": "#102030", "title": "light-green", "border": "244" }"##).unwrap();
+        assert_eq!(theme.background, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.title, Color::LightGreen);
+        assert_eq!(theme.border, Color::Indexed(244));
+        assert_eq!(theme.cursor, Theme::light().cursor);
+        assert_eq!(parse(r#"{ "minimum_contrast": 4.5 }"#).unwrap().minimum_contrast, 4.5);
+
+        assert!(parse(r#"{ "colour": "red" }"#).is_err());
+        assert!(parse(r#"{ "title": "mauve" }"#).is_err());
+        assert!(parse(r#"{ "base": "neon" }"#).is_err());
+        assert!(parse(r#"{ "minimum_contrast": 30 }"#).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+// Guided tour for new users (`matrix tutorial`, or :tutorial): a box describing one
+// thing to try at a time, moving on once it's been done. The tutorial only looks at
+// what the app reports, so everything underneath works as usual.
+
+use std::time::{Duration, Instant};
+
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+use uuid::Uuid;
+
+use crate::ui::style::Theme;
+
+// How long the last box stays up
+const FINISHED_TIME: Duration = Duration::from_secs(20);
+
+// What a step waits for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Goal {
+    // A window split in two
+    Split,
+    // The focus moved to another window
+    Navigate,
+    CopyMode,
+    // Something new on the clipboard
+    Copy,
+    // A command run from command mode
+    Command(&'static str),
+}
+
+struct Step {
+    title: &'static str,
+    text: &'static str,
+    goal: Goal,
+}
+
+const STEPS: [Step; 5] = [
+    Step {
+        title: "Split a window",
+        text: "Press Ctrl+V to split the window side by side (Ctrl+H splits it top and bottom). \
+               Arrows pick where the new window goes; Enter splits.",
+        goal: Goal::Split,
+    },
+    Step {
+        title: "Move between windows",
+        text: "Press Ctrl+Left or Ctrl+Right to go to the window on that side, or Ctrl+Tab \
+               to go through them in turn.",
+        goal: Goal::Navigate,
+    },
+    Step {
+        title: "Enter copy mode",
+        text: "Press Alt+C. A cursor appears over the window's text and history, \
+               and the program in it stops getting your keys.",
+        goal: Goal::CopyMode,
+    },
+    Step {
+        title: "Copy some text",
+        text: "Move with the arrows (or hjkl), press v to start selecting, move again \
+               and press y to copy. Esc leaves without copying.",
+        goal: Goal::Copy,
+    },
+    Step {
+        title: "Run a command",
+        text: "Press : to type a command. Try layout grid and Enter to line the windows \
+               up in a grid; :help lists every command.",
+        goal: Goal::Command("layout"),
+    },
+];
+
+// The state of the app a step looks at
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub windows: usize,
+    pub focused: Option<Uuid>,
+    pub copy_mode: bool,
+    pub clipboard: Option<String>,
+}
+
+pub struct Tutorial {
+    // Index into STEPS; past the end once finished
+    step: usize,
+    // Where things stood when the step began
+    start: Progress,
+    finished: Option<Instant>,
+}
+
+impl Tutorial {
+    pub fn new(progress: Progress) -> Self {
+        Self { step: 0, start: progress, finished: None }
+    }
+
+    // Look at the app again; true if that completed the step
+    pub fn observe(&mut self, progress: &Progress) -> bool {
+        let Some(step) = STEPS.get(self.step) else { return false };
+        let done = match step.goal {
+            Goal::Split => progress.windows > self.start.windows,
+            Goal::Navigate => progress.focused.is_some() && progress.focused != self.start.focused,
+            Goal::CopyMode => progress.copy_mode,
+            Goal::Copy => progress.clipboard.is_some() && progress.clipboard != self.start.clipboard,
+            Goal::Command(_) => false,
+        };
+        if done {
+            self.advance(progress.clone());
+        }
+        done
+    }
+
+    // A command was run; true if that completed the step
+    pub fn command_run(&mut self, name: &str, progress: &Progress) -> bool {
+        let done = STEPS.get(self.step).is_some_and(|step| matches!(step.goal, Goal::Command(command) if command == name));
+        if done {
+            self.advance(progress.clone());
+        }
+        done
+    }
+
+    // Go on to the next step without doing this one
+    pub fn skip(&mut self, progress: &Progress) {
+        if self.step < STEPS.len() {
+            self.advance(progress.clone());
+        }
+    }
+
+    fn advance(&mut self, progress: Progress) {
+        self.step += 1;
+        self.start = progress;
+        if self.step == STEPS.len() {
+            self.finished = Some(Instant::now());
+        }
+    }
+
+    // Whether the last box has been up long enough
+    pub fn is_over(&self) -> bool {
+        self.finished.is_some_and(|finished| finished.elapsed() >= FINISHED_TIME)
+    }
+
+    // What to do now, to be read out
+    pub fn instructions(&self) -> String {
+        match STEPS.get(self.step) {
+            Some(step) => format!("{}: {}", step.title, step.text.split_whitespace().collect::<Vec<_>>().join(" ")),
+            None => String::from("That's the tour. :cheatsheet lists the keys and :help everything else."),
+        }
+    }
+
+    // Render the current step in a box at the bottom of an area
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let width = 64.min(area.width);
+        let height = 7.min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.bottom().saturating_sub(height + 2).max(area.y),
+            width,
+            height,
+        );
+
+        let (title, text) = match STEPS.get(self.step) {
+            Some(step) => (
+                format!("Tutorial {}/{}: {}", self.step + 1, STEPS.len(), step.title),
+                step.text.split_whitespace().collect::<Vec<_>>().join(" "),
+            ),
+            None => (String::from("Tutorial: done"), self.instructions()),
+        };
+        let paragraph = Paragraph::new(vec![Line::from(text)])
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .border_style(Style::default().fg(theme.focused_border))
+                    .title(title)
+                    .title_style(Style::default().fg(theme.title).add_modifier(Modifier::BOLD))
+                    .title(Title::from(":tutorial skip  :tutorial quit").position(Position::Bottom)),
+            )
+            .style(Style::default().fg(theme.foreground).bg(theme.background));
+
+        f.render_widget(Clear, rect);
+        f.render_widget(paragraph, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_advance_when_done() {
+        let mut progress = Progress { windows: 1, focused: Some(Uuid::new_v4()), ..Progress::default() };
+        let mut tutorial = Tutorial::new(progress.clone());
+
+        // Nothing changed yet
+        assert!(!tutorial.observe(&progress));
+
+        // A split, which also focuses the new window
+        progress.windows = 2;
+        progress.focused = Some(Uuid::new_v4());
+        assert!(tutorial.observe(&progress));
+
+        // Moving the focus counts from where it was after the split
+        assert!(!tutorial.observe(&progress));
+        progress.focused = Some(Uuid::new_v4());
+        assert!(tutorial.observe(&progress));
+
+        progress.copy_mode = true;
+        assert!(tutorial.observe(&progress));
+        progress.copy_mode = false;
+        progress.clipboard = Some(String::from("ls"));
+        assert!(tutorial.observe(&progress));
+
+        // Only the command asked for completes the last step
+        assert!(!tutorial.command_run("split", &progress));
+        assert!(tutorial.command_run("layout", &progress));
+        assert!(!tutorial.is_over());
+        assert!(tutorial.instructions().starts_with("That's the tour"));
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
-use ratatui::layout::{Layout, Direction as TuiDirection, Constraint, Rect};
+use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::collections::HashMap;
 
 use crate::terminal::window::TerminalWindow;
+use crate::ui::layout;
 
 // Navigation directions for window focus
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,14 +17,14 @@ pub enum Direction {
 }
 
 // The different types of window layouts
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum SplitDirection {
     Horizontal,
     Vertical,
 }
 
 // A node in the window layout tree
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LayoutNode {
     // A leaf node containing a terminal window
     Window {
@@ -65,31 +67,17 @@ impl LayoutNode {
             LayoutNode::Split { direction, ratio, first, second, rect } => {
                 *rect = outer_rect;
                 
-                let constraints = match direction {
-                    SplitDirection::Horizontal => {
-                        let left_width = (outer_rect.width as f32 * *ratio).floor() as u16;
-                        let right_width = outer_rect.width - left_width;
-                        [Constraint::Length(left_width), Constraint::Length(right_width)]
-                    }
-                    SplitDirection::Vertical => {
-                        let top_height = (outer_rect.height as f32 * *ratio).floor() as u16;
-                        let bottom_height = outer_rect.height - top_height;
-                        [Constraint::Length(top_height), Constraint::Length(bottom_height)]
-                    }
-                };
-                
-                let layout_direction = match direction {
-                    SplitDirection::Horizontal => TuiDirection::Horizontal,
-                    SplitDirection::Vertical => TuiDirection::Vertical,
+                // Each side keeps room for its windows where possible
+                let (min_first, min_second) = (layout::min_size(first), layout::min_size(second));
+                let (length, min_first, min_second) = match direction {
+                    SplitDirection::Horizontal => (outer_rect.width, min_first.0, min_second.0),
+                    SplitDirection::Vertical => (outer_rect.height, min_first.1, min_second.1),
                 };
+                let first_length = layout::split_length(length, *ratio, min_first, min_second);
+                let (first_rect, second_rect) = layout::split_rect(outer_rect, *direction, first_length);
                 
-                let areas = Layout::default()
-                    .direction(layout_direction)
-                    .constraints(constraints)
-                    .split(outer_rect);
-                
-                first.calculate_layout(areas[0]);
-                second.calculate_layout(areas[1]);
+                first.calculate_layout(first_rect);
+                second.calculate_layout(second_rect);
             }
         }
     }
@@ -122,6 +110,72 @@ impl LayoutNode {
         }
     }
     
+    // Does this layout contain a window
+    pub fn contains(&self, id: &Uuid) -> bool {
+        match self {
+            LayoutNode::Window { id: window_id, .. } => window_id == id,
+            LayoutNode::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+    
+    // Split ratios in pre-order (parent before children)
+    pub fn ratios(&self) -> Vec<f32> {
+        match self {
+            LayoutNode::Window { .. } => Vec::new(),
+            LayoutNode::Split { ratio, first, second, .. } => {
+                let mut ratios = vec![*ratio];
+                ratios.extend(first.ratios());
+                ratios.extend(second.ratios());
+                ratios
+            }
+        }
+    }
+    
+    // Overwrite the split ratios in pre-order, as returned by `ratios`
+    pub fn set_ratios(&mut self, ratios: &mut impl Iterator<Item = f32>) {
+        if let LayoutNode::Split { ratio, first, second, .. } = self {
+            if let Some(value) = ratios.next() {
+                *ratio = value;
+            }
+            first.set_ratios(ratios);
+            second.set_ratios(ratios);
+        }
+    }
+    
+    // Move the border of a window in a direction by `amount` (a fraction of the split),
+    // stopping at the snap ratios it passes if `snap` is set. The nearest split with a
+    // border on that side of the window is adjusted.
+    pub fn adjust_ratio(&mut self, id: &Uuid, direction: Direction, amount: f32, snap: bool) -> bool {
+        match self {
+            LayoutNode::Window { .. } => false,
+            LayoutNode::Split { direction: split_direction, ratio, first, second, .. } => {
+                let in_first = first.contains(id);
+                if !in_first && !second.contains(id) {
+                    return false;
+                }
+                
+                // Prefer the innermost split
+                let child = if in_first { first } else { second };
+                if child.adjust_ratio(id, direction, amount, snap) {
+                    return true;
+                }
+                
+                // The border between the children lies right of / below the first child
+                let delta = match (*split_direction, direction, in_first) {
+                    (SplitDirection::Horizontal, Direction::Right, true) => amount,
+                    (SplitDirection::Horizontal, Direction::Left, false) => -amount,
+                    (SplitDirection::Vertical, Direction::Down, true) => amount,
+                    (SplitDirection::Vertical, Direction::Up, false) => -amount,
+                    _ => return false,
+                };
+                
+                let moved = (*ratio + delta).clamp(0.1, 0.9);
+                *ratio = if snap { snap_ratio(*ratio, moved) } else { moved };
+                true
+            }
+        }
+    }
+    
     // Find the node containing a window
     pub fn find_window_node(&mut self, id: &Uuid) -> Option<&mut LayoutNode> {
         match self {
@@ -138,8 +192,8 @@ impl LayoutNode {
         }
     }
     
-    // Split a window in the layout
-    pub fn split_window(&mut self, id: &Uuid, direction: SplitDirection, new_id: Uuid, ratio: f32) -> Result<()> {
+    // Split a window in the layout; the new window goes left/above when `new_first` is set
+    pub fn split_window(&mut self, id: &Uuid, direction: SplitDirection, new_id: Uuid, ratio: f32, new_first: bool) -> Result<()> {
         if let Some(node) = self.find_window_node(id) {
             match node {
                 LayoutNode::Window { id, rect } => {
@@ -147,12 +201,16 @@ impl LayoutNode {
                     let original_id = *id;
                     let original_rect = *rect;
                     
+                    let original = LayoutNode::Window { id: original_id, rect: original_rect };
+                    let new = LayoutNode::Window { id: new_id, rect: original_rect };
+                    let (first, second) = if new_first { (new, original) } else { (original, new) };
+                    
                     // Replace this node with a split node
                     *node = LayoutNode::Split {
                         direction,
                         ratio,
-                        first: Box::new(LayoutNode::Window { id: original_id, rect: original_rect }),
-                        second: Box::new(LayoutNode::Window { id: new_id, rect: original_rect }),
+                        first: Box::new(first),
+                        second: Box::new(second),
                         rect: original_rect,
                     };
                     
@@ -169,6 +227,105 @@ impl LayoutNode {
     }
 }
 
+// Maximum number of layouts kept for undo
+const LAYOUT_HISTORY_LIMIT: usize = 50;
+
+// Ratios a border stops at when moved in resize mode: quarters, thirds and the half
+pub const SNAP_RATIOS: [f32; 5] = [0.25, 1.0 / 3.0, 0.5, 2.0 / 3.0, 0.75];
+
+// Where a border moving from one ratio to another stops: at the first snap ratio
+// past where it starts, if it gets there
+pub fn snap_ratio(from: f32, to: f32) -> f32 {
+    let passed = |snap: &&f32| (from < **snap && **snap <= to) || (to <= **snap && **snap < from);
+    let mut passed: Vec<f32> = SNAP_RATIOS.iter().filter(passed).copied().collect();
+    passed.sort_by(|a, b| (a - from).abs().total_cmp(&(b - from).abs()));
+    passed.first().copied().unwrap_or(to)
+}
+
+// A saved layout for undo/redo
+#[derive(Debug, Clone)]
+struct LayoutSnapshot {
+    // Position in the layout history
+    number: usize,
+    layout: Option<LayoutNode>,
+    zoomed_window: Option<Uuid>,
+    pre_zoom_layout: Option<LayoutNode>,
+}
+
+// Remove the windows that no longer exist from a layout, promoting their siblings
+fn prune_layout(node: LayoutNode, windows: &HashMap<Uuid, TerminalWindow>) -> Option<LayoutNode> {
+    layout::retain_windows(node, &|id| windows.contains_key(id))
+}
+
+// Length of the overlap of two ranges
+fn overlap(start_a: u16, len_a: u16, start_b: u16, len_b: u16) -> u16 {
+    let start = start_a.max(start_b);
+    let end = (start_a + len_a).min(start_b + len_b);
+    end.saturating_sub(start)
+}
+
+// Overlap of two rectangles along the axis perpendicular to a direction
+fn perpendicular_overlap(a: Rect, b: Rect, direction: Direction) -> u16 {
+    match direction {
+        Direction::Left | Direction::Right => overlap(a.y, a.height, b.y, b.height),
+        Direction::Up | Direction::Down => overlap(a.x, a.width, b.x, b.width),
+    }
+}
+
+// Pick the candidate that lines up best with `from`: the one spanning its center, else the largest overlap
+fn best_aligned(from: Rect, candidates: Vec<(Uuid, Rect)>, direction: Direction) -> Option<Uuid> {
+    let center = match direction {
+        Direction::Left | Direction::Right => from.y + from.height / 2,
+        Direction::Up | Direction::Down => from.x + from.width / 2,
+    };
+    
+    candidates.into_iter()
+        .max_by_key(|&(_, rect)| {
+            let spans_center = match direction {
+                Direction::Left | Direction::Right => rect.y <= center && center < rect.y + rect.height,
+                Direction::Up | Direction::Down => rect.x <= center && center < rect.x + rect.width,
+            };
+            (spans_center, perpendicular_overlap(from, rect, direction))
+        })
+        .map(|(id, _)| id)
+}
+
+// Find the window sharing the border of `from` on the given side
+fn adjacent_window(from: Rect, candidates: &[(Uuid, Rect)], direction: Direction) -> Option<Uuid> {
+    let touching: Vec<(Uuid, Rect)> = candidates.iter()
+        .filter(|(_, rect)| {
+            let shares_border = match direction {
+                Direction::Left => rect.x + rect.width == from.x,
+                Direction::Right => from.x + from.width == rect.x,
+                Direction::Up => rect.y + rect.height == from.y,
+                Direction::Down => from.y + from.height == rect.y,
+            };
+            shares_border && perpendicular_overlap(from, *rect, direction) > 0
+        })
+        .cloned()
+        .collect();
+    
+    best_aligned(from, touching, direction)
+}
+
+// Find the window on the opposite edge of the area, for wrap-around navigation
+fn wrapped_window(from: Rect, candidates: &[(Uuid, Rect)], direction: Direction, area: Rect) -> Option<Uuid> {
+    let on_edge: Vec<(Uuid, Rect)> = candidates.iter()
+        .filter(|(_, rect)| {
+            let at_opposite_edge = match direction {
+                Direction::Left => rect.x + rect.width == area.x + area.width,
+                Direction::Right => rect.x == area.x,
+                Direction::Up => rect.y + rect.height == area.y + area.height,
+                Direction::Down => rect.y == area.y,
+            };
+            at_opposite_edge && perpendicular_overlap(from, *rect, direction) > 0
+        })
+        .cloned()
+        .collect();
+    
+    best_aligned(from, on_edge, direction)
+}
+
 // The window manager handles the layout and interactions between windows
 pub struct WindowManager {
     // The layout tree
@@ -183,6 +340,18 @@ pub struct WindowManager {
     zoomed_window: Option<Uuid>,
     // Original layout before zooming
     pre_zoom_layout: Option<LayoutNode>,
+    // Signature (preset and window count) of the preset currently shown, if any
+    active_preset: Option<String>,
+    // Split ratios the user set for each preset signature
+    preset_ratios: HashMap<String, Vec<f32>>,
+    // Layouts before each mutation, most recent last
+    undo_stack: Vec<LayoutSnapshot>,
+    // Layouts undone, most recent last
+    redo_stack: Vec<LayoutSnapshot>,
+    // Number of the next layout snapshot
+    next_snapshot: usize,
+    // Directional focus wraps around to the opposite edge
+    wrap_navigation: bool,
 }
 
 impl WindowManager {
@@ -195,6 +364,12 @@ impl WindowManager {
             area,
             zoomed_window: None,
             pre_zoom_layout: None,
+            active_preset: None,
+            preset_ratios: HashMap::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            next_snapshot: 1,
+            wrap_navigation: false,
         }
     }
     
@@ -217,13 +392,20 @@ impl WindowManager {
         Ok(window_id)
     }
     
-    // Split a window
+    // Split a window, placing the new window right of / below it
     pub fn split_window(&mut self, id: Uuid, direction: SplitDirection, ratio: f32) -> Result<Uuid> {
+        self.split_window_placed(id, direction, ratio, false)
+    }
+    
+    // Split a window, placing the new window left of / above it when `new_first` is set
+    pub fn split_window_placed(&mut self, id: Uuid, direction: SplitDirection, ratio: f32, new_first: bool) -> Result<Uuid> {
         // Make sure the window exists
         if !self.windows.contains_key(&id) {
             anyhow::bail!("Window not found");
         }
         
+        self.record_layout();
+        
         // Create a new window for the split
         let title = if let Some(window) = self.windows.get(&id) {
             format!("{} (Split)", window.title)
@@ -237,9 +419,12 @@ impl WindowManager {
         // Add the new window to our collection
         self.windows.insert(new_id, new_window);
         
+        // The layout no longer matches a preset
+        self.active_preset = None;
+        
         // Update the layout
         if let Some(layout) = &mut self.layout {
-            layout.split_window(&id, direction, new_id, ratio)?;
+            layout.split_window(&id, direction, new_id, ratio, new_first)?;
             
             // Recalculate the layout
             layout.calculate_layout(self.area);
@@ -270,6 +455,11 @@ impl WindowManager {
         Ok(())
     }
     
+    // Area the windows are laid out in
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+    
     // Resize the window manager
     pub fn resize(&mut self, area: Rect) -> Result<()> {
         self.area = area;
@@ -283,6 +473,160 @@ impl WindowManager {
         Ok(())
     }
     
+    // Grow a window towards a direction by moving its border (amount is a fraction of the
+    // split), stopping at the snap ratios it passes if `snap` is set
+    pub fn resize_window(&mut self, id: Uuid, direction: Direction, amount: f32, snap: bool) -> Result<()> {
+        let layout = match &mut self.layout {
+            Some(layout) => layout,
+            None => anyhow::bail!("No layout"),
+        };
+        
+        if !layout.adjust_ratio(&id, direction, amount, snap) {
+            anyhow::bail!("No border to move in that direction");
+        }
+        
+        // Remember the adjustment for the preset being shown
+        if let Some(signature) = &self.active_preset {
+            self.preset_ratios.insert(signature.clone(), layout.ratios());
+        }
+        
+        layout.calculate_layout(self.area);
+        self.apply_layout()
+    }
+    
+    // Finish applying a preset: restore the user's ratios for it, focus and lay out
+    fn finish_preset(&mut self, signature: String, focus_id: Uuid) -> Result<()> {
+        if let Some(layout) = &mut self.layout {
+            if let Some(ratios) = self.preset_ratios.get(&signature) {
+                // Only reuse ratios recorded for the same tree shape
+                if ratios.len() == layout.ratios().len() {
+                    layout.set_ratios(&mut ratios.iter().copied());
+                }
+            }
+        }
+        
+        self.active_preset = Some(signature);
+        
+        self.focus_window(focus_id)?;
+        
+        // Recalculate the layout
+        if let Some(layout) = &mut self.layout {
+            layout.calculate_layout(self.area);
+            self.apply_layout()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Save the current layout before a mutation
+    fn record_layout(&mut self) {
+        let snapshot = self.snapshot();
+        self.undo_stack.push(snapshot);
+        if self.undo_stack.len() > LAYOUT_HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+    
+    // Capture the current layout
+    fn snapshot(&mut self) -> LayoutSnapshot {
+        let number = self.next_snapshot;
+        self.next_snapshot += 1;
+        
+        LayoutSnapshot {
+            number,
+            layout: self.layout.clone(),
+            zoomed_window: self.zoomed_window,
+            pre_zoom_layout: self.pre_zoom_layout.clone(),
+        }
+    }
+    
+    // Undo up to `steps` layout changes, returning the number of the layout restored
+    pub fn undo_layout(&mut self, steps: usize) -> Result<usize> {
+        self.step_history(steps, true)
+    }
+    
+    // Redo up to `steps` undone layout changes, returning the number of the layout restored
+    pub fn redo_layout(&mut self, steps: usize) -> Result<usize> {
+        self.step_history(steps, false)
+    }
+    
+    // Move through the layout history: the layout left goes onto the other stack, and so
+    // does every layout stepped over, so the same number of steps comes back to it
+    fn step_history(&mut self, steps: usize, undo: bool) -> Result<usize> {
+        let first = if undo { self.undo_stack.pop() } else { self.redo_stack.pop() };
+        let Some(mut target) = first else {
+            anyhow::bail!("Nothing to {}", if undo { "undo" } else { "redo" });
+        };
+        
+        let current = self.snapshot();
+        let mut passed = vec![current];
+        for _ in 1..steps.max(1) {
+            let snapshot = if undo { self.undo_stack.pop() } else { self.redo_stack.pop() };
+            let Some(snapshot) = snapshot else { break };
+            passed.push(std::mem::replace(&mut target, snapshot));
+        }
+        if undo {
+            self.redo_stack.extend(passed);
+        } else {
+            self.undo_stack.extend(passed);
+        }
+        
+        let number = target.number;
+        self.restore_snapshot(target)?;
+        Ok(number)
+    }
+    
+    // Show a saved layout with the windows that are alive now
+    fn restore_snapshot(&mut self, snapshot: LayoutSnapshot) -> Result<()> {
+        let mut layout = snapshot.layout.and_then(|l| prune_layout(l, &self.windows));
+        let mut pre_zoom_layout = snapshot.pre_zoom_layout.and_then(|l| prune_layout(l, &self.windows));
+        let mut zoomed_window = snapshot.zoomed_window.filter(|id| self.windows.contains_key(id));
+        
+        if zoomed_window.is_none() && pre_zoom_layout.is_some() {
+            // The zoomed window is gone, so show the layout behind it
+            layout = pre_zoom_layout.take();
+        } else if zoomed_window.is_some() && pre_zoom_layout.is_none() {
+            zoomed_window = None;
+        }
+        
+        // Windows opened after the snapshot was taken keep a place in the layout
+        {
+            let full_layout = if zoomed_window.is_some() { &mut pre_zoom_layout } else { &mut layout };
+            let mut missing: Vec<Uuid> = self.windows.keys()
+                .filter(|id| !full_layout.as_ref().is_some_and(|l| l.contains(id)))
+                .cloned()
+                .collect();
+            missing.sort();
+            
+            for id in missing {
+                *full_layout = Some(match full_layout.take() {
+                    Some(node) => LayoutNode::split(SplitDirection::Vertical, 0.5, node, LayoutNode::window(id, self.area), self.area),
+                    None => LayoutNode::window(id, self.area),
+                });
+            }
+        }
+        
+        self.layout = layout;
+        self.pre_zoom_layout = pre_zoom_layout;
+        self.zoomed_window = zoomed_window;
+        self.active_preset = None;
+        
+        // Keep focus if the focused window is still shown
+        let visible = self.layout.as_ref().map(|l| l.window_ids()).unwrap_or_default();
+        if let Some(id) = self.focused_window.filter(|id| visible.contains(id)).or_else(|| visible.first().copied()) {
+            self.focus_window(id)?;
+        }
+        
+        // Recalculate the layout
+        if let Some(layout) = &mut self.layout {
+            layout.calculate_layout(self.area);
+            self.apply_layout()?;
+        }
+        
+        Ok(())
+    }
+    
     // Focus a window
     pub fn focus_window(&mut self, id: Uuid) -> Result<()> {
         // Make sure the window exists
@@ -317,6 +661,40 @@ impl WindowManager {
         self.focused_window.and_then(|id| self.windows.get_mut(&id))
     }
     
+    // Get the layout tree
+    pub fn layout(&self) -> Option<&LayoutNode> {
+        self.layout.as_ref()
+    }
+    
+    // Replace the layout tree (e.g. when restoring a session)
+    pub fn set_layout(&mut self, layout: LayoutNode) -> Result<()> {
+        self.record_layout();
+        
+        self.layout = Some(layout);
+        self.zoomed_window = None;
+        self.pre_zoom_layout = None;
+        self.active_preset = None;
+        
+        // Focus the first window unless the focused one is part of the new layout
+        let ids = self.layout.as_ref().map(|l| l.window_ids()).unwrap_or_default();
+        if let Some(id) = self.focused_window.filter(|id| ids.contains(id)).or_else(|| ids.first().copied()) {
+            self.focus_window(id)?;
+        }
+        
+        // Recalculate the layout
+        if let Some(layout) = &mut self.layout {
+            layout.calculate_layout(self.area);
+            self.apply_layout()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Choose whether directional focus wraps around at the screen edges
+    pub fn set_wrap_navigation(&mut self, wrap: bool) {
+        self.wrap_navigation = wrap;
+    }
+    
     // Focus the next window in sequence
     pub fn focus_next_window(&mut self) -> Result<()> {
         let window_ids: Vec<Uuid> = self.windows.keys().cloned().collect();
@@ -397,66 +775,82 @@ impl WindowManager {
             return Ok(());
         };
         
+        // Focus the best candidate if found
+        if let Some(id) = self.window_in_direction(current_id, direction) {
+            self.focus_window(id)?;
+        }
+        
+        Ok(())
+    }
+    
+    // The window next to a window in a direction, if any
+    pub fn window_in_direction(&self, current_id: Uuid, direction: Direction) -> Option<Uuid> {
+        let layout = self.layout.as_ref()?;
+        
         // Get the current window's rectangle
-        let current_rect = if let Some(layout) = &self.layout {
-            if let Some(rect) = layout.window_rect(&current_id) {
-                rect
-            } else {
-                return Ok(());
-            }
-        } else {
-            return Ok(());
-        };
+        let current_rect = layout.window_rect(&current_id)?;
+        
+        // Rectangles of the other windows in the layout
+        let candidates: Vec<(Uuid, Rect)> = layout.window_ids().into_iter()
+            .filter(|&id| id != current_id)
+            .filter_map(|id| layout.window_rect(&id).map(|rect| (id, rect)))
+            .collect();
+        
+        adjacent_window(current_rect, &candidates, direction)
+            .or_else(|| {
+                // Continue from the opposite edge of the screen
+                if self.wrap_navigation {
+                    wrapped_window(current_rect, &candidates, direction, self.area)
+                } else {
+                    None
+                }
+            })
+    }
+    
+    // Move a window next to another one, on the given edge of it. The window keeps
+    // its process; the window it leaves behind is replaced by its sibling subtree.
+    pub fn move_window(&mut self, id: Uuid, target_id: Uuid, edge: Direction) -> Result<()> {
+        if id == target_id {
+            anyhow::bail!("Can't move a window next to itself");
+        }
         
-        // Find the nearest window in the specified direction
-        let mut best_candidate = None;
-        let mut best_distance = f32::MAX;
+        // Move within the whole layout, even if only a zoomed window is shown
+        let layout = if self.zoomed_window.is_some() { &self.pre_zoom_layout } else { &self.layout };
+        let Some(layout) = layout.clone() else { anyhow::bail!("No layout") };
+        if !layout.contains(&id) || !layout.contains(&target_id) {
+            anyhow::bail!("Window not found in layout");
+        }
         
-        // Get center points of current window
-        let current_center_x = current_rect.x as f32 + current_rect.width as f32 / 2.0;
-        let current_center_y = current_rect.y as f32 + current_rect.height as f32 / 2.0;
+        let (direction, new_first) = match edge {
+            Direction::Left => (SplitDirection::Horizontal, true),
+            Direction::Right => (SplitDirection::Horizontal, false),
+            Direction::Up => (SplitDirection::Vertical, true),
+            Direction::Down => (SplitDirection::Vertical, false),
+        };
         
-        // Check all other windows to find the best candidate
-        for (&id, window) in &self.windows {
-            if id == current_id {
-                continue;
-            }
-            
-            let candidate_rect = window.size();
-            let candidate_center_x = candidate_rect.x as f32 + candidate_rect.width as f32 / 2.0;
-            let candidate_center_y = candidate_rect.y as f32 + candidate_rect.height as f32 / 2.0;
-            
-            // Calculate directional vectors
-            let dx = candidate_center_x - current_center_x;
-            let dy = candidate_center_y - current_center_y;
-            
-            // Check if the window is in the specified direction
-            let is_in_direction = match direction {
-                Direction::Up => dy < 0.0 && dy.abs() > dx.abs(),
-                Direction::Down => dy > 0.0 && dy.abs() > dx.abs(),
-                Direction::Left => dx < 0.0 && dx.abs() > dy.abs(),
-                Direction::Right => dx > 0.0 && dx.abs() > dy.abs(),
-            };
-            
-            if is_in_direction {
-                // Calculate distance (squared for efficiency)
-                let distance = dx * dx + dy * dy;
-                
-                if distance < best_distance {
-                    best_distance = distance;
-                    best_candidate = Some(id);
-                }
-            }
+        // The target is still there after the window is taken out, so the layout can't be empty
+        let mut moved = layout::without_window(layout, &id)
+            .ok_or_else(|| anyhow::anyhow!("Window not found in layout"))?;
+        moved.split_window(&target_id, direction, id, 0.5, new_first)?;
+        
+        self.record_layout();
+        self.active_preset = None;
+        if self.zoomed_window.is_some() {
+            self.pre_zoom_layout = Some(moved);
+        } else {
+            self.layout = Some(moved);
         }
         
-        // Focus the best candidate if found
-        if let Some(id) = best_candidate {
-            self.focus_window(id)?;
+        // Recalculate the layout
+        if let Some(layout) = &mut self.layout {
+            layout.calculate_layout(self.area);
+            self.apply_layout()?;
         }
         
         Ok(())
     }
     
+    
     // Zoom in on a window (or the focused window if none specified)
     pub fn zoom_window(&mut self, id: Option<Uuid>) -> Result<()> {
         // If already zoomed, first unzoom
@@ -479,6 +873,8 @@ impl WindowManager {
             anyhow::bail!("Window not found");
         }
         
+        self.record_layout();
+        
         // Save the current layout
         if let Some(layout) = &self.layout {
             self.pre_zoom_layout = Some(layout.clone());
@@ -509,6 +905,8 @@ impl WindowManager {
             return Ok(());
         }
         
+        self.record_layout();
+        
         // Restore the original layout
         if let Some(layout) = &self.pre_zoom_layout {
             self.layout = Some(layout.clone());
@@ -554,29 +952,7 @@ impl WindowManager {
             anyhow::bail!("Window not found");
         }
         
-        // If this is the only window, remove it completely
-        if self.windows.len() == 1 {
-            if let Some(mut window) = self.windows.remove(&id) {
-                window.close()?;
-            }
-            self.layout = None;
-            self.focused_window = None;
-            return Ok(());
-        }
-        
-        // Find a new window to focus if we're closing the focused window
-        if self.focused_window == Some(id) {
-            let other_window = self.windows.keys()
-                .find(|&&window_id| window_id != id)
-                .cloned();
-                
-            if let Some(other_id) = other_window {
-                self.focused_window = Some(other_id);
-                if let Some(window) = self.windows.get_mut(&other_id) {
-                    window.focus();
-                }
-            }
-        }
+        self.record_layout();
         
         // Close the window
         if let Some(mut window) = self.windows.remove(&id) {
@@ -584,26 +960,35 @@ impl WindowManager {
         }
         
         // Restructure the layout
+        self.active_preset = None;
         self.restructure_layout(&id)?;
         
         Ok(())
     }
     
-    // Restructure the layout after removing a window
+    // Restructure the layout after removing a window: the sibling subtree of the
+    // removed window takes the place of the split that held both
     fn restructure_layout(&mut self, removed_id: &Uuid) -> Result<()> {
-        if self.windows.is_empty() {
-            self.layout = None;
-            self.focused_window = None;
-            return Ok(());
+        // The window that moves into the removed window's place gets focus
+        let successor = self.pre_zoom_layout.as_ref()
+            .or(self.layout.as_ref())
+            .and_then(|layout| layout::sibling_window(layout, removed_id));
+        
+        self.layout = self.layout.take().and_then(|layout| layout::without_window(layout, removed_id));
+        self.pre_zoom_layout = self.pre_zoom_layout.take().and_then(|layout| layout::without_window(layout, removed_id));
+        
+        // Closing the zoomed window shows the rest of the layout again
+        if self.zoomed_window == Some(*removed_id) {
+            self.layout = self.pre_zoom_layout.take();
+            self.zoomed_window = None;
         }
         
-        // Create a new layout, preserving the structure as much as possible
-        if let Some(old_layout) = &self.layout {
-            self.layout = Some(self.create_new_layout_without(old_layout, removed_id)?);
-        } else {
-            // If there was no layout, create one with the first window
-            let window_ids: Vec<Uuid> = self.windows.keys().cloned().collect();
-            self.layout = Some(LayoutNode::window(window_ids[0], self.area));
+        if self.focused_window == Some(*removed_id) {
+            self.focused_window = None;
+            let shown = self.layout.as_ref().map(|layout| layout.window_ids()).unwrap_or_default();
+            if let Some(next) = successor.filter(|id| shown.contains(id)).or_else(|| shown.first().copied()) {
+                self.focus_window(next)?;
+            }
         }
         
         // Recalculate the layout
@@ -615,106 +1000,6 @@ impl WindowManager {
         Ok(())
     }
     
-    // Create a new layout without the specified window
-    fn create_new_layout_without(&self, old_layout: &LayoutNode, removed_id: &Uuid) -> Result<LayoutNode> {
-        match old_layout {
-            LayoutNode::Window { id, rect } => {
-                if id == removed_id {
-                    // This is the window to remove, but we need to replace it
-                    // with something. For now, just grab the first available window.
-                    let window_ids: Vec<Uuid> = self.windows.keys().cloned().collect();
-                    if window_ids.is_empty() {
-                        anyhow::bail!("No windows available");
-                    }
-                    Ok(LayoutNode::window(window_ids[0], *rect))
-                } else {
-                    // This window stays as is
-                    Ok(LayoutNode::window(*id, *rect))
-                }
-            }
-            LayoutNode::Split { direction, ratio, first, second, rect } => {
-                // First check if the removed window is in either branch
-                let first_contains = self.layout_contains_window(first, removed_id);
-                let second_contains = self.layout_contains_window(second, removed_id);
-                
-                match (first_contains, second_contains) {
-                    (true, false) => {
-                        // The removed window is in the first branch
-                        let new_first = self.create_new_layout_without(first, removed_id)?;
-                        
-                        // If the first branch now has only one window, we can simplify
-                        if let LayoutNode::Window { id: first_id, .. } = new_first {
-                            // Just return the second branch, which becomes parent
-                            Ok(LayoutNode::window(first_id, *rect))
-                        } else {
-                            // Keep the split structure with the new first branch
-                            Ok(LayoutNode::Split {
-                                direction: *direction,
-                                ratio: *ratio,
-                                first: Box::new(new_first),
-                                second: Box::new(second.as_ref().clone()),
-                                rect: *rect,
-                            })
-                        }
-                    }
-                    (false, true) => {
-                        // The removed window is in the second branch
-                        let new_second = self.create_new_layout_without(second, removed_id)?;
-                        
-                        // If the second branch now has only one window, we can simplify
-                        if let LayoutNode::Window { id: second_id, .. } = new_second {
-                            // Just return the first branch, which becomes parent
-                            Ok(LayoutNode::window(second_id, *rect))
-                        } else {
-                            // Keep the split structure with the new second branch
-                            Ok(LayoutNode::Split {
-                                direction: *direction,
-                                ratio: *ratio,
-                                first: Box::new(first.as_ref().clone()),
-                                second: Box::new(new_second),
-                                rect: *rect,
-                            })
-                        }
-                    }
-                    (true, true) => {
-                        // Both branches contain windows to be removed (unlikely)
-                        // Recursively handle both sides
-                        let new_first = self.create_new_layout_without(first, removed_id)?;
-                        let new_second = self.create_new_layout_without(second, removed_id)?;
-                        
-                        Ok(LayoutNode::Split {
-                            direction: *direction,
-                            ratio: *ratio,
-                            first: Box::new(new_first),
-                            second: Box::new(new_second),
-                            rect: *rect,
-                        })
-                    }
-                    (false, false) => {
-                        // The split doesn't contain the window to be removed
-                        Ok(LayoutNode::Split {
-                            direction: *direction,
-                            ratio: *ratio,
-                            first: Box::new(first.as_ref().clone()),
-                            second: Box::new(second.as_ref().clone()),
-                            rect: *rect,
-                        })
-                    }
-                }
-            }
-        }
-    }
-    
-    // Check if a layout contains a window
-    fn layout_contains_window(&self, layout: &LayoutNode, window_id: &Uuid) -> bool {
-        match layout {
-            LayoutNode::Window { id, .. } => id == window_id,
-            LayoutNode::Split { first, second, .. } => {
-                self.layout_contains_window(first, window_id) || self.layout_contains_window(second, window_id)
-            }
-        }
-    }
-    
     // Layout presets for common window arrangements
     
     // Apply a horizontal split layout with the given windows
@@ -730,6 +1015,8 @@ impl WindowManager {
             }
         }
         
+        self.record_layout();
+        
         // For a single window, just set it as the layout
         if window_ids.len() == 1 {
             self.layout = Some(LayoutNode::window(window_ids[0], self.area));
@@ -756,18 +1043,8 @@ impl WindowManager {
             self.layout = Some(layout);
         }
         
-        // Focus the first window
-        if !window_ids.is_empty() {
-            self.focus_window(window_ids[0])?;
-        }
-        
-        // Recalculate the layout
-        if let Some(layout) = &mut self.layout {
-            layout.calculate_layout(self.area);
-            self.apply_layout()?;
-        }
-        
-        Ok(())
+        // Focus the first window and restore any ratios the user chose for this preset
+        self.finish_preset(format!("horizontal:{}", window_ids.len()), window_ids[0])
     }
     
     // Apply a vertical split layout with the given windows
@@ -783,6 +1060,8 @@ impl WindowManager {
             }
         }
         
+        self.record_layout();
+        
         // For a single window, just set it as the layout
         if window_ids.len() == 1 {
             self.layout = Some(LayoutNode::window(window_ids[0], self.area));
@@ -809,18 +1088,8 @@ impl WindowManager {
             self.layout = Some(layout);
         }
         
-        // Focus the first window
-        if !window_ids.is_empty() {
-            self.focus_window(window_ids[0])?;
-        }
-        
-        // Recalculate the layout
-        if let Some(layout) = &mut self.layout {
-            layout.calculate_layout(self.area);
-            self.apply_layout()?;
-        }
-        
-        Ok(())
+        // Focus the first window and restore any ratios the user chose for this preset
+        self.finish_preset(format!("vertical:{}", window_ids.len()), window_ids[0])
     }
     
     // Apply a grid layout with the given windows
@@ -836,6 +1105,8 @@ impl WindowManager {
             }
         }
         
+        self.record_layout();
+        
         // For a single window, just set it as the layout
         if window_ids.len() == 1 {
             self.layout = Some(LayoutNode::window(window_ids[0], self.area));
@@ -927,18 +1198,8 @@ impl WindowManager {
             }
         }
         
-        // Focus the first window
-        if !window_ids.is_empty() {
-            self.focus_window(window_ids[0])?;
-        }
-        
-        // Recalculate the layout
-        if let Some(layout) = &mut self.layout {
-            layout.calculate_layout(self.area);
-            self.apply_layout()?;
-        }
-        
-        Ok(())
+        // Focus the first window and restore any ratios the user chose for this preset
+        self.finish_preset(format!("grid:{}", window_ids.len()), window_ids[0])
     }
     
     // Apply a layout that maximizes the main window with smaller windows to the side
@@ -955,6 +1216,8 @@ impl WindowManager {
             }
         }
         
+        self.record_layout();
+        
         // If there are no stack windows, just use the main window
         if stack_window_ids.is_empty() {
             self.layout = Some(LayoutNode::window(main_window_id, self.area));
@@ -985,15 +1248,222 @@ impl WindowManager {
             });
         }
         
-        // Focus the main window
-        self.focus_window(main_window_id)?;
-        
-        // Recalculate the layout
-        if let Some(layout) = &mut self.layout {
-            layout.calculate_layout(self.area);
-            self.apply_layout()?;
+        // Focus the main window and restore any ratios the user chose for this preset
+        self.finish_preset(format!("main:{}", stack_window_ids.len() + 1), main_window_id)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> WindowManager {
+        WindowManager::new(Rect::new(0, 0, 120, 40))
+    }
+
+    // The layout as text, e.g. "H(a, V(b, c))", naming windows by their order in `ids`
+    fn shape(manager: &WindowManager, ids: &[Uuid]) -> String {
+        fn describe(node: &LayoutNode, ids: &[Uuid]) -> String {
+            match node {
+                LayoutNode::Window { id, .. } => match ids.iter().position(|i| i == id) {
+                    Some(index) => ((b'a' + index as u8) as char).to_string(),
+                    None => String::from("?"),
+                },
+                LayoutNode::Split { direction, first, second, .. } => {
+                    let kind = match direction {
+                        SplitDirection::Horizontal => "H",
+                        SplitDirection::Vertical => "V",
+                    };
+                    format!("{}({}, {})", kind, describe(first, ids), describe(second, ids))
+                }
+            }
         }
-        
-        Ok(())
+        manager.layout().map(|layout| describe(layout, ids)).unwrap_or_default()
+    }
+
+    // Every open window is in the layout exactly once, and the windows tile the area
+    fn assert_consistent(manager: &WindowManager) {
+        let mut shown = manager.layout().map(|layout| layout.window_ids()).unwrap_or_default();
+        let count = shown.len();
+        shown.sort();
+        shown.dedup();
+        assert_eq!(shown.len(), count, "a window is in the layout twice");
+
+        let mut open: Vec<Uuid> = manager.windows().keys().cloned().collect();
+        open.sort();
+        assert_eq!(shown, open, "the layout and the open windows differ");
+
+        let covered: u32 = manager.windows().values().map(|w| w.size().area() as u32).sum();
+        if !open.is_empty() {
+            assert_eq!(covered, manager.area.area() as u32);
+        }
+    }
+
+    // a | (b / c)
+    fn nested() -> (WindowManager, Vec<Uuid>) {
+        let mut manager = manager();
+        let a = manager.create_window("a").unwrap();
+        let b = manager.split_window(a, SplitDirection::Horizontal, 0.3).unwrap();
+        let c = manager.split_window(b, SplitDirection::Vertical, 0.5).unwrap();
+        (manager, vec![a, b, c])
+    }
+
+    #[test]
+    fn closing_a_window_promotes_the_sibling_subtree() {
+        let (mut manager, ids) = nested();
+        assert_eq!(shape(&manager, &ids), "H(a, V(b, c))");
+
+        manager.close_window(ids[0]).unwrap();
+        assert_eq!(shape(&manager, &ids), "V(b, c)");
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn closing_a_window_in_a_nested_split_keeps_the_outer_split() {
+        let (mut manager, ids) = nested();
+        let right = match manager.layout() {
+            Some(LayoutNode::Split { second, .. }) => match second.as_ref() {
+                LayoutNode::Split { rect, .. } => Some(*rect),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        manager.close_window(ids[1]).unwrap();
+        assert_eq!(shape(&manager, &ids), "H(a, c)");
+        assert_eq!(manager.layout().unwrap().ratios(), vec![0.3]);
+        assert_eq!(Some(manager.windows()[&ids[2]].size()), right);
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn closing_windows_in_any_order_never_duplicates_or_drops_windows() {
+        for order in [[0, 1, 2, 3, 4, 5], [5, 4, 3, 2, 1, 0], [2, 0, 4, 1, 5, 3], [3, 5, 1, 4, 0, 2]] {
+            let mut manager = manager();
+            let mut ids = vec![manager.create_window("a").unwrap()];
+            for i in 1..6 {
+                let direction = if i % 2 == 0 { SplitDirection::Horizontal } else { SplitDirection::Vertical };
+                ids.push(manager.split_window(ids[i / 2], direction, 0.5).unwrap());
+            }
+            assert_consistent(&manager);
+
+            for index in order {
+                manager.close_window(ids[index]).unwrap();
+                assert!(!manager.layout().is_some_and(|l| l.contains(&ids[index])));
+                assert_consistent(&manager);
+            }
+            assert!(manager.layout().is_none());
+            assert!(manager.focused_window().is_none());
+        }
+    }
+
+    #[test]
+    fn closing_the_focused_window_focuses_the_window_taking_its_place() {
+        let (mut manager, ids) = nested();
+        manager.focus_window(ids[2]).unwrap();
+
+        manager.close_window(ids[2]).unwrap();
+        assert_eq!(manager.focused_window().map(|w| w.id()), Some(ids[1]));
+
+        // A subtree taking the place focuses its first window
+        manager.split_window(ids[1], SplitDirection::Vertical, 0.5).unwrap();
+        manager.focus_window(ids[0]).unwrap();
+        manager.close_window(ids[0]).unwrap();
+        assert_eq!(manager.focused_window().map(|w| w.id()), Some(ids[1]));
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn closing_the_zoomed_window_shows_the_rest_of_the_layout() {
+        let (mut manager, ids) = nested();
+        manager.zoom_window(Some(ids[1])).unwrap();
+
+        manager.close_window(ids[1]).unwrap();
+        assert!(!manager.is_zoomed());
+        assert_eq!(shape(&manager, &ids), "H(a, c)");
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn closing_a_window_hidden_by_a_zoom_keeps_the_zoom() {
+        let (mut manager, ids) = nested();
+        manager.zoom_window(Some(ids[0])).unwrap();
+
+        manager.close_window(ids[2]).unwrap();
+        assert_eq!(manager.zoomed_window(), Some(ids[0]));
+        assert_eq!(shape(&manager, &ids), "a");
+
+        manager.unzoom().unwrap();
+        assert_eq!(shape(&manager, &ids), "H(a, b)");
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn moving_a_window_detaches_it_and_inserts_it_at_the_edge() {
+        let (mut manager, ids) = nested();
+        manager.windows_mut().get_mut(&ids[2]).unwrap().title = String::from("kept");
+
+        manager.move_window(ids[2], ids[0], Direction::Left).unwrap();
+        assert_eq!(shape(&manager, &ids), "H(H(c, a), b)");
+        assert_eq!(manager.windows()[&ids[2]].title, "kept");
+        assert_consistent(&manager);
+
+        manager.move_window(ids[2], ids[1], Direction::Down).unwrap();
+        assert_eq!(shape(&manager, &ids), "H(a, V(b, c))");
+        assert_consistent(&manager);
+    }
+
+    #[test]
+    fn moving_a_window_onto_itself_or_a_missing_window_fails() {
+        let (mut manager, ids) = nested();
+        assert!(manager.move_window(ids[0], ids[0], Direction::Left).is_err());
+        assert!(manager.move_window(ids[0], Uuid::new_v4(), Direction::Left).is_err());
+        assert_eq!(shape(&manager, &ids), "H(a, V(b, c))");
+    }
+
+    #[test]
+    fn undoing_and_redoing_several_steps_brings_back_every_layout() {
+        let (mut manager, ids) = nested();
+        let mut shapes = vec![shape(&manager, &ids)];
+        manager.move_window(ids[2], ids[0], Direction::Left).unwrap();
+        shapes.push(shape(&manager, &ids));
+        manager.move_window(ids[1], ids[2], Direction::Up).unwrap();
+        shapes.push(shape(&manager, &ids));
+        manager.move_window(ids[0], ids[1], Direction::Right).unwrap();
+        shapes.push(shape(&manager, &ids));
+        assert_eq!(shapes, ["H(a, V(b, c))", "H(H(c, a), b)", "H(V(b, c), a)", "V(H(b, a), c)"]);
+
+        manager.undo_layout(3).unwrap();
+        assert_eq!(shape(&manager, &ids), shapes[0]);
+        manager.redo_layout(3).unwrap();
+        assert_eq!(shape(&manager, &ids), shapes[3]);
+
+        // The layouts stepped over are there one step at a time
+        for expected in shapes.iter().rev().skip(1) {
+            manager.undo_layout(1).unwrap();
+            assert_eq!(&shape(&manager, &ids), expected);
+        }
+        manager.redo_layout(2).unwrap();
+        assert_eq!(shape(&manager, &ids), shapes[2]);
+        manager.redo_layout(1).unwrap();
+        assert_eq!(shape(&manager, &ids), shapes[3]);
+        assert!(manager.redo_layout(1).is_err());
+        assert_consistent(&manager);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn snapping_borders_stop_at_the_ratios_they_pass() {
+        let (mut manager, ids) = nested();
+        let ratio = |manager: &WindowManager| manager.layout().unwrap().ratios()[0];
+
+        // 0.3 to 0.4 passes a third
+        manager.resize_window(ids[0], Direction::Right, 0.1, true).unwrap();
+        assert_eq!(ratio(&manager), 1.0 / 3.0);
+        // From a snap ratio the border moves on
+        manager.resize_window(ids[0], Direction::Right, 0.02, true).unwrap();
+        assert!((ratio(&manager) - 0.3533).abs() < 0.001);
+        manager.resize_window(ids[1], Direction::Left, 0.2, true).unwrap();
+        assert_eq!(ratio(&manager), 1.0 / 3.0);
+        manager.resize_window(ids[1], Direction::Left, 0.05, false).unwrap();
+        assert!((ratio(&manager) - 0.2833).abs() < 0.001);
+    }
+}
@@ -0,0 +1,79 @@
+// The system's light or dark appearance, for switching themes along with it.
+// macOS reports it through the global AppleInterfaceStyle default, GNOME and
+// other desktops following the freedesktop setting through gsettings.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often the appearance is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+// The current appearance, or None where it can't be found out
+pub fn detect() -> Option<Appearance> {
+    if cfg!(target_os = "macos") {
+        // Only set at all in dark mode
+        let output = Command::new("defaults").args(["read", "-g", "AppleInterfaceStyle"]).output().ok()?;
+        let dark = String::from_utf8_lossy(&output.stdout).trim().eq_ignore_ascii_case("dark");
+        return Some(if dark { Appearance::Dark } else { Appearance::Light });
+    }
+
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let scheme = String::from_utf8_lossy(&output.stdout);
+    Some(if scheme.contains("dark") { Appearance::Dark } else { Appearance::Light })
+}
+
+// Checks the appearance in the background and reports changes
+pub struct AppearanceWatcher {
+    changes: Receiver<Appearance>,
+    // Cleared to stop the checking thread
+    running: Arc<AtomicBool>,
+}
+
+impl AppearanceWatcher {
+    pub fn start() -> Self {
+        let (sender, changes) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let still_running = Arc::clone(&running);
+        thread::spawn(move || {
+            let mut last = None;
+            while still_running.load(Ordering::Relaxed) {
+                let current = detect();
+                if current != last {
+                    if let Some(appearance) = current {
+                        if sender.send(appearance).is_err() {
+                            break;
+                        }
+                    }
+                    last = current;
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Self { changes, running }
+    }
+
+    // The appearance, if it changed since the last call (the first call reports the current one)
+    pub fn poll(&self) -> Option<Appearance> {
+        self.changes.try_iter().last()
+    }
+}
+
+impl Drop for AppearanceWatcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
@@ -1 +1,3 @@
 // Utility functions and helpers
+pub mod paths;
+pub mod appearance;
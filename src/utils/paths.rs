@@ -0,0 +1,56 @@
+use directories::ProjectDirs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// Directory everything is kept in when running portably, next to the binary
+static PORTABLE: OnceLock<PathBuf> = OnceLock::new();
+
+// Keep the configuration and state in a `matrix-data` directory next to the binary
+// instead of the platform's directories, e.g. when running from a USB stick
+pub fn set_portable() -> std::io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let dir = exe.parent().map(|dir| dir.join("matrix-data")).unwrap_or_else(|| PathBuf::from("matrix-data"));
+    let _ = PORTABLE.set(dir);
+    Ok(())
+}
+
+// Directory holding the configuration (on Linux $XDG_CONFIG_HOME/matrix or ~/.config/matrix)
+pub fn config_dir() -> PathBuf {
+    match (PORTABLE.get(), project_dirs()) {
+        (Some(dir), _) => dir.join("config"),
+        (None, Some(dirs)) => dirs.config_dir().to_path_buf(),
+        (None, None) => PathBuf::from(".matrix/config"),
+    }
+}
+
+// Directory holding state such as saved sessions (on Linux $XDG_STATE_HOME/matrix or
+// ~/.local/state/matrix; platforms without a state directory use the local data directory)
+pub fn state_dir() -> PathBuf {
+    match (PORTABLE.get(), project_dirs()) {
+        (Some(dir), _) => dir.join("state"),
+        (None, Some(dirs)) => dirs.state_dir().unwrap_or(dirs.data_local_dir()).to_path_buf(),
+        (None, None) => PathBuf::from(".matrix/state"),
+    }
+}
+
+// Directory holding the control socket (on Linux $XDG_RUNTIME_DIR/matrix; elsewhere,
+// and when running portably, the state directory)
+pub fn runtime_dir() -> PathBuf {
+    match (PORTABLE.get(), project_dirs()) {
+        (None, Some(dirs)) => dirs.runtime_dir().map(|dir| dir.to_path_buf()).unwrap_or_else(state_dir),
+        _ => state_dir(),
+    }
+}
+
+// A leading ~ replaced by the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+// The platform's directories for Matrix; None without a home directory
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "matrix")
+}
@@ -0,0 +1,167 @@
+// Workspace templates: a layout with a directory and startup command for each window,
+// saved in the configuration directory. Templates can be bound to directories, so
+// launching Matrix in a project sets up that project's windows. Directories inside
+// the one a template was saved from are kept relative, so a template fits any
+// checkout of the project.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::settings::WorkspaceBinding;
+use crate::session::SessionLayout;
+use crate::ui::window_manager::WindowManager;
+use crate::utils::paths;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateWindow {
+    pub title: String,
+    // Directory the shell starts in, relative to the workspace's
+    #[serde(default)]
+    pub dir: Option<String>,
+    // Typed into the shell once it starts
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    // Typed into the shell at its first prompt, before `command`
+    #[serde(default)]
+    pub init_commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceTemplate {
+    pub windows: Vec<TemplateWindow>,
+    #[serde(default)]
+    pub layout: Option<SessionLayout>,
+    // Index of the focused window
+    #[serde(default)]
+    pub focused: Option<usize>,
+}
+
+impl WorkspaceTemplate {
+    // Capture the windows and layout, with directories relative to `base` where inside it
+    pub fn capture(window_manager: &WindowManager, base: &Path) -> Self {
+        let ids = window_manager.layout().map(|l| l.window_ids()).unwrap_or_default();
+        let windows = ids.iter()
+            .filter_map(|id| window_manager.windows().get(id))
+            .map(|window| TemplateWindow {
+                title: window.title.clone(),
+                dir: window.current_dir().map(|dir| relative_to(Path::new(&dir), base)),
+                command: window.command.clone(),
+                group: window.group.clone(),
+                init_commands: Vec::new(),
+            })
+            .collect();
+
+        let focused = window_manager.focused_window()
+            .and_then(|w| ids.iter().position(|id| *id == w.id()));
+
+        Self {
+            windows,
+            layout: window_manager.layout().and_then(|l| SessionLayout::from_layout(l, &ids)),
+            focused,
+        }
+    }
+
+    // Path of a named template file
+    pub fn path(name: &str) -> PathBuf {
+        paths::config_dir().join("templates").join(format!("{}.json", name))
+    }
+
+    // Save the template under a name
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = Self::path(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Load a named template
+    pub fn load(name: &str) -> Result<Self> {
+        let data = fs::read(Self::path(name))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+// A directory as saved in a template: relative to `base` if inside it
+fn relative_to(dir: &Path, base: &Path) -> String {
+    match dir.strip_prefix(base) {
+        Ok(rest) if rest.as_os_str().is_empty() => String::from("."),
+        Ok(rest) => rest.to_string_lossy().into_owned(),
+        Err(_) => dir.to_string_lossy().into_owned(),
+    }
+}
+
+// A directory saved in a template, resolved against the workspace's
+pub fn resolve(dir: &str, base: &Path) -> PathBuf {
+    base.join(paths::expand_home(dir))
+}
+
+// The template bound to a directory or the closest directory above it, and the
+// directory bound (the workspace's); the first binding matching wins
+pub fn template_for(bindings: &[WorkspaceBinding], dir: &Path) -> Option<(String, PathBuf)> {
+    let patterns: Vec<(Regex, &str)> = bindings.iter()
+        .filter_map(|binding| match glob_regex(&paths::expand_home(&binding.dir)) {
+            Ok(regex) => Some((regex, binding.template.as_str())),
+            Err(e) => {
+                eprintln!("Invalid workspace directory {}: {}", binding.dir, e);
+                None
+            }
+        })
+        .collect();
+
+    dir.ancestors().find_map(|dir| {
+        let path = dir.to_string_lossy();
+        let path = path.trim_end_matches('/');
+        patterns.iter()
+            .find(|(regex, _)| regex.is_match(path))
+            .map(|(_, template)| (template.to_string(), dir.to_path_buf()))
+    })
+}
+
+// A glob (*, ** and ?) matching whole paths
+fn glob_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.trim_end_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories_and_their_subdirectories_find_their_template() {
+        let binding = |dir: &str, template: &str| WorkspaceBinding { dir: dir.to_string(), template: template.to_string() };
+        let bindings = [binding("/work/api", "api"), binding("/work/*-service/", "service"), binding("/src/**", "src")];
+        let found = |dir: &str| template_for(&bindings, Path::new(dir))
+            .map(|(template, base)| (template, base.to_string_lossy().into_owned()));
+
+        assert_eq!(found("/work/api"), Some((String::from("api"), String::from("/work/api"))));
+        assert_eq!(found("/work/api/src/handlers"), Some((String::from("api"), String::from("/work/api"))));
+        assert_eq!(found("/work/auth-service"), Some((String::from("service"), String::from("/work/auth-service"))));
+        assert_eq!(found("/src/a/b").map(|(template, _)| template), Some(String::from("src")));
+        assert_eq!(found("/work/apiary"), None);
+
+        assert_eq!(relative_to(Path::new("/work/api/web"), Path::new("/work/api")), "web");
+        assert_eq!(relative_to(Path::new("/work/api"), Path::new("/work/api")), ".");
+        assert_eq!(relative_to(Path::new("/tmp"), Path::new("/work/api")), "/tmp");
+    }
+}